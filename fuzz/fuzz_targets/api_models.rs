@@ -0,0 +1,46 @@
+//! Parse arbitrary responses of the REST API
+//!
+//! Every input is parsed as each of the models, as the mutations of one model's JSON often hit the fields of another.
+//! Parsing must fail with an error instead of panicking, and every parsed model must serialize again.
+//!
+//! ```text
+//! cargo +nightly fuzz run api_models fuzz/corpus/api_models tests/golden
+//! ```
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use mattermost_structs::{
+    api::{
+        Channel, ChannelBookmark, FileInfo, LogEntry, PostList, Role, ScheduledPost, Scheme, User,
+    },
+    websocket::{ChannelMember, Post, Reaction, Team},
+};
+use serde::{de::DeserializeOwned, Serialize};
+
+fn round_trip<T>(text: &str)
+where
+    T: DeserializeOwned + Serialize,
+{
+    if let Ok(model) = serde_json::from_str::<T>(text) {
+        serde_json::to_string(&model).expect("Parsed models must serialize");
+    }
+}
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(text) = std::str::from_utf8(data) {
+        round_trip::<Channel>(text);
+        round_trip::<ChannelBookmark>(text);
+        round_trip::<ChannelMember>(text);
+        round_trip::<FileInfo>(text);
+        round_trip::<LogEntry>(text);
+        round_trip::<Post>(text);
+        round_trip::<PostList>(text);
+        round_trip::<Reaction>(text);
+        round_trip::<Role>(text);
+        round_trip::<ScheduledPost>(text);
+        round_trip::<Scheme>(text);
+        round_trip::<Team>(text);
+        round_trip::<User>(text);
+    }
+});
@@ -0,0 +1,21 @@
+//! Parse arbitrary websocket frames
+//!
+//! Parsing must fail with an error instead of panicking, and every parsed message must serialize again.
+//! The golden files are a good seed corpus:
+//!
+//! ```text
+//! cargo +nightly fuzz run websocket_message fuzz/corpus/websocket_message tests/golden
+//! ```
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use mattermost_structs::websocket::Message;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(text) = std::str::from_utf8(data) {
+        if let Ok(message) = serde_json::from_str::<Message>(text) {
+            serde_json::to_string(&message).expect("Parsed messages must serialize");
+        }
+    }
+});
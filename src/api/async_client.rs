@@ -0,0 +1,179 @@
+//! Non-blocking variant of [`Client`]
+//!
+//! Only available with the cargo feature `async`.
+
+use super::{check_response, Channel, Client, CreatePostRequest, Query, User};
+use crate::{
+    clock::ClockSkew,
+    error::{Error, ResultExt},
+    websocket::Post,
+};
+use futures::{
+    future::{self, Either},
+    Future,
+};
+use log::debug;
+use reqwest::r#async::{Client as WebClient, RequestBuilder};
+use serde::de::DeserializeOwned;
+use url::Url;
+
+/// A pending request of the [`AsyncClient`]
+pub type ApiFuture<T> = Box<dyn Future<Item = T, Error = Error> + Send>;
+
+/// Client for the REST API, which returns futures instead of blocking
+///
+/// The futures must run on a tokio runtime.
+/// Unlike [`Client`], the connection pool is shared by all requests and clones of the client.
+///
+/// ```no_run
+/// # fn main() -> mattermost_structs::Result<()> {
+/// use futures::Future;
+/// use mattermost_structs::api::AsyncClient;
+///
+/// let client = AsyncClient::new("https://chat.example.com", "token")?;
+/// let greeting = client
+///     .get_me()
+///     .map(|me| println!("Logged in as {}", me.username));
+/// // Spawn `greeting` on the tokio runtime of the bot
+/// # drop(greeting);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub struct AsyncClient {
+    base_url: Url,
+    token: String,
+    client: WebClient,
+    clock: ClockSkew,
+    read_only: bool,
+}
+
+impl AsyncClient {
+    pub fn new<B, T>(base_url: B, token: T) -> crate::Result<AsyncClient>
+    where
+        B: AsRef<str>,
+        T: Into<String>,
+    {
+        Client::new(base_url, token)?.to_async()
+    }
+
+    pub fn clock(&self) -> &ClockSkew {
+        &self.clock
+    }
+
+    fn url(&self, path: &str) -> crate::Result<Url> {
+        Ok(self.base_url.join(path)?)
+    }
+
+    /// Send the request and parse the JSON response
+    fn send<T>(&self, req: RequestBuilder, name: &'static str) -> ApiFuture<T>
+    where
+        T: DeserializeOwned + Send + 'static,
+    {
+        let clock = self.clock.clone();
+        Box::new(
+            req.header("authorization", format!("bearer {}", self.token))
+                .send()
+                .then(|res| res.chain_err(|| "Failed to send webrequest"))
+                .and_then(move |mut res| {
+                    debug!("{} response {}", name, res.status());
+                    match check_response(&clock, res.status(), res.headers()) {
+                        // Add the error sent by the server
+                        Err(Error::Api { status, .. }) => {
+                            let reason = res.status().canonical_reason();
+                            Either::B(res.text().then(move |body| {
+                                Err(Error::from_response(
+                                    status,
+                                    reason,
+                                    &body.unwrap_or_default(),
+                                ))
+                            }))
+                        }
+                        result => Either::A(future::result(result.map(|()| res))),
+                    }
+                })
+                .and_then(|mut res| res.json().from_err()),
+        )
+    }
+
+    fn get<T>(&self, url: crate::Result<Url>, name: &'static str) -> ApiFuture<T>
+    where
+        T: DeserializeOwned + Send + 'static,
+    {
+        match url {
+            Ok(url) => self.send(self.client.get(url), name),
+            Err(err) => Box::new(future::err(err)),
+        }
+    }
+
+    pub fn get_me(&self) -> ApiFuture<User> {
+        self.get(self.url("/api/v4/users/me"), "get_me")
+    }
+
+    pub fn get_user<S>(&self, user_id: S) -> ApiFuture<User>
+    where
+        S: AsRef<str>,
+    {
+        self.get(
+            self.url(&format!("/api/v4/users/{}", user_id.as_ref())),
+            "get_user",
+        )
+    }
+
+    pub fn get_users(&self, page: usize, per_page: usize) -> ApiFuture<Vec<User>> {
+        let url = self.url("/api/v4/users").map(|mut url| {
+            Query::new().page(page, per_page).apply(&mut url);
+            url
+        });
+        self.get(url, "get_users")
+    }
+
+    pub fn get_users_by_id(&self, ids: &[String]) -> ApiFuture<Vec<User>> {
+        match self.url("/api/v4/users/ids") {
+            Ok(url) => self.send(self.client.post(url).json(&ids), "get_users_by_id"),
+            Err(err) => Box::new(future::err(err)),
+        }
+    }
+
+    pub fn get_channel_by_id<S>(&self, id: S) -> ApiFuture<Channel>
+    where
+        S: AsRef<str>,
+    {
+        self.get(
+            self.url(&format!("/api/v4/channels/{}", id.as_ref())),
+            "get_channel_by_id",
+        )
+    }
+
+    pub fn create_post(&self, post: &CreatePostRequest) -> ApiFuture<Post> {
+        if self.read_only {
+            return Box::new(future::err(Error::ReadOnly("create_post".to_string())));
+        }
+        match self.url("/api/v4/posts") {
+            Ok(url) => self.send(self.client.post(url).json(post), "create_post"),
+            Err(err) => Box::new(future::err(err)),
+        }
+    }
+}
+
+impl Client {
+    /// An [`AsyncClient`] with the same server, token, timeouts, clock, and read-only mode
+    ///
+    /// The request limit and the ETag cache are not supported by the [`AsyncClient`].
+    pub fn to_async(&self) -> crate::Result<AsyncClient> {
+        let mut builder = WebClient::builder();
+        if let Some(timeout) = self.timeout {
+            builder = builder.timeout(timeout);
+        }
+        if let Some(timeout) = self.connect_timeout {
+            builder = builder.connect_timeout(timeout);
+        }
+        Ok(AsyncClient {
+            base_url: self.base_url.clone(),
+            token: self.token.clone(),
+            client: builder.build()?,
+            clock: self.clock.clone(),
+            read_only: self.read_only,
+        })
+    }
+}
@@ -0,0 +1,74 @@
+use std::{
+    collections::HashMap,
+    fmt,
+    sync::{Arc, Mutex},
+};
+
+/// A response body together with the `ETag` the server sent for it
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct CachedResponse {
+    pub etag: String,
+    pub body: Vec<u8>,
+}
+
+/// Storage for responses of cacheable GET requests, see [`ClientBuilder::etag_cache`](super::ClientBuilder::etag_cache)
+///
+/// Keys are the request URLs.
+/// Responses depend on the user, so a cache must not be shared between clients with different tokens.
+pub trait EtagCache: Send + Sync {
+    fn get(&self, url: &str) -> Option<CachedResponse>;
+    fn put(&self, url: &str, response: CachedResponse);
+}
+
+/// Unbounded in-memory [`EtagCache`]
+#[derive(Debug, Default)]
+pub struct MemoryCache {
+    responses: Mutex<HashMap<String, CachedResponse>>,
+}
+
+impl MemoryCache {
+    pub fn new() -> MemoryCache {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.responses.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn clear(&self) {
+        self.responses.lock().unwrap().clear();
+    }
+}
+
+impl EtagCache for MemoryCache {
+    fn get(&self, url: &str) -> Option<CachedResponse> {
+        self.responses.lock().unwrap().get(url).cloned()
+    }
+
+    fn put(&self, url: &str, response: CachedResponse) {
+        self.responses
+            .lock()
+            .unwrap()
+            .insert(url.to_string(), response);
+    }
+}
+
+/// Shared cache of a [`Client`](super::Client)
+#[derive(Clone)]
+pub(crate) struct CacheHandle(pub(crate) Arc<dyn EtagCache>);
+
+impl CacheHandle {
+    fn addr(&self) -> usize {
+        Arc::as_ptr(&self.0) as *const u8 as usize
+    }
+}
+
+impl fmt::Debug for CacheHandle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "CacheHandle({:#x})", self.addr())
+    }
+}
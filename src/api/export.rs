@@ -0,0 +1,133 @@
+use crate::{
+    api::{Client, User},
+    error::{Result, ResultExt},
+};
+use std::{fmt, io::Write, str::FromStr};
+
+/// Number of users fetched per request while exporting
+const EXPORT_PAGE_SIZE: usize = 200;
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub enum ExportFormat {
+    Csv,
+    Json,
+}
+
+impl fmt::Display for ExportFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            ExportFormat::Csv => write!(f, "csv"),
+            ExportFormat::Json => write!(f, "json"),
+        }
+    }
+}
+
+impl FromStr for ExportFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> ::std::result::Result<Self, Self::Err> {
+        match s {
+            "csv" => Ok(ExportFormat::Csv),
+            "json" => Ok(ExportFormat::Json),
+            _ => Err(format!(
+                "Unexpected value '{}', expected one of 'csv', 'json'",
+                s
+            )),
+        }
+    }
+}
+
+impl Client {
+    /// Write all users of the server to `writer`
+    ///
+    /// The users are fetched page by page and written immediately, such that the full user list is never kept in memory.
+    /// JSON is written as a single array of user objects.
+    ///
+    /// Returns the number of exported users.
+    pub fn export_users<W>(&self, mut writer: W, format: ExportFormat) -> Result<usize>
+    where
+        W: Write,
+    {
+        let mut count = 0;
+        match format {
+            ExportFormat::Csv => writeln!(writer, "{}", CSV_HEADER.join(","))?,
+            ExportFormat::Json => write!(writer, "[")?,
+        }
+
+        for page in 0.. {
+            let users = self
+                .get_users(page, EXPORT_PAGE_SIZE)
+                .chain_err(|| format!("Failed to fetch page {} of the users", page))?;
+            for user in &users {
+                match format {
+                    ExportFormat::Csv => write_csv_record(&mut writer, user)?,
+                    ExportFormat::Json => {
+                        if count > 0 {
+                            write!(writer, ",")?;
+                        }
+                        serde_json::to_writer(&mut writer, user)?;
+                    }
+                }
+                count += 1;
+            }
+            if users.len() < EXPORT_PAGE_SIZE {
+                break;
+            }
+        }
+
+        if format == ExportFormat::Json {
+            writeln!(writer, "]")?;
+        }
+        writer.flush()?;
+        Ok(count)
+    }
+}
+
+const CSV_HEADER: &[&str] = &[
+    "id",
+    "username",
+    "email",
+    "first_name",
+    "last_name",
+    "nickname",
+    "position",
+    "roles",
+    "locale",
+    "create_at",
+    "update_at",
+    "delete_at",
+];
+
+fn write_csv_record<W>(writer: &mut W, user: &User) -> Result<()>
+where
+    W: Write,
+{
+    let mut roles: Vec<_> = user.roles.iter().map(ToString::to_string).collect();
+    roles.sort();
+    let fields = [
+        user.id.to_string(),
+        user.username.clone(),
+        user.email.clone(),
+        user.first_name.clone(),
+        user.last_name.clone(),
+        user.nickname.clone(),
+        user.position.clone(),
+        roles.join(" "),
+        user.locale.clone(),
+        user.create_at.to_rfc3339(),
+        user.update_at.to_rfc3339(),
+        user.delete_at.to_rfc3339(),
+    ];
+    let record: Vec<_> = fields.iter().map(|field| csv_escape(field)).collect();
+    writeln!(writer, "{}", record.join(","))?;
+    Ok(())
+}
+
+/// Quote a CSV field according to RFC 4180, if necessary
+fn csv_escape(field: &str) -> String {
+    if field.contains([',', '"', '\r', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
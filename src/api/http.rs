@@ -0,0 +1,20 @@
+use reqwest::Client as WebClient;
+use std::{fmt, sync::Arc};
+
+/// HTTP client of a [`Client`](super::Client), shared by all its clones
+///
+/// Reusing the client keeps the connections to the server alive between requests.
+#[derive(Clone)]
+pub(crate) struct HttpHandle(pub(crate) Arc<WebClient>);
+
+impl HttpHandle {
+    fn addr(&self) -> usize {
+        Arc::as_ptr(&self.0) as usize
+    }
+}
+
+impl fmt::Debug for HttpHandle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "HttpHandle({:#x})", self.addr())
+    }
+}
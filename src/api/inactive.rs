@@ -0,0 +1,126 @@
+use crate::{
+    api::{
+        reconcile::{retry_rate_limited, BATCH_PAUSE, BATCH_SIZE},
+        AdminChannelSearch, ChannelWithTeamData, Client,
+    },
+    error::Result,
+    ids::{ChannelId, TeamId},
+};
+use chrono::{DateTime, Utc};
+use log::warn;
+use serde::Serialize;
+use std::thread;
+
+/// A channel which the server refused to archive
+#[derive(Debug, Clone, Eq, PartialEq, Serialize)]
+pub struct ArchiveFailure {
+    pub channel_id: ChannelId,
+    pub error: String,
+}
+
+/// Result of [`Client::archive_channels`]
+///
+/// In a dry run `archived` lists the channels which would have been archived.
+#[derive(Debug, Clone, Eq, PartialEq, Serialize)]
+pub struct ArchiveReport {
+    pub dry_run: bool,
+    pub archived: Vec<ChannelId>,
+    pub failed: Vec<ArchiveFailure>,
+}
+
+impl ArchiveReport {
+    /// All channels were archived
+    pub fn is_complete(&self) -> bool {
+        self.failed.is_empty()
+    }
+}
+
+impl Client {
+    /// Public and private channels without activity since `since`, least recently active first
+    ///
+    /// The activity of a channel is its last post, or its creation if it has no posts, see [`Channel::last_activity_at`](super::Channel::last_activity_at).
+    /// Archived channels and the default channels of the teams, which cannot be archived, are left out.
+    /// With an empty `team_ids` the channels of all teams are searched.
+    ///
+    /// Requires the `sysconsole_read_user_management_channels` permission.
+    pub fn get_inactive_channels<T>(
+        &self,
+        since: DateTime<Utc>,
+        team_ids: &[T],
+    ) -> Result<Vec<ChannelWithTeamData>>
+    where
+        T: AsRef<TeamId>,
+    {
+        const PER_PAGE: usize = 200;
+
+        let mut search = AdminChannelSearch {
+            team_ids: team_ids
+                .iter()
+                .map(|team_id| team_id.as_ref().to_string())
+                .collect(),
+            exclude_default_channels: Some(true),
+            per_page: PER_PAGE,
+            ..AdminChannelSearch::default()
+        };
+        let mut inactive = Vec::new();
+        loop {
+            let result = self.search_all_channels(&search)?;
+            let count = result.channels.len();
+            inactive.extend(result.channels.into_iter().filter(|channel| {
+                !channel.channel.is_archived() && channel.channel.last_activity_at() < since
+            }));
+            if count < PER_PAGE {
+                break;
+            }
+            search.page += 1;
+        }
+        inactive.sort_by_key(|channel| channel.channel.last_activity_at());
+        Ok(inactive)
+    }
+
+    /// Archive all `channels`
+    ///
+    /// The channels are archived in batches with a pause in between.
+    /// If the server answers with its rate limit, the request is retried after the limit resets.
+    ///
+    /// Channels which the server refuses to archive are reported in [`ArchiveReport::failed`] without stopping the others.
+    /// Errors which affect all channels, like an invalid token, abort the archival.
+    pub fn archive_channels<C>(&self, channels: &[C], dry_run: bool) -> Result<ArchiveReport>
+    where
+        C: AsRef<ChannelId>,
+    {
+        let mut report = ArchiveReport {
+            dry_run,
+            archived: Vec::new(),
+            failed: Vec::new(),
+        };
+        if dry_run {
+            report.archived = channels
+                .iter()
+                .map(|channel_id| channel_id.as_ref().clone())
+                .collect();
+            return Ok(report);
+        }
+
+        for (idx, batch) in channels.chunks(BATCH_SIZE).enumerate() {
+            if idx > 0 {
+                thread::sleep(BATCH_PAUSE);
+            }
+            for channel_id in batch {
+                let channel_id = channel_id.as_ref();
+                match retry_rate_limited(|| self.delete_channel(channel_id)) {
+                    Ok(()) => report.archived.push(channel_id.clone()),
+                    Err(err) if err.status() == Some(401) => return Err(err),
+                    Err(err) => {
+                        warn!("Failed to archive channel {}: {}", channel_id, err);
+                        report.failed.push(ArchiveFailure {
+                            channel_id: channel_id.clone(),
+                            error: err.to_string(),
+                        });
+                    }
+                }
+            }
+        }
+        Ok(report)
+    }
+}
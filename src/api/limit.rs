@@ -0,0 +1,46 @@
+use std::sync::{Condvar, Mutex};
+
+/// Counting semaphore limiting the number of requests in flight
+#[derive(Debug)]
+pub(crate) struct RequestLimiter {
+    max: usize,
+    in_flight: Mutex<usize>,
+    released: Condvar,
+}
+
+impl RequestLimiter {
+    pub(crate) fn new(max: usize) -> RequestLimiter {
+        RequestLimiter {
+            max: max.max(1),
+            in_flight: Mutex::new(0),
+            released: Condvar::new(),
+        }
+    }
+
+    pub(crate) fn limit(&self) -> usize {
+        self.max
+    }
+
+    /// Block until a request may be sent
+    pub(crate) fn acquire(&self) -> Permit<'_> {
+        let mut in_flight = self.in_flight.lock().unwrap();
+        while *in_flight >= self.max {
+            in_flight = self.released.wait(in_flight).unwrap();
+        }
+        *in_flight += 1;
+        Permit { limiter: self }
+    }
+}
+
+/// Allows one request, until it is dropped
+#[derive(Debug)]
+pub(crate) struct Permit<'a> {
+    limiter: &'a RequestLimiter,
+}
+
+impl Drop for Permit<'_> {
+    fn drop(&mut self) {
+        *self.limiter.in_flight.lock().unwrap() -= 1;
+        self.limiter.released.notify_one();
+    }
+}
@@ -1,18 +1,257 @@
+#[cfg(feature = "async")]
+mod async_client;
+mod cache;
+mod export;
+mod http;
+mod inactive;
+mod limit;
+mod offboard;
+mod permissions;
+mod query;
+mod reconcile;
+mod search;
+
+#[cfg(feature = "async")]
+pub use self::async_client::{ApiFuture, AsyncClient};
+use self::{
+    cache::CacheHandle,
+    http::HttpHandle,
+    limit::{Permit, RequestLimiter},
+};
+pub use self::{
+    cache::{CachedResponse, EtagCache, MemoryCache},
+    export::ExportFormat,
+    inactive::{ArchiveFailure, ArchiveReport},
+    offboard::{OffboardOptions, OffboardReport, OffboardStep, OffboardStepResult},
+    permissions::Permissions,
+    query::{Query, QueryValue, UserSort},
+    reconcile::{MembershipAction, MembershipChanges, MembershipFailure},
+    search::SearchTerms,
+};
 use crate::{
-    error::{ErrorKind, Result, ResultExt},
-    websocket::Post,
+    clock::ClockSkew,
+    error::{Error, Result, ResultExt},
+    ids::{ChannelId, PostId, TeamId, UserId},
+    websocket::{
+        ChannelMember, ClientFeatures, ClientLicense, Config, Emoji, Events, Post, PostProps, Team,
+        UserStatus,
+    },
 };
-use chrono::prelude::{DateTime, Utc};
+use chrono::prelude::{DateTime, FixedOffset, TimeZone, Utc};
 use log::debug;
-use reqwest::{Client as WebClient, StatusCode};
-use serde::{Deserialize, Serialize};
-use std::{collections::HashSet, fmt, str::FromStr};
+use reqwest::{
+    header::{HeaderMap, ETAG, IF_NONE_MATCH},
+    multipart::{Form, Part},
+    Client as WebClient, Response, StatusCode,
+};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::{
+    cmp::{Ordering, Reverse},
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
+    fmt,
+    hash::{Hash, Hasher},
+    io::{Read, Write},
+    str::FromStr,
+    sync::Arc,
+    time::Duration,
+};
 use url::Url;
 
-#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
+/// Clients compare by their server and token, the other settings are ignored
+///
+/// ```
+/// use mattermost_structs::api::Client;
+///
+/// let client = Client::new("https://chat.example.com", "token")?;
+/// assert_eq!(client, Client::new("https://chat.example.com", "token")?);
+/// assert_ne!(client, Client::new("https://chat.example.com", "other")?);
+/// # Ok::<(), mattermost_structs::Error>(())
+/// ```
+#[derive(Clone, Debug)]
 pub struct Client {
     base_url: Url,
     token: String,
+    /// Shared by all clones of the client
+    limiter: Option<Arc<RequestLimiter>>,
+    etag_cache: Option<CacheHandle>,
+    /// Shared by all clones of the client
+    http: HttpHandle,
+    /// Applied to the HTTP client of [`Client::to_async`]
+    #[cfg(feature = "async")]
+    timeout: Option<Duration>,
+    #[cfg(feature = "async")]
+    connect_timeout: Option<Duration>,
+    clock: ClockSkew,
+    read_only: bool,
+}
+
+/// Builder for [`Client`] with non-default settings
+#[derive(Clone, Debug)]
+pub struct ClientBuilder {
+    base_url: String,
+    token: String,
+    max_concurrent_requests: Option<usize>,
+    etag_cache: Option<CacheHandle>,
+    http_client: Option<WebClient>,
+    timeout: Option<Duration>,
+    connect_timeout: Option<Duration>,
+    clock: Option<ClockSkew>,
+    read_only: bool,
+}
+
+impl ClientBuilder {
+    /// Block requests while `max` requests are in flight
+    ///
+    /// The limit is shared by all clones of the client and prevents bursts, e.g., resolving many users after a reconnect, from opening many connections at once.
+    /// By default requests are not limited.
+    pub fn max_concurrent_requests(mut self, max: usize) -> Self {
+        self.max_concurrent_requests = Some(max);
+        self
+    }
+
+    /// Cache the responses of users, channels, and the config, and revalidate them with `If-None-Match`
+    ///
+    /// If the server answers with `304 Not Modified`, the cached response is returned instead.
+    pub fn etag_cache(mut self, cache: Arc<dyn EtagCache>) -> Self {
+        self.etag_cache = Some(CacheHandle(cache));
+        self
+    }
+
+    /// Abort requests which take longer than `timeout` in total
+    ///
+    /// Defaults to 30 seconds. Timeouts fail with [`Error::Timeout`] in [`Client::check_token`].
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Abort requests if the connection to the server cannot be established within `timeout`
+    pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.connect_timeout = Some(timeout);
+        self
+    }
+
+    /// Share the measured clock skew with other clients, see [`Client::clock`]
+    pub fn clock(mut self, clock: ClockSkew) -> Self {
+        self.clock = Some(clock);
+        self
+    }
+
+    /// Send the requests with `client`, e.g., to configure a proxy or additional root certificates
+    ///
+    /// The [`timeout`](ClientBuilder::timeout) and [`connect_timeout`](ClientBuilder::connect_timeout) of the builder are not applied to `client`.
+    /// By default a new HTTP client is created, which is then shared by all clones of the [`Client`].
+    pub fn http_client(mut self, client: WebClient) -> Self {
+        self.http_client = Some(client);
+        self
+    }
+
+    /// Refuse all requests which change anything on the server, see [`Client::read_only`]
+    pub fn read_only(mut self, read_only: bool) -> Self {
+        self.read_only = read_only;
+        self
+    }
+
+    /// Log in with a username or email address and password, and use the returned session token
+    ///
+    /// `mfa_token` is the current code of the authenticator app, which is required for users with multi-factor authentication.
+    /// The token passed to [`Client::builder`] is ignored.
+    /// Rejected credentials fail with [`Error::LoginFailed`], containing the reason given by the server.
+    pub fn login(self, login_id: &str, password: &str, mfa_token: Option<&str>) -> Result<Client> {
+        let mut client = self.build()?;
+        let url = client.base_url.join("/api/v4/users/login")?;
+        let mut res = {
+            let _permit = client.acquire_permit();
+            client
+                .web_client()
+                .post(url)
+                .json(&LoginRequest {
+                    login_id,
+                    password,
+                    token: mfa_token,
+                })
+                .send()
+                .chain_err(|| "Failed to send webrequest")?
+        };
+        debug!("login response {}", res.status());
+
+        if let StatusCode::BAD_REQUEST | StatusCode::UNAUTHORIZED = res.status() {
+            let reason = res
+                .json::<ServerMessage>()
+                .map(|body| body.message)
+                .unwrap_or_else(|_| format!("status {}", res.status()));
+            return Err(Error::LoginFailed(reason));
+        }
+        let mut res = client.check_status(res)?;
+        let token = res
+            .headers()
+            .get("Token")
+            .and_then(|token| token.to_str().ok())
+            .map(ToString::to_string)
+            .ok_or("The server did not return a session token")?;
+        // Drain the body (the logged in user), such that the connection can be reused
+        res.copy_to(&mut std::io::sink())?;
+        client.token = token;
+        Ok(client)
+    }
+
+    pub fn build(self) -> Result<Client> {
+        let http = match self.http_client {
+            Some(client) => client,
+            None => {
+                let mut builder = WebClient::builder();
+                if let Some(timeout) = self.timeout {
+                    builder = builder.timeout(timeout);
+                }
+                if let Some(timeout) = self.connect_timeout {
+                    builder = builder.connect_timeout(timeout);
+                }
+                builder.build()?
+            }
+        };
+        Ok(Client {
+            base_url: Url::parse(&self.base_url)?,
+            token: self.token,
+            limiter: self
+                .max_concurrent_requests
+                .map(|max| Arc::new(RequestLimiter::new(max))),
+            etag_cache: self.etag_cache,
+            http: HttpHandle(Arc::new(http)),
+            #[cfg(feature = "async")]
+            timeout: self.timeout,
+            #[cfg(feature = "async")]
+            connect_timeout: self.connect_timeout,
+            clock: self.clock.unwrap_or_default(),
+            read_only: self.read_only,
+        })
+    }
+}
+
+impl PartialEq for Client {
+    fn eq(&self, other: &Self) -> bool {
+        self.base_url == other.base_url && self.token == other.token
+    }
+}
+
+impl Eq for Client {}
+
+impl PartialOrd for Client {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Client {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (&self.base_url, &self.token).cmp(&(&other.base_url, &other.token))
+    }
+}
+
+impl Hash for Client {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.base_url.hash(state);
+        self.token.hash(state);
+    }
 }
 
 impl Client {
@@ -21,94 +260,340 @@ impl Client {
         B: AsRef<str>,
         T: Into<String>,
     {
-        Ok(Client {
-            base_url: Url::parse(base_url.as_ref())?,
+        Self::builder(base_url, token).build()
+    }
+
+    pub fn builder<B, T>(base_url: B, token: T) -> ClientBuilder
+    where
+        B: AsRef<str>,
+        T: Into<String>,
+    {
+        ClientBuilder {
+            base_url: base_url.as_ref().to_string(),
             token: token.into(),
-        })
+            max_concurrent_requests: None,
+            etag_cache: None,
+            http_client: None,
+            timeout: None,
+            connect_timeout: None,
+            clock: None,
+            read_only: false,
+        }
+    }
+
+    /// Log in with a username or email address and password, see [`ClientBuilder::login`]
+    pub fn login<B>(base_url: B, login_id: &str, password: &str) -> Result<Client>
+    where
+        B: AsRef<str>,
+    {
+        Self::builder(base_url, String::new()).login(login_id, password, None)
+    }
+
+    /// Log in like [`Client::login`], for users with multi-factor authentication
+    pub fn login_with_mfa<B>(
+        base_url: B,
+        login_id: &str,
+        password: &str,
+        mfa_token: &str,
+    ) -> Result<Client>
+    where
+        B: AsRef<str>,
+    {
+        Self::builder(base_url, String::new()).login(login_id, password, Some(mfa_token))
+    }
+
+    pub fn base_url(&self) -> &Url {
+        &self.base_url
+    }
+
+    /// The access token used for authentication, e.g., the session token after [`Client::login`]
+    pub fn token(&self) -> &str {
+        &self.token
+    }
+
+    /// Websocket client for the same server and token
+    pub fn websocket(&self) -> Result<crate::websocket::Client> {
+        crate::websocket::Client::new(&self.base_url, self.token.clone())
+    }
+
+    /// A clone of the client which refuses all requests that change anything on the server
+    ///
+    /// Creating, updating, and deleting fails with [`Error::ReadOnly`] without sending a request.
+    /// This guarantees that archival and audit tools cannot write, even when run with an admin token.
+    ///
+    /// ```
+    /// # fn main() -> mattermost_structs::Result<()> {
+    /// use mattermost_structs::{
+    ///     api::{Client, CreatePostRequest},
+    ///     error::Error,
+    /// };
+    ///
+    /// let client = Client::new("https://chat.example.com", "token")?.read_only();
+    /// let post = CreatePostRequest::builder()
+    ///     .channel_id("4xp9fdt77pncbef59f4k1qe83o")
+    ///     .message("Hello")
+    ///     .build()?;
+    /// match client.create_post(&post) {
+    ///     Err(err) => assert!(matches!(err, Error::ReadOnly(_))),
+    ///     Ok(_) => unreachable!(),
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn read_only(&self) -> Client {
+        Client {
+            read_only: true,
+            ..self.clone()
+        }
+    }
+
+    pub fn is_read_only(&self) -> bool {
+        self.read_only
+    }
+
+    /// Fail if the client is read-only, call first in every endpoint which changes anything
+    fn check_writable(&self, name: &str) -> Result<()> {
+        if self.read_only {
+            return Err(Error::ReadOnly(name.to_string()));
+        }
+        Ok(())
+    }
+
+    /// Maximal number of concurrent requests, if limited
+    pub fn max_concurrent_requests(&self) -> Option<usize> {
+        self.limiter.as_ref().map(|limiter| limiter.limit())
+    }
+
+    /// HTTP client with the configured timeouts, which keeps the connections alive between requests
+    fn web_client(&self) -> &WebClient {
+        &self.http.0
+    }
+
+    /// Offset of the server clock, measured with the `Date` header of the responses
+    ///
+    /// Clones of the client share the measurement.
+    pub fn clock(&self) -> &ClockSkew {
+        &self.clock
+    }
+
+    /// Server time minus local time, if any response was received yet
+    pub fn clock_skew(&self) -> Option<chrono::Duration> {
+        self.clock.skew()
+    }
+
+    /// Turn the error status codes of the API into errors, including the error sent by the server
+    fn check_status(&self, mut res: Response) -> Result<Response> {
+        match check_response(&self.clock, res.status(), res.headers()) {
+            Err(Error::Api { status, .. }) => {
+                let body = res.text().unwrap_or_default();
+                Err(Error::from_response(
+                    status,
+                    res.status().canonical_reason(),
+                    &body,
+                ))
+            }
+            result => result.map(|()| res),
+        }
+    }
+
+    /// Wait until the request limit allows another request
+    fn acquire_permit(&self) -> Option<Permit<'_>> {
+        self.limiter.as_ref().map(|limiter| limiter.acquire())
+    }
+
+    /// GET request which is revalidated with the ETag cache, if there is one
+    fn get_cached<T>(&self, url: Url, name: &str) -> Result<T>
+    where
+        T: DeserializeOwned,
+    {
+        let client = self.web_client();
+        let _permit = self.acquire_permit();
+        let cached = self
+            .etag_cache
+            .as_ref()
+            .and_then(|cache| cache.0.get(url.as_str()));
+        let mut req = client
+            .get(url.clone())
+            .header("authorization", format!("bearer {}", self.token));
+        if let Some(cached) = &cached {
+            req = req.header(IF_NONE_MATCH, cached.etag.as_str());
+        }
+        let res = req.send().chain_err(|| "Failed to send webrequest")?;
+        debug!("{} response {}", name, res.status());
+
+        if let (StatusCode::NOT_MODIFIED, Some(cached)) = (res.status(), &cached) {
+            return Ok(serde_json::from_slice(&cached.body)?);
+        }
+        let mut res = self.check_status(res)?;
+        let etag = res
+            .headers()
+            .get(ETAG)
+            .and_then(|etag| etag.to_str().ok())
+            .map(ToString::to_string);
+        let mut body = Vec::new();
+        res.copy_to(&mut body)?;
+        let value = serde_json::from_slice(&body)?;
+        if let (Some(cache), Some(etag)) = (&self.etag_cache, etag) {
+            cache.0.put(url.as_str(), CachedResponse { etag, body });
+        }
+        Ok(value)
+    }
+
+    /// Check the token by fetching the user it belongs to.
+    ///
+    /// Returns the reason why the token was rejected, if any.
+    pub fn check_token(&self) -> Result<()> {
+        match self.get_me() {
+            Ok(_) => Ok(()),
+            Err(ref err) if err.is_timeout() => Err(Error::Timeout(format!(
+                "validating the token at {}",
+                self.base_url
+            ))),
+            Err(err) => Err(err).chain_err(|| "Token validation failed"),
+        }
     }
 
     pub fn is_token_valid(&self) -> bool {
-        self.get_users(0, 0).is_ok()
+        self.check_token().is_ok()
+    }
+
+    pub fn get_me(&self) -> Result<User> {
+        let url = self.base_url.join("/api/v4/users/me")?;
+        self.get_cached(url, "get_me")
     }
 
     pub fn get_users(&self, page: usize, per_page: usize) -> Result<Vec<User>> {
-        let client = WebClient::new();
         let mut url = self.base_url.join("/api/v4/users")?;
-        url.query_pairs_mut()
-            .append_pair("page", &page.to_string())
-            .append_pair("per_page", &per_page.to_string());
-        let mut res = client
-            .get(url)
+        Query::new().page(page, per_page).apply(&mut url);
+        self.get_cached(url, "get_users")
+    }
+
+    /// Get a page of the members of a channel as users, in the order of `sort`
+    ///
+    /// Only [`UserSort::Status`] and [`UserSort::Admin`] are supported, `None` orders by username.
+    pub fn get_users_in_channel<S>(
+        &self,
+        channel_id: S,
+        sort: Option<UserSort>,
+        page: usize,
+        per_page: usize,
+    ) -> Result<Vec<User>>
+    where
+        S: AsRef<ChannelId>,
+    {
+        let mut url = self.base_url.join("/api/v4/users")?;
+        Query::new()
+            .param("in_channel", channel_id.as_ref().as_str())
+            .opt_param("sort", sort)
+            .page(page, per_page)
+            .apply(&mut url);
+        self.get_cached(url, "get_users_in_channel")
+    }
+
+    pub fn get_users_by_id(&self, ids: &[UserId]) -> Result<Vec<User>> {
+        let client = self.web_client();
+        let _permit = self.acquire_permit();
+        let url = self.base_url.join("/api/v4/users/ids")?;
+        let res = client
+            .post(url)
             .header("authorization", format!("bearer {}", self.token))
+            .json(&ids)
             .send()
             .chain_err(|| "Failed to send webrequest")?;
 
-        match res.status() {
-            // 400
-            StatusCode::BAD_REQUEST => Err(ErrorKind::InvalidOrMissingParameter.into()),
-            // 401
-            StatusCode::UNAUTHORIZED => Err(ErrorKind::MissingAccessToken.into()),
-            // 403
-            StatusCode::FORBIDDEN => Err(ErrorKind::MissingPermissions.into()),
-            // 200
-            // StatusCode::Ok => Ok(res.json()?),
-            _ => Ok(res.json()?),
-        }
+        Ok(self.check_status(res)?.json()?)
     }
 
-    pub fn get_users_by_id(&self, ids: &[String]) -> Result<Vec<User>> {
-        let client = WebClient::new();
-        let url = self.base_url.join("/api/v4/users/ids")?;
-        let mut res = client
+    /// Like [`Client::get_users_by_id`], but only returns the users which changed after `since`
+    pub fn get_users_by_id_since(&self, ids: &[UserId], since: DateTime<Utc>) -> Result<Vec<User>> {
+        let client = self.web_client();
+        let _permit = self.acquire_permit();
+        let mut url = self.base_url.join("/api/v4/users/ids")?;
+        Query::new().param("since", since).apply(&mut url);
+        let res = client
             .post(url)
             .header("authorization", format!("bearer {}", self.token))
             .json(&ids)
             .send()
             .chain_err(|| "Failed to send webrequest")?;
+        debug!("get_users_by_id_since response {}", res.status());
 
-        match res.status() {
-            // 400
-            StatusCode::BAD_REQUEST => Err(ErrorKind::InvalidOrMissingParameter.into()),
-            // 401
-            StatusCode::UNAUTHORIZED => Err(ErrorKind::MissingAccessToken.into()),
-            // 403
-            StatusCode::FORBIDDEN => Err(ErrorKind::MissingPermissions.into()),
-            // 200
-            // StatusCode::Ok => Ok(res.json()?),
-            _ => Ok(res.json()?),
-        }
+        Ok(self.check_status(res)?.json()?)
     }
 
-    pub fn get_channel_by_id<S>(&self, id: S) -> Result<Channel>
+    /// Look up several users by their usernames with a single request
+    ///
+    /// Unknown usernames are skipped, so the result may contain fewer users than requested.
+    pub fn get_users_by_usernames<S>(&self, usernames: &[S]) -> Result<Vec<User>>
     where
         S: AsRef<str>,
     {
-        let client = WebClient::new();
-        let url = self.base_url.join("/api/v4/channels/")?.join(id.as_ref())?;
-        let mut res = client
+        let usernames: Vec<&str> = usernames.iter().map(AsRef::as_ref).collect();
+        let client = self.web_client();
+        let _permit = self.acquire_permit();
+        let url = self.base_url.join("/api/v4/users/usernames")?;
+        let res = client
+            .post(url)
+            .header("authorization", format!("bearer {}", self.token))
+            .json(&usernames)
+            .send()
+            .chain_err(|| "Failed to send webrequest")?;
+        debug!("get_users_by_usernames response {}", res.status());
+
+        Ok(self.check_status(res)?.json()?)
+    }
+
+    pub fn get_user_status<S>(&self, user_id: S) -> Result<UserStatus>
+    where
+        S: AsRef<UserId>,
+    {
+        let client = self.web_client();
+        let _permit = self.acquire_permit();
+        let url = self
+            .base_url
+            .join(&format!("/api/v4/users/{}/status", user_id.as_ref()))?;
+        let res = client
             .get(url)
             .header("authorization", format!("bearer {}", self.token))
             .send()
             .chain_err(|| "Failed to send webrequest")?;
-        debug!("get_channel_by_id response {}", res.status());
+        debug!("get_user_status response {}", res.status());
 
-        match res.status() {
-            // 400
-            StatusCode::BAD_REQUEST => Err(ErrorKind::InvalidOrMissingParameter.into()),
-            // 401
-            StatusCode::UNAUTHORIZED => Err(ErrorKind::MissingAccessToken.into()),
-            // 403
-            StatusCode::FORBIDDEN => Err(ErrorKind::MissingPermissions.into()),
-            // 200
-            // StatusCode::Ok => Ok(res.json()?),
-            _ => Ok(res.json()?),
-        }
+        Ok(self.check_status(res)?.json()?)
+    }
+
+    /// The statuses of many users at once
+    ///
+    /// Users without a known status are reported as [`Status::Offline`](crate::websocket::Status::Offline).
+    pub fn get_statuses_by_ids(&self, ids: &[UserId]) -> Result<Vec<UserStatus>> {
+        let client = self.web_client();
+        let _permit = self.acquire_permit();
+        let url = self.base_url.join("/api/v4/users/status/ids")?;
+        let res = client
+            .post(url)
+            .header("authorization", format!("bearer {}", self.token))
+            .json(&ids)
+            .send()
+            .chain_err(|| "Failed to send webrequest")?;
+        debug!("get_statuses_by_ids response {}", res.status());
+
+        Ok(self.check_status(res)?.json()?)
+    }
+
+    pub fn get_channel_by_id<S>(&self, id: S) -> Result<Channel>
+    where
+        S: AsRef<ChannelId>,
+    {
+        let url = self.base_url.join("/api/v4/channels/")?.join(id.as_ref())?;
+        self.get_cached(url, "get_channel_by_id")
     }
 
     pub fn create_post(&self, post: &CreatePostRequest) -> Result<Post> {
-        let client = WebClient::new();
+        self.check_writable("create_post")?;
+        let client = self.web_client();
+        let _permit = self.acquire_permit();
         let url = self.base_url.join("/api/v4/posts")?;
-        let mut res = client
+        let res = client
             .post(url)
             .header("authorization", format!("bearer {}", self.token))
             .json(&post)
@@ -116,131 +601,2443 @@ impl Client {
             .chain_err(|| "Failed to send webrequest")?;
         debug!("create_post response {}", res.status());
 
-        match res.status() {
-            // 400
-            StatusCode::BAD_REQUEST => Err(ErrorKind::InvalidOrMissingParameter.into()),
-            // 401
-            StatusCode::UNAUTHORIZED => Err(ErrorKind::MissingAccessToken.into()),
-            // 403
-            StatusCode::FORBIDDEN => Err(ErrorKind::MissingPermissions.into()),
-            // 200
-            // StatusCode::Ok => Ok(res.json()?),
-            _ => Ok(res.json()?),
-        }
+        Ok(self.check_status(res)?.json()?)
     }
-}
 
-#[derive(Debug, Deserialize, Serialize, Clone, Eq, PartialEq)]
-#[serde(deny_unknown_fields)]
-pub struct User {
-    pub id: String,
-    #[serde(with = "crate::serialize::ts_seconds")]
-    pub create_at: DateTime<Utc>,
-    #[serde(with = "crate::serialize::ts_seconds")]
-    pub update_at: DateTime<Utc>,
-    #[serde(with = "crate::serialize::ts_seconds")]
-    pub delete_at: DateTime<Utc>,
-    pub username: String,
-    pub first_name: String,
-    pub last_name: String,
-    pub nickname: String,
-    pub email: String,
-    pub email_verified: Option<bool>,
-    pub auth_data: String,
-    pub auth_service: String,
-    pub position: String,
-    #[serde(with = "::serde_with::rust::StringWithSeparator::<::serde_with::SpaceSeparator>")]
-    pub roles: HashSet<UserRole>,
-    // pub roles: UserRole,
-    pub locale: String,
-    // pub notify_props: {},
-    // pub props: {},
-    #[serde(
-        skip_serializing_if = "Option::is_none",
-        with = "crate::serialize::option_ts_milliseconds",
-        default
-    )]
-    pub last_password_update: Option<DateTime<Utc>>,
-    #[serde(
-        skip_serializing_if = "Option::is_none",
-        with = "crate::serialize::option_ts_milliseconds",
-        default
-    )]
-    pub last_picture_update: Option<DateTime<Utc>>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub failed_attempts: Option<u64>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub mfa_active: Option<bool>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub timezone: Option<Timezone>,
-}
+    /// Get a single post by its id
+    pub fn get_post<S>(&self, post_id: S) -> Result<Post>
+    where
+        S: AsRef<PostId>,
+    {
+        let client = self.web_client();
+        let _permit = self.acquire_permit();
+        let url = self
+            .base_url
+            .join(&format!("/api/v4/posts/{}", post_id.as_ref()))?;
+        let res = client
+            .get(url)
+            .header("authorization", format!("bearer {}", self.token))
+            .send()
+            .chain_err(|| "Failed to send webrequest")?;
+        debug!("get_post response {}", res.status());
 
-#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Default, Deserialize, Serialize)]
-#[serde(rename_all = "camelCase")]
-pub struct Timezone {
-    automatic_timezone: String,
-    manual_timezone: String,
-    #[serde(with = "::serde_with::rust::display_fromstr")]
-    use_automatic_timezone: bool,
-}
+        Ok(self.check_status(res)?.json()?)
+    }
 
-#[derive(Debug, Eq, Hash, PartialEq, Copy, Clone, Ord, PartialOrd)]
-pub enum UserRole {
-    SystemUser,
-    SystemAdmin,
-    ChannelUser,
-    ChannelAdmin,
-}
+    /// Link to the post, which opens it in the web app
+    ///
+    /// Permalinks contain the name of the team.
+    /// Posts in direct and group messages belong to no team, so the first team of the current user is used.
+    pub fn get_permalink<S>(&self, post_id: S) -> Result<String>
+    where
+        S: AsRef<PostId>,
+    {
+        let post = self.get_post(post_id.as_ref())?;
+        let channel = self.get_channel_by_id(&post.channel_id)?;
+        let team_name = self.team_name_of(&channel)?;
+        Ok(crate::permalink::of_post(&self.base_url, &team_name, &post)?.into_string())
+    }
 
-impl fmt::Display for UserRole {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match *self {
-            UserRole::SystemUser => write!(f, "system_user"),
-            UserRole::SystemAdmin => write!(f, "system_admin"),
-            UserRole::ChannelUser => write!(f, "channel_user"),
-            UserRole::ChannelAdmin => write!(f, "channel_admin"),
+    /// Name of the team of the channel, or of the first team of the user for channels without team
+    pub(crate) fn team_name_of(&self, channel: &Channel) -> Result<String> {
+        if !channel.team_id.is_empty() {
+            return Ok(self.get_team(&channel.team_id)?.name);
         }
+        self.get_my_teams()?
+            .into_iter()
+            .next()
+            .map(|team| team.name)
+            .ok_or_else(|| "The user is in no team, which is required for permalinks".into())
     }
-}
 
-impl FromStr for UserRole {
-    type Err = String;
+    /// Change some fields of a post, leaving the others untouched
+    pub fn patch_post<S>(&self, post_id: S, patch: &PostPatch) -> Result<Post>
+    where
+        S: AsRef<PostId>,
+    {
+        self.check_writable("patch_post")?;
+        let client = self.web_client();
+        let _permit = self.acquire_permit();
+        let url = self
+            .base_url
+            .join(&format!("/api/v4/posts/{}/patch", post_id.as_ref()))?;
+        let res = client
+            .put(url)
+            .header("authorization", format!("bearer {}", self.token))
+            .json(patch)
+            .send()
+            .chain_err(|| "Failed to send webrequest")?;
+        debug!("patch_post response {}", res.status());
 
-    fn from_str(s: &str) -> ::std::result::Result<Self, Self::Err> {
-        match s {
-            "system_user" => Ok(UserRole::SystemUser),
-            "system_admin" => Ok(UserRole::SystemAdmin),
-            "channel_user" => Ok(UserRole::ChannelUser),
-            "channel_admin" => Ok(UserRole::ChannelAdmin),
-            _ => Err(format!(
-                "Unexpected value '{}', expected one of 'system_user', 'system_admin'",
-                s
-            )),
-        }
+        Ok(self.check_status(res)?.json()?)
     }
-}
 
-#[derive(Debug, Deserialize, Serialize, Clone, Ord, PartialOrd, Eq, PartialEq)]
-#[serde(rename_all = "snake_case")]
-pub struct Channel {
-    id: String,
-    #[serde(with = "crate::serialize::ts_seconds")]
-    pub create_at: DateTime<Utc>,
-    #[serde(with = "crate::serialize::ts_seconds")]
-    pub update_at: DateTime<Utc>,
-    #[serde(with = "crate::serialize::ts_seconds")]
-    pub delete_at: DateTime<Utc>,
-    pub team_id: String,
-    #[serde(rename = "type")]
-    pub type_: ChannelType,
-    pub display_name: String,
-    pub header: String,
-    #[serde(with = "crate::serialize::ts_seconds")]
-    pub last_post_at: DateTime<Utc>,
-    pub total_msg_count: u64,
+    pub fn delete_post<S>(&self, post_id: S) -> Result<()>
+    where
+        S: AsRef<PostId>,
+    {
+        self.check_writable("delete_post")?;
+        let client = self.web_client();
+        let _permit = self.acquire_permit();
+        let url = self
+            .base_url
+            .join(&format!("/api/v4/posts/{}", post_id.as_ref()))?;
+        let res = client
+            .delete(url)
+            .header("authorization", format!("bearer {}", self.token))
+            .send()
+            .chain_err(|| "Failed to send webrequest")?;
+        debug!("delete_post response {}", res.status());
+
+        self.check_status(res)?;
+        Ok(())
+    }
+
+    /// Upload a file to a channel
+    ///
+    /// The file is not visible until a post references its id in [`CreatePostRequest::file_ids`].
+    ///
+    /// ```no_run
+    /// # fn main() -> mattermost_structs::Result<()> {
+    /// use mattermost_structs::{
+    ///     api::{Client, CreatePostRequest},
+    ///     ids::ChannelId,
+    /// };
+    ///
+    /// let client = Client::new("https://chat.example.com", "token")?;
+    /// let channel_id = ChannelId::from("4xp9fdt77pncbef59f4k1qe83o");
+    /// let files = client.upload_file(&channel_id, "report.csv", b"id,name\n".to_vec())?;
+    /// let post = CreatePostRequest::builder()
+    ///     .channel_id(channel_id)
+    ///     .message("The latest report")
+    ///     .file_ids(files.into_iter().map(|file| file.id))
+    ///     .build()?;
+    /// client.create_post(&post)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn upload_file<C, F>(
+        &self,
+        channel_id: C,
+        filename: F,
+        data: Vec<u8>,
+    ) -> Result<Vec<FileInfo>>
+    where
+        C: AsRef<ChannelId>,
+        F: Into<String>,
+    {
+        self.upload_part(
+            channel_id.as_ref(),
+            Part::bytes(data).file_name(filename.into()),
+        )
+    }
+
+    /// Like [`Client::upload_file`], but streams the content from `reader`
+    ///
+    /// The file is never completely kept in memory, so it is suited for large files.
+    pub fn upload_file_from_reader<C, F, R>(
+        &self,
+        channel_id: C,
+        filename: F,
+        reader: R,
+    ) -> Result<Vec<FileInfo>>
+    where
+        C: AsRef<ChannelId>,
+        F: Into<String>,
+        R: Read + Send + 'static,
+    {
+        self.upload_part(
+            channel_id.as_ref(),
+            Part::reader(reader).file_name(filename.into()),
+        )
+    }
+
+    fn upload_part(&self, channel_id: &ChannelId, part: Part) -> Result<Vec<FileInfo>> {
+        #[derive(Deserialize)]
+        struct FileUploadResponse {
+            file_infos: Vec<FileInfo>,
+        }
+
+        self.check_writable("upload_file")?;
+        let client = self.web_client();
+        let _permit = self.acquire_permit();
+        let url = self.base_url.join("/api/v4/files")?;
+        let form = Form::new()
+            .text("channel_id", channel_id.to_string())
+            .part("files", part);
+        let res = client
+            .post(url)
+            .header("authorization", format!("bearer {}", self.token))
+            .multipart(form)
+            .send()
+            .chain_err(|| "Failed to send webrequest")?;
+        debug!("upload_file response {}", res.status());
+
+        let response: FileUploadResponse = self.check_status(res)?.json()?;
+        Ok(response.file_infos)
+    }
+
+    /// Write the content of a file to `writer`
+    ///
+    /// Returns the number of written bytes.
+    pub fn get_file<S, W>(&self, file_id: S, mut writer: W) -> Result<u64>
+    where
+        S: AsRef<str>,
+        W: Write,
+    {
+        let url = self
+            .base_url
+            .join(&format!("/api/v4/files/{}", file_id.as_ref()))?;
+        self.download(url, "get_file", &mut writer)
+    }
+
+    /// Download the small thumbnail of an image file, a JPEG of at most 120x100 pixels
+    ///
+    /// Only images have a thumbnail, see [`FileInfo::has_preview_image`].
+    /// Returns the number of bytes written.
+    pub fn get_file_thumbnail<S, W>(&self, file_id: S, mut writer: W) -> Result<u64>
+    where
+        S: AsRef<str>,
+        W: Write,
+    {
+        let url = self
+            .base_url
+            .join(&format!("/api/v4/files/{}/thumbnail", file_id.as_ref()))?;
+        self.download(url, "get_file_thumbnail", &mut writer)
+    }
+
+    /// Download the preview of an image file, a JPEG at most 1920 pixels wide
+    ///
+    /// Only images have a preview, see [`FileInfo::has_preview_image`].
+    /// Returns the number of bytes written.
+    pub fn get_file_preview<S, W>(&self, file_id: S, mut writer: W) -> Result<u64>
+    where
+        S: AsRef<str>,
+        W: Write,
+    {
+        let url = self
+            .base_url
+            .join(&format!("/api/v4/files/{}/preview", file_id.as_ref()))?;
+        self.download(url, "get_file_preview", &mut writer)
+    }
+
+    /// Stream the body of a GET request into `writer`
+    fn download<W>(&self, url: Url, name: &str, writer: &mut W) -> Result<u64>
+    where
+        W: Write,
+    {
+        let client = self.web_client();
+        let _permit = self.acquire_permit();
+        let res = client
+            .get(url)
+            .header("authorization", format!("bearer {}", self.token))
+            .send()
+            .chain_err(|| "Failed to send webrequest")?;
+        debug!("{} response {}", name, res.status());
+
+        Ok(self.check_status(res)?.copy_to(writer)?)
+    }
+
+    /// Public link to a file, which works without logging in
+    ///
+    /// Fails unless public links are enabled on the server.
+    pub fn get_file_link<S>(&self, file_id: S) -> Result<String>
+    where
+        S: AsRef<str>,
+    {
+        #[derive(Deserialize)]
+        struct FileLinkResponse {
+            link: String,
+        }
+
+        let client = self.web_client();
+        let _permit = self.acquire_permit();
+        let url = self
+            .base_url
+            .join(&format!("/api/v4/files/{}/link", file_id.as_ref()))?;
+        let res = client
+            .get(url)
+            .header("authorization", format!("bearer {}", self.token))
+            .send()
+            .chain_err(|| "Failed to send webrequest")?;
+        debug!("get_file_link response {}", res.status());
+
+        let response: FileLinkResponse = self.check_status(res)?.json()?;
+        Ok(response.link)
+    }
+
+    pub fn get_file_info<S>(&self, file_id: S) -> Result<FileInfo>
+    where
+        S: AsRef<str>,
+    {
+        let url = self
+            .base_url
+            .join(&format!("/api/v4/files/{}/info", file_id.as_ref()))?;
+        self.get_cached(url, "get_file_info")
+    }
+
+    /// The custom emoji with this name
+    ///
+    /// Fails with a 404 error for system emoji, which are built into the clients.
+    pub fn get_emoji_by_name<S>(&self, name: S) -> Result<Emoji>
+    where
+        S: AsRef<str>,
+    {
+        let url = self
+            .base_url
+            .join("/api/v4/emoji/name/")?
+            .join(name.as_ref())?;
+        self.get_cached(url, "get_emoji_by_name")
+    }
+
+    /// URL of the image of a custom emoji
+    ///
+    /// Downloading the image requires the same authorization as all other requests.
+    pub fn emoji_image_url(&self, emoji: &Emoji) -> Result<Url> {
+        Ok(self
+            .base_url
+            .join(&format!("/api/v4/emoji/{}/image", emoji.id))?)
+    }
+
+    pub fn get_team<S>(&self, team_id: S) -> Result<Team>
+    where
+        S: AsRef<TeamId>,
+    {
+        let url = self
+            .base_url
+            .join(&format!("/api/v4/teams/{}", team_id.as_ref()))?;
+        self.get_cached(url, "get_team")
+    }
+
+    /// The teams the current user is a member of
+    pub fn get_my_teams(&self) -> Result<Vec<Team>> {
+        let url = self.base_url.join("/api/v4/users/me/teams")?;
+        self.get_cached(url, "get_my_teams")
+    }
+
+    /// Remove a user from a team and all its channels
+    pub fn remove_team_member<T, U>(&self, team_id: T, user_id: U) -> Result<()>
+    where
+        T: AsRef<TeamId>,
+        U: AsRef<UserId>,
+    {
+        self.check_writable("remove_team_member")?;
+        let client = self.web_client();
+        let _permit = self.acquire_permit();
+        let url = self.base_url.join(&format!(
+            "/api/v4/teams/{}/members/{}",
+            team_id.as_ref(),
+            user_id.as_ref()
+        ))?;
+        let res = client
+            .delete(url)
+            .header("authorization", format!("bearer {}", self.token))
+            .send()
+            .chain_err(|| "Failed to send webrequest")?;
+        debug!("remove_team_member response {}", res.status());
+
+        self.check_status(res)?;
+        Ok(())
+    }
+
+    pub fn get_team_icon<S>(&self, team_id: S) -> Result<Vec<u8>>
+    where
+        S: AsRef<TeamId>,
+    {
+        let client = self.web_client();
+        let _permit = self.acquire_permit();
+        let url = self
+            .base_url
+            .join(&format!("/api/v4/teams/{}/image", team_id.as_ref()))?;
+        let res = client
+            .get(url)
+            .header("authorization", format!("bearer {}", self.token))
+            .send()
+            .chain_err(|| "Failed to send webrequest")?;
+        debug!("get_team_icon response {}", res.status());
+
+        let mut icon = Vec::new();
+        self.check_status(res)?.copy_to(&mut icon)?;
+        Ok(icon)
+    }
+
+    pub fn set_team_icon<S, F>(&self, team_id: S, filename: F, image: Vec<u8>) -> Result<()>
+    where
+        S: AsRef<TeamId>,
+        F: Into<String>,
+    {
+        self.check_writable("set_team_icon")?;
+        let client = self.web_client();
+        let _permit = self.acquire_permit();
+        let url = self
+            .base_url
+            .join(&format!("/api/v4/teams/{}/image", team_id.as_ref()))?;
+        let form = Form::new().part("image", Part::bytes(image).file_name(filename.into()));
+        let res = client
+            .post(url)
+            .header("authorization", format!("bearer {}", self.token))
+            .multipart(form)
+            .send()
+            .chain_err(|| "Failed to send webrequest")?;
+        debug!("set_team_icon response {}", res.status());
+
+        self.check_status(res)?;
+        Ok(())
+    }
+
+    /// Get all bookmarks of a channel
+    ///
+    /// If `since` is set, only bookmarks changed after this point in time are returned, including deleted ones.
+    pub fn get_channel_bookmarks<S>(
+        &self,
+        channel_id: S,
+        since: Option<DateTime<Utc>>,
+    ) -> Result<Vec<ChannelBookmark>>
+    where
+        S: AsRef<ChannelId>,
+    {
+        let client = self.web_client();
+        let _permit = self.acquire_permit();
+        let mut url = self.base_url.join(&format!(
+            "/api/v4/channels/{}/bookmarks",
+            channel_id.as_ref()
+        ))?;
+        if let Some(since) = since {
+            Query::new().param("bookmarks_since", since).apply(&mut url);
+        }
+        let res = client
+            .get(url)
+            .header("authorization", format!("bearer {}", self.token))
+            .send()
+            .chain_err(|| "Failed to send webrequest")?;
+        debug!("get_channel_bookmarks response {}", res.status());
+
+        Ok(self.check_status(res)?.json()?)
+    }
+
+    pub fn create_channel_bookmark<S>(
+        &self,
+        channel_id: S,
+        bookmark: &CreateChannelBookmarkRequest,
+    ) -> Result<ChannelBookmark>
+    where
+        S: AsRef<ChannelId>,
+    {
+        self.check_writable("create_channel_bookmark")?;
+        let client = self.web_client();
+        let _permit = self.acquire_permit();
+        let url = self.base_url.join(&format!(
+            "/api/v4/channels/{}/bookmarks",
+            channel_id.as_ref()
+        ))?;
+        let res = client
+            .post(url)
+            .header("authorization", format!("bearer {}", self.token))
+            .json(bookmark)
+            .send()
+            .chain_err(|| "Failed to send webrequest")?;
+        debug!("create_channel_bookmark response {}", res.status());
+
+        Ok(self.check_status(res)?.json()?)
+    }
+
+    pub fn update_channel_bookmark<S, B>(
+        &self,
+        channel_id: S,
+        bookmark_id: B,
+        patch: &PatchChannelBookmarkRequest,
+    ) -> Result<UpdateChannelBookmarkResponse>
+    where
+        S: AsRef<ChannelId>,
+        B: AsRef<str>,
+    {
+        self.check_writable("update_channel_bookmark")?;
+        let client = self.web_client();
+        let _permit = self.acquire_permit();
+        let url = self.base_url.join(&format!(
+            "/api/v4/channels/{}/bookmarks/{}",
+            channel_id.as_ref(),
+            bookmark_id.as_ref()
+        ))?;
+        let res = client
+            .patch(url)
+            .header("authorization", format!("bearer {}", self.token))
+            .json(patch)
+            .send()
+            .chain_err(|| "Failed to send webrequest")?;
+        debug!("update_channel_bookmark response {}", res.status());
+
+        Ok(self.check_status(res)?.json()?)
+    }
+
+    /// Move a bookmark to position `sort_order`
+    ///
+    /// Returns all bookmarks of the channel whose sort order changed.
+    pub fn update_channel_bookmark_sort_order<S, B>(
+        &self,
+        channel_id: S,
+        bookmark_id: B,
+        sort_order: i64,
+    ) -> Result<Vec<ChannelBookmark>>
+    where
+        S: AsRef<ChannelId>,
+        B: AsRef<str>,
+    {
+        self.check_writable("update_channel_bookmark_sort_order")?;
+        let client = self.web_client();
+        let _permit = self.acquire_permit();
+        let url = self.base_url.join(&format!(
+            "/api/v4/channels/{}/bookmarks/{}/sort_order",
+            channel_id.as_ref(),
+            bookmark_id.as_ref()
+        ))?;
+        let res = client
+            .post(url)
+            .header("authorization", format!("bearer {}", self.token))
+            .json(&sort_order)
+            .send()
+            .chain_err(|| "Failed to send webrequest")?;
+        debug!(
+            "update_channel_bookmark_sort_order response {}",
+            res.status()
+        );
+
+        Ok(self.check_status(res)?.json()?)
+    }
+
+    pub fn delete_channel_bookmark<S, B>(
+        &self,
+        channel_id: S,
+        bookmark_id: B,
+    ) -> Result<ChannelBookmark>
+    where
+        S: AsRef<ChannelId>,
+        B: AsRef<str>,
+    {
+        self.check_writable("delete_channel_bookmark")?;
+        let client = self.web_client();
+        let _permit = self.acquire_permit();
+        let url = self.base_url.join(&format!(
+            "/api/v4/channels/{}/bookmarks/{}",
+            channel_id.as_ref(),
+            bookmark_id.as_ref()
+        ))?;
+        let res = client
+            .delete(url)
+            .header("authorization", format!("bearer {}", self.token))
+            .send()
+            .chain_err(|| "Failed to send webrequest")?;
+        debug!("delete_channel_bookmark response {}", res.status());
+
+        Ok(self.check_status(res)?.json()?)
+    }
+
+    /// Schedule a post to be sent at `scheduled_post.scheduled_at` (server version 10.3 and newer)
+    pub fn create_scheduled_post(
+        &self,
+        scheduled_post: &ScheduledPostRequest,
+    ) -> Result<ScheduledPost> {
+        self.check_writable("create_scheduled_post")?;
+        let client = self.web_client();
+        let _permit = self.acquire_permit();
+        let url = self.base_url.join("/api/v4/posts/schedule")?;
+        let res = client
+            .post(url)
+            .header("authorization", format!("bearer {}", self.token))
+            .json(scheduled_post)
+            .send()
+            .chain_err(|| "Failed to send webrequest")?;
+        debug!("create_scheduled_post response {}", res.status());
+
+        Ok(self.check_status(res)?.json()?)
+    }
+
+    /// Get the scheduled posts of the current user
+    ///
+    /// The posts are grouped by team id.
+    /// Posts in direct and group channels are listed under the key `directChannels`, if `include_direct_channels` is set.
+    pub fn get_scheduled_posts<S>(
+        &self,
+        team_id: S,
+        include_direct_channels: bool,
+    ) -> Result<HashMap<String, Vec<ScheduledPost>>>
+    where
+        S: AsRef<TeamId>,
+    {
+        let client = self.web_client();
+        let _permit = self.acquire_permit();
+        let mut url = self.base_url.join(&format!(
+            "/api/v4/posts/scheduled/team/{}",
+            team_id.as_ref()
+        ))?;
+        Query::new()
+            .param("includeDirectChannels", include_direct_channels)
+            .apply(&mut url);
+        let res = client
+            .get(url)
+            .header("authorization", format!("bearer {}", self.token))
+            .send()
+            .chain_err(|| "Failed to send webrequest")?;
+        debug!("get_scheduled_posts response {}", res.status());
+
+        Ok(self.check_status(res)?.json()?)
+    }
+
+    pub fn update_scheduled_post(&self, scheduled_post: &ScheduledPost) -> Result<ScheduledPost> {
+        self.check_writable("update_scheduled_post")?;
+        let client = self.web_client();
+        let _permit = self.acquire_permit();
+        let url = self
+            .base_url
+            .join(&format!("/api/v4/posts/schedule/{}", scheduled_post.id))?;
+        let res = client
+            .put(url)
+            .header("authorization", format!("bearer {}", self.token))
+            .json(scheduled_post)
+            .send()
+            .chain_err(|| "Failed to send webrequest")?;
+        debug!("update_scheduled_post response {}", res.status());
+
+        Ok(self.check_status(res)?.json()?)
+    }
+
+    pub fn delete_scheduled_post<S>(&self, scheduled_post_id: S) -> Result<ScheduledPost>
+    where
+        S: AsRef<str>,
+    {
+        self.check_writable("delete_scheduled_post")?;
+        let client = self.web_client();
+        let _permit = self.acquire_permit();
+        let url = self.base_url.join(&format!(
+            "/api/v4/posts/schedule/{}",
+            scheduled_post_id.as_ref()
+        ))?;
+        let res = client
+            .delete(url)
+            .header("authorization", format!("bearer {}", self.token))
+            .send()
+            .chain_err(|| "Failed to send webrequest")?;
+        debug!("delete_scheduled_post response {}", res.status());
+
+        Ok(self.check_status(res)?.json()?)
+    }
+
+    /// Ask the server to remind the current user about a post at `target_time`
+    ///
+    /// The reminder is delivered as a direct message from the system bot with the post type [`PostType::Reminder`](crate::websocket::PostType::Reminder).
+    pub fn set_post_reminder<S>(&self, post_id: S, target_time: DateTime<Utc>) -> Result<()>
+    where
+        S: AsRef<PostId>,
+    {
+        self.check_writable("set_post_reminder")?;
+        let client = self.web_client();
+        let _permit = self.acquire_permit();
+        let url = self.base_url.join(&format!(
+            "/api/v4/users/me/posts/{}/reminder",
+            post_id.as_ref()
+        ))?;
+        let res = client
+            .post(url)
+            .header("authorization", format!("bearer {}", self.token))
+            .json(&PostReminderRequest { target_time })
+            .send()
+            .chain_err(|| "Failed to send webrequest")?;
+        debug!("set_post_reminder response {}", res.status());
+
+        self.check_status(res)?;
+        Ok(())
+    }
+
+    pub fn get_my_channel_member<S>(&self, channel_id: S) -> Result<ChannelMember>
+    where
+        S: AsRef<ChannelId>,
+    {
+        let client = self.web_client();
+        let _permit = self.acquire_permit();
+        let url = self.base_url.join(&format!(
+            "/api/v4/channels/{}/members/me",
+            channel_id.as_ref()
+        ))?;
+        let res = client
+            .get(url)
+            .header("authorization", format!("bearer {}", self.token))
+            .send()
+            .chain_err(|| "Failed to send webrequest")?;
+        debug!("get_my_channel_member response {}", res.status());
+
+        Ok(self.check_status(res)?.json()?)
+    }
+
+    /// Mark a channel as read by the current user
+    ///
+    /// The server sends a [`Events::ChannelViewed`] event to all sessions of the user.
+    pub fn view_channel<S>(&self, channel_id: S) -> Result<()>
+    where
+        S: AsRef<ChannelId>,
+    {
+        #[derive(Serialize)]
+        struct ViewChannelRequest<'a> {
+            channel_id: &'a str,
+        }
+
+        self.check_writable("view_channel")?;
+        let client = self.web_client();
+        let _permit = self.acquire_permit();
+        let url = self.base_url.join("/api/v4/channels/members/me/view")?;
+        let res = client
+            .post(url)
+            .header("authorization", format!("bearer {}", self.token))
+            .json(&ViewChannelRequest {
+                channel_id: channel_id.as_ref(),
+            })
+            .send()
+            .chain_err(|| "Failed to send webrequest")?;
+        debug!("view_channel response {}", res.status());
+
+        self.check_status(res)?;
+        Ok(())
+    }
+
+    /// Get all roles the current user has in the channel
+    ///
+    /// This includes the explicitly assigned roles and the roles granted by the channel's scheme.
+    pub fn get_my_channel_roles<S>(&self, channel_id: S) -> Result<Vec<Role>>
+    where
+        S: AsRef<ChannelId>,
+    {
+        let channel = self.get_channel_by_id(channel_id)?;
+        let role_names = self.my_channel_role_names(&channel)?;
+        self.get_roles_by_names(&role_names)
+    }
+
+    /// Names of the roles the current user has in `channel`
+    pub(crate) fn my_channel_role_names(&self, channel: &Channel) -> Result<BTreeSet<String>> {
+        let member = self.get_my_channel_member(&channel.id)?;
+        let scheme = match channel.scheme_id {
+            Some(ref scheme_id) if !scheme_id.is_empty() => Some(self.get_scheme(scheme_id)?),
+            _ => None,
+        };
+
+        let mut role_names: BTreeSet<String> =
+            member.roles.iter().map(ToString::to_string).collect();
+        if member.scheme_user {
+            role_names.insert(scheme.as_ref().map_or_else(
+                || UserRole::ChannelUser.to_string(),
+                |s| s.default_channel_user_role.clone(),
+            ));
+        }
+        if member.scheme_admin {
+            role_names.insert(scheme.as_ref().map_or_else(
+                || UserRole::ChannelAdmin.to_string(),
+                |s| s.default_channel_admin_role.clone(),
+            ));
+        }
+        if member.scheme_guest == Some(true) {
+            role_names.insert(scheme.as_ref().map_or_else(
+                || UserRole::ChannelGuest.to_string(),
+                |s| s.default_channel_guest_role.clone(),
+            ));
+        }
+        Ok(role_names)
+    }
+
+    pub fn get_roles_by_names<S>(&self, names: &BTreeSet<S>) -> Result<Vec<Role>>
+    where
+        S: AsRef<str> + Ord + Serialize,
+    {
+        let client = self.web_client();
+        let _permit = self.acquire_permit();
+        let url = self.base_url.join("/api/v4/roles/names")?;
+        let res = client
+            .post(url)
+            .header("authorization", format!("bearer {}", self.token))
+            .json(names)
+            .send()
+            .chain_err(|| "Failed to send webrequest")?;
+        debug!("get_roles_by_names response {}", res.status());
+
+        Ok(self.check_status(res)?.json()?)
+    }
+
+    pub fn get_scheme<S>(&self, scheme_id: S) -> Result<Scheme>
+    where
+        S: AsRef<str>,
+    {
+        let client = self.web_client();
+        let _permit = self.acquire_permit();
+        let url = self
+            .base_url
+            .join("/api/v4/schemes/")?
+            .join(scheme_id.as_ref())?;
+        let res = client
+            .get(url)
+            .header("authorization", format!("bearer {}", self.token))
+            .send()
+            .chain_err(|| "Failed to send webrequest")?;
+        debug!("get_scheme response {}", res.status());
+
+        Ok(self.check_status(res)?.json()?)
+    }
+
+    pub fn get_channel_by_name<T, N>(&self, team_id: T, channel_name: N) -> Result<Channel>
+    where
+        T: AsRef<TeamId>,
+        N: AsRef<str>,
+    {
+        let url = self.base_url.join(&format!(
+            "/api/v4/teams/{}/channels/name/{}",
+            team_id.as_ref(),
+            channel_name.as_ref()
+        ))?;
+        self.get_cached(url, "get_channel_by_name")
+    }
+
+    /// Create a public or private channel, see [`CreateChannelRequest::builder`]
+    pub fn create_channel(&self, channel: &CreateChannelRequest) -> Result<Channel> {
+        self.check_writable("create_channel")?;
+        let client = self.web_client();
+        let _permit = self.acquire_permit();
+        let url = self.base_url.join("/api/v4/channels")?;
+        let res = client
+            .post(url)
+            .header("authorization", format!("bearer {}", self.token))
+            .json(channel)
+            .send()
+            .chain_err(|| "Failed to send webrequest")?;
+        debug!("create_channel response {}", res.status());
+
+        Ok(self.check_status(res)?.json()?)
+    }
+
+    /// Change the names, header, or purpose of a channel
+    pub fn patch_channel<S>(&self, channel_id: S, patch: &ChannelPatch) -> Result<Channel>
+    where
+        S: AsRef<ChannelId>,
+    {
+        self.check_writable("patch_channel")?;
+        let client = self.web_client();
+        let _permit = self.acquire_permit();
+        let url = self
+            .base_url
+            .join(&format!("/api/v4/channels/{}/patch", channel_id.as_ref()))?;
+        let res = client
+            .put(url)
+            .header("authorization", format!("bearer {}", self.token))
+            .json(patch)
+            .send()
+            .chain_err(|| "Failed to send webrequest")?;
+        debug!("patch_channel response {}", res.status());
+
+        Ok(self.check_status(res)?.json()?)
+    }
+
+    /// Archive a channel
+    ///
+    /// Archived channels are read-only and hidden from the channel list, but keep their posts.
+    pub fn delete_channel<S>(&self, channel_id: S) -> Result<()>
+    where
+        S: AsRef<ChannelId>,
+    {
+        self.check_writable("delete_channel")?;
+        let client = self.web_client();
+        let _permit = self.acquire_permit();
+        let url = self
+            .base_url
+            .join(&format!("/api/v4/channels/{}", channel_id.as_ref()))?;
+        let res = client
+            .delete(url)
+            .header("authorization", format!("bearer {}", self.token))
+            .send()
+            .chain_err(|| "Failed to send webrequest")?;
+        debug!("delete_channel response {}", res.status());
+
+        self.check_status(res)?;
+        Ok(())
+    }
+
+    /// Restore an archived channel
+    pub fn restore_channel<S>(&self, channel_id: S) -> Result<Channel>
+    where
+        S: AsRef<ChannelId>,
+    {
+        self.check_writable("restore_channel")?;
+        let client = self.web_client();
+        let _permit = self.acquire_permit();
+        let url = self
+            .base_url
+            .join(&format!("/api/v4/channels/{}/restore", channel_id.as_ref()))?;
+        let res = client
+            .post(url)
+            .header("authorization", format!("bearer {}", self.token))
+            .send()
+            .chain_err(|| "Failed to send webrequest")?;
+        debug!("restore_channel response {}", res.status());
+
+        Ok(self.check_status(res)?.json()?)
+    }
+
+    /// Add a user to a channel, returning the new membership
+    pub fn add_channel_member<C, U>(&self, channel_id: C, user_id: U) -> Result<ChannelMember>
+    where
+        C: AsRef<ChannelId>,
+        U: AsRef<UserId>,
+    {
+        #[derive(Serialize)]
+        struct AddChannelMemberRequest<'a> {
+            user_id: &'a str,
+        }
+
+        self.check_writable("add_channel_member")?;
+        let client = self.web_client();
+        let _permit = self.acquire_permit();
+        let url = self
+            .base_url
+            .join(&format!("/api/v4/channels/{}/members", channel_id.as_ref()))?;
+        let res = client
+            .post(url)
+            .header("authorization", format!("bearer {}", self.token))
+            .json(&AddChannelMemberRequest {
+                user_id: user_id.as_ref(),
+            })
+            .send()
+            .chain_err(|| "Failed to send webrequest")?;
+        debug!("add_channel_member response {}", res.status());
+
+        Ok(self.check_status(res)?.json()?)
+    }
+
+    /// Remove a user from a channel, or leave it if `user_id` is the current user
+    pub fn remove_channel_member<C, U>(&self, channel_id: C, user_id: U) -> Result<()>
+    where
+        C: AsRef<ChannelId>,
+        U: AsRef<UserId>,
+    {
+        self.check_writable("remove_channel_member")?;
+        let client = self.web_client();
+        let _permit = self.acquire_permit();
+        let url = self.base_url.join(&format!(
+            "/api/v4/channels/{}/members/{}",
+            channel_id.as_ref(),
+            user_id.as_ref()
+        ))?;
+        let res = client
+            .delete(url)
+            .header("authorization", format!("bearer {}", self.token))
+            .send()
+            .chain_err(|| "Failed to send webrequest")?;
+        debug!("remove_channel_member response {}", res.status());
+
+        self.check_status(res)?;
+        Ok(())
+    }
+
+    /// The membership of a user in a channel, including their roles and notification settings
+    ///
+    /// Fails with a 404 error if the user is not a member.
+    pub fn get_channel_member<C, U>(&self, channel_id: C, user_id: U) -> Result<ChannelMember>
+    where
+        C: AsRef<ChannelId>,
+        U: AsRef<UserId>,
+    {
+        let client = self.web_client();
+        let _permit = self.acquire_permit();
+        let url = self.base_url.join(&format!(
+            "/api/v4/channels/{}/members/{}",
+            channel_id.as_ref(),
+            user_id.as_ref()
+        ))?;
+        let res = client
+            .get(url)
+            .header("authorization", format!("bearer {}", self.token))
+            .send()
+            .chain_err(|| "Failed to send webrequest")?;
+        debug!("get_channel_member response {}", res.status());
+
+        Ok(self.check_status(res)?.json()?)
+    }
+
+    /// Get a page of the members of a channel
+    pub fn get_channel_members<S>(
+        &self,
+        channel_id: S,
+        page: usize,
+        per_page: usize,
+    ) -> Result<Vec<ChannelMember>>
+    where
+        S: AsRef<ChannelId>,
+    {
+        let client = self.web_client();
+        let _permit = self.acquire_permit();
+        let mut url = self
+            .base_url
+            .join(&format!("/api/v4/channels/{}/members", channel_id.as_ref()))?;
+        Query::new().page(page, per_page).apply(&mut url);
+        let res = client
+            .get(url)
+            .header("authorization", format!("bearer {}", self.token))
+            .send()
+            .chain_err(|| "Failed to send webrequest")?;
+        debug!("get_channel_members response {}", res.status());
+
+        Ok(self.check_status(res)?.json()?)
+    }
+
+    /// Members of a channel whose membership changed after `since`
+    ///
+    /// The server cannot filter the members, so all pages are fetched and filtered locally.
+    pub fn get_channel_members_since<S>(
+        &self,
+        channel_id: S,
+        since: DateTime<Utc>,
+    ) -> Result<Vec<ChannelMember>>
+    where
+        S: AsRef<ChannelId>,
+    {
+        const PER_PAGE: usize = 200;
+        let mut members = Vec::new();
+        for page in 0.. {
+            let batch = self.get_channel_members(channel_id.as_ref(), page, PER_PAGE)?;
+            let done = batch.len() < PER_PAGE;
+            members.extend(
+                batch
+                    .into_iter()
+                    .filter(|member| member.last_update_at.is_none_or(|time| time > since)),
+            );
+            if done {
+                break;
+            }
+        }
+        Ok(members)
+    }
+
+    /// Get a page of the posts of a channel, newest first
+    pub fn get_posts_for_channel<S>(
+        &self,
+        channel_id: S,
+        page: usize,
+        per_page: usize,
+    ) -> Result<PostList>
+    where
+        S: AsRef<ChannelId>,
+    {
+        let client = self.web_client();
+        let _permit = self.acquire_permit();
+        let mut url = self
+            .base_url
+            .join(&format!("/api/v4/channels/{}/posts", channel_id.as_ref()))?;
+        Query::new().page(page, per_page).apply(&mut url);
+        let res = client
+            .get(url)
+            .header("authorization", format!("bearer {}", self.token))
+            .send()
+            .chain_err(|| "Failed to send webrequest")?;
+        debug!("get_posts_for_channel response {}", res.status());
+
+        Ok(self.check_status(res)?.json()?)
+    }
+
+    /// All posts of a channel which were created, edited, or deleted after `since`
+    ///
+    /// Deleted posts are included with their `delete_at` set, such that mirrors can remove them.
+    /// The server returns at most 1000 posts for such a query.
+    /// If there are more, the posts created after `since` are fetched page by page in addition, newest first.
+    /// Only edits and deletions of posts created before `since` can be missing then.
+    pub fn get_posts_for_channel_since<S>(
+        &self,
+        channel_id: S,
+        since: DateTime<Utc>,
+    ) -> Result<PostList>
+    where
+        S: AsRef<ChannelId>,
+    {
+        const SINCE_LIMIT: usize = 1000;
+        const PER_PAGE: usize = 200;
+
+        let channel_id = channel_id.as_ref();
+        let mut posts: PostList = {
+            let client = self.web_client();
+            let _permit = self.acquire_permit();
+            let mut url = self
+                .base_url
+                .join(&format!("/api/v4/channels/{}/posts", channel_id))?;
+            Query::new().param("since", since).apply(&mut url);
+            let res = client
+                .get(url)
+                .header("authorization", format!("bearer {}", self.token))
+                .send()
+                .chain_err(|| "Failed to send webrequest")?;
+            debug!("get_posts_for_channel_since response {}", res.status());
+            self.check_status(res)?.json()?
+        };
+        if posts.order.len() < SINCE_LIMIT {
+            return Ok(posts);
+        }
+
+        debug!(
+            "get_posts_for_channel_since reached the limit of {} posts, paging",
+            SINCE_LIMIT
+        );
+        for page in 0.. {
+            let list = self.get_posts_for_channel(channel_id, page, PER_PAGE)?;
+            let count = list.order.len();
+            let reached_since = list.posts.values().any(|post| post.create_at <= since);
+            for (id, post) in list.posts {
+                if post.create_at > since && !posts.posts.contains_key(&id) {
+                    posts.order.push(id.clone());
+                    posts.posts.insert(id, post);
+                }
+            }
+            if count < PER_PAGE || reached_since {
+                break;
+            }
+        }
+        let PostList {
+            order,
+            posts: by_id,
+            ..
+        } = &mut posts;
+        order.sort_by_key(|id| Reverse(by_id.get(id).map(|post| post.create_at)));
+        Ok(posts)
+    }
+
+    /// Make sure the channel `name` exists in the team and the current user is a member of it
+    ///
+    /// The channel is created if it does not exist, using `name` as display name.
+    /// The current user joins the channel if they are not a member yet.
+    pub fn ensure_channel<T, N>(&self, team_id: T, name: N, type_: ChannelType) -> Result<Channel>
+    where
+        T: AsRef<TeamId>,
+        N: AsRef<str>,
+    {
+        let team_id = team_id.as_ref();
+        let name = name.as_ref();
+
+        let channel = match self.get_channel_by_name(team_id, name) {
+            Ok(channel) => channel,
+            Err(ref err) if err.is_not_found() => {
+                debug!("Channel '{}' does not exist, creating it", name);
+                self.create_channel(&CreateChannelRequest {
+                    team_id: team_id.to_string(),
+                    name: name.to_string(),
+                    display_name: name.to_string(),
+                    type_,
+                    ..CreateChannelRequest::default()
+                })
+                .chain_err(|| format!("Failed to create channel '{}'", name))?
+            }
+            Err(err) => {
+                return Err(err).chain_err(|| format!("Failed to look up channel '{}'", name));
+            }
+        };
+
+        match self.get_my_channel_member(&channel.id) {
+            Ok(_) => {}
+            Err(ref err) if err.is_not_found() => {
+                debug!("Not a member of channel '{}', joining it", name);
+                let me = self
+                    .get_me()
+                    .chain_err(|| format!("Failed to join channel '{}'", name))?;
+                self.add_channel_member(&channel.id, &me.id)
+                    .chain_err(|| format!("Failed to join channel '{}'", name))?;
+            }
+            Err(err) => {
+                return Err(err)
+                    .chain_err(|| format!("Failed to check membership of channel '{}'", name));
+            }
+        }
+
+        Ok(channel)
+    }
+
+    /// Search the posts of a team
+    ///
+    /// `terms` uses the same syntax as the search box of the web client, which [`SearchTerms`] builds.
+    /// If `is_or_search` is set, posts matching any of the terms are returned, otherwise only posts matching all of them.
+    /// The results are ordered by relevance, see [`PostList::iter`].
+    pub fn search_posts<T, S>(&self, team_id: T, terms: S, is_or_search: bool) -> Result<PostList>
+    where
+        T: AsRef<TeamId>,
+        S: Into<String>,
+    {
+        self.search_posts_request(
+            team_id.as_ref(),
+            &SearchPostsRequest {
+                terms: terms.into(),
+                is_or_search,
+                page: None,
+                per_page: None,
+            },
+        )
+    }
+
+    fn search_posts_request(
+        &self,
+        team_id: &TeamId,
+        request: &SearchPostsRequest,
+    ) -> Result<PostList> {
+        let client = self.web_client();
+        let _permit = self.acquire_permit();
+        let url = self
+            .base_url
+            .join(&format!("/api/v4/teams/{}/posts/search", team_id))?;
+        let res = client
+            .post(url)
+            .header("authorization", format!("bearer {}", self.token))
+            .json(request)
+            .send()
+            .chain_err(|| "Failed to send webrequest")?;
+        debug!("search_posts response {}", res.status());
+
+        Ok(self.check_status(res)?.json()?)
+    }
+
+    /// Posts mentioning the current user by `@username`, created after `since`, oldest first
+    ///
+    /// Uses the search of all teams of the user, like the "Recent mentions" of the web app.
+    /// Mentions in direct messages and by `@channel` or groups are not found.
+    /// The search results are fetched page by page, until a page is incomplete or reaches back to `since`.
+    pub fn get_my_recent_mentions(&self, since: DateTime<Utc>) -> Result<Vec<Post>> {
+        const PER_PAGE: usize = 60;
+
+        let me = self.get_me()?;
+        // `after:` excludes the given day
+        let terms = SearchTerms::new()
+            .word(format!("@{}", me.username))
+            .after((since - chrono::Duration::days(1)).date_naive())
+            .to_string();
+        let mut mentions = BTreeMap::new();
+        for team in self.get_my_teams()? {
+            for page in 0.. {
+                let results = self.search_posts_request(
+                    &team.id,
+                    &SearchPostsRequest {
+                        terms: terms.clone(),
+                        is_or_search: false,
+                        page: Some(page),
+                        per_page: Some(PER_PAGE),
+                    },
+                )?;
+                let complete = results.order.len() < PER_PAGE
+                    || results.posts.values().any(|post| post.create_at <= since);
+                mentions.extend(results.posts.into_iter().filter(|(_, post)| {
+                    post.create_at > since && post.user_id != me.id && !post.is_deleted()
+                }));
+                if complete {
+                    break;
+                }
+            }
+        }
+        let mut mentions: Vec<Post> = mentions.into_values().collect();
+        mentions.sort_by_key(|post| post.create_at);
+        Ok(mentions)
+    }
+
+    /// Channels of the team whose name or display name starts with `name`, for autocompletion
+    pub fn autocomplete_channels<T, N>(&self, team_id: T, name: N) -> Result<Vec<Channel>>
+    where
+        T: AsRef<TeamId>,
+        N: AsRef<str>,
+    {
+        let client = self.web_client();
+        let _permit = self.acquire_permit();
+        let mut url = self.base_url.join(&format!(
+            "/api/v4/teams/{}/channels/autocomplete",
+            team_id.as_ref()
+        ))?;
+        Query::new().param("name", name.as_ref()).apply(&mut url);
+        let res = client
+            .get(url)
+            .header("authorization", format!("bearer {}", self.token))
+            .send()
+            .chain_err(|| "Failed to send webrequest")?;
+        debug!("autocomplete_channels response {}", res.status());
+
+        Ok(self.check_status(res)?.json()?)
+    }
+
+    /// Search the channels of all teams
+    ///
+    /// Requires the `sysconsole_read_user_management_channels` permission.
+    pub fn search_all_channels(&self, search: &AdminChannelSearch) -> Result<ChannelSearchResult> {
+        let client = self.web_client();
+        let _permit = self.acquire_permit();
+        let url = self.base_url.join("/api/v4/channels/search")?;
+        let res = client
+            .post(url)
+            .header("authorization", format!("bearer {}", self.token))
+            .json(search)
+            .send()
+            .chain_err(|| "Failed to send webrequest")?;
+        debug!("search_all_channels response {}", res.status());
+
+        Ok(self.check_status(res)?.json()?)
+    }
+
+    /// Clear all caches of the server, e.g., after changing the database manually
+    ///
+    /// Requires the `manage_system` permission.
+    pub fn invalidate_caches(&self) -> Result<()> {
+        self.check_writable("invalidate_caches")?;
+        let client = self.web_client();
+        let _permit = self.acquire_permit();
+        let url = self.base_url.join("/api/v4/caches/invalidate")?;
+        let res = client
+            .post(url)
+            .header("authorization", format!("bearer {}", self.token))
+            .send()
+            .chain_err(|| "Failed to send webrequest")?;
+        debug!("invalidate_caches response {}", res.status());
+
+        self.check_status(res)?;
+        Ok(())
+    }
+
+    /// Close and reopen all database connections, e.g., after a failover of the database
+    ///
+    /// Requires the `manage_system` permission.
+    pub fn recycle_database(&self) -> Result<()> {
+        self.check_writable("recycle_database")?;
+        let client = self.web_client();
+        let _permit = self.acquire_permit();
+        let url = self.base_url.join("/api/v4/database/recycle")?;
+        let res = client
+            .post(url)
+            .header("authorization", format!("bearer {}", self.token))
+            .send()
+            .chain_err(|| "Failed to send webrequest")?;
+        debug!("recycle_database response {}", res.status());
+
+        self.check_status(res)?;
+        Ok(())
+    }
+
+    /// Get lines of the server log, newest last
+    ///
+    /// Each line is a JSON object, which can be parsed into a [`LogEntry`].
+    /// Requires the `manage_system` permission.
+    pub fn get_logs(&self, page: usize, per_page: usize) -> Result<Vec<String>> {
+        let client = self.web_client();
+        let _permit = self.acquire_permit();
+        let mut url = self.base_url.join("/api/v4/logs")?;
+        Query::new()
+            .param("page", page)
+            .param("logs_per_page", per_page)
+            .apply(&mut url);
+        let res = client
+            .get(url)
+            .header("authorization", format!("bearer {}", self.token))
+            .send()
+            .chain_err(|| "Failed to send webrequest")?;
+        debug!("get_logs response {}", res.status());
+
+        Ok(self.check_status(res)?.json()?)
+    }
+
+    /// Get a page of the audit log of the server, newest first
+    ///
+    /// Requires the `manage_system` permission.
+    pub fn get_audits(&self, page: usize, per_page: usize) -> Result<Vec<Audit>> {
+        let client = self.web_client();
+        let _permit = self.acquire_permit();
+        let mut url = self.base_url.join("/api/v4/audits")?;
+        Query::new().page(page, per_page).apply(&mut url);
+        let res = client
+            .get(url)
+            .header("authorization", format!("bearer {}", self.token))
+            .send()
+            .chain_err(|| "Failed to send webrequest")?;
+        debug!("get_audits response {}", res.status());
+
+        Ok(self.check_status(res)?.json()?)
+    }
+
+    /// Get the parts of the server configuration which are visible to all users
+    ///
+    /// With `old_format` the configuration is a flat map of strings, which is the only format implemented by current servers.
+    /// Without it the `format` parameter is left out, which servers up to at least version 9 reject as not implemented.
+    pub fn get_client_config(&self, old_format: bool) -> Result<Config> {
+        let mut url = self.base_url.join("/api/v4/config/client")?;
+        Query::new()
+            .opt_param("format", if old_format { Some("old") } else { None })
+            .apply(&mut url);
+        self.get_cached(url, "get_client_config")
+    }
+
+    /// The features of the server which bots commonly depend on
+    pub fn get_client_features(&self) -> Result<ClientFeatures> {
+        Ok(self.get_client_config(true)?.features())
+    }
+
+    /// Get the client visible parts of the license
+    pub fn get_client_license(&self) -> Result<ClientLicense> {
+        let mut url = self.base_url.join("/api/v4/license/client")?;
+        Query::new().param("format", "old").apply(&mut url);
+        self.get_cached(url, "get_client_license")
+    }
+
+    /// Get the license information, i.e., the client visible parts of the license
+    ///
+    /// Same as [`Client::get_client_license`].
+    pub fn get_license_info(&self) -> Result<ClientLicense> {
+        self.get_client_license()
+    }
+
+    /// Install a new license file
+    ///
+    /// Requires the `manage_license_information` permission.
+    pub fn upload_license(&self, license: Vec<u8>) -> Result<License> {
+        self.check_writable("upload_license")?;
+        let client = self.web_client();
+        let _permit = self.acquire_permit();
+        let url = self.base_url.join("/api/v4/license")?;
+        let form = Form::new().part("license", Part::bytes(license).file_name("license"));
+        let res = client
+            .post(url)
+            .header("authorization", format!("bearer {}", self.token))
+            .multipart(form)
+            .send()
+            .chain_err(|| "Failed to send webrequest")?;
+        debug!("upload_license response {}", res.status());
+
+        Ok(self.check_status(res)?.json()?)
+    }
+
+    /// Remove the license, which turns the server into the free edition
+    ///
+    /// Requires the `manage_license_information` permission.
+    pub fn remove_license(&self) -> Result<()> {
+        self.check_writable("remove_license")?;
+        let client = self.web_client();
+        let _permit = self.acquire_permit();
+        let url = self.base_url.join("/api/v4/license")?;
+        let res = client
+            .delete(url)
+            .header("authorization", format!("bearer {}", self.token))
+            .send()
+            .chain_err(|| "Failed to send webrequest")?;
+        debug!("remove_license response {}", res.status());
+
+        self.check_status(res)?;
+        Ok(())
+    }
+
+    /// Get the number of posts and the used file storage
+    pub fn get_usage(&self) -> Result<Usage> {
+        #[derive(Deserialize)]
+        struct PostsUsage {
+            count: u64,
+        }
+        #[derive(Deserialize)]
+        struct StorageUsage {
+            bytes: u64,
+        }
+
+        let client = self.web_client();
+        let _permit = self.acquire_permit();
+        let url = self.base_url.join("/api/v4/usage/posts")?;
+        let res = client
+            .get(url)
+            .header("authorization", format!("bearer {}", self.token))
+            .send()
+            .chain_err(|| "Failed to send webrequest")?;
+        debug!("get_usage response {}", res.status());
+        let posts: PostsUsage = self.check_status(res)?.json()?;
+
+        let url = self.base_url.join("/api/v4/usage/storage")?;
+        let res = client
+            .get(url)
+            .header("authorization", format!("bearer {}", self.token))
+            .send()
+            .chain_err(|| "Failed to send webrequest")?;
+        debug!("get_usage response {}", res.status());
+        let storage: StorageUsage = self.check_status(res)?.json()?;
+
+        Ok(Usage {
+            posts: posts.count,
+            storage_bytes: storage.bytes,
+        })
+    }
+
+    /// Get the latest custom terms of service
+    pub fn get_terms_of_service(&self) -> Result<TermsOfService> {
+        let client = self.web_client();
+        let _permit = self.acquire_permit();
+        let url = self.base_url.join("/api/v4/terms_of_service")?;
+        let res = client
+            .get(url)
+            .header("authorization", format!("bearer {}", self.token))
+            .send()
+            .chain_err(|| "Failed to send webrequest")?;
+        debug!("get_terms_of_service response {}", res.status());
+
+        Ok(self.check_status(res)?.json()?)
+    }
+
+    /// Publish new terms of service, which all users have to accept again
+    ///
+    /// Requires the `manage_system` permission.
+    pub fn create_terms_of_service<S>(&self, text: S) -> Result<TermsOfService>
+    where
+        S: Into<String>,
+    {
+        self.check_writable("create_terms_of_service")?;
+        let client = self.web_client();
+        let _permit = self.acquire_permit();
+        let url = self.base_url.join("/api/v4/terms_of_service")?;
+        let res = client
+            .post(url)
+            .header("authorization", format!("bearer {}", self.token))
+            .json(&CreateTermsOfServiceRequest { text: text.into() })
+            .send()
+            .chain_err(|| "Failed to send webrequest")?;
+        debug!("create_terms_of_service response {}", res.status());
+
+        Ok(self.check_status(res)?.json()?)
+    }
+
+    /// Record whether a user accepted or rejected the terms of service with id `terms_of_service_id`
+    pub fn record_user_terms_acceptance<U, T>(
+        &self,
+        user_id: U,
+        terms_of_service_id: T,
+        accepted: bool,
+    ) -> Result<()>
+    where
+        U: AsRef<UserId>,
+        T: Into<String>,
+    {
+        self.check_writable("record_user_terms_acceptance")?;
+        let client = self.web_client();
+        let _permit = self.acquire_permit();
+        let url = self.base_url.join(&format!(
+            "/api/v4/users/{}/terms_of_service",
+            user_id.as_ref()
+        ))?;
+        let res = client
+            .post(url)
+            .header("authorization", format!("bearer {}", self.token))
+            .json(&UserTermsAcceptanceRequest {
+                terms_of_service_id: terms_of_service_id.into(),
+                accepted,
+            })
+            .send()
+            .chain_err(|| "Failed to send webrequest")?;
+        debug!("record_user_terms_acceptance response {}", res.status());
+
+        self.check_status(res)?;
+        Ok(())
+    }
+
+    /// Get which terms of service the user accepted last
+    ///
+    /// Fails with a [not found](crate::Error::is_not_found) error if the user never accepted any terms of service.
+    pub fn get_user_terms_of_service<U>(&self, user_id: U) -> Result<UserTermsOfService>
+    where
+        U: AsRef<UserId>,
+    {
+        let client = self.web_client();
+        let _permit = self.acquire_permit();
+        let url = self.base_url.join(&format!(
+            "/api/v4/users/{}/terms_of_service",
+            user_id.as_ref()
+        ))?;
+        let res = client
+            .get(url)
+            .header("authorization", format!("bearer {}", self.token))
+            .send()
+            .chain_err(|| "Failed to send webrequest")?;
+        debug!("get_user_terms_of_service response {}", res.status());
+
+        Ok(self.check_status(res)?.json()?)
+    }
+
+    pub fn get_user<S>(&self, user_id: S) -> Result<User>
+    where
+        S: AsRef<UserId>,
+    {
+        let url = self
+            .base_url
+            .join("/api/v4/users/")?
+            .join(user_id.as_ref())?;
+        self.get_cached(url, "get_user")
+    }
+
+    pub fn get_user_by_username<S>(&self, username: S) -> Result<User>
+    where
+        S: AsRef<str>,
+    {
+        let url = self
+            .base_url
+            .join("/api/v4/users/username/")?
+            .join(username.as_ref())?;
+        self.get_cached(url, "get_user_by_username")
+    }
+
+    /// Get the direct channel between two users, creating it if necessary
+    pub fn create_direct_channel<A, B>(&self, user_id: A, other_user_id: B) -> Result<Channel>
+    where
+        A: AsRef<UserId>,
+        B: AsRef<UserId>,
+    {
+        self.check_writable("create_direct_channel")?;
+        let client = self.web_client();
+        let _permit = self.acquire_permit();
+        let url = self.base_url.join("/api/v4/channels/direct")?;
+        let res = client
+            .post(url)
+            .header("authorization", format!("bearer {}", self.token))
+            .json(&[user_id.as_ref(), other_user_id.as_ref()])
+            .send()
+            .chain_err(|| "Failed to send webrequest")?;
+        debug!("create_direct_channel response {}", res.status());
+
+        Ok(self.check_status(res)?.json()?)
+    }
+
+    /// Get the group channel between the users, creating it if necessary
+    ///
+    /// A group channel has between three and eight members.
+    /// The current user is always a member, even if missing from `user_ids`.
+    pub fn create_group_channel<U>(&self, user_ids: &[U]) -> Result<Channel>
+    where
+        U: AsRef<UserId>,
+    {
+        self.check_writable("create_group_channel")?;
+        let client = self.web_client();
+        let _permit = self.acquire_permit();
+        let url = self.base_url.join("/api/v4/channels/group")?;
+        let user_ids: Vec<&UserId> = user_ids.iter().map(AsRef::as_ref).collect();
+        let res = client
+            .post(url)
+            .header("authorization", format!("bearer {}", self.token))
+            .json(&user_ids)
+            .send()
+            .chain_err(|| "Failed to send webrequest")?;
+        debug!("create_group_channel response {}", res.status());
+
+        Ok(self.check_status(res)?.json()?)
+    }
+
+    /// Send a direct message to the user with the id `user_id`
+    ///
+    /// The direct channel is created if the users have never talked before.
+    /// Use [`Client::dm_user`] to message a user by username.
+    /// Errors are returned unwrapped, such that [`Error::RateLimited`] can be retried.
+    pub fn send_direct_message<U, M>(&self, user_id: U, message: M) -> Result<Post>
+    where
+        U: AsRef<UserId>,
+        M: Into<String>,
+    {
+        let me = self.get_me()?;
+        let channel = self.create_direct_channel(&me.id, user_id.as_ref())?;
+        let post = CreatePostRequest::builder()
+            .channel_id(channel.id)
+            .message(message)
+            .build()?;
+        self.create_post(&post)
+    }
+
+    /// Send a direct message to a user
+    ///
+    /// The user is given by username, optionally with a leading `@`, or by id.
+    pub fn dm_user<U, M>(&self, username_or_id: U, message: M) -> Result<Post>
+    where
+        U: AsRef<str>,
+        M: Into<String>,
+    {
+        let name = username_or_id.as_ref();
+        let username = name.strip_prefix('@').unwrap_or(name);
+        let user = match self.get_user_by_username(username) {
+            Ok(user) => user,
+            Err(ref err) if err.is_not_found() && !name.starts_with('@') => self
+                .get_user(UserId::from(name))
+                .chain_err(|| format!("Failed to look up user '{}'", name))?,
+            Err(err) => {
+                return Err(err).chain_err(|| format!("Failed to look up user '{}'", name));
+            }
+        };
+        self.send_direct_message(&user.id, message)
+            .chain_err(|| format!("Failed to message user '{}'", name))
+    }
+
+    /// Invite guests by email to a team and some of its channels
+    ///
+    /// Requires guest accounts to be enabled on the server.
+    pub fn invite_guests_to_team<T>(&self, team_id: T, invite: &GuestsInvite) -> Result<()>
+    where
+        T: AsRef<TeamId>,
+    {
+        self.check_writable("invite_guests_to_team")?;
+        let client = self.web_client();
+        let _permit = self.acquire_permit();
+        let url = self.base_url.join(&format!(
+            "/api/v4/teams/{}/invite-guests/email",
+            team_id.as_ref()
+        ))?;
+        let res = client
+            .post(url)
+            .header("authorization", format!("bearer {}", self.token))
+            .json(invite)
+            .send()
+            .chain_err(|| "Failed to send webrequest")?;
+        debug!("invite_guests_to_team response {}", res.status());
+
+        self.check_status(res)?;
+        Ok(())
+    }
+
+    /// Turn a guest into a regular user
+    pub fn promote_guest_to_user<U>(&self, user_id: U) -> Result<()>
+    where
+        U: AsRef<UserId>,
+    {
+        self.check_writable("promote_guest_to_user")?;
+        let client = self.web_client();
+        let _permit = self.acquire_permit();
+        let url = self
+            .base_url
+            .join(&format!("/api/v4/users/{}/promote", user_id.as_ref()))?;
+        let res = client
+            .post(url)
+            .header("authorization", format!("bearer {}", self.token))
+            .send()
+            .chain_err(|| "Failed to send webrequest")?;
+        debug!("promote_guest_to_user response {}", res.status());
+
+        self.check_status(res)?;
+        Ok(())
+    }
+
+    /// Turn a regular user into a guest
+    pub fn demote_user_to_guest<U>(&self, user_id: U) -> Result<()>
+    where
+        U: AsRef<UserId>,
+    {
+        self.check_writable("demote_user_to_guest")?;
+        let client = self.web_client();
+        let _permit = self.acquire_permit();
+        let url = self
+            .base_url
+            .join(&format!("/api/v4/users/{}/demote", user_id.as_ref()))?;
+        let res = client
+            .post(url)
+            .header("authorization", format!("bearer {}", self.token))
+            .send()
+            .chain_err(|| "Failed to send webrequest")?;
+        debug!("demote_user_to_guest response {}", res.status());
+
+        self.check_status(res)?;
+        Ok(())
+    }
+
+    /// Create a personal access token for a user
+    ///
+    /// The token is only returned on creation and cannot be retrieved later.
+    pub fn create_user_access_token<U, D>(
+        &self,
+        user_id: U,
+        description: D,
+    ) -> Result<UserAccessToken>
+    where
+        U: AsRef<UserId>,
+        D: Into<String>,
+    {
+        self.check_writable("create_user_access_token")?;
+        let client = self.web_client();
+        let _permit = self.acquire_permit();
+        let url = self
+            .base_url
+            .join(&format!("/api/v4/users/{}/tokens", user_id.as_ref()))?;
+        let res = client
+            .post(url)
+            .header("authorization", format!("bearer {}", self.token))
+            .json(&CreateUserAccessTokenRequest {
+                description: description.into(),
+            })
+            .send()
+            .chain_err(|| "Failed to send webrequest")?;
+        debug!("create_user_access_token response {}", res.status());
+
+        Ok(self.check_status(res)?.json()?)
+    }
+
+    /// Get a page of the personal access tokens of a user, without the tokens themselves
+    pub fn get_user_access_tokens<U>(
+        &self,
+        user_id: U,
+        page: usize,
+        per_page: usize,
+    ) -> Result<Vec<UserAccessToken>>
+    where
+        U: AsRef<UserId>,
+    {
+        let client = self.web_client();
+        let _permit = self.acquire_permit();
+        let mut url = self
+            .base_url
+            .join(&format!("/api/v4/users/{}/tokens", user_id.as_ref()))?;
+        Query::new().page(page, per_page).apply(&mut url);
+        let res = client
+            .get(url)
+            .header("authorization", format!("bearer {}", self.token))
+            .send()
+            .chain_err(|| "Failed to send webrequest")?;
+        debug!("get_user_access_tokens response {}", res.status());
+
+        Ok(self.check_status(res)?.json()?)
+    }
+
+    /// Revoke a personal access token, given by the id of the token
+    pub fn revoke_user_access_token<S>(&self, token_id: S) -> Result<()>
+    where
+        S: AsRef<str>,
+    {
+        #[derive(Serialize)]
+        struct RevokeUserAccessTokenRequest<'a> {
+            token_id: &'a str,
+        }
+
+        self.check_writable("revoke_user_access_token")?;
+        let client = self.web_client();
+        let _permit = self.acquire_permit();
+        let url = self.base_url.join("/api/v4/users/tokens/revoke")?;
+        let res = client
+            .post(url)
+            .header("authorization", format!("bearer {}", self.token))
+            .json(&RevokeUserAccessTokenRequest {
+                token_id: token_id.as_ref(),
+            })
+            .send()
+            .chain_err(|| "Failed to send webrequest")?;
+        debug!("revoke_user_access_token response {}", res.status());
+
+        self.check_status(res)?;
+        Ok(())
+    }
+
+    /// Log a user out of all sessions, on all devices
+    pub fn revoke_all_sessions<U>(&self, user_id: U) -> Result<()>
+    where
+        U: AsRef<UserId>,
+    {
+        self.check_writable("revoke_all_sessions")?;
+        let client = self.web_client();
+        let _permit = self.acquire_permit();
+        let url = self.base_url.join(&format!(
+            "/api/v4/users/{}/sessions/revoke/all",
+            user_id.as_ref()
+        ))?;
+        let res = client
+            .post(url)
+            .header("authorization", format!("bearer {}", self.token))
+            .send()
+            .chain_err(|| "Failed to send webrequest")?;
+        debug!("revoke_all_sessions response {}", res.status());
+
+        self.check_status(res)?;
+        Ok(())
+    }
+
+    /// Deactivate a user, who can no longer log in
+    ///
+    /// The user and their posts are kept, so the account can be activated again.
+    pub fn deactivate_user<U>(&self, user_id: U) -> Result<()>
+    where
+        U: AsRef<UserId>,
+    {
+        self.check_writable("deactivate_user")?;
+        let client = self.web_client();
+        let _permit = self.acquire_permit();
+        let url = self
+            .base_url
+            .join(&format!("/api/v4/users/{}", user_id.as_ref()))?;
+        let res = client
+            .delete(url)
+            .header("authorization", format!("bearer {}", self.token))
+            .send()
+            .chain_err(|| "Failed to send webrequest")?;
+        debug!("deactivate_user response {}", res.status());
+
+        self.check_status(res)?;
+        Ok(())
+    }
+}
+
+/// A user account
+///
+/// Hashing and ordering only use the [`id`](User::id) and [`update_at`](User::update_at), which identify a version of a user, as the roles are unordered.
+/// Copies which differ in other fields, e.g., after changing one locally, are ordered as equal although they are not `==`.
+#[derive(Debug, Deserialize, Serialize, Clone, Eq, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct User {
+    pub id: UserId,
+    #[serde(with = "crate::serialize::ts_seconds")]
+    pub create_at: DateTime<Utc>,
+    #[serde(with = "crate::serialize::ts_seconds")]
+    pub update_at: DateTime<Utc>,
+    #[serde(with = "crate::serialize::ts_seconds")]
+    pub delete_at: DateTime<Utc>,
+    pub username: String,
+    pub first_name: String,
+    pub last_name: String,
+    pub nickname: String,
+    pub email: String,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub email_verified: Option<bool>,
+    pub auth_data: String,
+    pub auth_service: String,
+    pub position: String,
+    #[serde(with = "::serde_with::rust::StringWithSeparator::<::serde_with::SpaceSeparator>")]
+    pub roles: HashSet<UserRole>,
+    // pub roles: UserRole,
+    pub locale: String,
+    // pub notify_props: {},
+    // pub props: {},
+    #[serde(
+        skip_serializing_if = "Option::is_none",
+        with = "crate::serialize::option_ts_milliseconds",
+        default
+    )]
+    pub last_password_update: Option<DateTime<Utc>>,
+    #[serde(
+        skip_serializing_if = "Option::is_none",
+        with = "crate::serialize::option_ts_milliseconds",
+        default
+    )]
+    pub last_picture_update: Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub failed_attempts: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mfa_active: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timezone: Option<Timezone>,
+    /// Id of the remote cluster the user originates from, if it is a user of a shared channel
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub remote_id: Option<String>,
+}
+
+impl Hash for User {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.id.hash(state);
+        self.update_at.hash(state);
+    }
+}
+
+impl PartialOrd for User {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for User {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (&self.id, self.update_at).cmp(&(&other.id, other.update_at))
+    }
+}
+
+impl User {
+    /// Guests can only access the teams and channels they were invited to
+    pub fn is_guest(&self) -> bool {
+        self.roles.contains(&UserRole::SystemGuest)
+    }
+}
+
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Timezone {
+    automatic_timezone: String,
+    manual_timezone: String,
+    #[serde(with = "::serde_with::rust::display_fromstr")]
+    use_automatic_timezone: bool,
+}
+
+#[derive(Debug, Eq, Hash, PartialEq, Copy, Clone, Ord, PartialOrd)]
+pub enum UserRole {
+    SystemUser,
+    SystemAdmin,
+    /// Guest accounts, which only have access to the teams and channels they are invited to
+    SystemGuest,
+    TeamGuest,
+    ChannelUser,
+    ChannelAdmin,
+    ChannelGuest,
+}
+
+impl fmt::Display for UserRole {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            UserRole::SystemUser => write!(f, "system_user"),
+            UserRole::SystemAdmin => write!(f, "system_admin"),
+            UserRole::SystemGuest => write!(f, "system_guest"),
+            UserRole::TeamGuest => write!(f, "team_guest"),
+            UserRole::ChannelUser => write!(f, "channel_user"),
+            UserRole::ChannelAdmin => write!(f, "channel_admin"),
+            UserRole::ChannelGuest => write!(f, "channel_guest"),
+        }
+    }
+}
+
+impl FromStr for UserRole {
+    type Err = String;
+
+    fn from_str(s: &str) -> ::std::result::Result<Self, Self::Err> {
+        match s {
+            "system_user" => Ok(UserRole::SystemUser),
+            "system_admin" => Ok(UserRole::SystemAdmin),
+            "system_guest" => Ok(UserRole::SystemGuest),
+            "team_guest" => Ok(UserRole::TeamGuest),
+            "channel_user" => Ok(UserRole::ChannelUser),
+            "channel_admin" => Ok(UserRole::ChannelAdmin),
+            "channel_guest" => Ok(UserRole::ChannelGuest),
+            _ => Err(format!(
+                "Unexpected value '{}', expected one of 'system_user', 'system_admin', 'system_guest', 'team_guest', 'channel_user', 'channel_admin', 'channel_guest'",
+                s
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Ord, PartialOrd, Eq, PartialEq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub struct Channel {
+    pub(crate) id: ChannelId,
+    #[serde(with = "crate::serialize::ts_seconds")]
+    pub create_at: DateTime<Utc>,
+    #[serde(with = "crate::serialize::ts_seconds")]
+    pub update_at: DateTime<Utc>,
+    #[serde(with = "crate::serialize::ts_seconds")]
+    pub delete_at: DateTime<Utc>,
+    pub team_id: TeamId,
+    #[serde(rename = "type")]
+    pub type_: ChannelType,
+    pub display_name: String,
+    /// Name used in URLs
+    #[serde(default)]
+    pub name: String,
+    pub header: String,
+    #[serde(default)]
+    pub purpose: String,
+    #[serde(with = "crate::serialize::ts_seconds")]
+    pub last_post_at: DateTime<Utc>,
+    pub total_msg_count: u64,
+    #[serde(with = "crate::serialize::ts_seconds")]
+    pub extra_update_at: DateTime<Utc>,
+    pub creator_id: UserId,
+    /// The channel is shared with one or more remote clusters
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub shared: Option<bool>,
+    /// Scheme overriding the default roles of the channel members
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub scheme_id: Option<String>,
+}
+
+impl Channel {
+    pub fn id(&self) -> &ChannelId {
+        &self.id
+    }
+
+    /// Archived channels are read-only, but can be restored
+    pub fn is_archived(&self) -> bool {
+        self.delete_at.timestamp_millis() != 0
+    }
+
+    /// Time of the last post, or the creation of the channel if nobody posted yet
+    pub fn last_activity_at(&self) -> DateTime<Utc> {
+        self.last_post_at.max(self.create_at)
+    }
+
+    /// Update the channel with an event concerning it
+    ///
+    /// Handles archiving, restoring, converting, and updating the channel.
+    /// Returns whether the event concerned this channel.
+    pub fn apply(&mut self, event: &Events) -> bool {
+        match event {
+            Events::ChannelDeleted {
+                channel_id,
+                delete_at,
+            } if *channel_id == self.id => {
+                // Older servers do not send the time of archival
+                self.delete_at = delete_at.unwrap_or_else(Utc::now);
+            }
+            Events::ChannelRestored { channel_id } if *channel_id == self.id => {
+                self.delete_at = Utc.timestamp_millis_opt(0).unwrap();
+            }
+            Events::ChannelConverted { channel_id } if *channel_id == self.id => {
+                self.type_ = ChannelType::Private;
+            }
+            Events::ChannelUpdated { channel } if channel.id == self.id => {
+                *self = channel.clone();
+            }
+            _ => return false,
+        }
+        true
+    }
+}
+
+/// Record the server time and turn the error status codes of the API into errors
+fn check_response(clock: &ClockSkew, status: StatusCode, headers: &HeaderMap) -> Result<()> {
+    if let Some(date) = headers.get("Date").and_then(|date| date.to_str().ok()) {
+        clock.observe_date_header(date);
+    }
+    match status {
+        // 429
+        StatusCode::TOO_MANY_REQUESTS => {
+            let reset = headers
+                .get("X-Ratelimit-Reset")
+                .and_then(|reset| reset.to_str().ok())
+                .and_then(|reset| reset.parse().ok())
+                .unwrap_or(1);
+            Err(Error::RateLimited(reset))
+        }
+        // 4xx and 5xx, the caller adds the body of the response
+        status if status.is_client_error() || status.is_server_error() => Err(
+            Error::from_response(status.as_u16(), status.canonical_reason(), ""),
+        ),
+        // 200
+        _ => Ok(()),
+    }
+}
+
+#[derive(Serialize)]
+struct LoginRequest<'a> {
+    login_id: &'a str,
+    password: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    token: Option<&'a str>,
+}
+
+/// Error body of the API, only the human readable part
+#[derive(Deserialize)]
+struct ServerMessage {
+    message: String,
+}
+
+/// A personal access token, see [`Client::create_user_access_token`]
+///
+/// The `Debug` output does not contain the token itself, such that it does not end up in logs.
+#[derive(Deserialize, Serialize, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct UserAccessToken {
+    pub id: String,
+    /// Only set in the response to the creation
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub token: Option<String>,
+    pub user_id: String,
+    pub description: String,
+    #[serde(default)]
+    pub is_active: bool,
+}
+
+impl fmt::Debug for UserAccessToken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("UserAccessToken")
+            .field("id", &self.id)
+            .field("token", &self.token.as_ref().map(|_| "<redacted>"))
+            .field("user_id", &self.user_id)
+            .field("description", &self.description)
+            .field("is_active", &self.is_active)
+            .finish()
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct CreateUserAccessTokenRequest {
+    description: String,
+}
+
+/// Invitation of guests, see [`Client::invite_guests_to_team`]
+#[derive(Debug, Deserialize, Serialize, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Default)]
+pub struct GuestsInvite {
+    pub emails: Vec<String>,
+    /// Ids of the channels the guests get access to, at least one is required
+    pub channels: Vec<String>,
+    /// Custom message included in the invitation email
+    #[serde(skip_serializing_if = "String::is_empty", default)]
+    pub message: String,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct CreateChannelRequest {
+    pub team_id: String,
+    pub name: String,
+    pub display_name: String,
+    #[serde(rename = "type")]
+    pub type_: ChannelType,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub purpose: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub header: Option<String>,
+}
+
+/// Maximum length of the name of a channel, which is used in URLs
+pub const MAX_CHANNEL_NAME_LENGTH: usize = 64;
+/// Maximum number of characters of the display name of a channel
+pub const MAX_CHANNEL_DISPLAY_NAME_LENGTH: usize = 64;
+/// Maximum number of characters of the purpose of a channel
+pub const MAX_CHANNEL_PURPOSE_LENGTH: usize = 250;
+/// Maximum number of characters of the header of a channel
+pub const MAX_CHANNEL_HEADER_LENGTH: usize = 1024;
+
+impl CreateChannelRequest {
+    /// Builder for a channel of `team_id`, which is named `name` in URLs
+    pub fn builder<T, N>(team_id: T, name: N) -> CreateChannelRequestBuilder
+    where
+        T: Into<TeamId>,
+        N: Into<String>,
+    {
+        CreateChannelRequestBuilder {
+            request: CreateChannelRequest {
+                team_id: team_id.into().into_string(),
+                name: name.into(),
+                ..CreateChannelRequest::default()
+            },
+        }
+    }
+}
+
+/// Builder for [`CreateChannelRequest`] which validates the request before sending it
+///
+/// ```
+/// use mattermost_structs::api::{ChannelType, CreateChannelRequest};
+///
+/// let request = CreateChannelRequest::builder("ka6h8ccbe7rn5kq4o8ik7t9yao", "release-planning")
+///     .channel_type(ChannelType::Private)
+///     .purpose("Plan releases")
+///     .build()
+///     .unwrap();
+/// // The display name defaults to the name
+/// assert_eq!(request.display_name, "release-planning");
+///
+/// assert!(CreateChannelRequest::builder("ka6h8ccbe7rn5kq4o8ik7t9yao", "Release Planning")
+///     .build()
+///     .is_err());
+/// ```
+#[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct CreateChannelRequestBuilder {
+    request: CreateChannelRequest,
+}
+
+impl CreateChannelRequestBuilder {
+    pub fn display_name<S>(mut self, display_name: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.request.display_name = display_name.into();
+        self
+    }
+
+    /// Either [`ChannelType::Open`], the default, or [`ChannelType::Private`]
+    pub fn channel_type(mut self, type_: ChannelType) -> Self {
+        self.request.type_ = type_;
+        self
+    }
+
+    pub fn purpose<S>(mut self, purpose: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.request.purpose = Some(purpose.into());
+        self
+    }
+
+    pub fn header<S>(mut self, header: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.request.header = Some(header.into());
+        self
+    }
+
+    pub fn build(mut self) -> Result<CreateChannelRequest> {
+        if self.request.team_id.is_empty() {
+            return Err(Error::InvalidChannelRequest(
+                "team_id must not be empty".into(),
+            ));
+        }
+        let name = &self.request.name;
+        if name.is_empty() || name.len() > MAX_CHANNEL_NAME_LENGTH {
+            return Err(Error::InvalidChannelRequest(format!(
+                "name must have 1 to {} characters",
+                MAX_CHANNEL_NAME_LENGTH
+            )));
+        }
+        if !name
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '_')
+        {
+            return Err(Error::InvalidChannelRequest(format!(
+                "name '{}' may only contain lowercase letters, digits, '-', and '_'",
+                name
+            )));
+        }
+        match self.request.type_ {
+            ChannelType::Open | ChannelType::Private => {}
+            type_ => {
+                return Err(Error::InvalidChannelRequest(format!(
+                    "cannot create channels of type {:?}, use create_direct_channel for direct messages",
+                    type_
+                )));
+            }
+        }
+        if self.request.display_name.is_empty() {
+            self.request.display_name = self.request.name.clone();
+        }
+        let limits = [
+            (
+                "display_name",
+                Some(&self.request.display_name),
+                MAX_CHANNEL_DISPLAY_NAME_LENGTH,
+            ),
+            (
+                "purpose",
+                self.request.purpose.as_ref(),
+                MAX_CHANNEL_PURPOSE_LENGTH,
+            ),
+            (
+                "header",
+                self.request.header.as_ref(),
+                MAX_CHANNEL_HEADER_LENGTH,
+            ),
+        ];
+        for (field, value, limit) in &limits {
+            let length = value.map_or(0, |value| value.chars().count());
+            if length > *limit {
+                return Err(Error::InvalidChannelRequest(format!(
+                    "{} has {} characters, but at most {} are allowed",
+                    field, length, limit
+                )));
+            }
+        }
+        Ok(self.request)
+    }
+}
+
+impl Default for CreateChannelRequest {
+    fn default() -> Self {
+        CreateChannelRequest {
+            team_id: String::new(),
+            name: String::new(),
+            display_name: String::new(),
+            type_: ChannelType::Open,
+            purpose: None,
+            header: None,
+        }
+    }
+}
+
+/// Fields of a channel to change with [`Client::patch_channel`], `None` keeps the current value
+#[derive(Debug, Deserialize, Serialize, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Default)]
+pub struct ChannelPatch {
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub display_name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub header: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub purpose: Option<String>,
+}
+
+/// Parameters of [`Client::search_all_channels`]
+///
+/// Unset filters do not restrict the results.
+#[derive(Debug, Deserialize, Serialize, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct AdminChannelSearch {
+    /// Matched against the name and display name
+    pub term: String,
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub team_ids: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub public: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub private: Option<bool>,
+    /// Only return archived channels
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub deleted: Option<bool>,
+    /// Return archived channels in addition to the active ones
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub include_deleted: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub exclude_default_channels: Option<bool>,
+    pub page: usize,
+    pub per_page: usize,
+}
+
+impl Default for AdminChannelSearch {
+    fn default() -> Self {
+        AdminChannelSearch {
+            term: String::new(),
+            team_ids: Vec::new(),
+            public: None,
+            private: None,
+            deleted: None,
+            include_deleted: None,
+            exclude_default_channels: None,
+            page: 0,
+            per_page: 60,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct ChannelSearchResult {
+    pub channels: Vec<ChannelWithTeamData>,
+    /// Number of matching channels on all pages
+    pub total_count: usize,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct ChannelWithTeamData {
+    #[serde(flatten)]
+    pub channel: Channel,
+    pub team_display_name: String,
+    pub team_name: String,
     #[serde(with = "crate::serialize::ts_seconds")]
-    pub extra_update_at: DateTime<Utc>,
-    pub creator_id: String,
+    pub team_update_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct Role {
+    pub id: String,
+    pub name: String,
+    pub display_name: String,
+    pub description: String,
+    #[serde(with = "crate::serialize::ts_seconds")]
+    pub create_at: DateTime<Utc>,
+    #[serde(with = "crate::serialize::ts_seconds")]
+    pub update_at: DateTime<Utc>,
+    #[serde(with = "crate::serialize::ts_seconds")]
+    pub delete_at: DateTime<Utc>,
+    pub permissions: Vec<String>,
+    pub scheme_managed: bool,
+    pub built_in: bool,
+}
+
+/// A permission scheme, which defines the default roles of teams or channels
+#[derive(Debug, Deserialize, Serialize, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct Scheme {
+    pub id: String,
+    pub name: String,
+    pub display_name: String,
+    pub description: String,
+    #[serde(with = "crate::serialize::ts_seconds")]
+    pub create_at: DateTime<Utc>,
+    #[serde(with = "crate::serialize::ts_seconds")]
+    pub update_at: DateTime<Utc>,
+    #[serde(with = "crate::serialize::ts_seconds")]
+    pub delete_at: DateTime<Utc>,
+    pub scope: String,
+    #[serde(default)]
+    pub default_team_admin_role: String,
+    #[serde(default)]
+    pub default_team_user_role: String,
+    #[serde(default)]
+    pub default_channel_admin_role: String,
+    #[serde(default)]
+    pub default_channel_user_role: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub default_team_guest_role: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub default_channel_guest_role: String,
 }
 
 #[derive(Debug, Deserialize, Serialize, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
@@ -257,14 +3054,563 @@ pub enum ChannelType {
     Internal,
 }
 
+/// A link or file pinned to the top of a channel (server version 9.x and newer)
+///
+/// This has a field `file` in json, which contains the file info of file bookmarks and is not modelled here
+#[derive(Debug, Deserialize, Serialize, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct ChannelBookmark {
+    pub id: String,
+    #[serde(with = "crate::serialize::ts_seconds")]
+    pub create_at: DateTime<Utc>,
+    #[serde(with = "crate::serialize::ts_seconds")]
+    pub update_at: DateTime<Utc>,
+    #[serde(with = "crate::serialize::ts_seconds")]
+    pub delete_at: DateTime<Utc>,
+    pub channel_id: String,
+    pub owner_id: String,
+    pub display_name: String,
+    pub sort_order: i64,
+    #[serde(rename = "type")]
+    pub type_: ChannelBookmarkType,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub file_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub link_url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub image_url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub emoji: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub original_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub parent_id: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum ChannelBookmarkType {
+    Link,
+    File,
+}
+
+/// Result of updating a bookmark
+///
+/// Updating a bookmark creates a new one and deletes the old one, if the bookmark was created by a different user.
+#[derive(Debug, Deserialize, Serialize, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+#[serde(deny_unknown_fields)]
+pub struct UpdateChannelBookmarkResponse {
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub updated: Option<ChannelBookmark>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub deleted: Option<ChannelBookmark>,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct CreateChannelBookmarkRequest {
+    pub display_name: String,
+    #[serde(rename = "type")]
+    pub type_: ChannelBookmarkType,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub link_url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub file_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub image_url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub emoji: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Default)]
+pub struct PatchChannelBookmarkRequest {
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub display_name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub link_url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub file_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub image_url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub emoji: Option<String>,
+}
+
 #[derive(Debug, Deserialize, Serialize, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Default)]
 pub struct CreatePostRequest {
+    pub channel_id: ChannelId,
+    pub message: String,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub root_id: Option<PostId>,
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub file_ids: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub props: Option<PostProps>,
+}
+
+/// Fields of a post to change with [`Client::patch_post`], `None` keeps the current value
+#[derive(Debug, Deserialize, Serialize, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Default)]
+pub struct PostPatch {
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub message: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub file_ids: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub props: Option<PostProps>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub is_pinned: Option<bool>,
+}
+
+/// Metadata of an uploaded file
+#[derive(Debug, Deserialize, Serialize, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct FileInfo {
+    pub id: String,
+    pub user_id: String,
+    /// Empty until a post references the file
+    #[serde(default)]
+    pub post_id: String,
+    #[serde(default)]
+    pub channel_id: String,
+    #[serde(with = "crate::serialize::ts_seconds")]
+    pub create_at: DateTime<Utc>,
+    #[serde(with = "crate::serialize::ts_seconds")]
+    pub update_at: DateTime<Utc>,
+    #[serde(with = "crate::serialize::ts_seconds")]
+    pub delete_at: DateTime<Utc>,
+    pub name: String,
+    /// Without the leading dot
+    pub extension: String,
+    /// In bytes
+    pub size: u64,
+    pub mime_type: String,
+    #[serde(default)]
+    pub width: Option<u32>,
+    #[serde(default)]
+    pub height: Option<u32>,
+    #[serde(default)]
+    pub has_preview_image: bool,
+}
+
+/// A post which the server sends on behalf of the user at `scheduled_at`
+#[derive(Debug, Deserialize, Serialize, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct ScheduledPost {
+    pub id: String,
+    #[serde(with = "crate::serialize::ts_seconds")]
+    pub create_at: DateTime<Utc>,
+    #[serde(with = "crate::serialize::ts_seconds")]
+    pub update_at: DateTime<Utc>,
+    #[serde(with = "crate::serialize::ts_seconds")]
+    pub delete_at: DateTime<Utc>,
+    pub user_id: String,
     pub channel_id: String,
+    pub root_id: String,
     pub message: String,
     #[serde(skip_serializing_if = "Option::is_none", default)]
-    pub root_id: Option<String>,
+    pub props: Option<PostProps>,
     #[serde(skip_serializing_if = "Vec::is_empty", default)]
     pub file_ids: Vec<String>,
+    #[serde(with = "crate::serialize::ts_seconds")]
+    pub scheduled_at: DateTime<Utc>,
+    #[serde(
+        skip_serializing_if = "Option::is_none",
+        with = "crate::serialize::option_ts_milliseconds",
+        default
+    )]
+    pub processed_at: Option<DateTime<Utc>>,
+    /// Reason why the server failed to send the post
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub error_code: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct ScheduledPostRequest {
+    #[serde(flatten)]
+    pub post: CreatePostRequest,
+    #[serde(with = "crate::serialize::ts_seconds")]
+    pub scheduled_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize)]
+struct PostReminderRequest {
+    #[serde(with = "chrono::serde::ts_seconds")]
+    target_time: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize)]
+struct SearchPostsRequest {
+    terms: String,
+    is_or_search: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    page: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    per_page: Option<usize>,
+}
+
+/// A list of posts as returned by the server, e.g., by [`Client::search_posts`]
+///
+/// Hashing and ordering only use the [`order`](PostList::order) and the ids of the neighboring pages.
+/// Lists with the same posts in different versions are ordered as equal although they are not `==`.
+#[derive(Debug, Deserialize, Serialize, Clone, Eq, PartialEq)]
+pub struct PostList {
+    /// Ids of the posts in the order determined by the server
+    pub order: Vec<String>,
+    pub posts: HashMap<String, Post>,
+    #[serde(default)]
+    pub next_post_id: String,
+    #[serde(default)]
+    pub prev_post_id: String,
+    /// Search results only: the terms which matched, by post id
     #[serde(skip_serializing_if = "Option::is_none", default)]
-    pub props: Option<String>,
+    pub matches: Option<HashMap<String, Vec<String>>>,
+}
+
+impl Hash for PostList {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.order.hash(state);
+        self.next_post_id.hash(state);
+        self.prev_post_id.hash(state);
+    }
+}
+
+impl PartialOrd for PostList {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for PostList {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (&self.order, &self.next_post_id, &self.prev_post_id).cmp(&(
+            &other.order,
+            &other.next_post_id,
+            &other.prev_post_id,
+        ))
+    }
+}
+
+impl PostList {
+    /// The posts in the order determined by the server
+    ///
+    /// Ids in the order without a post are skipped.
+    pub fn iter(&self) -> impl Iterator<Item = &Post> {
+        self.order.iter().filter_map(move |id| self.posts.get(id))
+    }
+
+    /// Take the posts in the order determined by the server
+    pub fn into_ordered(mut self) -> Vec<Post> {
+        let order = std::mem::take(&mut self.order);
+        order
+            .iter()
+            .filter_map(|id| self.posts.remove(id))
+            .collect()
+    }
+
+    /// Latest change of any post in the list, usable as the next `since` of an incremental sync
+    pub fn latest_update_at(&self) -> Option<DateTime<Utc>> {
+        self.posts
+            .values()
+            .map(|post| post.update_at.max(post.delete_at))
+            .max()
+    }
+
+    /// Message of the post with all matched search terms in bold
+    ///
+    /// Returns the unchanged message, if there are no matches for the post.
+    pub fn highlighted_message<S>(&self, post_id: S) -> Option<String>
+    where
+        S: AsRef<str>,
+    {
+        let post = self.posts.get(post_id.as_ref())?;
+        let terms = self
+            .matches
+            .as_ref()
+            .and_then(|matches| matches.get(post_id.as_ref()));
+        Some(match terms {
+            Some(terms) => crate::format::emphasize(&post.message, terms),
+            None => post.message.clone(),
+        })
+    }
+}
+
+/// Maximum number of characters the server accepts in a post message
+pub const MAX_POST_MESSAGE_LENGTH: usize = 16383;
+/// Maximum number of files which can be attached to a single post
+pub const MAX_POST_FILE_IDS: usize = 5;
+
+impl CreatePostRequest {
+    pub fn builder() -> CreatePostRequestBuilder {
+        CreatePostRequestBuilder::default()
+    }
+
+    /// Check that the server keeps the overridden sender of the post
+    ///
+    /// The server silently drops overrides which are disabled in the `config`, see [`Client::get_client_config`].
+    pub fn check_sender_override(&self, config: &Config) -> Result<()> {
+        let props = match &self.props {
+            Some(props) => props,
+            None => return Ok(()),
+        };
+        if props.override_username().is_some() && !config.allows_username_override() {
+            return Err(Error::InvalidPostRequest(
+                "the server does not allow overriding the username".into(),
+            ));
+        }
+        if props.override_icon_url().is_some() && !config.allows_icon_override() {
+            return Err(Error::InvalidPostRequest(
+                "the server does not allow overriding the icon".into(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Builder for [`CreatePostRequest`] which validates the request before sending it
+#[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Default)]
+pub struct CreatePostRequestBuilder {
+    request: CreatePostRequest,
+}
+
+impl CreatePostRequestBuilder {
+    pub fn channel_id<S>(mut self, channel_id: S) -> Self
+    where
+        S: Into<ChannelId>,
+    {
+        self.request.channel_id = channel_id.into();
+        self
+    }
+
+    pub fn message<S>(mut self, message: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.request.message = message.into();
+        self
+    }
+
+    pub fn root_id<S>(mut self, root_id: S) -> Self
+    where
+        S: Into<PostId>,
+    {
+        self.request.root_id = Some(root_id.into());
+        self
+    }
+
+    /// Reply in the thread of `post`
+    ///
+    /// If `post` is already part of a thread, the reply is added to the same thread.
+    /// Otherwise `post` becomes the root of a new thread.
+    /// The channel is also set to the channel of `post`.
+    pub fn in_reply_to(mut self, post: &Post) -> Self {
+        let root_id = if !post.root_id.is_empty() {
+            post.root_id.clone()
+        } else {
+            post.id.clone()
+        };
+        self.request.root_id = Some(root_id);
+        self.request.channel_id = post.channel_id.clone();
+        self
+    }
+
+    pub fn file_id<S>(mut self, file_id: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.request.file_ids.push(file_id.into());
+        self
+    }
+
+    pub fn file_ids<I, S>(mut self, file_ids: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.request
+            .file_ids
+            .extend(file_ids.into_iter().map(Into::into));
+        self
+    }
+
+    pub fn props(mut self, props: PostProps) -> Self {
+        self.request.props = Some(props);
+        self
+    }
+
+    /// Show the post under a different username
+    ///
+    /// Only works for integrations and if enabled on the server, see [`CreatePostRequest::check_sender_override`].
+    pub fn override_username<S>(mut self, username: S) -> Self
+    where
+        S: Into<String>,
+    {
+        let props = self.request.props.get_or_insert_with(PostProps::default);
+        props.set_override_username(username);
+        props.set_from_webhook(true);
+        self
+    }
+
+    /// Show the post with a different profile picture
+    ///
+    /// Only works for integrations and if enabled on the server, see [`CreatePostRequest::check_sender_override`].
+    pub fn override_icon_url<S>(mut self, icon_url: S) -> Self
+    where
+        S: Into<String>,
+    {
+        let props = self.request.props.get_or_insert_with(PostProps::default);
+        props.set_override_icon_url(icon_url);
+        props.set_from_webhook(true);
+        self
+    }
+
+    pub fn build(self) -> Result<CreatePostRequest> {
+        if self.request.channel_id.is_empty() {
+            return Err(Error::InvalidPostRequest(
+                "channel_id must not be empty".into(),
+            ));
+        }
+        let message_length = self.request.message.chars().count();
+        if message_length > MAX_POST_MESSAGE_LENGTH {
+            return Err(Error::InvalidPostRequest(format!(
+                "message has {} characters, but at most {} are allowed",
+                message_length, MAX_POST_MESSAGE_LENGTH
+            )));
+        }
+        if self.request.file_ids.len() > MAX_POST_FILE_IDS {
+            return Err(Error::InvalidPostRequest(format!(
+                "{} files attached, but at most {} are allowed",
+                self.request.file_ids.len(),
+                MAX_POST_FILE_IDS
+            )));
+        }
+        Ok(self.request)
+    }
+}
+
+/// A line of the server log, see [`Client::get_logs`]
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+pub struct LogEntry {
+    /// Time in the format `2006-01-02 15:04:05.000 Z07:00`, see [`LogEntry::time`]
+    pub timestamp: String,
+    pub level: String,
+    pub msg: String,
+    /// Source location which created the log entry
+    #[serde(default)]
+    pub caller: String,
+    /// All other fields, which depend on the log message
+    #[serde(flatten)]
+    pub fields: BTreeMap<String, serde_json::Value>,
+}
+
+impl LogEntry {
+    pub fn time(&self) -> Option<DateTime<FixedOffset>> {
+        // The offset is `Z` for UTC, which chrono's `%:z` does not accept
+        let timestamp = match self.timestamp.strip_suffix(" Z") {
+            Some(timestamp) => format!("{} +00:00", timestamp),
+            None => self.timestamp.clone(),
+        };
+        DateTime::parse_from_str(&timestamp, "%Y-%m-%d %H:%M:%S%.f %:z").ok()
+    }
+}
+
+impl FromStr for LogEntry {
+    type Err = Error;
+
+    fn from_str(line: &str) -> Result<Self> {
+        Ok(serde_json::from_str(line)?)
+    }
+}
+
+/// An entry of the audit log, see [`Client::get_audits`]
+#[derive(Debug, Deserialize, Serialize, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct Audit {
+    pub id: String,
+    #[serde(with = "crate::serialize::ts_seconds")]
+    pub create_at: DateTime<Utc>,
+    /// User who made the request, empty for requests without a session
+    #[serde(default)]
+    pub user_id: UserId,
+    /// Path of the request, e.g., `/api/v4/channels/<channel_id>/members`
+    pub action: String,
+    /// Details depending on the action, as space separated `key=value` pairs
+    #[serde(default)]
+    pub extra_info: String,
+    #[serde(default)]
+    pub ip_address: String,
+    #[serde(default)]
+    pub session_id: String,
+}
+
+impl Audit {
+    /// The value of `key` in [`extra_info`](Audit::extra_info)
+    pub fn extra(&self, key: &str) -> Option<&str> {
+        self.extra_info.split_whitespace().find_map(|pair| {
+            let mut parts = pair.splitn(2, '=');
+            match (parts.next(), parts.next()) {
+                (Some(k), Some(value)) if k == key => Some(value),
+                _ => None,
+            }
+        })
+    }
+}
+
+/// A license as stored on the server
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+pub struct License {
+    pub id: String,
+    #[serde(with = "crate::serialize::ts_seconds")]
+    pub issued_at: DateTime<Utc>,
+    #[serde(with = "crate::serialize::ts_seconds")]
+    pub starts_at: DateTime<Utc>,
+    #[serde(with = "crate::serialize::ts_seconds")]
+    pub expires_at: DateTime<Utc>,
+    pub customer: LicenseCustomer,
+    /// Licensed features, like `users` or `ldap`
+    pub features: BTreeMap<String, serde_json::Value>,
+    pub sku_name: String,
+    pub sku_short_name: String,
+    #[serde(default)]
+    pub is_trial: bool,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct LicenseCustomer {
+    pub id: String,
+    pub name: String,
+    pub email: String,
+    pub company: String,
+}
+
+/// Usage of the server, see [`Client::get_usage`]
+#[derive(Debug, Deserialize, Serialize, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct Usage {
+    pub posts: u64,
+    pub storage_bytes: u64,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct TermsOfService {
+    pub id: String,
+    #[serde(with = "crate::serialize::ts_seconds")]
+    pub create_at: DateTime<Utc>,
+    /// The user who published the terms of service
+    pub user_id: String,
+    pub text: String,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct UserTermsOfService {
+    pub user_id: String,
+    pub terms_of_service_id: String,
+    /// Time of the acceptance
+    #[serde(with = "crate::serialize::ts_seconds")]
+    pub create_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize)]
+struct CreateTermsOfServiceRequest {
+    text: String,
+}
+
+#[derive(Debug, Serialize)]
+struct UserTermsAcceptanceRequest {
+    #[serde(rename = "serviceTermsId")]
+    terms_of_service_id: String,
+    accepted: bool,
 }
@@ -0,0 +1,161 @@
+use crate::{
+    api::{reconcile::retry_rate_limited, Client},
+    error::{Error, Result},
+    ids::{TeamId, UserId},
+};
+use log::warn;
+use serde::Serialize;
+
+/// What [`Client::offboard_user`] does
+///
+/// By default the user is not messaged and stays member of their teams, but all their sessions and tokens are revoked and the account is deactivated.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct OffboardOptions {
+    /// Direct message sent to the user before anything else
+    pub message: Option<String>,
+    /// Teams to remove the user from
+    pub team_ids: Vec<TeamId>,
+    pub revoke_tokens: bool,
+    pub revoke_sessions: bool,
+    pub deactivate: bool,
+}
+
+impl Default for OffboardOptions {
+    fn default() -> Self {
+        OffboardOptions {
+            message: None,
+            team_ids: Vec::new(),
+            revoke_tokens: true,
+            revoke_sessions: true,
+            deactivate: true,
+        }
+    }
+}
+
+/// A step of [`Client::offboard_user`], in the order they are run
+#[derive(Debug, Clone, Eq, PartialEq, Serialize)]
+#[serde(tag = "step", content = "team_id", rename_all = "snake_case")]
+pub enum OffboardStep {
+    Notify,
+    LeaveTeam(TeamId),
+    RevokeTokens,
+    RevokeSessions,
+    Deactivate,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Serialize)]
+pub struct OffboardStepResult {
+    #[serde(flatten)]
+    pub step: OffboardStep,
+    /// Why the step failed, `None` if it succeeded
+    pub error: Option<String>,
+}
+
+/// Result of [`Client::offboard_user`]
+#[derive(Debug, Clone, Eq, PartialEq, Serialize)]
+pub struct OffboardReport {
+    pub user_id: UserId,
+    pub steps: Vec<OffboardStepResult>,
+}
+
+impl OffboardReport {
+    /// All steps succeeded
+    pub fn is_complete(&self) -> bool {
+        self.steps.iter().all(|step| step.error.is_none())
+    }
+
+    pub fn failed(&self) -> impl Iterator<Item = &OffboardStepResult> {
+        self.steps.iter().filter(|step| step.error.is_some())
+    }
+}
+
+impl Client {
+    /// Remove a leaving user from the server, as selected by `options`
+    ///
+    /// The steps run in the order of [`OffboardStep`], such that the user can still read the message until the account is deactivated.
+    /// A failing step is reported in the [`OffboardReport`] and the remaining steps still run, e.g., the account is deactivated even if a team could not be left.
+    /// Errors which affect all steps, like an invalid token, abort the offboarding.
+    ///
+    /// Requires the `manage_system` permission for most steps.
+    pub fn offboard_user<U>(&self, user_id: U, options: &OffboardOptions) -> Result<OffboardReport>
+    where
+        U: AsRef<UserId>,
+    {
+        let user_id = user_id.as_ref();
+        let mut report = OffboardReport {
+            user_id: user_id.clone(),
+            steps: Vec::new(),
+        };
+        let mut run = |step: OffboardStep, result: Result<()>| -> Result<()> {
+            let error = match result {
+                Ok(()) => None,
+                Err(err) if err.status() == Some(401) => return Err(err),
+                Err(err) => {
+                    warn!("Offboarding {} failed at {:?}: {}", user_id, step, err);
+                    Some(err.to_string())
+                }
+            };
+            report.steps.push(OffboardStepResult { step, error });
+            Ok(())
+        };
+
+        if let Some(message) = &options.message {
+            run(
+                OffboardStep::Notify,
+                retry_rate_limited(|| self.send_direct_message(user_id, message.as_str()))
+                    .map(drop),
+            )?;
+        }
+        for team_id in &options.team_ids {
+            run(
+                OffboardStep::LeaveTeam(team_id.clone()),
+                retry_rate_limited(|| self.remove_team_member(team_id, user_id)),
+            )?;
+        }
+        if options.revoke_tokens {
+            run(OffboardStep::RevokeTokens, self.revoke_all_tokens(user_id))?;
+        }
+        if options.revoke_sessions {
+            run(
+                OffboardStep::RevokeSessions,
+                retry_rate_limited(|| self.revoke_all_sessions(user_id)),
+            )?;
+        }
+        if options.deactivate {
+            run(
+                OffboardStep::Deactivate,
+                retry_rate_limited(|| self.deactivate_user(user_id)),
+            )?;
+        }
+        Ok(report)
+    }
+
+    /// Revoke all active tokens, continuing after failures and returning the first one
+    fn revoke_all_tokens(&self, user_id: &UserId) -> Result<()> {
+        const PER_PAGE: usize = 100;
+
+        let mut tokens = Vec::new();
+        for page in 0.. {
+            let batch =
+                retry_rate_limited(|| self.get_user_access_tokens(user_id, page, PER_PAGE))?;
+            let count = batch.len();
+            tokens.extend(batch.into_iter().filter(|token| token.is_active));
+            if count < PER_PAGE {
+                break;
+            }
+        }
+
+        let mut first_error: Option<Error> = None;
+        for token in &tokens {
+            match retry_rate_limited(|| self.revoke_user_access_token(&token.id)) {
+                Ok(()) => {}
+                Err(err) if err.status() == Some(401) => return Err(err),
+                Err(err) => {
+                    warn!("Failed to revoke token {}: {}", token.id, err);
+                    first_error.get_or_insert(err);
+                }
+            }
+        }
+        first_error.map_or(Ok(()), Err)
+    }
+}
@@ -0,0 +1,64 @@
+use crate::{
+    api::{ChannelType, Client},
+    error::Result,
+    ids::ChannelId,
+};
+use std::collections::{BTreeSet, HashSet};
+
+/// The permissions the current user has in a channel
+///
+/// This combines the channel roles, including the ones granted by the channel scheme, with the system wide roles of the user.
+/// Team level roles, like `team_admin`, are not considered, so the answers err on the side of caution.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Permissions {
+    channel_type: ChannelType,
+    permissions: HashSet<String>,
+}
+
+impl Permissions {
+    /// Fetch the roles of the current user for the channel and resolve their permissions
+    pub fn for_channel<S>(client: &Client, channel_id: S) -> Result<Self>
+    where
+        S: AsRef<ChannelId>,
+    {
+        let channel = client.get_channel_by_id(channel_id)?;
+        let me = client.get_me()?;
+
+        let mut role_names: BTreeSet<String> = client.my_channel_role_names(&channel)?;
+        role_names.extend(me.roles.iter().map(ToString::to_string));
+        let roles = client.get_roles_by_names(&role_names)?;
+
+        Ok(Permissions {
+            channel_type: channel.type_,
+            permissions: roles
+                .into_iter()
+                .flat_map(|role| role.permissions.into_iter())
+                .collect(),
+        })
+    }
+
+    /// Check for a permission by its server side name, e.g., `create_post`
+    pub fn has_permission<S>(&self, permission: S) -> bool
+    where
+        S: AsRef<str>,
+    {
+        self.permissions.contains(permission.as_ref())
+    }
+
+    pub fn can_post(&self) -> bool {
+        self.has_permission("create_post")
+    }
+
+    pub fn can_delete_others_posts(&self) -> bool {
+        self.has_permission("delete_others_posts")
+    }
+
+    pub fn can_add_members(&self) -> bool {
+        match self.channel_type {
+            ChannelType::Open => self.has_permission("manage_public_channel_members"),
+            ChannelType::Private => self.has_permission("manage_private_channel_members"),
+            // The members of direct messages are fixed and adding members to a group message creates a new channel
+            ChannelType::DirectMessage | ChannelType::Group | ChannelType::Internal => false,
+        }
+    }
+}
@@ -0,0 +1,173 @@
+use crate::{
+    api::{ChannelType, UserRole},
+    websocket::Status,
+};
+use chrono::{DateTime, Utc};
+use std::fmt;
+use url::Url;
+
+/// Query parameters of a request
+///
+/// Values are formatted with [`QueryValue`], such that enums are sent in the form the server expects.
+///
+/// ```
+/// use mattermost_structs::api::{ChannelType, Query};
+/// use url::Url;
+///
+/// let mut url = Url::parse("https://chat.example.com/api/v4/channels").unwrap();
+/// Query::new()
+///     .param("type", ChannelType::Private)
+///     .page(0, 60)
+///     .apply(&mut url);
+/// assert_eq!(url.query(), Some("type=P&page=0&per_page=60"));
+/// ```
+#[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Default)]
+pub struct Query {
+    pairs: Vec<(String, String)>,
+}
+
+impl Query {
+    pub fn new() -> Query {
+        Query::default()
+    }
+
+    pub fn param<K, V>(mut self, key: K, value: V) -> Self
+    where
+        K: Into<String>,
+        V: QueryValue,
+    {
+        self.pairs.push((key.into(), value.to_query_string()));
+        self
+    }
+
+    /// Add the parameter only if there is a value
+    pub fn opt_param<K, V>(self, key: K, value: Option<V>) -> Self
+    where
+        K: Into<String>,
+        V: QueryValue,
+    {
+        match value {
+            Some(value) => self.param(key, value),
+            None => self,
+        }
+    }
+
+    /// The usual `page` and `per_page` parameters
+    pub fn page(self, page: usize, per_page: usize) -> Self {
+        self.param("page", page).param("per_page", per_page)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pairs.is_empty()
+    }
+
+    /// Append the parameters to the query of `url`
+    pub fn apply(&self, url: &mut Url) {
+        if !self.pairs.is_empty() {
+            url.query_pairs_mut().extend_pairs(&self.pairs);
+        }
+    }
+}
+
+/// A value which can be sent as query parameter
+pub trait QueryValue {
+    fn to_query_string(&self) -> String;
+}
+
+impl<T> QueryValue for &T
+where
+    T: QueryValue + ?Sized,
+{
+    fn to_query_string(&self) -> String {
+        (**self).to_query_string()
+    }
+}
+
+macro_rules! display_query_value {
+    ($($type:ty),*) => {
+        $(
+            impl QueryValue for $type {
+                fn to_query_string(&self) -> String {
+                    self.to_string()
+                }
+            }
+        )*
+    };
+}
+
+display_query_value!(str, String, bool, usize, u32, u64, i64, UserRole);
+
+/// Timestamps are sent as milliseconds since the epoch
+impl QueryValue for DateTime<Utc> {
+    fn to_query_string(&self) -> String {
+        self.timestamp_millis().to_string()
+    }
+}
+
+macro_rules! query_enum_value {
+    ($($type:ty),*) => {
+        $(
+            impl QueryValue for $type {
+                fn to_query_string(&self) -> String {
+                    self.as_query_str().to_string()
+                }
+            }
+
+            impl fmt::Display for $type {
+                fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                    f.write_str(self.as_query_str())
+                }
+            }
+        )*
+    };
+}
+
+query_enum_value!(ChannelType, Status, UserSort);
+
+impl ChannelType {
+    /// The single letter used by the server, e.g., `O` for open channels
+    pub fn as_query_str(self) -> &'static str {
+        match self {
+            ChannelType::Open => "O",
+            ChannelType::Private => "P",
+            ChannelType::DirectMessage => "D",
+            ChannelType::Group => "G",
+            ChannelType::Internal => "I",
+        }
+    }
+}
+
+impl Status {
+    pub fn as_query_str(self) -> &'static str {
+        match self {
+            Status::Online => "online",
+            Status::Away => "away",
+            Status::DoNotDisturb => "dnd",
+            Status::Offline => "offline",
+        }
+    }
+}
+
+/// Order of user lists
+///
+/// Which orders are supported depends on the endpoint.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub enum UserSort {
+    CreateAt,
+    LastActivityAt,
+    /// Online users first, only within a channel
+    Status,
+    /// Channel admins first, only within a channel
+    Admin,
+}
+
+impl UserSort {
+    pub fn as_query_str(self) -> &'static str {
+        match self {
+            UserSort::CreateAt => "create_at",
+            UserSort::LastActivityAt => "last_activity_at",
+            UserSort::Status => "status",
+            UserSort::Admin => "admin",
+        }
+    }
+}
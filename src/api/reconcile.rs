@@ -0,0 +1,194 @@
+use crate::{
+    api::Client,
+    error::{Error, Result},
+    ids::{ChannelId, UserId},
+};
+use log::{debug, warn};
+use serde::Serialize;
+use std::{collections::BTreeSet, thread, time::Duration};
+
+/// Number of changes applied before pausing
+pub(super) const BATCH_SIZE: usize = 50;
+/// Pause between two batches, to stay below the rate limit of the server
+pub(super) const BATCH_PAUSE: Duration = Duration::from_millis(500);
+/// How often a single change is retried after hitting the rate limit
+const RATE_LIMIT_RETRIES: u32 = 5;
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MembershipAction {
+    Add,
+    Remove,
+}
+
+/// A change which the server refused
+#[derive(Debug, Clone, Eq, PartialEq, Serialize)]
+pub struct MembershipFailure {
+    pub user_id: UserId,
+    pub action: MembershipAction,
+    pub error: String,
+}
+
+/// Result of [`Client::reconcile_channel_members`]
+///
+/// In a dry run `added` and `removed` list the changes which would have been made.
+#[derive(Debug, Clone, Eq, PartialEq, Serialize)]
+pub struct MembershipChanges {
+    pub channel_id: ChannelId,
+    pub dry_run: bool,
+    pub added: Vec<UserId>,
+    pub removed: Vec<UserId>,
+    /// Members which were neither added nor removed
+    pub unchanged: usize,
+    pub failed: Vec<MembershipFailure>,
+}
+
+impl MembershipChanges {
+    /// No changes were needed
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.failed.is_empty()
+    }
+
+    /// All changes were applied
+    pub fn is_complete(&self) -> bool {
+        self.failed.is_empty()
+    }
+}
+
+impl Client {
+    /// Ids of all members of a channel
+    pub fn get_channel_member_ids<S>(&self, channel_id: S) -> Result<BTreeSet<UserId>>
+    where
+        S: AsRef<ChannelId>,
+    {
+        const PER_PAGE: usize = 200;
+
+        let mut ids = BTreeSet::new();
+        for page in 0.. {
+            let members = self.get_channel_members(channel_id.as_ref(), page, PER_PAGE)?;
+            let count = members.len();
+            ids.extend(
+                members
+                    .into_iter()
+                    .map(|member| UserId::from(member.user_id)),
+            );
+            if count < PER_PAGE {
+                break;
+            }
+        }
+        Ok(ids)
+    }
+
+    /// Make `desired` the exact member list of a channel
+    ///
+    /// Users missing from the channel are added, members not in `desired` are removed.
+    /// The changes are applied in batches with a pause in between.
+    /// If the server answers with its rate limit, the change is retried after the limit resets.
+    ///
+    /// Changes refused by the server, e.g., for deactivated users, are reported in [`MembershipChanges::failed`] without stopping the others.
+    /// Errors which affect all changes, like an invalid token, abort the reconciliation.
+    pub fn reconcile_channel_members<C, U>(
+        &self,
+        channel_id: C,
+        desired: &[U],
+        dry_run: bool,
+    ) -> Result<MembershipChanges>
+    where
+        C: AsRef<ChannelId>,
+        U: AsRef<UserId>,
+    {
+        let channel_id = channel_id.as_ref();
+        let desired: BTreeSet<&UserId> = desired.iter().map(AsRef::as_ref).collect();
+        let current = self.get_channel_member_ids(channel_id)?;
+
+        let to_add: Vec<UserId> = desired
+            .iter()
+            .filter(|user_id| !current.contains(**user_id))
+            .map(|user_id| (*user_id).clone())
+            .collect();
+        let to_remove: Vec<UserId> = current
+            .iter()
+            .filter(|user_id| !desired.contains(user_id))
+            .cloned()
+            .collect();
+        let mut changes = MembershipChanges {
+            channel_id: channel_id.clone(),
+            dry_run,
+            unchanged: current.len() - to_remove.len(),
+            added: Vec::new(),
+            removed: Vec::new(),
+            failed: Vec::new(),
+        };
+        if dry_run {
+            changes.added = to_add;
+            changes.removed = to_remove;
+            return Ok(changes);
+        }
+
+        let pending = to_add
+            .into_iter()
+            .map(|user_id| (MembershipAction::Add, user_id))
+            .chain(
+                to_remove
+                    .into_iter()
+                    .map(|user_id| (MembershipAction::Remove, user_id)),
+            )
+            .collect::<Vec<_>>();
+        for (idx, batch) in pending.chunks(BATCH_SIZE).enumerate() {
+            if idx > 0 {
+                thread::sleep(BATCH_PAUSE);
+            }
+            for (action, user_id) in batch {
+                match self.apply_membership_change(channel_id, *action, user_id) {
+                    Ok(()) => match action {
+                        MembershipAction::Add => changes.added.push(user_id.clone()),
+                        MembershipAction::Remove => changes.removed.push(user_id.clone()),
+                    },
+                    Err(err) if err.status() == Some(401) => return Err(err),
+                    Err(err) => {
+                        warn!(
+                            "Failed to {:?} user {} in channel {}: {}",
+                            action, user_id, channel_id, err
+                        );
+                        changes.failed.push(MembershipFailure {
+                            user_id: user_id.clone(),
+                            action: *action,
+                            error: err.to_string(),
+                        });
+                    }
+                }
+            }
+        }
+        Ok(changes)
+    }
+
+    fn apply_membership_change(
+        &self,
+        channel_id: &ChannelId,
+        action: MembershipAction,
+        user_id: &UserId,
+    ) -> Result<()> {
+        retry_rate_limited(|| match action {
+            MembershipAction::Add => self.add_channel_member(channel_id, user_id).map(|_| ()),
+            MembershipAction::Remove => self.remove_channel_member(channel_id, user_id),
+        })
+    }
+}
+
+/// Call `request` again after the rate limit resets, up to [`RATE_LIMIT_RETRIES`] times
+pub(super) fn retry_rate_limited<T, F>(mut request: F) -> Result<T>
+where
+    F: FnMut() -> Result<T>,
+{
+    let mut attempt = 0;
+    loop {
+        match request() {
+            Err(Error::RateLimited(reset)) if attempt < RATE_LIMIT_RETRIES => {
+                debug!("Rate limited, waiting {}s", reset);
+                thread::sleep(Duration::from_secs(reset.max(1)));
+                attempt += 1;
+            }
+            result => return result,
+        }
+    }
+}
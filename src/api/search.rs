@@ -0,0 +1,142 @@
+use chrono::NaiveDate;
+use std::fmt;
+
+/// Search terms with the modifiers of the search box of the web client, see [`Client::search_posts`](super::Client::search_posts)
+///
+/// Modifiers are combined with the words and phrases, e.g., only posts of `@alice` in `~town-square` containing `release`.
+/// Dates are whole days in the time zone of the server: `before:` and `after:` exclude the given day, `on:` matches only it.
+///
+/// ```
+/// use chrono::NaiveDate;
+/// use mattermost_structs::api::SearchTerms;
+///
+/// let terms = SearchTerms::new()
+///     .word("release")
+///     .phrase("known issues")
+///     .from("@alice")
+///     .in_channel("town-square")
+///     .after(NaiveDate::from_ymd_opt(2024, 1, 1).unwrap())
+///     .exclude("draft");
+/// assert_eq!(
+///     terms.to_string(),
+///     r#"release "known issues" -draft from:alice in:town-square after:2024-01-01"#
+/// );
+/// ```
+#[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Default)]
+pub struct SearchTerms {
+    words: Vec<String>,
+    modifiers: Vec<String>,
+}
+
+impl SearchTerms {
+    pub fn new() -> SearchTerms {
+        SearchTerms::default()
+    }
+
+    /// A single word, a trailing `*` matches all words with this prefix
+    pub fn word<S>(mut self, word: S) -> Self
+    where
+        S: AsRef<str>,
+    {
+        self.words.push(word.as_ref().to_string());
+        self
+    }
+
+    /// Words which must appear in this order
+    ///
+    /// Quotes inside the phrase are removed, since the search does not support escaping them.
+    pub fn phrase<S>(mut self, phrase: S) -> Self
+    where
+        S: AsRef<str>,
+    {
+        self.words
+            .push(format!("\"{}\"", phrase.as_ref().replace('"', "")));
+        self
+    }
+
+    /// Only posts without this word
+    pub fn exclude<S>(mut self, word: S) -> Self
+    where
+        S: AsRef<str>,
+    {
+        self.words.push(format!("-{}", word.as_ref()));
+        self
+    }
+
+    /// Only posts of the user with this username, with or without the leading `@`
+    pub fn from<S>(mut self, username: S) -> Self
+    where
+        S: AsRef<str>,
+    {
+        let username = username.as_ref();
+        self.modifiers.push(format!(
+            "from:{}",
+            username.strip_prefix('@').unwrap_or(username)
+        ));
+        self
+    }
+
+    /// Only posts in the channel with this name, with or without the leading `~`
+    ///
+    /// This is the name used in URLs, not the display name.
+    pub fn in_channel<S>(mut self, channel_name: S) -> Self
+    where
+        S: AsRef<str>,
+    {
+        let channel_name = channel_name.as_ref();
+        self.modifiers.push(format!(
+            "in:{}",
+            channel_name.strip_prefix('~').unwrap_or(channel_name)
+        ));
+        self
+    }
+
+    /// Only posts created before `date`, excluding the day itself
+    pub fn before(mut self, date: NaiveDate) -> Self {
+        self.modifiers
+            .push(format!("before:{}", date.format("%Y-%m-%d")));
+        self
+    }
+
+    /// Only posts created after `date`, excluding the day itself
+    pub fn after(mut self, date: NaiveDate) -> Self {
+        self.modifiers
+            .push(format!("after:{}", date.format("%Y-%m-%d")));
+        self
+    }
+
+    /// Only posts created on `date`
+    pub fn on(mut self, date: NaiveDate) -> Self {
+        self.modifiers
+            .push(format!("on:{}", date.format("%Y-%m-%d")));
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.words.is_empty() && self.modifiers.is_empty()
+    }
+}
+
+impl fmt::Display for SearchTerms {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, term) in self.words.iter().chain(&self.modifiers).enumerate() {
+            if i > 0 {
+                f.write_str(" ")?;
+            }
+            f.write_str(term)?;
+        }
+        Ok(())
+    }
+}
+
+impl From<SearchTerms> for String {
+    fn from(terms: SearchTerms) -> String {
+        terms.to_string()
+    }
+}
+
+impl From<&SearchTerms> for String {
+    fn from(terms: &SearchTerms) -> String {
+        terms.to_string()
+    }
+}
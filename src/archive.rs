@@ -0,0 +1,271 @@
+//! Archive posts, edits, deletions, and reactions from the websocket stream in SQLite
+//!
+//! Only available with the cargo feature `archive`.
+
+use crate::{
+    error::Result,
+    ids::{ChannelId, PostId, UserId},
+    render::{render_system_post, English},
+    sink::EventSink,
+    websocket::{Events, MessagePush, Post, Reaction},
+};
+use chrono::{DateTime, TimeZone, Utc};
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use std::path::Path;
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS posts (
+    id TEXT PRIMARY KEY NOT NULL,
+    channel_id TEXT NOT NULL,
+    user_id TEXT NOT NULL,
+    root_id TEXT,
+    type TEXT NOT NULL,
+    message TEXT NOT NULL,
+    is_pinned INTEGER NOT NULL,
+    create_at INTEGER NOT NULL,
+    update_at INTEGER NOT NULL,
+    edit_at INTEGER,
+    delete_at INTEGER
+);
+CREATE INDEX IF NOT EXISTS posts_channel_create_at ON posts (channel_id, create_at);
+
+CREATE TABLE IF NOT EXISTS post_edits (
+    post_id TEXT NOT NULL REFERENCES posts (id),
+    edit_at INTEGER NOT NULL,
+    message TEXT NOT NULL,
+    PRIMARY KEY (post_id, edit_at)
+);
+
+CREATE TABLE IF NOT EXISTS reactions (
+    post_id TEXT NOT NULL,
+    user_id TEXT NOT NULL,
+    emoji_name TEXT NOT NULL,
+    create_at INTEGER NOT NULL,
+    PRIMARY KEY (post_id, user_id, emoji_name)
+);
+";
+
+/// A post as stored in the archive
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct ArchivedPost {
+    pub id: PostId,
+    pub channel_id: ChannelId,
+    pub user_id: UserId,
+    pub root_id: Option<PostId>,
+    pub message: String,
+    pub is_pinned: bool,
+    pub create_at: DateTime<Utc>,
+    pub edit_at: Option<DateTime<Utc>>,
+    pub delete_at: Option<DateTime<Utc>>,
+}
+
+pub struct Archive {
+    connection: Connection,
+}
+
+impl Archive {
+    /// Open the archive at `path`, creating the database and the schema if necessary
+    pub fn open<P>(path: P) -> Result<Archive>
+    where
+        P: AsRef<Path>,
+    {
+        Self::from_connection(Connection::open(path)?)
+    }
+
+    pub fn from_connection(connection: Connection) -> Result<Archive> {
+        connection.execute_batch(SCHEMA)?;
+        Ok(Archive { connection })
+    }
+
+    /// Store the effects of a websocket event
+    ///
+    /// Events which do not affect posts or reactions are ignored.
+    pub fn handle(&mut self, event: &Events) -> Result<()> {
+        match event {
+            Events::Posted { post, .. } | Events::PostDeleted { post } => self.upsert_post(post),
+            Events::PostEdited { post } => {
+                let tx = self.connection.transaction()?;
+                upsert_post(&tx, post)?;
+                tx.execute(
+                    "INSERT INTO post_edits (post_id, edit_at, message) VALUES (?1, ?2, ?3)
+                    ON CONFLICT (post_id, edit_at) DO NOTHING",
+                    params![post.id, post.edit_at.timestamp_millis(), post.message],
+                )?;
+                tx.commit()?;
+                Ok(())
+            }
+            Events::ReactionAdded { reaction } => self.insert_reaction(reaction),
+            Events::ReactionRemoved { reaction } => {
+                self.connection.execute(
+                    "DELETE FROM reactions WHERE post_id = ?1 AND user_id = ?2 AND emoji_name = ?3",
+                    params![reaction.post_id, reaction.user_id, reaction.emoji_name],
+                )?;
+                Ok(())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    pub fn upsert_post(&self, post: &Post) -> Result<()> {
+        upsert_post(&self.connection, post)
+    }
+
+    pub fn insert_reaction(&self, reaction: &Reaction) -> Result<()> {
+        self.connection.execute(
+            "INSERT INTO reactions (post_id, user_id, emoji_name, create_at) VALUES (?1, ?2, ?3, ?4)
+            ON CONFLICT (post_id, user_id, emoji_name) DO NOTHING",
+            params![
+                reaction.post_id,
+                reaction.user_id,
+                reaction.emoji_name,
+                reaction.create_at.timestamp_millis()
+            ],
+        )?;
+        Ok(())
+    }
+
+    pub fn get_post<S>(&self, post_id: S) -> Result<Option<ArchivedPost>>
+    where
+        S: AsRef<str>,
+    {
+        Ok(self
+            .connection
+            .query_row(
+                "SELECT id, channel_id, user_id, root_id, message, is_pinned, create_at, edit_at, delete_at
+                FROM posts WHERE id = ?1",
+                params![post_id.as_ref()],
+                archived_post_from_row,
+            )
+            .optional()?)
+    }
+
+    /// All posts of the channel created in the half-open interval [`start`, `end`), ordered by creation time
+    ///
+    /// Deleted posts are included and can be recognized by `delete_at`.
+    pub fn posts_in_channel_between<S>(
+        &self,
+        channel_id: S,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Result<Vec<ArchivedPost>>
+    where
+        S: AsRef<str>,
+    {
+        let mut statement = self.connection.prepare(
+            "SELECT id, channel_id, user_id, root_id, message, is_pinned, create_at, edit_at, delete_at
+            FROM posts
+            WHERE channel_id = ?1 AND create_at >= ?2 AND create_at < ?3
+            ORDER BY create_at",
+        )?;
+        let posts = statement
+            .query_map(
+                params![
+                    channel_id.as_ref(),
+                    start.timestamp_millis(),
+                    end.timestamp_millis()
+                ],
+                archived_post_from_row,
+            )?
+            .collect::<rusqlite::Result<_>>()?;
+        Ok(posts)
+    }
+
+    /// All versions of a post's message, oldest first
+    pub fn post_edits<S>(&self, post_id: S) -> Result<Vec<(DateTime<Utc>, String)>>
+    where
+        S: AsRef<str>,
+    {
+        let mut statement = self.connection.prepare(
+            "SELECT edit_at, message FROM post_edits WHERE post_id = ?1 ORDER BY edit_at",
+        )?;
+        let edits = statement
+            .query_map(params![post_id.as_ref()], |row| {
+                Ok((timestamp_from_row(row, 0)?, row.get(1)?))
+            })?
+            .collect::<rusqlite::Result<_>>()?;
+        Ok(edits)
+    }
+}
+
+impl EventSink for Archive {
+    fn publish(&mut self, message: &MessagePush) -> Result<()> {
+        self.handle(&message.event)
+    }
+}
+
+fn upsert_post(connection: &Connection, post: &Post) -> Result<()> {
+    // System posts may only describe the event in their props
+    let message = if post.message.is_empty() {
+        render_system_post(post, &English)
+    } else {
+        post.message.clone()
+    };
+    connection.execute(
+        "INSERT INTO posts (id, channel_id, user_id, root_id, type, message, is_pinned, create_at, update_at, edit_at, delete_at)
+        VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)
+        ON CONFLICT (id) DO UPDATE SET
+            message = excluded.message,
+            is_pinned = excluded.is_pinned,
+            update_at = excluded.update_at,
+            edit_at = excluded.edit_at,
+            delete_at = excluded.delete_at
+        WHERE excluded.update_at >= posts.update_at",
+        params![
+            post.id,
+            post.channel_id,
+            post.user_id,
+            if post.root_id.is_empty() {
+                None
+            } else {
+                Some(&post.root_id)
+            },
+            serde_json::to_value(post.type_)?.as_str().unwrap_or(""),
+            message,
+            post.is_pinned,
+            post.create_at.timestamp_millis(),
+            post.update_at.timestamp_millis(),
+            optional_timestamp(post.edit_at),
+            optional_timestamp(post.delete_at),
+        ],
+    )?;
+    Ok(())
+}
+
+/// The server uses 0 for timestamps which are not set
+fn optional_timestamp(timestamp: DateTime<Utc>) -> Option<i64> {
+    match timestamp.timestamp_millis() {
+        0 => None,
+        millis => Some(millis),
+    }
+}
+
+fn archived_post_from_row(row: &Row<'_>) -> rusqlite::Result<ArchivedPost> {
+    Ok(ArchivedPost {
+        id: row.get(0)?,
+        channel_id: row.get(1)?,
+        user_id: row.get(2)?,
+        root_id: row.get(3)?,
+        message: row.get(4)?,
+        is_pinned: row.get(5)?,
+        create_at: timestamp_from_row(row, 6)?,
+        edit_at: optional_timestamp_from_row(row, 7)?,
+        delete_at: optional_timestamp_from_row(row, 8)?,
+    })
+}
+
+fn timestamp_from_row(row: &Row<'_>, idx: usize) -> rusqlite::Result<DateTime<Utc>> {
+    let millis: i64 = row.get(idx)?;
+    Utc.timestamp_millis_opt(millis)
+        .single()
+        .ok_or_else(|| rusqlite::Error::IntegralValueOutOfRange(idx, millis))
+}
+
+fn optional_timestamp_from_row(
+    row: &Row<'_>,
+    idx: usize,
+) -> rusqlite::Result<Option<DateTime<Utc>>> {
+    match row.get::<_, Option<i64>>(idx)? {
+        Some(_) => timestamp_from_row(row, idx).map(Some),
+        None => Ok(None),
+    }
+}
@@ -0,0 +1,125 @@
+//! Decide how attachments are passed on
+//!
+//! An [`AttachmentPolicy`] limits which files are copied when relaying a post, e.g., to keep large videos off a small server.
+//! Files may be uploaded again, replaced by a link to the original, or dropped.
+//! With the cargo feature `image`, large images are downscaled before uploading.
+
+use crate::api::FileInfo;
+use serde::{Deserialize, Serialize};
+
+/// How files which pass the policy are handled
+#[derive(
+    Debug, Deserialize, Serialize, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Default,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum AttachmentMode {
+    /// Upload the file to the destination
+    #[default]
+    Reupload,
+    /// Add a link to the original file to the message
+    LinkOnly,
+    /// Leave out all files
+    Drop,
+}
+
+/// What to do with a single file
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum AttachmentDecision {
+    Reupload,
+    Link,
+    /// The file is left out for the given reason
+    Skip(String),
+}
+
+/// Rules for the attachments sent to one destination
+#[derive(Debug, Deserialize, Serialize, Clone, Eq, PartialEq, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct AttachmentPolicy {
+    pub mode: AttachmentMode,
+    /// Larger files are linked instead of uploaded
+    pub max_size: Option<u64>,
+    /// MIME types which are passed on, e.g., `image/png` or `image/*`, empty allows all types
+    pub allowed_mime_types: Vec<String>,
+    /// Images are scaled down until neither side is longer, requires the feature `image`
+    pub max_image_dimension: Option<u32>,
+}
+
+impl AttachmentPolicy {
+    /// Only link to the files
+    pub fn link_only() -> AttachmentPolicy {
+        AttachmentPolicy {
+            mode: AttachmentMode::LinkOnly,
+            ..Default::default()
+        }
+    }
+
+    pub fn allows_mime_type(&self, mime_type: &str) -> bool {
+        let mime_type = mime_type.to_ascii_lowercase();
+        self.allowed_mime_types.is_empty()
+            || self.allowed_mime_types.iter().any(|allowed| {
+                let allowed = allowed.to_ascii_lowercase();
+                match allowed.strip_suffix("/*") {
+                    Some(kind) => mime_type
+                        .strip_prefix(kind)
+                        .is_some_and(|rest| rest.starts_with('/')),
+                    None => allowed == mime_type,
+                }
+            })
+    }
+
+    pub fn decide(&self, info: &FileInfo) -> AttachmentDecision {
+        if self.mode == AttachmentMode::Drop {
+            return AttachmentDecision::Skip("attachments are dropped".to_string());
+        }
+        if !self.allows_mime_type(&info.mime_type) {
+            return AttachmentDecision::Skip(format!("type {} is not allowed", info.mime_type));
+        }
+        // Images which are downscaled may still fit
+        let shrinks = cfg!(feature = "image")
+            && self.max_image_dimension.is_some()
+            && info.mime_type.starts_with("image/");
+        let too_large = self.max_size.is_some_and(|max| info.size > max);
+        if self.mode == AttachmentMode::LinkOnly || (too_large && !shrinks) {
+            AttachmentDecision::Link
+        } else {
+            AttachmentDecision::Reupload
+        }
+    }
+
+    /// Whether the file, after downscaling, may be uploaded
+    pub fn fits(&self, size: u64) -> bool {
+        self.max_size.is_none_or(|max| size <= max)
+    }
+
+    /// Scale down an image larger than [`max_image_dimension`](AttachmentPolicy::max_image_dimension)
+    ///
+    /// The image keeps its format and aspect ratio.
+    /// Data which is not an image, or in a format which cannot be written, is returned unchanged.
+    #[cfg(feature = "image")]
+    pub fn downscale(&self, data: Vec<u8>) -> Vec<u8> {
+        let max = match self.max_image_dimension {
+            Some(max) => max,
+            None => return data,
+        };
+        let format = match image::guess_format(&data) {
+            Ok(format) => format,
+            Err(_) => return data,
+        };
+        let image = match image::load_from_memory_with_format(&data, format) {
+            Ok(image) => image,
+            Err(_) => return data,
+        };
+        let (width, height) = image::GenericImageView::dimensions(&image);
+        if width <= max && height <= max {
+            return data;
+        }
+        let mut scaled = Vec::new();
+        match image.thumbnail(max, max).write_to(&mut scaled, format) {
+            Ok(()) => scaled,
+            Err(err) => {
+                log::debug!("Keeping the original image, cannot write it: {}", err);
+                data
+            }
+        }
+    }
+}
@@ -0,0 +1,257 @@
+//! A single log of who joined and left channels
+//!
+//! Membership changes show up in three places: as system posts in the channel, as `user_added` and `user_removed` events of the websocket, and in the audit log of the server.
+//! Each source misses some changes, e.g., events are lost while disconnected and system posts are not created for all changes.
+//! [`MembershipAuditStream`] turns all of them into [`MembershipRecord`]s and drops the changes already reported by another source.
+//!
+//! ```
+//! use chrono::{Duration, TimeZone, Utc};
+//! use mattermost_structs::{
+//!     audit::{MembershipAction, MembershipAuditStream},
+//!     websocket::MessagePush,
+//! };
+//!
+//! let added: MessagePush = serde_json::from_str(
+//!     r#"{"event":"user_added","data":{"team_id":"","user_id":"alice"},
+//!         "broadcast":{"omit_users":null,"user_id":"","channel_id":"town-square","team_id":""},"seq":1}"#,
+//! )?;
+//! let at = Utc.with_ymd_and_hms(2024, 1, 1, 12, 0, 0).unwrap();
+//! let mut stream = MembershipAuditStream::new(std::time::Duration::from_secs(60));
+//! let record = stream.record_event(&added, at).unwrap();
+//! assert_eq!(record.action, MembershipAction::Joined);
+//! assert_eq!(record.user_id, "alice");
+//! // The same change, e.g., from a duplicated event
+//! assert!(stream.record_event(&added, at + Duration::seconds(2)).is_none());
+//! # Ok::<(), serde_json::Error>(())
+//! ```
+
+use crate::{
+    api::{Audit, Client},
+    error::Result,
+    ids::{ChannelId, UserId},
+    websocket::{Events, MessagePush, Post, PostType},
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, time::Duration};
+
+#[derive(Debug, Deserialize, Serialize, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum MembershipAction {
+    /// The user joined or was added to the channel
+    Joined,
+    /// The user left or was removed from the channel
+    Left,
+}
+
+/// Where a [`MembershipRecord`] was found
+#[derive(Debug, Deserialize, Serialize, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditSource {
+    /// A system post, like "@alice joined the channel."
+    Post,
+    /// A `user_added` or `user_removed` event of the websocket
+    Event,
+    /// An entry of the audit log, see [`Client::get_audits`]
+    Audit,
+}
+
+/// A user joined or left a channel
+#[derive(Debug, Deserialize, Serialize, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct MembershipRecord {
+    pub channel_id: ChannelId,
+    pub user_id: UserId,
+    pub action: MembershipAction,
+    /// User who made the change, the same as `user_id` if the user joined or left on their own
+    ///
+    /// Unknown for `user_added` events.
+    pub actor_id: Option<UserId>,
+    #[serde(with = "crate::serialize::ts_seconds")]
+    pub at: DateTime<Utc>,
+    pub source: AuditSource,
+}
+
+impl MembershipRecord {
+    /// The change described by a system post
+    ///
+    /// Only the individual posts are supported, not [`PostType::SystemCombinedUserActivity`].
+    pub fn from_post(post: &Post) -> Option<MembershipRecord> {
+        let props = &post.props;
+        let author = Some(post.user_id.clone());
+        let (user_id, action, actor_id) = match post.type_ {
+            PostType::SystemJoinChannel | PostType::SystemGuestJoinChannel => {
+                (post.user_id.clone(), MembershipAction::Joined, author)
+            }
+            PostType::SystemLeaveChannel => (post.user_id.clone(), MembershipAction::Left, author),
+            PostType::SystemAddToChannel => (
+                UserId::from(props.added_user_id()?),
+                MembershipAction::Joined,
+                props.user_id().map(UserId::from).or(author),
+            ),
+            PostType::SystemRemoveFromChannel => (
+                UserId::from(props.removed_user_id()?),
+                MembershipAction::Left,
+                author,
+            ),
+            _ => return None,
+        };
+        Some(MembershipRecord {
+            channel_id: post.channel_id.clone(),
+            user_id,
+            action,
+            actor_id,
+            at: post.create_at,
+            source: AuditSource::Post,
+        })
+    }
+
+    /// The change described by an event, which happened `at`
+    ///
+    /// Only events broadcast to the channel are supported, not the ones sent to the removed user.
+    pub fn from_event(message: &MessagePush, at: DateTime<Utc>) -> Option<MembershipRecord> {
+        let (user_id, action, actor_id) = match &message.event {
+            Events::UserAdded { user_id, .. } => (user_id, MembershipAction::Joined, None),
+            Events::UserRemoved {
+                user_id,
+                remover_id,
+            } => (
+                user_id,
+                MembershipAction::Left,
+                Some(remover_id).filter(|id| !id.is_empty()),
+            ),
+            _ => return None,
+        };
+        if message.broadcast.channel_id.is_empty() || user_id.is_empty() {
+            return None;
+        }
+        Some(MembershipRecord {
+            channel_id: message.broadcast.channel_id.clone(),
+            user_id: UserId::from(user_id),
+            action,
+            actor_id: actor_id.map(UserId::from),
+            at,
+            source: AuditSource::Event,
+        })
+    }
+
+    /// The change described by an entry of the audit log
+    ///
+    /// Adding a member is logged as `/api/v4/channels/<channel_id>/members` with the `user_id` in the extra info,
+    /// removing one as `/api/v4/channels/<channel_id>/members/<user_id>`.
+    pub fn from_audit(audit: &Audit) -> Option<MembershipRecord> {
+        let path = audit.action.strip_prefix("/api/v4/channels/")?;
+        let parts: Vec<&str> = path.split('/').collect();
+        let (channel_id, user_id, action) = match parts[..] {
+            [channel_id, "members"] => (
+                channel_id,
+                audit.extra("user_id")?,
+                MembershipAction::Joined,
+            ),
+            [channel_id, "members", user_id] => (channel_id, user_id, MembershipAction::Left),
+            _ => return None,
+        };
+        Some(MembershipRecord {
+            channel_id: ChannelId::from(channel_id),
+            user_id: UserId::from(user_id),
+            action,
+            actor_id: Some(audit.user_id.clone()).filter(|id| !id.is_empty()),
+            at: audit.create_at,
+            source: AuditSource::Audit,
+        })
+    }
+}
+
+/// Deduplicates membership changes across sources
+///
+/// A change is a duplicate if the same user joined, or left, the same channel at most `window` apart.
+/// The sources disagree slightly about the time of a change, and events only carry the time they were received, so the window should cover a few seconds at least.
+/// Changes are remembered for the [`retention`](MembershipAuditStream::retention), changes older than that compared to the newest one are dropped.
+#[derive(Debug)]
+pub struct MembershipAuditStream {
+    window_millis: i64,
+    retention_millis: i64,
+    /// Reported actions and their timestamp in milliseconds by channel and user
+    history: HashMap<(ChannelId, UserId), Vec<(MembershipAction, i64)>>,
+    /// Timestamp of the newest change in milliseconds
+    newest: i64,
+}
+
+impl MembershipAuditStream {
+    pub fn new(window: Duration) -> MembershipAuditStream {
+        MembershipAuditStream {
+            window_millis: millis(window),
+            retention_millis: millis(Duration::from_secs(24 * 60 * 60)),
+            history: HashMap::new(),
+            newest: i64::MIN,
+        }
+    }
+
+    /// How long changes are remembered, one day by default
+    ///
+    /// Must be longer than the delay between a change and its last report, e.g., the interval in which the audit log is fetched.
+    pub fn retention(mut self, retention: Duration) -> Self {
+        self.retention_millis = millis(retention);
+        self
+    }
+
+    pub fn record_post(&mut self, post: &Post) -> Option<MembershipRecord> {
+        self.record(MembershipRecord::from_post(post)?)
+    }
+
+    pub fn record_event(
+        &mut self,
+        message: &MessagePush,
+        at: DateTime<Utc>,
+    ) -> Option<MembershipRecord> {
+        self.record(MembershipRecord::from_event(message, at)?)
+    }
+
+    pub fn record_audit(&mut self, audit: &Audit) -> Option<MembershipRecord> {
+        self.record(MembershipRecord::from_audit(audit)?)
+    }
+
+    /// Record the newest `per_page` entries of the audit log, returning the new changes oldest first
+    pub fn fetch_audits(
+        &mut self,
+        client: &Client,
+        per_page: usize,
+    ) -> Result<Vec<MembershipRecord>> {
+        let mut audits = client.get_audits(0, per_page)?;
+        audits.sort_by_key(|audit| audit.create_at);
+        Ok(audits
+            .iter()
+            .filter_map(|audit| self.record_audit(audit))
+            .collect())
+    }
+
+    /// Returns the record, unless it is a duplicate or too old
+    pub fn record(&mut self, record: MembershipRecord) -> Option<MembershipRecord> {
+        let at = record.at.timestamp_millis();
+        self.newest = self.newest.max(at);
+        let horizon = self.newest.saturating_sub(self.retention_millis);
+        if at < horizon {
+            return None;
+        }
+
+        let window_millis = self.window_millis;
+        let key = (record.channel_id.clone(), record.user_id.clone());
+        let history = self.history.entry(key).or_default();
+        let duplicate = history.iter().any(|(action, reported)| {
+            *action == record.action
+                && reported.saturating_sub(at).saturating_abs() <= window_millis
+        });
+        if duplicate {
+            return None;
+        }
+        history.push((record.action, at));
+        self.history.retain(|_, history| {
+            history.retain(|(_, reported)| *reported >= horizon);
+            !history.is_empty()
+        });
+        Some(record)
+    }
+}
+
+fn millis(duration: Duration) -> i64 {
+    duration.as_millis().min(i64::MAX as u128) as i64
+}
@@ -0,0 +1,837 @@
+use chrono::{Local, Utc};
+use chrono_tz::Tz;
+use log::{error, warn};
+use mattermost_structs::{
+    api::{Client, ExportFormat},
+    emoji::EmojiResolver,
+    error::ResultExt,
+    forward::{ForwardQueue, Forwarder},
+    ids::{ChannelId, TeamId, UserId},
+    preflight,
+    reconnect::Reconnector,
+    scheduler::{Job, Scheduler},
+    stats::ChannelActivity,
+    websocket::{self, Message},
+    Result,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{hash_map::Entry, BTreeSet, HashMap},
+    env,
+    fs::{self, File},
+    io::{self, BufRead, BufReader, BufWriter, Write},
+    path::{Path, PathBuf},
+    process, thread,
+    time::Duration,
+};
+use structopt::StructOpt;
+
+/// Command line client for Mattermost
+#[derive(Debug, StructOpt)]
+#[structopt(
+    author = "",
+    raw(setting = "structopt::clap::AppSettings::ColoredHelp")
+)]
+struct CliArgs {
+    /// Base URL of the Mattermost server
+    #[structopt(long = "server", alias = "url", env = "MATTERMOST_URL")]
+    server: Option<String>,
+    /// Access token used for authentication
+    #[structopt(
+        long = "token",
+        env = "MATTERMOST_TOKEN",
+        raw(hide_env_values = "true")
+    )]
+    token: Option<String>,
+    /// Refuse all requests which change anything on the server
+    #[structopt(long = "read-only")]
+    read_only: bool,
+    #[structopt(subcommand)]
+    cmd: Command,
+}
+
+/// Settings stored by `mmcli login`, used if `--server` or `--token` are missing
+///
+/// With the `keyring` feature the token is kept in the keyring of the operating system instead.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct CliConfig {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    server: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    token: Option<String>,
+}
+
+impl CliConfig {
+    /// `$MMCLI_CONFIG`, or `mmcli/config.yaml` in the user's configuration directory
+    fn path() -> Option<PathBuf> {
+        if let Some(path) = env::var_os("MMCLI_CONFIG") {
+            return Some(path.into());
+        }
+        let config_dir = env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .or_else(|| env::var_os("HOME").map(|home| Path::new(&home).join(".config")))?;
+        Some(config_dir.join("mmcli").join("config.yaml"))
+    }
+
+    fn load() -> Result<CliConfig> {
+        match Self::path() {
+            Some(ref path) if path.exists() => Ok(serde_yaml::from_reader(File::open(path)?)?),
+            _ => Ok(CliConfig::default()),
+        }
+    }
+
+    /// Write the config, readable only by the current user as it contains the token
+    fn save(&self) -> Result<PathBuf> {
+        let path = Self::path().ok_or("Cannot determine the config directory, set MMCLI_CONFIG")?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let mut options = fs::OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(0o600);
+        }
+        serde_yaml::to_writer(options.open(&path)?, self)?;
+        Ok(path)
+    }
+}
+
+/// Entry of the token for `server` in the keyring of the operating system
+#[cfg(feature = "keyring")]
+fn keyring_entry(server: &str) -> Result<keyring::Entry> {
+    keyring::Entry::new("mmcli", server).chain_err(|| "Cannot access the keyring")
+}
+
+/// The token for `server` stored by `mmcli login`, if any
+#[cfg(feature = "keyring")]
+fn keyring_token(server: &str) -> Result<Option<String>> {
+    match keyring_entry(server)?.get_password() {
+        Ok(token) => Ok(Some(token)),
+        Err(keyring::Error::NoEntry) => Ok(None),
+        Err(err) => Err(err).chain_err(|| "Cannot read the token from the keyring"),
+    }
+}
+
+impl CliArgs {
+    /// Server and token, which are required for all commands talking to a server
+    fn credentials(&self) -> Result<(&str, &str)> {
+        match (&self.server, &self.token) {
+            (Some(server), Some(token)) => Ok((server, token)),
+            _ => Err("This command requires --server and --token".into()),
+        }
+    }
+
+    fn client(&self) -> Result<Client> {
+        let (server, token) = self.credentials()?;
+        Client::builder(server, token)
+            .read_only(self.read_only)
+            .build()
+    }
+}
+
+#[derive(Debug, StructOpt)]
+enum Command {
+    /// Log in with username and password and store the server and token in the config file
+    ///
+    /// The password is read from MATTERMOST_PASSWORD or prompted for without echoing it.
+    /// With the `keyring` feature the token is stored in the keyring of the operating system instead of the config file.
+    #[structopt(name = "login")]
+    Login {
+        /// Base URL of the Mattermost server
+        url: String,
+        /// Username or email address (default: prompt)
+        #[structopt(long = "user")]
+        login_id: Option<String>,
+        /// Code for multi-factor authentication
+        #[structopt(long = "mfa")]
+        mfa: Option<String>,
+        /// Create a personal access token with this description and store it instead of the session token
+        ///
+        /// Session tokens expire, personal access tokens do not.
+        #[structopt(long = "create-token")]
+        token_description: Option<String>,
+    },
+    /// Check DNS, TLS, the token, the websocket, and the clock of the server
+    #[structopt(name = "preflight")]
+    Preflight {
+        /// Print the report as JSON
+        #[structopt(long = "json")]
+        json: bool,
+        /// Seconds to wait for each check
+        #[structopt(long = "timeout", default_value = "10")]
+        timeout: u64,
+    },
+    /// Manage users
+    #[structopt(name = "users")]
+    Users(UsersCommand),
+    /// Forward websocket events as JSON to an HTTP endpoint
+    #[structopt(name = "forward")]
+    Forward {
+        /// Endpoint receiving the events
+        #[structopt(long = "url")]
+        url: String,
+        /// Comma separated list of events to forward, e.g., posted,reaction_added (default: all)
+        #[structopt(long = "events", raw(use_delimiter = "true"))]
+        events: Vec<String>,
+        /// Sign the requests with HMAC-SHA256 using this secret
+        #[structopt(
+            long = "secret",
+            env = "MMCLI_FORWARD_SECRET",
+            raw(hide_env_values = "true")
+        )]
+        secret: Option<String>,
+        /// Number of retries for failed deliveries
+        #[structopt(long = "retries", default_value = "3")]
+        retries: u32,
+        /// Store undeliverable events in this directory and send them again on the next start
+        #[structopt(long = "spool", parse(from_os_str))]
+        spool: Option<PathBuf>,
+        /// Add the character or image URL of the emoji to reaction_added events
+        #[structopt(long = "resolve-emoji")]
+        resolve_emoji: bool,
+    },
+    /// List the posts mentioning you, to find out what you missed
+    #[structopt(name = "mentions")]
+    Mentions {
+        /// Only show mentions of the last this many hours
+        #[structopt(long = "hours", default_value = "24")]
+        hours: i64,
+    },
+    /// Run recurring jobs, like rotating a channel header or posting a reminder
+    ///
+    /// The jobs are a YAML list, each with a name, a cron-like schedule, an optional timezone, and an action.
+    #[structopt(name = "schedule")]
+    Schedule {
+        /// YAML file with the jobs
+        #[structopt(parse(from_os_str))]
+        jobs: PathBuf,
+        /// Remember the last runs in this file (default: the jobs file with the extension .state.json)
+        #[structopt(long = "state", parse(from_os_str))]
+        state: Option<PathBuf>,
+        /// Only print when each job runs next
+        #[structopt(long = "list")]
+        list: bool,
+    },
+    /// Save or restore the members of channels
+    #[structopt(name = "membership")]
+    Membership(MembershipCommand),
+    /// Manage the channels of all teams
+    #[structopt(name = "channels")]
+    Channels(ChannelsCommand),
+    /// Activity statistics
+    #[structopt(name = "stats")]
+    Stats(StatsCommand),
+    /// Convert captured websocket messages (JSON lines) into normalized JSON lines, one file per event type
+    ///
+    /// Every line is parsed into the typed models and serialized again.
+    /// Lines which cannot be parsed are reported and copied to `quarantine.jsonl` in the output directory.
+    #[structopt(name = "convert")]
+    Convert {
+        /// Directory for the converted files
+        #[structopt(short = "o", long = "out-dir", parse(from_os_str))]
+        out_dir: PathBuf,
+        /// Capture files to convert (default: stdin)
+        #[structopt(parse(from_os_str))]
+        inputs: Vec<PathBuf>,
+    },
+}
+
+impl Command {
+    /// Whether the command talks to a server and needs `--server` and `--token`
+    fn needs_server(&self) -> bool {
+        !matches!(self, Command::Login { .. } | Command::Convert { .. })
+    }
+}
+
+#[derive(Debug, StructOpt)]
+enum UsersCommand {
+    /// Export all users as CSV or JSON
+    #[structopt(name = "export")]
+    Export {
+        /// Output format, either csv or json
+        #[structopt(long = "format", default_value = "json")]
+        format: ExportFormat,
+        /// Write to this file instead of stdout
+        #[structopt(short = "o", long = "output", parse(from_os_str))]
+        output: Option<PathBuf>,
+    },
+}
+
+#[derive(Debug, StructOpt)]
+enum MembershipCommand {
+    /// Write the members of channels as YAML
+    #[structopt(name = "snapshot")]
+    Snapshot {
+        /// Id of a channel to include, can be repeated
+        #[structopt(long = "channel", required = true)]
+        channels: Vec<ChannelId>,
+        /// Write to this file instead of stdout
+        #[structopt(short = "o", long = "output", parse(from_os_str))]
+        output: Option<PathBuf>,
+    },
+    /// Add and remove channel members until they match a snapshot
+    #[structopt(name = "apply")]
+    Apply {
+        /// Snapshot created by `membership snapshot`
+        #[structopt(parse(from_os_str))]
+        snapshot: PathBuf,
+        /// Only print the changes without applying them
+        #[structopt(long = "dry-run")]
+        dry_run: bool,
+    },
+}
+
+#[derive(Debug, StructOpt)]
+enum ChannelsCommand {
+    /// Archive the public and private channels without posts for a while
+    ///
+    /// Lists the inactive channels and asks for confirmation before archiving them.
+    #[structopt(name = "archive")]
+    Archive {
+        /// Channels without posts for this time span are inactive, e.g., 180d or 26w
+        #[structopt(long = "inactive-for", parse(try_from_str = "parse_age"))]
+        inactive_for: chrono::Duration,
+        /// Id of a team to include, can be repeated (default: all teams)
+        #[structopt(long = "team")]
+        teams: Vec<TeamId>,
+        /// Only list the inactive channels without archiving them
+        #[structopt(long = "dry-run")]
+        dry_run: bool,
+        /// Archive without asking for confirmation
+        #[structopt(long = "yes")]
+        yes: bool,
+        /// Write the report as JSON to this file
+        #[structopt(long = "report", parse(from_os_str))]
+        report: Option<PathBuf>,
+    },
+}
+
+#[derive(Debug, StructOpt)]
+enum StatsCommand {
+    /// Posts per hour of the week and the most active posters of a channel
+    #[structopt(name = "channel")]
+    Channel {
+        channel_id: ChannelId,
+        /// Only count posts of this time span, e.g., 12h, 30d, or 4w
+        #[structopt(
+            long = "since",
+            default_value = "30d",
+            parse(try_from_str = "parse_age")
+        )]
+        since: chrono::Duration,
+        /// Time zone of the hours of the week
+        #[structopt(long = "timezone", default_value = "UTC")]
+        timezone: Tz,
+        /// Number of posters to list
+        #[structopt(long = "top", default_value = "10")]
+        top: usize,
+        /// Output format, either csv or json
+        #[structopt(long = "format", default_value = "json")]
+        format: ExportFormat,
+        /// Write to this file instead of stdout
+        #[structopt(short = "o", long = "output", parse(from_os_str))]
+        output: Option<PathBuf>,
+    },
+}
+
+/// Parse a time span like `30d`, with one of the units m, h, d, or w
+fn parse_age(age: &str) -> ::std::result::Result<chrono::Duration, String> {
+    let split = age.len() - age.chars().last().map_or(0, char::len_utf8);
+    let (number, unit) = age.split_at(split);
+    let number: i64 = number
+        .parse()
+        .map_err(|_| format!("Invalid time span '{}', expected e.g. 30d", age))?;
+    match unit {
+        "m" => Ok(chrono::Duration::minutes(number)),
+        "h" => Ok(chrono::Duration::hours(number)),
+        "d" => Ok(chrono::Duration::days(number)),
+        "w" => Ok(chrono::Duration::weeks(number)),
+        _ => Err(format!(
+            "Invalid unit of '{}', expected one of m, h, d, w",
+            age
+        )),
+    }
+}
+
+/// Members of channels as written by `membership snapshot`
+#[derive(Debug, Serialize, Deserialize)]
+struct MembershipSnapshot {
+    channels: Vec<ChannelMembers>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct ChannelMembers {
+    channel_id: ChannelId,
+    /// Only informational, to make the snapshot readable
+    #[serde(default)]
+    display_name: String,
+    members: BTreeSet<UserId>,
+}
+
+fn main() {
+    if let Err(err) = run() {
+        eprintln!("{}", err.display_chain());
+        process::exit(1);
+    }
+}
+
+fn run() -> Result<()> {
+    env_logger::init();
+    #[cfg(feature = "native-tls")]
+    openssl_probe::init_ssl_cert_env_vars();
+
+    let mut args = CliArgs::from_args();
+    // A broken config must not break the commands which do not need it
+    if args.cmd.needs_server() && (args.server.is_none() || args.token.is_none()) {
+        let config = CliConfig::load()?;
+        args.server = args.server.or(config.server);
+        args.token = args.token.or(config.token);
+        #[cfg(feature = "keyring")]
+        {
+            if let (None, Some(server)) = (&args.token, &args.server) {
+                args.token = keyring_token(server)?;
+            }
+        }
+    }
+
+    match args.cmd {
+        Command::Login {
+            ref url,
+            ref login_id,
+            ref mfa,
+            ref token_description,
+        } => {
+            let login_id = match login_id {
+                Some(login_id) => login_id.clone(),
+                None => prompt("Username: ")?,
+            };
+            let password = match env::var("MATTERMOST_PASSWORD") {
+                Ok(password) => password,
+                Err(_) => rpassword::prompt_password_stderr("Password: ")?,
+            };
+            let client = Client::builder(url, String::new()).login(
+                &login_id,
+                &password,
+                mfa.as_ref().map(String::as_str),
+            )?;
+            let me = client.get_me()?;
+            let token = match token_description {
+                Some(description) => client
+                    .create_user_access_token(&me.id, description.clone())
+                    .chain_err(|| "Failed to create a personal access token")?
+                    .token
+                    .ok_or("The server did not return the new token")?,
+                None => client.token().to_string(),
+            };
+            #[cfg(feature = "keyring")]
+            let token = {
+                keyring_entry(url)?
+                    .set_password(&token)
+                    .chain_err(|| "Failed to store the token in the keyring")?;
+                None
+            };
+            #[cfg(not(feature = "keyring"))]
+            let token = Some(token);
+            let stored_in_file = token.is_some();
+            let path = CliConfig {
+                server: Some(url.clone()),
+                token,
+            }
+            .save()?;
+            if stored_in_file {
+                eprintln!("Logged in as {}, saved to {}", me.username, path.display());
+            } else {
+                eprintln!(
+                    "Logged in as {}, saved to {} and the token to the keyring",
+                    me.username,
+                    path.display()
+                );
+            }
+        }
+        Command::Users(UsersCommand::Export { format, ref output }) => {
+            let writer: Box<dyn Write> = match output {
+                Some(path) => Box::new(File::create(path)?),
+                None => Box::new(io::stdout()),
+            };
+            let count = args
+                .client()?
+                .export_users(BufWriter::new(writer), format)?;
+            eprintln!("Exported {} users", count);
+        }
+        Command::Forward {
+            ref url,
+            ref events,
+            ref secret,
+            retries,
+            ref spool,
+            resolve_emoji,
+        } => {
+            let client = args.client()?;
+            client.check_token()?;
+            let mut forwarder = Forwarder::new(url)?.retries(retries);
+            if resolve_emoji {
+                forwarder = forwarder.resolve_emoji(EmojiResolver::new(client));
+            }
+            if !events.is_empty() {
+                forwarder = forwarder.events(events.clone());
+            }
+            if let Some(secret) = secret {
+                forwarder = forwarder.secret(secret.as_bytes());
+            }
+            if let Some(spool) = spool {
+                forwarder = forwarder.spool(spool)?;
+                let spooled = forwarder.spooled()?;
+                if spooled > 0 {
+                    match forwarder.redeliver() {
+                        Ok(count) => {
+                            eprintln!("Redelivered {} of {} spooled events", count, spooled)
+                        }
+                        Err(err) => warn!("Redelivering spooled events failed: {}", err),
+                    }
+                }
+            }
+            let (server, token) = args.credentials()?;
+            forward_events(server, token, forwarder.queue())?;
+        }
+        Command::Mentions { hours } => {
+            let client = args.client()?;
+            let since = Utc::now() - chrono::Duration::hours(hours);
+            let mentions = client.get_my_recent_mentions(since)?;
+            let mut usernames = HashMap::new();
+            let mut channels = HashMap::new();
+            for post in &mentions {
+                if !usernames.contains_key(&post.user_id) {
+                    let user = client.get_user(&post.user_id)?;
+                    usernames.insert(post.user_id.clone(), user.username);
+                }
+                if !channels.contains_key(&post.channel_id) {
+                    let channel = client.get_channel_by_id(&post.channel_id)?;
+                    channels.insert(post.channel_id.clone(), channel.display_name);
+                }
+                println!(
+                    "{} {} @{}: {}",
+                    post.create_at
+                        .with_timezone(&Local)
+                        .format("%Y-%m-%d %H:%M"),
+                    channels[&post.channel_id],
+                    usernames[&post.user_id],
+                    post.message
+                );
+            }
+            eprintln!("{} mentions in the last {} hours", mentions.len(), hours);
+        }
+        Command::Schedule {
+            ref jobs,
+            ref state,
+            list,
+        } => {
+            let state = state
+                .clone()
+                .unwrap_or_else(|| jobs.with_extension("state.json"));
+            let jobs: Vec<Job> = serde_yaml::from_reader(File::open(jobs)?)?;
+            let mut scheduler = Scheduler::load(jobs, state)?;
+            if list {
+                for job in scheduler.jobs() {
+                    match scheduler.next_run(job) {
+                        Some(next) => println!(
+                            "{} {}",
+                            next.with_timezone(&Local).format("%Y-%m-%d %H:%M"),
+                            job.name
+                        ),
+                        None => println!("never            {}", job.name),
+                    }
+                }
+                return Ok(());
+            }
+            let client = args.client()?;
+            client.check_token()?;
+            loop {
+                for run in scheduler.run_due(&client, Utc::now())? {
+                    match run.result {
+                        Ok(()) => eprintln!("Ran {}", run.name),
+                        Err(err) => error!("Job {} failed:\n{}", run.name, err.display_chain()),
+                    }
+                }
+                // Check at least every minute, in case the system clock jumps
+                let sleep = scheduler
+                    .next_wakeup()
+                    .and_then(|next| (next - Utc::now()).to_std().ok())
+                    .unwrap_or_default()
+                    .clamp(Duration::from_secs(1), Duration::from_secs(60));
+                thread::sleep(sleep);
+            }
+        }
+        Command::Membership(MembershipCommand::Snapshot {
+            ref channels,
+            ref output,
+        }) => {
+            let client = args.client()?;
+            let mut snapshot = MembershipSnapshot {
+                channels: Vec::new(),
+            };
+            for channel_id in channels {
+                let channel = client.get_channel_by_id(channel_id)?;
+                snapshot.channels.push(ChannelMembers {
+                    channel_id: channel_id.clone(),
+                    display_name: channel.display_name,
+                    members: client.get_channel_member_ids(channel_id)?,
+                });
+            }
+            let writer: Box<dyn Write> = match output {
+                Some(path) => Box::new(File::create(path)?),
+                None => Box::new(io::stdout()),
+            };
+            serde_yaml::to_writer(writer, &snapshot)?;
+        }
+        Command::Membership(MembershipCommand::Apply {
+            ref snapshot,
+            dry_run,
+        }) => {
+            let client = args.client()?;
+            let snapshot: MembershipSnapshot = serde_yaml::from_reader(File::open(snapshot)?)?;
+            let mut complete = true;
+            for channel in &snapshot.channels {
+                let members: Vec<&UserId> = channel.members.iter().collect();
+                let changes =
+                    client.reconcile_channel_members(&channel.channel_id, &members, dry_run)?;
+                println!("{} ({}):", channel.display_name, channel.channel_id);
+                for user_id in &changes.added {
+                    println!("+ {}", user_id);
+                }
+                for user_id in &changes.removed {
+                    println!("- {}", user_id);
+                }
+                for failure in &changes.failed {
+                    println!(
+                        "! {:?} {}: {}",
+                        failure.action, failure.user_id, failure.error
+                    );
+                }
+                complete &= changes.is_complete();
+            }
+            if !complete {
+                return Err("Some membership changes failed".into());
+            }
+        }
+        Command::Channels(ChannelsCommand::Archive {
+            inactive_for,
+            ref teams,
+            dry_run,
+            yes,
+            ref report,
+        }) => {
+            let client = args.client()?;
+            let inactive = client.get_inactive_channels(Utc::now() - inactive_for, teams)?;
+            for channel in &inactive {
+                println!(
+                    "{}/{} ({}), last active {}",
+                    channel.team_name,
+                    channel.channel.name,
+                    channel.channel.id(),
+                    channel.channel.last_activity_at().format("%Y-%m-%d")
+                );
+            }
+            eprintln!("{} inactive channels", inactive.len());
+            if inactive.is_empty() {
+                return Ok(());
+            }
+            if !dry_run && !yes {
+                let answer = prompt(&format!("Archive {} channels? [y/N] ", inactive.len()))?;
+                if !answer.eq_ignore_ascii_case("y") {
+                    return Err("Aborted".into());
+                }
+            }
+            let channel_ids: Vec<&ChannelId> = inactive
+                .iter()
+                .map(|channel| channel.channel.id())
+                .collect();
+            let archive_report = client.archive_channels(&channel_ids, dry_run)?;
+            for failure in &archive_report.failed {
+                println!("! {}: {}", failure.channel_id, failure.error);
+            }
+            if !dry_run {
+                eprintln!(
+                    "Archived {} of {} channels",
+                    archive_report.archived.len(),
+                    inactive.len()
+                );
+            }
+            if let Some(path) = report {
+                serde_json::to_writer_pretty(File::create(path)?, &archive_report)?;
+            }
+            if !archive_report.is_complete() {
+                return Err("Some channels could not be archived".into());
+            }
+        }
+        Command::Stats(StatsCommand::Channel {
+            ref channel_id,
+            since,
+            timezone,
+            top,
+            format,
+            ref output,
+        }) => {
+            let client = args.client()?;
+            let until = Utc::now();
+            let mut activity =
+                ChannelActivity::fetch(&client, channel_id, until - since, until, timezone)?;
+            activity.truncate_posters(top);
+            let writer: Box<dyn Write> = match output {
+                Some(path) => Box::new(File::create(path)?),
+                None => Box::new(io::stdout()),
+            };
+            activity.write(BufWriter::new(writer), format)?;
+            eprintln!("{} posts", activity.total_posts);
+        }
+        Command::Convert {
+            ref out_dir,
+            ref inputs,
+        } => {
+            fs::create_dir_all(out_dir)?;
+            let mut converter = Converter::new(out_dir)?;
+            if inputs.is_empty() {
+                let stdin = io::stdin();
+                converter.convert("<stdin>", stdin.lock())?;
+            }
+            for input in inputs {
+                let file = BufReader::new(File::open(input)?);
+                converter.convert(&input.display().to_string(), file)?;
+            }
+            converter.finish()?;
+        }
+        Command::Preflight { json, timeout } => {
+            let (server, token) = args.credentials()?;
+            let mut config = preflight::ServerConfig::new(server, token);
+            config.timeout = Duration::from_secs(timeout);
+            let report = preflight::preflight(&config);
+            if json {
+                serde_json::to_writer_pretty(io::stdout(), &report)?;
+                println!();
+            } else {
+                print!("{}", report);
+            }
+            if !report.is_ok() {
+                return Err("Preflight failed".into());
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Writes each parsed message into the file of its event type
+struct Converter {
+    out_dir: PathBuf,
+    outputs: HashMap<String, BufWriter<File>>,
+    quarantine: BufWriter<File>,
+    converted: usize,
+    quarantined: usize,
+}
+
+impl Converter {
+    fn new(out_dir: &Path) -> Result<Converter> {
+        Ok(Converter {
+            out_dir: out_dir.to_path_buf(),
+            outputs: HashMap::new(),
+            quarantine: BufWriter::new(File::create(out_dir.join("quarantine.jsonl"))?),
+            converted: 0,
+            quarantined: 0,
+        })
+    }
+
+    fn convert<R>(&mut self, source: &str, reader: R) -> Result<()>
+    where
+        R: BufRead,
+    {
+        for (idx, line) in reader.lines().enumerate() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            match serde_json::from_str::<Message>(&line) {
+                Ok(msg) => {
+                    let name = match msg {
+                        Message::Push(ref push) => file_stem(push.event.name()),
+                        Message::Reply(_) => "reply".to_string(),
+                    };
+                    let out_dir = &self.out_dir;
+                    let output = match self.outputs.entry(name) {
+                        Entry::Occupied(entry) => entry.into_mut(),
+                        Entry::Vacant(entry) => {
+                            let path = out_dir.join(format!("{}.jsonl", entry.key()));
+                            entry.insert(BufWriter::new(File::create(path)?))
+                        }
+                    };
+                    serde_json::to_writer(&mut *output, &msg)?;
+                    writeln!(output)?;
+                    self.converted += 1;
+                }
+                Err(err) => {
+                    eprintln!("{}:{}: {}", source, idx + 1, err);
+                    writeln!(self.quarantine, "{}", line)?;
+                    self.quarantined += 1;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn finish(mut self) -> Result<()> {
+        for output in self.outputs.values_mut() {
+            output.flush()?;
+        }
+        self.quarantine.flush()?;
+
+        eprintln!(
+            "Converted {} messages of {} event types, quarantined {} lines",
+            self.converted,
+            self.outputs.len(),
+            self.quarantined
+        );
+        Ok(())
+    }
+}
+
+/// File name without extension for the messages of the event `name`
+///
+/// Names of unknown and plugin events come from the server, so only `[A-Za-z0-9_.-]` is kept and other characters are replaced by `_`.
+/// This keeps all files in the output directory and away from `quarantine.jsonl`.
+fn file_stem(name: &str) -> String {
+    let stem: String = name
+        .chars()
+        .map(|c| match c {
+            'A'..='Z' | 'a'..='z' | '0'..='9' | '_' | '.' | '-' => c,
+            _ => '_',
+        })
+        .collect();
+    match stem.as_str() {
+        "" | "." | ".." | "quarantine" => format!("_{}", stem),
+        _ => stem,
+    }
+}
+
+/// Print `message` and read a line from stdin
+///
+/// The input is echoed, passwords are read with `rpassword` instead.
+fn prompt(message: &str) -> Result<String> {
+    eprint!("{}", message);
+    io::stderr().flush()?;
+    let mut line = String::new();
+    io::stdin().read_line(&mut line)?;
+    Ok(line.trim_end_matches(&['\r', '\n'][..]).to_string())
+}
+
+/// Connect to the websocket of `server` and forward all events, reconnecting if the connection fails
+fn forward_events(server: &str, token: &str, queue: ForwardQueue) -> Result<()> {
+    let client = websocket::Client::new(server, token)?;
+    let mut handler = |message| {
+        if let Message::Push(message) = message {
+            if let Err(err) = queue.push(message) {
+                error!("{}", err.display_chain());
+            }
+        }
+    };
+    client.run_reconnecting(&mut handler, &mut Reconnector::new())
+}
@@ -1,14 +1,36 @@
+#[cfg(feature = "ignored-fields")]
+use mattermost_structs::websocket::parse_message_reporting_ignored;
 use mattermost_structs::websocket::{Message, MessagePush};
 use serde_json::{Deserializer, Value};
-use std::io::stdin;
+use std::{collections::BTreeMap, io::stdin};
+use structopt::StructOpt;
+
+/// Check that websocket messages (JSON lines on stdin) can be parsed
+#[derive(Debug, StructOpt)]
+struct CliArgs {
+    /// Report fields which are present in the payloads but ignored or unknown to the structs, grouped by type
+    #[structopt(long = "schema-probe")]
+    schema_probe: bool,
+}
 
 fn main() {
+    let args = CliArgs::from_args();
+
     println!("Read json lines from stdin...");
     println!();
 
     let sin = stdin();
     let stream = Deserializer::from_reader(sin.lock()).into_iter::<Value>();
 
+    if args.schema_probe {
+        let mut probe = SchemaProbe::default();
+        for value in stream {
+            probe.probe_message(value.unwrap());
+        }
+        probe.report();
+        return;
+    }
+
     for value in stream {
         let value = value.unwrap();
         let msg: Result<Message, _> = serde_json::from_value(value.clone());
@@ -23,3 +45,72 @@ fn main() {
         }
     }
 }
+
+/// Collects the differences between the payloads and the structs
+///
+/// Fields which are ignored during deserialization are found with `serde_ignored`.
+/// Structs with `deny_unknown_fields` reject unknown fields instead, so parse errors are collected too.
+/// Requires the cargo feature `ignored-fields`.
+#[derive(Debug, Default)]
+struct SchemaProbe {
+    /// Event name -> path of the ignored field -> occurrences
+    ignored: BTreeMap<String, BTreeMap<String, usize>>,
+    /// Event name -> error message -> occurrences
+    errors: BTreeMap<String, BTreeMap<String, usize>>,
+    messages: usize,
+}
+
+impl SchemaProbe {
+    #[cfg(feature = "ignored-fields")]
+    fn probe_message(&mut self, value: Value) {
+        self.messages += 1;
+        let name = match value.get("event").and_then(Value::as_str) {
+            Some(event) => event.to_string(),
+            None => "reply".to_string(),
+        };
+        let ignored = &mut self.ignored;
+        let res = parse_message_reporting_ignored(&value.to_string(), |path| {
+            *ignored
+                .entry(name.clone())
+                .or_default()
+                .entry(path)
+                .or_default() += 1;
+        });
+        if let Err(err) = res {
+            *self
+                .errors
+                .entry(name)
+                .or_default()
+                .entry(err.to_string())
+                .or_default() += 1;
+        }
+    }
+
+    #[cfg(not(feature = "ignored-fields"))]
+    fn probe_message(&mut self, _value: Value) {
+        eprintln!("The schema probe requires the cargo feature `ignored-fields`");
+        std::process::exit(1);
+    }
+
+    fn report(&self) {
+        println!("Probed {} messages", self.messages);
+        println!();
+        println!("Ignored fields:");
+        print_grouped(&self.ignored);
+        println!();
+        println!("Parse errors:");
+        print_grouped(&self.errors);
+    }
+}
+
+fn print_grouped(groups: &BTreeMap<String, BTreeMap<String, usize>>) {
+    if groups.is_empty() {
+        println!("    none");
+    }
+    for (name, entries) in groups {
+        println!("    {}:", name);
+        for (entry, count) in entries {
+            println!("        {} ({}x)", entry, count);
+        }
+    }
+}
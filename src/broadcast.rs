@@ -0,0 +1,53 @@
+//! Share a single websocket connection between multiple consumers
+//!
+//! Instead of opening one websocket per component, the websocket handler publishes each event to a [`Broadcaster`].
+//! Every component holds its own [`Receiver`] and processes the events at its own pace.
+
+use crate::{error::Result, sink::EventSink, websocket::MessagePush};
+use std::sync::{
+    mpsc::{channel, Receiver, Sender},
+    Arc, Mutex,
+};
+
+/// Delivers each published event to all subscribers
+///
+/// The events are shared via an [`Arc`], so they are not cloned for each subscriber.
+/// Subscribers are removed once their [`Receiver`] is dropped.
+#[derive(Debug, Default)]
+pub struct Broadcaster {
+    subscribers: Mutex<Vec<Sender<Arc<MessagePush>>>>,
+}
+
+impl Broadcaster {
+    pub fn new() -> Broadcaster {
+        Self::default()
+    }
+
+    /// Receive all events published from now on
+    pub fn subscribe(&self) -> Receiver<Arc<MessagePush>> {
+        let (sender, receiver) = channel();
+        self.subscribers.lock().unwrap().push(sender);
+        receiver
+    }
+
+    pub fn subscriber_count(&self) -> usize {
+        self.subscribers.lock().unwrap().len()
+    }
+
+    /// Send `message` to all subscribers
+    ///
+    /// Returns the number of subscribers which received the message.
+    pub fn send(&self, message: MessagePush) -> usize {
+        let message = Arc::new(message);
+        let mut subscribers = self.subscribers.lock().unwrap();
+        subscribers.retain(|subscriber| subscriber.send(message.clone()).is_ok());
+        subscribers.len()
+    }
+}
+
+impl EventSink for Broadcaster {
+    fn publish(&mut self, message: &MessagePush) -> Result<()> {
+        self.send(message.clone());
+        Ok(())
+    }
+}
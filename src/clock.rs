@@ -0,0 +1,77 @@
+//! Measure how far the local clock deviates from the clock of the server
+//!
+//! All timestamps in events and API responses are set by the server.
+//! If the local clock drifts, comparing them with local time, e.g., to decide whether it is night, gives wrong results.
+//! [`ClockSkew`] learns the offset from the `Date` header of API responses, see [`Client::clock`](crate::api::Client::clock),
+//! and converts server timestamps into local time.
+
+use crate::websocket::Post;
+use chrono::{DateTime, Duration, Utc};
+use std::sync::{Arc, Mutex};
+
+/// Latest measured offset between the server and the local clock
+///
+/// Clones share the measurement.
+#[derive(Debug, Clone, Default)]
+pub struct ClockSkew {
+    skew_ms: Arc<Mutex<Option<i64>>>,
+}
+
+impl ClockSkew {
+    pub fn new() -> ClockSkew {
+        Self::default()
+    }
+
+    /// Record that the server clock showed `server_time` at `local_time`
+    pub fn observe(&self, server_time: DateTime<Utc>, local_time: DateTime<Utc>) {
+        let skew = server_time.signed_duration_since(local_time);
+        *self.skew_ms.lock().unwrap() = Some(skew.num_milliseconds());
+    }
+
+    /// Record the value of an HTTP `Date` header received just now
+    ///
+    /// Returns `false` if the header cannot be parsed.
+    pub fn observe_date_header(&self, date: &str) -> bool {
+        match DateTime::parse_from_rfc2822(date) {
+            Ok(date) => {
+                // The header is truncated to full seconds, so on average the server is half a second further
+                let server_time = date.with_timezone(&Utc) + Duration::milliseconds(500);
+                self.observe(server_time, Utc::now());
+                true
+            }
+            Err(_) => false,
+        }
+    }
+
+    /// Server time minus local time, if measured
+    pub fn skew(&self) -> Option<Duration> {
+        self.skew_ms.lock().unwrap().map(Duration::milliseconds)
+    }
+
+    /// Convert a timestamp of the server into local time
+    ///
+    /// Timestamps are returned unchanged until a skew was measured.
+    pub fn to_local(&self, server_time: DateTime<Utc>) -> DateTime<Utc> {
+        match self.skew() {
+            Some(skew) => server_time - skew,
+            None => server_time,
+        }
+    }
+
+    /// Convert all timestamps of `post` into local time
+    ///
+    /// Unset timestamps, i.e., the Unix epoch, are kept.
+    pub fn adjust_post(&self, post: &mut Post) {
+        let epoch = DateTime::<Utc>::from(std::time::UNIX_EPOCH);
+        for time in &mut [
+            &mut post.create_at,
+            &mut post.update_at,
+            &mut post.edit_at,
+            &mut post.delete_at,
+        ] {
+            if **time != epoch {
+                **time = self.to_local(**time);
+            }
+        }
+    }
+}
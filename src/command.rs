@@ -0,0 +1,295 @@
+//! Receive slash commands and interactive actions
+//!
+//! Mattermost sends slash commands as form encoded POST requests to the URL configured for the command.
+//! Clicks on interactive buttons and menus are sent as JSON to the URL of the action.
+//! This module models both requests and their responses.
+//! With the cargo feature `command-server`, [`serve`] runs a small HTTP server dispatching them to a [`CommandHandler`].
+
+use crate::error::{Result, ResultExt};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+
+#[cfg(feature = "command-server")]
+pub use self::server::serve;
+
+/// Invocation of a slash command
+#[derive(Debug, Deserialize, Serialize, Clone, Eq, PartialEq, Default)]
+#[serde(default)]
+pub struct SlashCommand {
+    pub channel_id: String,
+    pub channel_name: String,
+    /// The command including the leading slash, e.g., `/deploy`
+    pub command: String,
+    /// URL for delayed responses, valid for 30 minutes
+    pub response_url: String,
+    pub team_domain: String,
+    pub team_id: String,
+    /// Everything after the command
+    pub text: String,
+    /// The token of the command, see [`verify_token`]
+    pub token: String,
+    /// Allows opening an interactive dialog
+    pub trigger_id: String,
+    pub user_id: String,
+    pub user_name: String,
+}
+
+impl SlashCommand {
+    /// Parse the form encoded body of the request
+    pub fn from_form(body: &[u8]) -> Result<SlashCommand> {
+        serde_urlencoded::from_bytes(body).chain_err(|| "Invalid slash command")
+    }
+
+    /// The arguments, i.e., the words of [`text`](SlashCommand::text)
+    pub fn args(&self) -> impl Iterator<Item = &str> {
+        self.text.split_whitespace()
+    }
+}
+
+/// Click on an interactive button or selection in a menu
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Default)]
+#[serde(default)]
+pub struct ActionCallback {
+    pub user_id: String,
+    pub user_name: String,
+    pub channel_id: String,
+    pub channel_name: String,
+    pub team_id: String,
+    pub team_domain: String,
+    /// The post containing the button or menu
+    pub post_id: String,
+    pub trigger_id: String,
+    /// `button` or `select`
+    #[serde(rename = "type")]
+    pub type_: String,
+    pub data_source: String,
+    /// The `context` of the action as defined in the post, including the selected option for menus
+    pub context: HashMap<String, Value>,
+}
+
+impl ActionCallback {
+    pub fn from_json(body: &[u8]) -> Result<ActionCallback> {
+        serde_json::from_slice(body).chain_err(|| "Invalid action callback")
+    }
+
+    /// The option picked in a menu
+    pub fn selected_option(&self) -> Option<&str> {
+        self.context.get("selected_option").and_then(Value::as_str)
+    }
+}
+
+/// Who can see the response to a slash command
+#[derive(
+    Debug, Default, Deserialize, Serialize, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum ResponseType {
+    /// Only the user who ran the command
+    #[default]
+    Ephemeral,
+    /// Everybody in the channel
+    InChannel,
+}
+
+/// Response to a slash command
+#[derive(Debug, Deserialize, Serialize, Clone, Eq, PartialEq, Default)]
+pub struct CommandResponse {
+    pub response_type: ResponseType,
+    pub text: String,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub username: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub icon_url: Option<String>,
+    /// Open this URL or channel path in the client of the user
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub goto_location: Option<String>,
+}
+
+impl CommandResponse {
+    /// Response only shown to the user who ran the command
+    pub fn ephemeral<S>(text: S) -> CommandResponse
+    where
+        S: Into<String>,
+    {
+        CommandResponse {
+            response_type: ResponseType::Ephemeral,
+            text: text.into(),
+            ..Default::default()
+        }
+    }
+
+    /// Response posted to the channel
+    pub fn in_channel<S>(text: S) -> CommandResponse
+    where
+        S: Into<String>,
+    {
+        CommandResponse {
+            response_type: ResponseType::InChannel,
+            text: text.into(),
+            ..Default::default()
+        }
+    }
+}
+
+/// Response to an [`ActionCallback`]
+#[derive(Debug, Deserialize, Serialize, Clone, Eq, PartialEq, Default)]
+pub struct ActionResponse {
+    /// Replace the message of the post containing the action
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub update: Option<ActionUpdate>,
+    /// Message only shown to the user who clicked
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub ephemeral_text: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Eq, PartialEq, Default)]
+pub struct ActionUpdate {
+    pub message: String,
+}
+
+/// Compare the token sent by Mattermost with the expected one
+///
+/// The comparison takes the same time for all tokens of the same length, such that the token cannot be guessed byte by byte.
+pub fn verify_token(expected: &str, given: &str) -> bool {
+    expected.len() == given.len()
+        && expected
+            .bytes()
+            .zip(given.bytes())
+            .fold(0, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+/// Token of the `Authorization: Token <token>` header
+pub fn token_from_header(value: &str) -> Option<&str> {
+    let mut parts = value.splitn(2, ' ');
+    match (parts.next(), parts.next()) {
+        (Some(scheme), Some(token)) if scheme.eq_ignore_ascii_case("token") => Some(token.trim()),
+        _ => None,
+    }
+}
+
+/// Application logic of a bot with slash commands or interactive messages
+pub trait CommandHandler: Send + Sync + 'static {
+    fn command(&self, command: SlashCommand) -> CommandResponse;
+
+    /// Ignores all actions by default
+    fn action(&self, action: ActionCallback) -> ActionResponse {
+        let _ = action;
+        ActionResponse::default()
+    }
+}
+
+#[cfg(feature = "command-server")]
+mod server {
+    use super::{token_from_header, verify_token, ActionCallback, CommandHandler, SlashCommand};
+    use crate::error::Result;
+    use futures::{future, Future, Stream};
+    use hyper::{
+        header::{AUTHORIZATION, CONTENT_TYPE},
+        service::service_fn,
+        Body, Method, Request, Response, Server, StatusCode,
+    };
+    use log::{error, warn};
+    use serde::Serialize;
+    use std::{net::SocketAddr, sync::Arc};
+
+    type ResponseFuture = Box<dyn Future<Item = Response<Body>, Error = hyper::Error> + Send>;
+
+    /// Serve slash commands and actions on `addr`, blocking the current thread
+    ///
+    /// Slash commands must carry `token`, either in the `Authorization` header or in the form.
+    /// Actions are recognized by their JSON content type and are not authenticated by Mattermost,
+    /// so include a secret in the action's `context` and check it in [`CommandHandler::action`].
+    pub fn serve<H>(addr: SocketAddr, token: String, handler: H) -> Result<()>
+    where
+        H: CommandHandler,
+    {
+        let handler = Arc::new(handler);
+        let token = Arc::new(token);
+        let server = Server::try_bind(&addr)
+            .map_err(|err| format!("Cannot listen on {}: {}", addr, err))?
+            .serve(move || {
+                let handler = handler.clone();
+                let token = token.clone();
+                service_fn(move |req| handle(req, handler.clone(), token.clone()))
+            })
+            .map_err(|err| error!("Command server failed: {}", err));
+        hyper::rt::run(server);
+        Ok(())
+    }
+
+    fn handle<H>(req: Request<Body>, handler: Arc<H>, token: Arc<String>) -> ResponseFuture
+    where
+        H: CommandHandler,
+    {
+        if req.method() != Method::POST {
+            return Box::new(future::ok(status(StatusCode::METHOD_NOT_ALLOWED)));
+        }
+        let is_json = req
+            .headers()
+            .get(CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .is_some_and(|value| value.starts_with("application/json"));
+        let header_token = req
+            .headers()
+            .get(AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(token_from_header)
+            .map(ToString::to_string);
+
+        Box::new(req.into_body().concat2().map(move |body| {
+            if is_json {
+                match ActionCallback::from_json(&body) {
+                    Ok(action) => json(&handler.action(action)),
+                    Err(err) => {
+                        warn!("Rejecting action: {}", err);
+                        status(StatusCode::BAD_REQUEST)
+                    }
+                }
+            } else {
+                match SlashCommand::from_form(&body) {
+                    Ok(command) => {
+                        let given = header_token.as_ref().unwrap_or(&command.token);
+                        if verify_token(&token, given) {
+                            json(&handler.command(command))
+                        } else {
+                            warn!(
+                                "Rejecting slash command {} with invalid token",
+                                command.command
+                            );
+                            status(StatusCode::UNAUTHORIZED)
+                        }
+                    }
+                    Err(err) => {
+                        warn!("Rejecting slash command: {}", err);
+                        status(StatusCode::BAD_REQUEST)
+                    }
+                }
+            }
+        }))
+    }
+
+    fn json<T>(value: &T) -> Response<Body>
+    where
+        T: Serialize,
+    {
+        match serde_json::to_vec(value) {
+            Ok(body) => Response::builder()
+                .header(CONTENT_TYPE, "application/json")
+                .body(Body::from(body))
+                .expect("The response is always valid"),
+            Err(err) => {
+                error!("Failed to serialize the response: {}", err);
+                status(StatusCode::INTERNAL_SERVER_ERROR)
+            }
+        }
+    }
+
+    fn status(status: StatusCode) -> Response<Body> {
+        Response::builder()
+            .status(status)
+            .body(Body::empty())
+            .expect("The response is always valid")
+    }
+}
@@ -0,0 +1,246 @@
+//! Describe what changed when a post was edited
+//!
+//! [`diff_posts`] compares two versions of a post, e.g., the archived one and the one of a [`Events::PostEdited`](crate::websocket::Events::PostEdited) event.
+//! The message is compared word by word.
+//!
+//! ```
+//! use mattermost_structs::diff::{diff_words, WordChange};
+//!
+//! let changes = diff_words("deploy on friday", "deploy on monday");
+//! assert_eq!(
+//!     changes,
+//!     vec![
+//!         WordChange::Kept("deploy on".to_string()),
+//!         WordChange::Removed("friday".to_string()),
+//!         WordChange::Added("monday".to_string()),
+//!     ]
+//! );
+//! ```
+
+use crate::{format::escape, websocket::Post};
+
+/// Largest table of the longest common subsequence, about 4 MiB
+const MAX_LCS_CELLS: usize = 1 << 20;
+
+/// A run of words which were kept, added, or removed
+#[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub enum WordChange {
+    Kept(String),
+    Added(String),
+    Removed(String),
+}
+
+/// Differences between two versions of a post
+#[derive(Debug, Clone, Eq, PartialEq, Default)]
+pub struct PostDiff {
+    /// Word-level changes of the message, empty if the message is unchanged
+    pub message: Vec<WordChange>,
+    pub added_files: Vec<String>,
+    pub removed_files: Vec<String>,
+    /// The new pin state, if it changed
+    pub pinned: Option<bool>,
+}
+
+impl PostDiff {
+    pub fn is_empty(&self) -> bool {
+        self.message.is_empty()
+            && self.added_files.is_empty()
+            && self.removed_files.is_empty()
+            && self.pinned.is_none()
+    }
+
+    pub fn message_changed(&self) -> bool {
+        !self.message.is_empty()
+    }
+
+    /// The message with removed words struck through and added words in bold
+    ///
+    /// The words are escaped, such that the markup of the message does not interfere.
+    pub fn to_markdown(&self) -> String {
+        self.message
+            .iter()
+            .map(|change| match change {
+                WordChange::Kept(words) => escape(words),
+                WordChange::Added(words) => format!("**{}**", escape(words)),
+                WordChange::Removed(words) => format!("~~{}~~", escape(words)),
+            })
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+/// Compare two versions of a post
+pub fn diff_posts(old: &Post, new: &Post) -> PostDiff {
+    let message = if old.message == new.message {
+        Vec::new()
+    } else {
+        diff_words(&old.message, &new.message)
+    };
+    PostDiff {
+        message,
+        added_files: new
+            .file_ids
+            .iter()
+            .filter(|id| !old.file_ids.contains(id))
+            .cloned()
+            .collect(),
+        removed_files: old
+            .file_ids
+            .iter()
+            .filter(|id| !new.file_ids.contains(id))
+            .cloned()
+            .collect(),
+        pinned: if old.is_pinned == new.is_pinned {
+            None
+        } else {
+            Some(new.is_pinned)
+        },
+    }
+}
+
+/// Word-level differences between `old` and `new`
+///
+/// Words are separated by whitespace, so changes of the whitespace alone are not reported.
+/// Removed words come before the added words replacing them.
+///
+/// Comparing long messages needs a lot of memory.
+/// If the changed part between the common prefix and suffix has more than about a thousand words on both sides, it is reported as removed and added as a whole.
+///
+/// ```
+/// # use mattermost_structs::diff::{diff_words, WordChange};
+/// let old = (0..2000).map(|i| format!("old{} ", i)).collect::<String>();
+/// let new = (0..2000).map(|i| format!("new{} ", i)).collect::<String>();
+/// let changes = diff_words(&format!("start {} end", old), &format!("start {} end", new));
+/// assert_eq!(changes.len(), 4);
+/// assert_eq!(changes[0], WordChange::Kept("start".to_string()));
+/// assert_eq!(changes[3], WordChange::Kept("end".to_string()));
+/// ```
+pub fn diff_words(old: &str, new: &str) -> Vec<WordChange> {
+    let old: Vec<&str> = old.split_whitespace().collect();
+    let new: Vec<&str> = new.split_whitespace().collect();
+
+    // Only the middle part between the common prefix and suffix needs the quadratic algorithm
+    let prefix = old.iter().zip(&new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let old_middle = &old[prefix..old.len() - suffix];
+    let new_middle = &new[prefix..new.len() - suffix];
+
+    let mut changes = Changes::default();
+    for word in &old[..prefix] {
+        changes.push(Kind::Kept, word);
+    }
+    if old_middle.len().saturating_mul(new_middle.len()) <= MAX_LCS_CELLS {
+        lcs_diff(old_middle, new_middle, &mut changes);
+    } else {
+        for word in old_middle {
+            changes.push(Kind::Removed, word);
+        }
+        for word in new_middle {
+            changes.push(Kind::Added, word);
+        }
+    }
+    for word in &old[old.len() - suffix..] {
+        changes.push(Kind::Kept, word);
+    }
+    changes.finish()
+}
+
+/// Diff based on the longest common subsequence
+fn lcs_diff(old: &[&str], new: &[&str], changes: &mut Changes) {
+    // lengths[i][j] is the length of the LCS of old[i..] and new[j..]
+    let mut lengths = vec![vec![0u32; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lengths[i][j] = if old[i] == new[j] {
+                lengths[i + 1][j + 1] + 1
+            } else {
+                lengths[i + 1][j].max(lengths[i][j + 1])
+            };
+        }
+    }
+
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() && j < new.len() {
+        if old[i] == new[j] {
+            changes.push(Kind::Kept, old[i]);
+            i += 1;
+            j += 1;
+        } else if lengths[i + 1][j] >= lengths[i][j + 1] {
+            changes.push(Kind::Removed, old[i]);
+            i += 1;
+        } else {
+            changes.push(Kind::Added, new[j]);
+            j += 1;
+        }
+    }
+    for word in &old[i..] {
+        changes.push(Kind::Removed, word);
+    }
+    for word in &new[j..] {
+        changes.push(Kind::Added, word);
+    }
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+enum Kind {
+    Kept,
+    Added,
+    Removed,
+}
+
+/// Collects words into runs of the same kind
+///
+/// Within a replaced section all removed words are reported before the added ones.
+#[derive(Debug, Default)]
+struct Changes {
+    done: Vec<WordChange>,
+    kept: Vec<String>,
+    removed: Vec<String>,
+    added: Vec<String>,
+}
+
+impl Changes {
+    fn push(&mut self, kind: Kind, word: &str) {
+        match kind {
+            Kind::Kept => {
+                self.flush_replaced();
+                self.kept.push(word.to_string());
+            }
+            Kind::Removed | Kind::Added => {
+                if !self.kept.is_empty() {
+                    self.done.push(WordChange::Kept(self.kept.join(" ")));
+                    self.kept.clear();
+                }
+                if kind == Kind::Removed {
+                    self.removed.push(word.to_string());
+                } else {
+                    self.added.push(word.to_string());
+                }
+            }
+        }
+    }
+
+    fn flush_replaced(&mut self) {
+        if !self.removed.is_empty() {
+            self.done.push(WordChange::Removed(self.removed.join(" ")));
+            self.removed.clear();
+        }
+        if !self.added.is_empty() {
+            self.done.push(WordChange::Added(self.added.join(" ")));
+            self.added.clear();
+        }
+    }
+
+    fn finish(mut self) -> Vec<WordChange> {
+        self.flush_replaced();
+        if !self.kept.is_empty() {
+            self.done.push(WordChange::Kept(self.kept.join(" ")));
+        }
+        self.done
+    }
+}
@@ -0,0 +1,227 @@
+//! Summarize the activity of a channel
+//!
+//! A [`Digest`] groups the posts of a time window by thread and ranks the threads by their replies and reactions.
+//! [`Digest::render`] turns it into a Markdown post, with permalinks to the threads.
+//!
+//! ```no_run
+//! # fn main() -> mattermost_structs::Result<()> {
+//! use chrono::{Duration, Utc};
+//! use mattermost_structs::{api::Client, digest::Digest, ids::ChannelId};
+//!
+//! let client = Client::new("https://chat.example.com", "token")?;
+//! let until = Utc::now();
+//! let channel_id = ChannelId::from("3df1ha9a7i8ftybnsp9u4iwcme");
+//! let digest = Digest::fetch(&client, &channel_id, until - Duration::days(1), until, 5)?;
+//! println!("{}", digest.render(5));
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::{
+    api::Client,
+    error::Result,
+    format::escape,
+    ids::{ChannelId, PostId, UserId},
+    permalink,
+    websocket::{Post, PostType},
+};
+use chrono::{DateTime, Utc};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Maximum number of characters of the first post shown for each thread
+const EXCERPT_LENGTH: usize = 80;
+
+/// The activity in one thread during the time window
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct ThreadSummary {
+    /// Id of the first post of the thread
+    pub root_id: PostId,
+    /// The first post, which may be older than the time window
+    pub root: Option<Post>,
+    /// Posts in the time window, including the root
+    pub posts: usize,
+    /// Replies in the time window
+    pub replies: usize,
+    /// Reactions on the posts in the time window
+    pub reactions: usize,
+    pub participants: BTreeSet<UserId>,
+    pub last_post_at: DateTime<Utc>,
+}
+
+impl ThreadSummary {
+    /// First line of the root post, shortened and escaped for Markdown
+    fn excerpt(&self) -> String {
+        let message = self
+            .root
+            .as_ref()
+            .and_then(|root| root.message.lines().find(|line| !line.trim().is_empty()))
+            .unwrap_or("(no text)")
+            .trim();
+        let mut excerpt: String = message.chars().take(EXCERPT_LENGTH).collect();
+        if excerpt.len() < message.len() {
+            excerpt.push('…');
+        }
+        escape(excerpt)
+    }
+}
+
+/// The threads of a channel during a time window, most active first
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Digest {
+    pub channel_id: ChannelId,
+    /// Display name of the channel, if known
+    pub channel_name: Option<String>,
+    pub since: DateTime<Utc>,
+    pub until: DateTime<Utc>,
+    /// Number of posts in the time window
+    pub total_posts: usize,
+    pub threads: Vec<ThreadSummary>,
+    /// Permalinks of the threads, by root id
+    pub permalinks: BTreeMap<PostId, String>,
+}
+
+impl Digest {
+    /// Group the posts created in `since..until` by thread
+    ///
+    /// Deleted posts and system messages are ignored.
+    pub fn from_posts<S, I>(
+        channel_id: S,
+        since: DateTime<Utc>,
+        until: DateTime<Utc>,
+        posts: I,
+    ) -> Digest
+    where
+        S: Into<ChannelId>,
+        I: IntoIterator<Item = Post>,
+    {
+        let mut threads: BTreeMap<PostId, ThreadSummary> = BTreeMap::new();
+        let mut total_posts = 0;
+        for post in posts {
+            if post.create_at < since
+                || post.create_at >= until
+                || post.is_deleted()
+                || post.type_ != PostType::UserMessage
+            {
+                continue;
+            }
+            total_posts += 1;
+            let thread = threads
+                .entry(post.thread_id().clone())
+                .or_insert_with(|| ThreadSummary {
+                    root_id: post.thread_id().clone(),
+                    root: None,
+                    posts: 0,
+                    replies: 0,
+                    reactions: 0,
+                    participants: BTreeSet::new(),
+                    last_post_at: post.create_at,
+                });
+            thread.posts += 1;
+            thread.reactions += post.reactions().len();
+            thread.participants.insert(post.user_id.clone());
+            thread.last_post_at = thread.last_post_at.max(post.create_at);
+            if post.root_id.is_empty() {
+                thread.root = Some(post);
+            } else {
+                thread.replies += 1;
+            }
+        }
+
+        let mut threads: Vec<_> = threads.into_values().collect();
+        threads.sort_by(|a, b| {
+            (b.replies, b.reactions, b.last_post_at).cmp(&(a.replies, a.reactions, a.last_post_at))
+        });
+        Digest {
+            channel_id: channel_id.into(),
+            channel_name: None,
+            since,
+            until,
+            total_posts,
+            threads,
+            permalinks: BTreeMap::new(),
+        }
+    }
+
+    /// Fetch the posts of the channel and build the digest
+    ///
+    /// The root posts and permalinks are only fetched for the `max_threads` most active threads.
+    pub fn fetch<S>(
+        client: &Client,
+        channel_id: S,
+        since: DateTime<Utc>,
+        until: DateTime<Utc>,
+        max_threads: usize,
+    ) -> Result<Digest>
+    where
+        S: AsRef<ChannelId>,
+    {
+        let channel = client.get_channel_by_id(channel_id)?;
+        let posts = client.get_posts_for_channel_since(channel.id(), since)?;
+        let mut digest = Digest::from_posts(
+            channel.id().clone(),
+            since,
+            until,
+            posts.posts.into_values(),
+        );
+        digest.channel_name = Some(channel.display_name.clone());
+
+        let team_name = client.team_name_of(&channel)?;
+        for thread in digest.threads.iter_mut().take(max_threads) {
+            if thread.root.is_none() {
+                thread.root = Some(client.get_post(&thread.root_id)?);
+            }
+            let permalink = permalink::build(client.base_url(), &team_name, &thread.root_id)?;
+            digest
+                .permalinks
+                .insert(thread.root_id.clone(), permalink.into_string());
+        }
+        Ok(digest)
+    }
+
+    /// Markdown text listing the `max_threads` most active threads
+    pub fn render(&self, max_threads: usize) -> String {
+        let mut text = format!(
+            "#### Digest of {} from {} to {}\n",
+            self.channel_name
+                .as_ref()
+                .map_or_else(|| self.channel_id.to_string(), escape),
+            self.since.format("%Y-%m-%d %H:%M UTC"),
+            self.until.format("%Y-%m-%d %H:%M UTC"),
+        );
+        if self.threads.is_empty() {
+            text.push_str("No new posts.\n");
+            return text;
+        }
+        text.push_str(&format!(
+            "{} posts in {} threads.\n\n",
+            self.total_posts,
+            self.threads.len()
+        ));
+        for (rank, thread) in self.threads.iter().take(max_threads).enumerate() {
+            let excerpt = match self.permalinks.get(&thread.root_id) {
+                Some(link) => format!("[{}]({})", thread.excerpt(), link),
+                None => thread.excerpt(),
+            };
+            text.push_str(&format!(
+                "{}. {} ({} {}, {} {}, {} {})\n",
+                rank + 1,
+                excerpt,
+                thread.replies,
+                plural(thread.replies, "reply", "replies"),
+                thread.reactions,
+                plural(thread.reactions, "reaction", "reactions"),
+                thread.participants.len(),
+                plural(thread.participants.len(), "participant", "participants"),
+            ));
+        }
+        text
+    }
+}
+
+fn plural(count: usize, one: &'static str, many: &'static str) -> &'static str {
+    if count == 1 {
+        one
+    } else {
+        many
+    }
+}
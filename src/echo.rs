@@ -0,0 +1,126 @@
+//! Match created posts with their `posted` websocket events
+//!
+//! The server broadcasts each new post as a [`Events::Posted`] event, which also reaches the author.
+//! Waiting for this echo confirms that the post was delivered and provides the post as the clients see it.
+
+use crate::{
+    api::{Client, CreatePostRequest},
+    error::Result,
+    sink::EventSink,
+    websocket::{Events, MessagePush, Post},
+};
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Condvar, Mutex},
+    time::{Duration, Instant},
+};
+
+/// Number of echoes kept by [`EchoTracker::new`]
+const DEFAULT_CAPACITY: usize = 256;
+
+/// Remembers the most recent posted events, such that they can be matched with created posts
+///
+/// The echo may arrive before [`Client::create_post`] returns, so the tracker must see all events, even if nobody waits for them.
+/// Feed it from the websocket handler with [`EchoTracker::observe`] or use it as [`EventSink`].
+/// Clones share the same state.
+#[derive(Debug, Clone)]
+pub struct EchoTracker {
+    inner: Arc<(Mutex<VecDeque<Post>>, Condvar)>,
+    capacity: usize,
+}
+
+impl EchoTracker {
+    pub fn new() -> EchoTracker {
+        Self::with_capacity(DEFAULT_CAPACITY)
+    }
+
+    /// Keep at most `capacity` echoes which were not claimed yet
+    pub fn with_capacity(capacity: usize) -> EchoTracker {
+        EchoTracker {
+            inner: Arc::new((
+                Mutex::new(VecDeque::with_capacity(capacity)),
+                Condvar::new(),
+            )),
+            capacity,
+        }
+    }
+
+    /// Remember the post, if `message` is a posted event
+    pub fn observe(&self, message: &MessagePush) {
+        if let Events::Posted { post, .. } = &message.event {
+            let (posts, echoed) = &*self.inner;
+            let mut posts = posts.lock().unwrap();
+            if posts.len() >= self.capacity {
+                posts.pop_front();
+            }
+            posts.push_back(post.clone());
+            echoed.notify_all();
+        }
+    }
+
+    /// Take the echo of the post with this id or pending post id, if it already arrived
+    pub fn try_take<S>(&self, id: S) -> Option<Post>
+    where
+        S: AsRef<str>,
+    {
+        let (posts, _) = &*self.inner;
+        take(&mut posts.lock().unwrap(), id.as_ref())
+    }
+
+    /// Wait up to `timeout` for the echo of the post with this id or pending post id
+    pub fn wait<S>(&self, id: S, timeout: Duration) -> Option<Post>
+    where
+        S: AsRef<str>,
+    {
+        let id = id.as_ref();
+        let deadline = Instant::now() + timeout;
+        let (posts, echoed) = &*self.inner;
+        let mut posts = posts.lock().unwrap();
+        loop {
+            if let Some(post) = take(&mut posts, id) {
+                return Some(post);
+            }
+            let remaining = deadline.checked_duration_since(Instant::now())?;
+            posts = echoed.wait_timeout(posts, remaining).unwrap().0;
+        }
+    }
+
+    /// Create a post and wait until it is broadcast
+    ///
+    /// Fails if the echo does not arrive within `timeout`, even though the post was created.
+    pub fn create_post(
+        &self,
+        client: &Client,
+        post: &CreatePostRequest,
+        timeout: Duration,
+    ) -> Result<Post> {
+        let created = client.create_post(post)?;
+        self.wait(&created.id, timeout).ok_or_else(|| {
+            format!(
+                "Post {} was created, but not broadcast within {:?}",
+                created.id, timeout
+            )
+            .into()
+        })
+    }
+}
+
+impl Default for EchoTracker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl EventSink for EchoTracker {
+    fn publish(&mut self, message: &MessagePush) -> Result<()> {
+        self.observe(message);
+        Ok(())
+    }
+}
+
+fn take(posts: &mut VecDeque<Post>, id: &str) -> Option<Post> {
+    let idx = posts
+        .iter()
+        .position(|post| post.id == id || (!id.is_empty() && post.pending_post_id == id))?;
+    posts.remove(idx)
+}
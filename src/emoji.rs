@@ -0,0 +1,143 @@
+//! Resolve the emoji of reactions to something a renderer can show
+//!
+//! Reactions only name their emoji, e.g., `+1` or `partyparrot`.
+//! System emoji are built into the clients and map to Unicode text, see [`system_emoji`].
+//! Custom emoji are images uploaded to the server, which [`EmojiResolver`] looks up with the emoji API and caches.
+//!
+//! ```no_run
+//! use mattermost_structs::{api::Client, emoji::EmojiResolver, websocket::Events};
+//!
+//! # fn handle(event: Events) -> mattermost_structs::Result<()> {
+//! let resolver = EmojiResolver::new(Client::new("https://mattermost.example.com", "token")?);
+//! if let Events::ReactionAdded { reaction } = event {
+//!     let resolved = resolver.resolve(&reaction)?;
+//!     match (&resolved.unicode, &resolved.image) {
+//!         (Some(unicode), _) => println!("{}", unicode),
+//!         (None, Some(image)) => println!("![{}]({})", resolved.emoji_name, image),
+//!         (None, None) => println!(":{}:", resolved.emoji_name),
+//!     }
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::{api::Client, error::Result, websocket::Reaction};
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, sync::Mutex};
+use url::Url;
+
+/// The emoji of a [`Reaction`]
+///
+/// At most one of `unicode` and `image` is set.
+/// Both are unset for names which are neither a [system emoji](system_emoji) nor a custom emoji of the server, e.g., deleted custom emoji.
+#[derive(Debug, Deserialize, Serialize, Clone, Eq, PartialEq, Hash)]
+pub struct ResolvedReaction {
+    pub emoji_name: String,
+    /// The text of a system emoji
+    pub unicode: Option<String>,
+    /// The image of a custom emoji, see [`Client::emoji_image_url`]
+    #[serde(with = "option_url")]
+    pub image: Option<Url>,
+}
+
+/// Looks up custom emoji, remembering the result for every name
+///
+/// Custom emoji cannot be renamed, so the cache is never invalidated.
+/// Unknown names are cached as well, such that repeated reactions with a deleted emoji cause no requests.
+#[derive(Debug)]
+pub struct EmojiResolver {
+    client: Client,
+    images: Mutex<HashMap<String, Option<Url>>>,
+}
+
+impl EmojiResolver {
+    pub fn new(client: Client) -> EmojiResolver {
+        EmojiResolver {
+            client,
+            images: Mutex::default(),
+        }
+    }
+
+    pub fn resolve(&self, reaction: &Reaction) -> Result<ResolvedReaction> {
+        self.resolve_name(&reaction.emoji_name)
+    }
+
+    /// Resolve the emoji named `emoji_name`, without the surrounding colons
+    pub fn resolve_name(&self, emoji_name: &str) -> Result<ResolvedReaction> {
+        let (unicode, image) = match system_emoji(emoji_name) {
+            Some(unicode) => (Some(unicode.to_string()), None),
+            None => (None, self.custom_image(emoji_name)?),
+        };
+        Ok(ResolvedReaction {
+            emoji_name: emoji_name.to_string(),
+            unicode,
+            image,
+        })
+    }
+
+    /// Number of custom emoji names in the cache
+    pub fn cached(&self) -> usize {
+        self.images.lock().unwrap().len()
+    }
+
+    fn custom_image(&self, emoji_name: &str) -> Result<Option<Url>> {
+        if let Some(image) = self.images.lock().unwrap().get(emoji_name) {
+            return Ok(image.clone());
+        }
+        // Not holding the lock during the request, concurrent lookups of the same name are harmless
+        let image = match self.client.get_emoji_by_name(emoji_name) {
+            Ok(emoji) => Some(self.client.emoji_image_url(&emoji)?),
+            Err(ref err) if err.is_not_found() => None,
+            Err(err) => return Err(err),
+        };
+        self.images
+            .lock()
+            .unwrap()
+            .insert(emoji_name.to_string(), image.clone());
+        Ok(image)
+    }
+}
+
+/// The Unicode text of a system emoji, which can be more than one character, e.g., for flags
+///
+/// Knows the names of the [gemoji](https://github.com/github/gemoji) set, which the clients of Mattermost share for most emoji.
+/// Flags are also found by their Mattermost name, e.g., `flag-de`.
+pub fn system_emoji(name: &str) -> Option<&'static str> {
+    let emoji = emojis::get_by_shortcode(name)
+        .or_else(|| emojis::get_by_shortcode(name.strip_prefix("flag-")?))
+        .or_else(|| {
+            // Names of Mattermost which gemoji spells differently
+            let alias = match name {
+                "hugging_face" => "hugs",
+                "thinking_face" => "thinking",
+                _ => return None,
+            };
+            emojis::get_by_shortcode(alias)
+        })?;
+    Some(emoji.as_str())
+}
+
+/// An `Option<Url>` as string or `null`
+mod option_url {
+    use serde::{de, Deserialize, Deserializer, Serializer};
+    use url::Url;
+
+    pub fn serialize<S>(url: &Option<Url>, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match url {
+            Some(url) => serializer.serialize_some(url.as_str()),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<Option<Url>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        Option::<String>::deserialize(deserializer)?
+            .map(|url| Url::parse(&url).map_err(de::Error::custom))
+            .transpose()
+    }
+}
@@ -1,55 +1,268 @@
-#![allow(deprecated)]
-
-use error_chain::{
-    error_chain, error_chain_processing, impl_error_chain_kind, impl_error_chain_processed,
-    impl_extract_backtrace,
-};
-
-error_chain! {
-    // Automatic conversions between this error chain and other
-    // error chains. In this case, it will e.g. generate an
-    // `ErrorKind` variant called `Another` which in turn contains
-    // the `other_error::ErrorKind`, with conversions from
-    // `other_error::Error`.
-    //
-    // Optionally, some attributes can be added to a variant.
-    //
-    // This section can be empty.
-    links {
-    }
-
-    // Automatic conversions between this error chain and other
-    // error types not defined by the `error_chain!`. These will be
-    // wrapped in a new error with, in the first case, the
-    // `ErrorKind::Fmt` variant. The description and cause will
-    // forward to the description and cause of the original error.
-    //
-    // Optionally, some attributes can be added to a variant.
-    //
-    // This section can be empty.
-    foreign_links {
-        Fmt(::std::fmt::Error);
-        Io(::std::io::Error);
-        Reqwest(::reqwest::Error);
-        SerdeJson(::serde_json::Error);
-        SerdeYaml(::serde_yaml::Error);
-        Url(::url::ParseError);
-    }
-
-    // Define additional `ErrorKind` variants. The syntax here is
-    // the same as `quick_error!`, but the `from()` and `cause()`
-    // syntax is not supported.
-    errors {
-        InvalidOrMissingParameter
-        MissingAccessToken
-        MissingPermissions
-        // InvalidOrMissingParameter(t: String) {
-        //     description("The request has an invalid or missing parameter.")
-        //     display("Invalid or missing parameter during '{}'", t)
-        // }
-        // MissingAccessToken(t: String) {
-        //     description("The request is missing an access token.")
-        //     display("Missing accesst token during '{}'", t)
-        // }
+//! The error type of all fallible operations
+//!
+//! Errors reported by the server keep the body the server sent, see [`Error::Api`].
+//! [`ResultExt::chain_err`] adds a description of the failed operation to an error, while keeping the original error as [`source`](std::error::Error::source).
+
+use reqwest::StatusCode;
+use serde::Deserialize;
+use std::{error::Error as StdError, fmt, io, iter};
+use thiserror::Error;
+
+pub type Result<T> = ::std::result::Result<T, Error>;
+
+#[derive(Debug, Error)]
+pub enum Error {
+    /// The server answered with an error status
+    ///
+    /// `id`, `message`, and `request_id` are taken from the error the server sent.
+    /// If the body is no Mattermost error, e.g., for errors of a proxy, `message` is the reason phrase of the status and the others are empty.
+    #[error("{message} (status {status})")]
+    Api {
+        /// Translation id of the error, e.g., `api.context.permissions.app_error`
+        id: String,
+        message: String,
+        status: u16,
+        request_id: String,
+    },
+    #[error("Rate limited, the limit resets in {0}s")]
+    RateLimited(u64),
+    #[error("Invalid post request: {0}")]
+    InvalidPostRequest(String),
+    #[error("Invalid channel request: {0}")]
+    InvalidChannelRequest(String),
+    #[error("Undecodable binary frame: {0}")]
+    UndecodableFrame(String),
+    #[error("Timed out while {0}")]
+    Timeout(String),
+    #[error("Login failed: {0}")]
+    LoginFailed(String),
+    #[error("{0} is not allowed with a read-only client")]
+    ReadOnly(String),
+    /// A description of the operation which failed because of `source`, see [`ResultExt::chain_err`]
+    #[error("{message}")]
+    Context {
+        message: String,
+        source: Box<dyn StdError + Send + Sync>,
+    },
+    /// Any other error, created from a `&str` or `String`
+    #[error("{0}")]
+    Msg(String),
+    #[error(transparent)]
+    Fmt(#[from] fmt::Error),
+    #[error(transparent)]
+    Io(#[from] ::std::io::Error),
+    #[error(transparent)]
+    Reqwest(#[from] ::reqwest::Error),
+    #[cfg(feature = "archive")]
+    #[error(transparent)]
+    Sqlite(#[from] ::rusqlite::Error),
+    #[error(transparent)]
+    SerdeJson(#[from] ::serde_json::Error),
+    #[error(transparent)]
+    SerdeYaml(#[from] ::serde_yaml::Error),
+    #[error(transparent)]
+    Url(#[from] ::url::ParseError),
+}
+
+impl From<&str> for Error {
+    fn from(message: &str) -> Error {
+        Error::Msg(message.to_string())
+    }
+}
+
+impl From<String> for Error {
+    fn from(message: String) -> Error {
+        Error::Msg(message)
+    }
+}
+
+/// The error object of the Mattermost API
+#[derive(Deserialize)]
+struct AppError {
+    id: String,
+    message: String,
+    #[serde(default)]
+    request_id: String,
+}
+
+impl Error {
+    /// The error for a response with the error `status` and `body`
+    pub(crate) fn from_response(status: u16, reason: Option<&str>, body: &str) -> Error {
+        match serde_json::from_str::<AppError>(body) {
+            Ok(error) => Error::Api {
+                id: error.id,
+                message: error.message,
+                status,
+                request_id: error.request_id,
+            },
+            Err(_) => Error::Api {
+                id: String::new(),
+                message: reason.unwrap_or("Unknown error").to_string(),
+                status,
+                request_id: String::new(),
+            },
+        }
+    }
+
+    /// The HTTP status of the error response of the server, if the error was caused by one
+    pub fn status(&self) -> Option<u16> {
+        causes(self).find_map(|err| {
+            if let Some(err) = err.downcast_ref::<::reqwest::Error>() {
+                return err.status().map(|status| status.as_u16());
+            }
+            match err.downcast_ref::<Error>()? {
+                Error::Api { status, .. } => Some(*status),
+                Error::RateLimited(_) => Some(429),
+                _ => None,
+            }
+        })
+    }
+
+    /// Whether repeating the operation later may succeed
+    ///
+    /// This covers timeouts, the rate limit, failed connections, and server errors except for unimplemented endpoints.
+    /// Errors caused by the request itself, like missing permissions or invalid parameters, are permanent.
+    ///
+    /// ```
+    /// # use mattermost_structs::Error;
+    /// let client = reqwest::Client::new();
+    /// let refused = client.get("http://127.0.0.1:1/").send().unwrap_err();
+    /// assert!(Error::from(refused).is_retryable());
+    /// let invalid_header = client
+    ///     .get("http://127.0.0.1:1/")
+    ///     .header("x-test", "line\nbreak")
+    ///     .send()
+    ///     .unwrap_err();
+    /// assert!(!Error::from(invalid_header).is_retryable());
+    /// ```
+    pub fn is_retryable(&self) -> bool {
+        causes(self).any(|err| {
+            if let Some(err) = err.downcast_ref::<Error>() {
+                return match err {
+                    Error::Timeout(_) | Error::RateLimited(_) => true,
+                    Error::Api { status, .. } => *status >= 500 && *status != 501,
+                    _ => false,
+                };
+            }
+            if let Some(err) = err.downcast_ref::<::reqwest::Error>() {
+                // Only failures of the connection, errors of building the request are permanent
+                let inner = err.get_ref();
+                let failed = inner
+                    .and_then(|inner| inner.downcast_ref::<::hyper::Error>())
+                    .is_some_and(|err| !err.is_user())
+                    || inner
+                        .and_then(|inner| inner.downcast_ref::<io::Error>())
+                        .is_some_and(is_transient_io_error);
+                return failed
+                    || err.is_timeout()
+                    || err.status().is_some_and(|status| {
+                        status.is_server_error() && status != StatusCode::NOT_IMPLEMENTED
+                    });
+            }
+            if let Some(err) = err.downcast_ref::<io::Error>() {
+                return is_transient_io_error(err);
+            }
+            false
+        })
+    }
+
+    /// Whether the server did not answer in time
+    pub fn is_timeout(&self) -> bool {
+        causes(self).any(|err| match err.downcast_ref::<Error>() {
+            Some(Error::Timeout(_)) => true,
+            _ => err
+                .downcast_ref::<::reqwest::Error>()
+                .is_some_and(::reqwest::Error::is_timeout),
+        })
+    }
+
+    /// Whether the token or the login credentials are invalid, or the token lacks the permissions for the request
+    pub fn is_auth_error(&self) -> bool {
+        causes(self).any(|err| match err.downcast_ref::<Error>() {
+            Some(Error::Api { status, .. }) => *status == 401 || *status == 403,
+            Some(Error::LoginFailed(_)) => true,
+            _ => false,
+        })
+    }
+
+    /// Whether the requested object does not exist, or the user cannot see it
+    pub fn is_not_found(&self) -> bool {
+        self.status() == Some(404)
+    }
+
+    /// Display the error and all its causes, one per line
+    pub fn display_chain(&self) -> DisplayChain<'_> {
+        DisplayChain(self)
+    }
+}
+
+/// Failures of the connection which may not happen again
+fn is_transient_io_error(err: &io::Error) -> bool {
+    use std::io::ErrorKind::*;
+    matches!(
+        err.kind(),
+        ConnectionRefused
+            | ConnectionReset
+            | ConnectionAborted
+            | BrokenPipe
+            | TimedOut
+            | Interrupted
+            | UnexpectedEof
+    )
+}
+
+/// An error and all its causes
+///
+/// The transparent variants hide the wrapped error from [`source`](StdError::source), so it takes the place of the variant.
+fn causes(err: &Error) -> impl Iterator<Item = &(dyn StdError + 'static)> {
+    iter::successors(Some(err as &(dyn StdError + 'static)), |&err| err.source())
+        .map(unwrap_transparent)
+}
+
+fn unwrap_transparent<'a>(err: &'a (dyn StdError + 'static)) -> &'a (dyn StdError + 'static) {
+    match err.downcast_ref::<Error>() {
+        Some(Error::Io(err)) => err,
+        Some(Error::Reqwest(err)) => err,
+        _ => err,
+    }
+}
+
+/// See [`Error::display_chain`]
+pub struct DisplayChain<'a>(&'a Error);
+
+impl fmt::Display for DisplayChain<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Error: {}", self.0)?;
+        let mut cause = self.0.source();
+        while let Some(err) = cause {
+            write!(f, "\nCaused by: {}", err)?;
+            cause = err.source();
+        }
+        Ok(())
+    }
+}
+
+/// Describe the operation which failed
+pub trait ResultExt<T> {
+    /// Wrap the error in an [`Error::Context`] with the message returned by `message`
+    fn chain_err<F, S>(self, message: F) -> Result<T>
+    where
+        F: FnOnce() -> S,
+        S: Into<String>;
+}
+
+impl<T, E> ResultExt<T> for ::std::result::Result<T, E>
+where
+    E: StdError + Send + Sync + 'static,
+{
+    fn chain_err<F, S>(self, message: F) -> Result<T>
+    where
+        F: FnOnce() -> S,
+        S: Into<String>,
+    {
+        self.map_err(|err| Error::Context {
+            message: message().into(),
+            source: Box::new(err),
+        })
     }
 }
@@ -0,0 +1,135 @@
+//! Helpers to build Markdown snippets for [`CreatePostRequest.message`](crate::api::CreatePostRequest)
+
+/// Characters with a special meaning in Mattermost's Markdown dialect
+const MARKDOWN_SPECIAL_CHARS: &[char] = &[
+    '\\', '`', '*', '_', '~', '#', '[', ']', '(', ')', '<', '>', '|', '!', '-', '+', '=', ':',
+];
+
+/// Escape `text` such that it is rendered literally
+///
+/// This prevents user controlled text from introducing formatting, links, or code blocks.
+pub fn escape<S>(text: S) -> String
+where
+    S: AsRef<str>,
+{
+    let text = text.as_ref();
+    let mut res = String::with_capacity(text.len());
+    for c in text.chars() {
+        if MARKDOWN_SPECIAL_CHARS.contains(&c) {
+            res.push('\\');
+        }
+        res.push(c);
+    }
+    res
+}
+
+/// Wrap `code` in a fenced code block with optional syntax highlighting
+///
+/// The fence is chosen longer than any run of backticks inside `code`, such that the block cannot be terminated early.
+pub fn code_block<S>(code: S, language: Option<&str>) -> String
+where
+    S: AsRef<str>,
+{
+    let code = code.as_ref();
+    let mut longest_run = 0;
+    let mut current_run = 0;
+    for c in code.chars() {
+        if c == '`' {
+            current_run += 1;
+            longest_run = longest_run.max(current_run);
+        } else {
+            current_run = 0;
+        }
+    }
+    let fence = "`".repeat((longest_run + 1).max(3));
+
+    let mut res = String::with_capacity(code.len() + 2 * fence.len() + 16);
+    res.push_str(&fence);
+    res.push_str(language.unwrap_or(""));
+    res.push('\n');
+    res.push_str(code);
+    if !code.ends_with('\n') {
+        res.push('\n');
+    }
+    res.push_str(&fence);
+    res
+}
+
+/// Render `rows` as a Markdown table
+///
+/// The first row is used as the table header.
+/// Rows with fewer cells than the widest row are padded with empty cells.
+/// Cell contents are escaped and newlines are replaced by spaces, since table cells cannot span multiple lines.
+pub fn table<S>(rows: &[Vec<S>]) -> String
+where
+    S: AsRef<str>,
+{
+    let columns = rows.iter().map(Vec::len).max().unwrap_or(0);
+    if columns == 0 {
+        return String::new();
+    }
+
+    let render_row = |row: &[S]| {
+        let mut line = String::from("|");
+        for idx in 0..columns {
+            let cell = row.get(idx).map(|c| c.as_ref()).unwrap_or("");
+            line.push(' ');
+            line.push_str(&escape(cell.replace(['\r', '\n'], " ")));
+            line.push_str(" |");
+        }
+        line
+    };
+
+    let mut lines = Vec::with_capacity(rows.len() + 1);
+    lines.push(render_row(&rows[0]));
+    lines.push(format!("|{}", " --- |".repeat(columns)));
+    lines.extend(rows[1..].iter().map(|row| render_row(row)));
+    lines.join("\n")
+}
+
+/// Render all occurrences of `terms` in `text` in bold
+///
+/// Terms only match whole words, ignoring ASCII case.
+/// If terms overlap, the longer one wins.
+/// `text` is expected to be Markdown already, so it is not escaped.
+pub fn emphasize<S>(text: &str, terms: &[S]) -> String
+where
+    S: AsRef<str>,
+{
+    let mut terms: Vec<&str> = terms
+        .iter()
+        .map(AsRef::as_ref)
+        .filter(|term| !term.is_empty())
+        .collect();
+    terms.sort_by_key(|term| std::cmp::Reverse(term.len()));
+
+    let is_word_char = |c: Option<char>| c.is_some_and(char::is_alphanumeric);
+    let mut res = String::with_capacity(text.len());
+    let mut pos = 0;
+    while pos < text.len() {
+        let rest = &text[pos..];
+        let matched = if is_word_char(text[..pos].chars().next_back()) {
+            None
+        } else {
+            terms.iter().find(|term| {
+                rest.get(..term.len())
+                    .is_some_and(|candidate| candidate.eq_ignore_ascii_case(term))
+                    && !is_word_char(rest[term.len()..].chars().next())
+            })
+        };
+        match matched {
+            Some(term) => {
+                res.push_str("**");
+                res.push_str(&rest[..term.len()]);
+                res.push_str("**");
+                pos += term.len();
+            }
+            None => {
+                let c = rest.chars().next().expect("pos is before the end of text");
+                res.push(c);
+                pos += c.len_utf8();
+            }
+        }
+    }
+    res
+}
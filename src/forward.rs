@@ -0,0 +1,446 @@
+//! Forward websocket events as JSON to an HTTP endpoint
+//!
+//! Every delivery carries a monotonically increasing id in the [`DELIVERY_HEADER`], which endpoints use to drop duplicates.
+//! With a [spool directory](Forwarder::spool), events which cannot be delivered are written to disk and sent again later,
+//! such that each event reaches the endpoint at least once, even across restarts.
+
+use crate::{
+    emoji::EmojiResolver,
+    error::{Error, Result, ResultExt},
+    websocket::{Events, MessagePush},
+};
+use hmac::{Hmac, Mac};
+use log::{debug, warn};
+use reqwest::{Client as WebClient, StatusCode};
+use sha2::Sha256;
+use std::{
+    collections::HashSet,
+    fs,
+    path::{Path, PathBuf},
+    result::Result as StdResult,
+    sync::{mpsc, Arc, Mutex},
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+use url::Url;
+
+/// Header containing the name of the forwarded event
+pub const EVENT_HEADER: &str = "X-Mattermost-Event";
+/// Header containing the HMAC-SHA256 signature of the body, in the form `sha256=<hex digest>`
+pub const SIGNATURE_HEADER: &str = "X-Mattermost-Signature";
+/// Header containing the delivery id, which increases with every event
+///
+/// Redelivered events keep their id.
+pub const DELIVERY_HEADER: &str = "X-Mattermost-Delivery";
+
+/// File in the spool directory storing the next delivery id
+const NEXT_ID_FILE: &str = "next_id";
+
+/// Sends events to an HTTP endpoint
+///
+/// Each event is sent as a POST request with the full [`MessagePush`] as JSON body.
+/// With an [emoji resolver](Forwarder::resolve_emoji), `reaction_added` events additionally contain a `resolved_reaction`, see [`ResolvedReaction`](crate::emoji::ResolvedReaction).
+/// Failed deliveries are retried with exponential backoff, if the endpoint is unreachable or answers with a server error.
+/// Clones share the delivery ids and the spool.
+#[derive(Debug, Clone)]
+pub struct Forwarder {
+    client: WebClient,
+    url: Url,
+    events: Option<HashSet<String>>,
+    secret: Option<Vec<u8>>,
+    retries: u32,
+    emoji: Option<Arc<EmojiResolver>>,
+    state: Arc<Mutex<DeliveryState>>,
+}
+
+/// Forwards events on a separate thread, created by [`Forwarder::queue`]
+///
+/// Deliveries retry with backoff for up to a minute, which must not block a websocket handler, or the connection times out.
+/// Dropping the queue waits until the queued events are forwarded.
+#[derive(Debug)]
+pub struct ForwardQueue {
+    forwarder: Forwarder,
+    sender: Option<mpsc::Sender<MessagePush>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+/// Held while delivering, such that events are sent in the order of their ids
+#[derive(Debug)]
+struct DeliveryState {
+    next_id: u64,
+    spool: Option<PathBuf>,
+}
+
+/// Why a delivery failed
+enum Failure {
+    /// The endpoint is down or overloaded, delivering later might work
+    Unavailable(Error),
+    /// The endpoint answered with a client error
+    Rejected(Error),
+}
+
+impl Forwarder {
+    pub fn new<U>(url: U) -> Result<Forwarder>
+    where
+        U: AsRef<str>,
+    {
+        Ok(Forwarder {
+            client: WebClient::new(),
+            url: Url::parse(url.as_ref())?,
+            events: None,
+            secret: None,
+            retries: 3,
+            emoji: None,
+            state: Arc::new(Mutex::new(DeliveryState {
+                next_id: 1,
+                spool: None,
+            })),
+        })
+    }
+
+    /// Only forward events with these names, e.g., `posted`
+    ///
+    /// All events are forwarded by default.
+    pub fn events<I, S>(mut self, events: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.events = Some(events.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Sign each request body with this secret
+    pub fn secret<S>(mut self, secret: S) -> Self
+    where
+        S: Into<Vec<u8>>,
+    {
+        self.secret = Some(secret.into());
+        self
+    }
+
+    /// Number of retries after the first failed delivery
+    pub fn retries(mut self, retries: u32) -> Self {
+        self.retries = retries;
+        self
+    }
+
+    /// Add the emoji of added reactions to the forwarded events
+    ///
+    /// Events are forwarded unchanged if the emoji cannot be resolved.
+    pub fn resolve_emoji(mut self, resolver: EmojiResolver) -> Self {
+        self.emoji = Some(Arc::new(resolver));
+        self
+    }
+
+    /// Store events which cannot be delivered in `dir` instead of dropping them
+    ///
+    /// Each spooled event is a file named after its delivery id, `<id>.json`.
+    /// The directory also keeps the next delivery id, such that ids keep increasing across restarts.
+    /// Call [`Forwarder::redeliver`] on startup to send the events left over from the last run.
+    pub fn spool<P>(self, dir: P) -> Result<Self>
+    where
+        P: Into<PathBuf>,
+    {
+        let dir = dir.into();
+        fs::create_dir_all(&dir)
+            .chain_err(|| format!("Cannot create spool directory {}", dir.display()))?;
+        let stored = match fs::read_to_string(dir.join(NEXT_ID_FILE)) {
+            Ok(content) => content
+                .trim()
+                .parse::<u64>()
+                .chain_err(|| format!("Invalid {} in {}", NEXT_ID_FILE, dir.display()))?,
+            Err(ref err) if err.kind() == std::io::ErrorKind::NotFound => 1,
+            Err(err) => return Err(err.into()),
+        };
+        let spooled = spooled_files(&dir)?.last().map_or(0, |(id, _)| *id);
+        {
+            let mut state = self.state.lock().unwrap();
+            state.next_id = stored.max(spooled + 1);
+            state.spool = Some(dir);
+        }
+        Ok(self)
+    }
+
+    /// Number of events waiting in the spool directory
+    pub fn spooled(&self) -> Result<usize> {
+        let state = self.state.lock().unwrap();
+        match &state.spool {
+            Some(dir) => Ok(spooled_files(dir)?.len()),
+            None => Ok(0),
+        }
+    }
+
+    /// Send all spooled events, oldest first
+    ///
+    /// Stops at the first event which cannot be delivered, keeping it and all newer ones.
+    /// Events rejected by the endpoint are dropped, since they would be rejected again.
+    /// Returns the number of delivered events.
+    pub fn redeliver(&self) -> Result<usize> {
+        let state = self.state.lock().unwrap();
+        match &state.spool {
+            Some(dir) => self.drain(dir, self.retries),
+            None => Ok(0),
+        }
+    }
+
+    /// Forward events in the order they are pushed to the returned queue, on a separate thread
+    ///
+    /// Failed deliveries are logged.
+    pub fn queue(self) -> ForwardQueue {
+        let (sender, receiver) = mpsc::channel::<MessagePush>();
+        let forwarder = self.clone();
+        let thread = thread::spawn(move || {
+            for message in receiver {
+                if let Err(err) = forwarder.forward(&message) {
+                    warn!(
+                        "Failed to forward event '{}': {}",
+                        message.event.name(),
+                        err
+                    );
+                }
+            }
+        });
+        ForwardQueue {
+            forwarder: self,
+            sender: Some(sender),
+            thread: Some(thread),
+        }
+    }
+
+    pub fn matches(&self, event: &Events) -> bool {
+        self.events
+            .as_ref()
+            .is_none_or(|events| events.contains(event.name()))
+    }
+
+    /// Forward `message`, if it matches the event filter
+    ///
+    /// Returns `false` if the event was filtered out.
+    /// With a spool directory, events which cannot be delivered are spooled and count as forwarded.
+    /// While older events wait in the spool, new events are appended to it to keep the order.
+    pub fn forward(&self, message: &MessagePush) -> Result<bool> {
+        if !self.matches(&message.event) {
+            return Ok(false);
+        }
+
+        let body = self.body(message)?;
+        let event = message.event.name();
+
+        let mut state = self.state.lock().unwrap();
+        let id = state.next_id;
+        state.next_id += 1;
+        let dir = match &state.spool {
+            Some(dir) => dir,
+            None => {
+                return match self.deliver(id, event, &body, self.retries) {
+                    Ok(()) => Ok(true),
+                    Err(Failure::Unavailable(err)) | Err(Failure::Rejected(err)) => Err(err),
+                }
+            }
+        };
+        save_next_id(dir, state.next_id)?;
+
+        if !spooled_files(dir)?.is_empty() {
+            write_spooled(dir, id, &body)?;
+            // A single attempt, the endpoint is probably still down
+            if let Err(err) = self.drain(dir, 0) {
+                debug!("Spooled event {}: {}", id, err);
+            }
+            return Ok(true);
+        }
+        match self.deliver(id, event, &body, self.retries) {
+            Ok(()) => Ok(true),
+            Err(Failure::Unavailable(err)) => {
+                warn!("Spooling event {}: {}", id, err);
+                write_spooled(dir, id, &body)?;
+                Ok(true)
+            }
+            Err(Failure::Rejected(err)) => Err(err),
+        }
+    }
+
+    fn body(&self, message: &MessagePush) -> Result<Vec<u8>> {
+        let resolved = match (&message.event, &self.emoji) {
+            (Events::ReactionAdded { reaction }, Some(resolver)) => {
+                match resolver.resolve(reaction) {
+                    Ok(resolved) => resolved,
+                    Err(err) => {
+                        warn!("Cannot resolve emoji '{}': {}", reaction.emoji_name, err);
+                        return Ok(serde_json::to_vec(message)?);
+                    }
+                }
+            }
+            _ => return Ok(serde_json::to_vec(message)?),
+        };
+        let mut body = serde_json::to_value(message)?;
+        if let Some(object) = body.as_object_mut() {
+            object.insert(
+                "resolved_reaction".to_string(),
+                serde_json::to_value(resolved)?,
+            );
+        }
+        Ok(serde_json::to_vec(&body)?)
+    }
+
+    /// Deliver spooled events until the spool is empty or a delivery fails
+    fn drain(&self, dir: &Path, retries: u32) -> Result<usize> {
+        let mut delivered = 0;
+        for (id, path) in spooled_files(dir)? {
+            let body = fs::read(&path)?;
+            let event = spooled_event(&body);
+            match self.deliver(id, &event, &body, retries) {
+                Ok(()) => delivered += 1,
+                Err(Failure::Rejected(err)) => {
+                    warn!("Dropping spooled event {}: {}", id, err);
+                }
+                Err(Failure::Unavailable(err)) => return Err(err),
+            }
+            fs::remove_file(&path)?;
+        }
+        Ok(delivered)
+    }
+
+    fn deliver(&self, id: u64, event: &str, body: &[u8], retries: u32) -> StdResult<(), Failure> {
+        let signature = self.secret.as_ref().map(|secret| sign(secret, body));
+        let mut attempt = 0;
+        loop {
+            let err = match self.send(id, event, body, signature.as_ref()) {
+                Ok(status) if status.is_success() => return Ok(()),
+                Ok(status)
+                    if status.is_server_error() || status == StatusCode::TOO_MANY_REQUESTS =>
+                {
+                    format!("Endpoint answered with {}", status).into()
+                }
+                // Client errors will not go away by retrying, so give up immediately
+                Ok(status) => {
+                    return Err(Failure::Rejected(
+                        format!(
+                            "Endpoint {} rejected event '{}' with {}",
+                            self.url, event, status
+                        )
+                        .into(),
+                    ));
+                }
+                Err(err) => err,
+            };
+
+            if attempt >= retries {
+                return Err(Failure::Unavailable(Error::Context {
+                    message: format!("Failed to deliver event '{}' to {}", event, self.url),
+                    source: Box::new(err),
+                }));
+            }
+            let backoff = Duration::from_secs(1 << attempt.min(6));
+            warn!(
+                "Delivering event '{}' failed, retrying in {}s: {}",
+                event,
+                backoff.as_secs(),
+                err
+            );
+            thread::sleep(backoff);
+            attempt += 1;
+        }
+    }
+
+    fn send(
+        &self,
+        id: u64,
+        event: &str,
+        body: &[u8],
+        signature: Option<&String>,
+    ) -> Result<StatusCode> {
+        let mut request = self
+            .client
+            .post(self.url.clone())
+            .header("content-type", "application/json")
+            .header(EVENT_HEADER, event)
+            .header(DELIVERY_HEADER, id.to_string())
+            .body(body.to_vec());
+        if let Some(signature) = signature {
+            request = request.header(SIGNATURE_HEADER, signature.as_str());
+        }
+        let res = request.send().chain_err(|| "Failed to send webrequest")?;
+        debug!("forward response {}", res.status());
+        Ok(res.status())
+    }
+}
+
+impl ForwardQueue {
+    /// Queue `message` for forwarding, if it matches the event filter
+    ///
+    /// Returns `false` if the event was filtered out.
+    pub fn push(&self, message: MessagePush) -> Result<bool> {
+        if !self.forwarder.matches(&message.event) {
+            return Ok(false);
+        }
+        self.sender
+            .as_ref()
+            .and_then(|sender| sender.send(message).ok())
+            .ok_or_else(|| Error::from("The forwarding thread stopped"))?;
+        Ok(true)
+    }
+}
+
+impl Drop for ForwardQueue {
+    fn drop(&mut self) {
+        // Closing the channel ends the thread after the remaining events
+        self.sender = None;
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// Compute the value of the [`SIGNATURE_HEADER`] for `body`
+pub fn sign(secret: &[u8], body: &[u8]) -> String {
+    let mut mac = Hmac::<Sha256>::new_varkey(secret).expect("HMAC accepts keys of any length");
+    mac.input(body);
+    format!("sha256={}", hex::encode(mac.result().code()))
+}
+
+/// Spooled events as delivery id and path, ordered by id
+///
+/// The files are named `<id>.json`.
+/// Older versions named them `<id>.<event>.json`, which are still picked up.
+fn spooled_files(dir: &Path) -> Result<Vec<(u64, PathBuf)>> {
+    let mut files = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        let name = match path.file_name().and_then(|name| name.to_str()) {
+            Some(name) if name.ends_with(".json") => name.trim_end_matches(".json"),
+            _ => continue,
+        };
+        let id = name.split('.').next().unwrap_or_default();
+        if let Ok(id) = id.parse() {
+            files.push((id, path.clone()));
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// Name of the event in a spooled body
+///
+/// The name is only used for the [`EVENT_HEADER`], so it is not stored in the file name, where event names chosen by the server could escape the spool directory.
+fn spooled_event(body: &[u8]) -> String {
+    serde_json::from_slice::<serde_json::Value>(body)
+        .ok()
+        .and_then(|body| body.get("event")?.as_str().map(ToString::to_string))
+        .unwrap_or_default()
+}
+
+fn write_spooled(dir: &Path, id: u64, body: &[u8]) -> Result<()> {
+    let path = dir.join(format!("{:020}.json", id));
+    let tmp = dir.join(format!("{:020}.tmp", id));
+    fs::write(&tmp, body)?;
+    fs::rename(&tmp, path)?;
+    Ok(())
+}
+
+fn save_next_id(dir: &Path, next_id: u64) -> Result<()> {
+    let tmp = dir.join(format!("{}.tmp", NEXT_ID_FILE));
+    fs::write(&tmp, next_id.to_string())?;
+    fs::rename(&tmp, dir.join(NEXT_ID_FILE))?;
+    Ok(())
+}
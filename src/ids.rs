@@ -0,0 +1,188 @@
+//! Typed ids of users, channels, posts, and teams
+//!
+//! All ids are strings on the wire, but mixing them up is a bug, e.g., passing a channel id to [`Client::get_users_by_id`](crate::api::Client::get_users_by_id).
+//! The newtypes serialize like plain strings and dereference to `str`, so they can be compared and formatted like the strings they replace.
+//!
+//! ```
+//! use mattermost_structs::ids::{ChannelId, UserId};
+//!
+//! let user_id = UserId::from("ur6ckbszh7nzje6hkkxjbngswo");
+//! assert_eq!(user_id, "ur6ckbszh7nzje6hkkxjbngswo");
+//! assert_eq!(serde_json::to_string(&user_id).unwrap(), r#""ur6ckbszh7nzje6hkkxjbngswo""#);
+//! let channel_id: ChannelId = serde_json::from_str(r#""3df1ha9a7i8ftybnsp9u4iwcme""#).unwrap();
+//! assert_eq!(channel_id.len(), 26);
+//! ```
+
+#[cfg(feature = "archive")]
+use rusqlite::types::{FromSql, FromSqlResult, ToSql, ToSqlOutput, ValueRef};
+use serde::{Deserialize, Serialize};
+use std::{borrow::Borrow, convert::Infallible, fmt, ops::Deref, str::FromStr};
+
+macro_rules! id_type {
+    ($(#[$attr:meta])* $name:ident) => {
+        $(#[$attr])*
+        #[derive(Debug, Clone, Default, Eq, PartialEq, Ord, PartialOrd, Hash, Serialize, Deserialize)]
+        #[serde(transparent)]
+        pub struct $name(String);
+
+        impl $name {
+            pub fn new<S>(id: S) -> $name
+            where
+                S: Into<String>,
+            {
+                $name(id.into())
+            }
+
+            pub fn as_str(&self) -> &str {
+                &self.0
+            }
+
+            pub fn into_string(self) -> String {
+                self.0
+            }
+        }
+
+        impl Deref for $name {
+            type Target = str;
+
+            fn deref(&self) -> &str {
+                &self.0
+            }
+        }
+
+        impl AsRef<str> for $name {
+            fn as_ref(&self) -> &str {
+                &self.0
+            }
+        }
+
+        /// Allows methods to accept the id by value or by reference, but no other kind of id
+        impl AsRef<$name> for $name {
+            fn as_ref(&self) -> &$name {
+                self
+            }
+        }
+
+        impl Borrow<str> for $name {
+            fn borrow(&self) -> &str {
+                &self.0
+            }
+        }
+
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str(&self.0)
+            }
+        }
+
+        impl FromStr for $name {
+            type Err = Infallible;
+
+            fn from_str(s: &str) -> Result<Self, Self::Err> {
+                Ok($name(s.to_string()))
+            }
+        }
+
+        impl From<String> for $name {
+            fn from(id: String) -> $name {
+                $name(id)
+            }
+        }
+
+        impl From<&str> for $name {
+            fn from(id: &str) -> $name {
+                $name(id.to_string())
+            }
+        }
+
+        impl From<&String> for $name {
+            fn from(id: &String) -> $name {
+                $name(id.clone())
+            }
+        }
+
+        impl From<&$name> for $name {
+            fn from(id: &$name) -> $name {
+                id.clone()
+            }
+        }
+
+        impl From<$name> for String {
+            fn from(id: $name) -> String {
+                id.0
+            }
+        }
+
+        impl PartialEq<str> for $name {
+            fn eq(&self, other: &str) -> bool {
+                self.0 == other
+            }
+        }
+
+        impl PartialEq<&str> for $name {
+            fn eq(&self, other: &&str) -> bool {
+                self.0 == *other
+            }
+        }
+
+        impl PartialEq<String> for $name {
+            fn eq(&self, other: &String) -> bool {
+                self.0 == *other
+            }
+        }
+
+        impl PartialEq<$name> for String {
+            fn eq(&self, other: &$name) -> bool {
+                *self == other.0
+            }
+        }
+
+        impl PartialEq<$name> for str {
+            fn eq(&self, other: &$name) -> bool {
+                self == other.0
+            }
+        }
+
+        impl PartialEq<$name> for &str {
+            fn eq(&self, other: &$name) -> bool {
+                *self == other.0
+            }
+        }
+
+        #[cfg(feature = "archive")]
+        impl ToSql for $name {
+            fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+                self.0.to_sql()
+            }
+        }
+
+        #[cfg(feature = "archive")]
+        impl FromSql for $name {
+            fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+                String::column_result(value).map($name)
+            }
+        }
+    };
+}
+
+id_type! {
+    /// Id of a [`User`](crate::api::User)
+    UserId
+}
+
+id_type! {
+    /// Id of a [`Channel`](crate::api::Channel)
+    ChannelId
+}
+
+id_type! {
+    /// Id of a [`Post`](crate::websocket::Post)
+    ///
+    /// The server uses the empty id if a post is not set, e.g., for the `root_id` of posts which are no reply.
+    PostId
+}
+
+id_type! {
+    /// Id of a [`Team`](crate::websocket::Team)
+    TeamId
+}
@@ -6,8 +6,43 @@
 )]
 
 pub mod api;
+#[cfg(feature = "archive")]
+pub mod archive;
+pub mod attachment;
+pub mod audit;
+pub mod broadcast;
+pub mod clock;
+pub mod command;
+pub mod diff;
+pub mod digest;
+pub mod echo;
+pub mod emoji;
 pub mod error;
-pub use crate::error::{Error, Result};
+pub mod format;
+pub mod forward;
+pub mod ids;
+pub mod liveness;
+pub mod mention;
+pub mod moderation;
+pub mod notification;
+pub mod permalink;
+pub mod preflight;
+pub mod presence;
+pub mod queue;
+pub mod reconnect;
+pub mod relay;
+pub mod render;
+pub mod replay;
+pub mod resume;
+pub mod scheduler;
+pub mod sink;
+pub mod stats;
+pub mod sync;
+#[cfg(feature = "testing")]
+pub mod testing;
+pub mod webhook;
 pub mod websocket;
 
 mod serialize;
+
+pub use crate::error::{Error, Result};
@@ -0,0 +1,74 @@
+//! Detect websocket connections which are open, but silently stopped delivering events
+//!
+//! Some proxies and NAT gateways drop idle connections without closing them, while still answering pings.
+//! Pongs are therefore no proof that the server is reachable.
+//! Only messages from the server, including the pings the server sends, count as activity.
+
+use std::{
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+/// Tracks the time since the last activity on a connection
+///
+/// The number of detected stalls is shared between all clones of the counter returned by [`LivenessDetector::stall_counter`],
+/// such that it survives reconnects if the same counter is passed to [`LivenessDetector::with_counter`].
+#[derive(Debug, Clone)]
+pub struct LivenessDetector {
+    deadline: Duration,
+    last_activity: Instant,
+    stalls: Arc<AtomicU64>,
+}
+
+impl LivenessDetector {
+    /// The connection counts as stalled if there is no activity for `deadline`
+    pub fn new(deadline: Duration) -> LivenessDetector {
+        Self::with_counter(deadline, Arc::default())
+    }
+
+    pub fn with_counter(deadline: Duration, stalls: Arc<AtomicU64>) -> LivenessDetector {
+        LivenessDetector {
+            deadline,
+            last_activity: Instant::now(),
+            stalls,
+        }
+    }
+
+    pub fn deadline(&self) -> Duration {
+        self.deadline
+    }
+
+    /// Call for every message received from the server
+    pub fn record_activity(&mut self) {
+        self.last_activity = Instant::now();
+    }
+
+    pub fn is_stalled(&self) -> bool {
+        self.last_activity.elapsed() >= self.deadline
+    }
+
+    /// Time until the connection counts as stalled, if there is no further activity
+    pub fn remaining(&self) -> Duration {
+        self.deadline
+            .checked_sub(self.last_activity.elapsed())
+            .unwrap_or_default()
+    }
+
+    /// Count a stall, e.g., before force-closing the connection
+    pub fn record_stall(&mut self) {
+        self.stalls.fetch_add(1, Ordering::Relaxed);
+        self.last_activity = Instant::now();
+    }
+
+    /// Number of stalls detected so far
+    pub fn stalls(&self) -> u64 {
+        self.stalls.load(Ordering::Relaxed)
+    }
+
+    pub fn stall_counter(&self) -> Arc<AtomicU64> {
+        self.stalls.clone()
+    }
+}
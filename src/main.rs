@@ -1,31 +1,50 @@
+mod read_mirror;
 mod websocket_client;
 
-use crate::websocket_client::WsClient;
+use crate::{
+    read_mirror::{ReadMirror, ReadStateMirror},
+    websocket_client::{Worker, WsClient, STALL_TIMEOUT},
+};
+use chrono::{DateTime, Utc};
 use chrono_tz::Europe::Berlin as TzBerlin;
-use error_chain::{quick_main, ChainedError};
-use log::{debug, error, warn};
+use log::{debug, error, info, warn};
 use mattermost_structs::{
     api::{ChannelType, Client, CreatePostRequest},
-    websocket::{Events, Message, Status},
+    clock::ClockSkew,
+    ids::{ChannelId, UserId},
+    liveness::LivenessDetector,
+    mention::MentionContext,
+    notification::{allows_push, Priority, PriorityRules},
+    preflight::{self, preflight, Check},
+    reconnect::{CircuitState, EscalationPolicy, FailureWindow, Reconnector},
+    resume::{ResumeOutcome, ResumeState},
+    stats::EventCounter,
+    websocket::{self, AuthMethod, Events, Message, NotifyProps, Post, Status},
     Result,
 };
 use serde::{Deserialize, Serialize};
 use std::{
+    collections::HashMap,
     ffi::{OsStr, OsString},
     fs::File,
     path::{Path, PathBuf},
-    sync::{Arc, Mutex},
+    process,
+    sync::{atomic::AtomicU64, Arc, Mutex},
     thread,
-    time::Duration,
+    time::{Duration, Instant},
 };
 use structopt::StructOpt;
-use url::Url;
-use ws::connect;
 
 #[derive(Clone, Debug, Deserialize, Serialize)]
 struct Config {
     signal_phone_number: String,
     servers: Vec<ServerConfig>,
+    /// What to do if a server connection keeps failing
+    #[serde(default)]
+    escalation: EscalationPolicy,
+    /// Groups of channels on different servers whose read state is kept in sync
+    #[serde(default)]
+    read_mirrors: Vec<ReadMirror>,
 }
 
 #[derive(Clone, Debug, Deserialize, Serialize)]
@@ -35,6 +54,34 @@ pub struct ServerConfig {
     base_url: String,
     token: String,
     servername: String,
+    /// How the websocket connection authenticates
+    #[serde(default)]
+    websocket_auth: AuthMethod,
+    /// How loudly to notify about mentions
+    #[serde(default)]
+    priorities: PriorityRules,
+    /// Notify about mentions in muted channels and ignore the notification preferences of the channels
+    #[serde(default)]
+    ignore_notify_props: bool,
+    /// Show the time of posts according to the local clock instead of the server clock
+    #[serde(default)]
+    adjust_clock_skew: bool,
+    /// Give up connecting to the server after this many seconds
+    #[serde(default = "default_connect_timeout_secs")]
+    connect_timeout_secs: u64,
+    /// After a reconnect which could not be resumed, send one notification listing the mentions missed in between
+    #[serde(default)]
+    catch_up_mentions: bool,
+    /// Log how many events were received, by event type and channel, every this many seconds
+    #[serde(default)]
+    event_stats_interval_secs: Option<u64>,
+}
+
+/// Warn if the server clock deviates more
+const MAX_CLOCK_SKEW_SECS: i64 = 60;
+
+fn default_connect_timeout_secs() -> u64 {
+    30
 }
 
 /// Mattermost to Signal Bridge
@@ -65,42 +112,56 @@ fn path_is_file(value: &OsStr) -> std::result::Result<(), OsString> {
     Ok(())
 }
 
-quick_main!(run);
+fn main() {
+    if let Err(err) = run() {
+        eprintln!("{}", err.display_chain());
+        process::exit(1);
+    }
+}
 
 fn run() -> Result<()> {
     // Setup logging
     env_logger::init();
     // this fixes connection problems with openssl
     // it set some environment variables to the correct value for the current system
+    #[cfg(feature = "native-tls")]
     openssl_probe::init_ssl_cert_env_vars();
 
     // parse arguments
     let args = CliArgs::from_args();
 
     let config: Config = serde_yaml::from_reader(File::open(args.config)?)?;
+    let read_mirror = Arc::new(ReadStateMirror::new(config.read_mirrors, &config.servers)?);
 
     // spawn a thread for each server
     let mut thread_handles = Vec::new();
     // Check connectivity and validity of credentials
     for server_config in config.servers {
-        println!("Check connectivity for: {}", server_config.servername);
-        let client = Client::new(server_config.base_url.clone(), server_config.token.clone());
-        if let Ok(client) = client {
-            // check internet connectivity
-            if client.is_token_valid() {
-                thread_handles.push(spawn_server_handle_thread(
-                    server_config.clone(),
-                    config.signal_phone_number.clone(),
-                ));
-                thread_handles.push(spawn_server_watchdog(
-                    server_config,
-                    config.signal_phone_number.clone(),
-                ));
-            } else {
-                error!("Invalid token for {}", server_config.servername);
-            }
+        let report = preflight(&preflight::ServerConfig {
+            base_url: server_config.base_url.clone(),
+            token: server_config.token.clone(),
+            websocket_auth: server_config.websocket_auth,
+            timeout: Duration::from_secs(server_config.connect_timeout_secs),
+            max_clock_skew: Duration::from_secs(MAX_CLOCK_SKEW_SECS as u64),
+        });
+        println!("{}: {}", server_config.servername, report);
+        // The websocket connection is retried, but without a valid token it can never succeed
+        if report.passed(Check::RestAuth) {
+            thread_handles.push(spawn_server_handle_thread(
+                server_config.clone(),
+                config.signal_phone_number.clone(),
+                config.escalation.clone(),
+                read_mirror.clone(),
+            ));
+            thread_handles.push(spawn_server_watchdog(
+                server_config,
+                config.signal_phone_number.clone(),
+            ));
         } else {
-            error!("Could not connect to server '{}'", server_config.servername);
+            error!(
+                "Skipping server '{}', it is unreachable or the token is invalid",
+                server_config.servername
+            );
         }
     }
 
@@ -114,73 +175,149 @@ fn run() -> Result<()> {
 fn spawn_server_handle_thread(
     server_config: ServerConfig,
     mobile_number: String,
+    escalation: EscalationPolicy,
+    read_mirror: Arc<ReadStateMirror>,
 ) -> thread::JoinHandle<Result<()>> {
+    #[allow(clippy::too_many_arguments)]
     fn handle_server(
         serverconfig: ServerConfig,
         mobile_number: String,
         serverstate: Arc<Mutex<Status>>,
+        stalls: Arc<AtomicU64>,
+        resume: Arc<Mutex<ResumeState>>,
+        clock: ClockSkew,
+        read_mirror: Arc<ReadStateMirror>,
+        disconnected_at: Arc<Mutex<Option<DateTime<Utc>>>>,
+        stats: Option<Arc<Mutex<EventCounter>>>,
     ) -> thread::JoinHandle<Result<()>> {
         thread::spawn(move || {
-            let mut url = Url::parse(&*serverconfig.base_url)?;
-            url.set_scheme("wss")
-                .expect("Setting the scheme to wss must always work");
-            let url = url.join("/api/v4/websocket")?;
-
-            // Connect to the url and call the closure
-            if let Err(error) = connect(url.as_str(), move |out| {
-                // Queue a message to be sent when the WebSocket is open
-                if out
-                    .send(format!(
-                        r#"
-                    {{
-                        "seq": 1,
-                        "action": "authentication_challenge",
-                        "data": {{
-                            "token": "{}"
-                        }}
-                    }}
-                "#,
-                        serverconfig.token
+            let websocket =
+                websocket::Client::new(&serverconfig.base_url, serverconfig.token.clone())?
+                    .auth(serverconfig.websocket_auth)
+                    .connect_timeout(Duration::from_secs(serverconfig.connect_timeout_secs))
+                    .liveness(LivenessDetector::with_counter(
+                        Duration::from_millis(STALL_TIMEOUT),
+                        stalls,
                     ))
-                    .is_err()
-                {
-                    error!("Websocket couldn't queue an initial message.")
-                }
-
-                WsClient {
-                    ws: out,
-                    timeout: None,
-                    own_id: None,
-                    mobile_number: mobile_number.clone(),
-                    serverconfig: serverconfig.clone(),
-                    serverstate: serverstate.clone(),
-                }
-            }) {
-                // Inform the user of failure
-                error!("Failed to create WebSocket due to: {:?}", error);
+                    .resume(&resume.lock().unwrap());
+            let worker = Worker {
+                mention_context: None,
+                notify_props: HashMap::new(),
+                serverconfig: serverconfig.clone(),
+                mobile_number: mobile_number.clone(),
+                clock: clock.clone(),
             }
-            Ok(())
+            .spawn();
+            let mut client = WsClient {
+                own_id: None,
+                worker,
+                mobile_number,
+                serverconfig,
+                serverstate,
+                resume,
+                clock,
+                read_mirror,
+                disconnected_at,
+                stats,
+            };
+            websocket.run(&mut client)
         })
     };
 
     let serverstate = Arc::new(Mutex::new(Status::Online));
+    // Number of stalled connections, kept across reconnects
+    let stalls = Arc::new(AtomicU64::new(0));
+    // Allows the server to replay events missed while reconnecting
+    let resume = Arc::new(Mutex::new(ResumeState::new()));
+    // Measured on every connect and kept across reconnects
+    let clock = ClockSkew::new();
+    let disconnected_at = Arc::new(Mutex::new(None));
+    // Counted across reconnects
+    let stats = server_config.event_stats_interval_secs.map(|secs| {
+        let interval = Duration::from_secs(secs);
+        let servername = server_config.servername.clone();
+        let counter = EventCounter::new(interval).on_summary(interval, move |summary| {
+            info!(
+                "\"{}\" received {} events in the last {}s, by type {:?}, by channel {:?}",
+                servername,
+                summary.total,
+                summary.window_secs,
+                summary.top_events(5),
+                summary.top_channels(5),
+            )
+        });
+        Arc::new(Mutex::new(counter))
+    });
     // the websocket client can die, e.g., if the Internet connection fails or
     // mattermost fails for some time
     // Therefore, make sure to restart the handle if it fails
+    let mut reconnector = Reconnector::new();
+    let exit_on_escalation = escalation.exit;
+    let mut failures = FailureWindow::new(escalation);
     thread::spawn(move || loop {
         let serverstate = serverstate.clone();
         let serverconfig = server_config.clone();
-        let mobile_number = mobile_number.clone();
-
-        match handle_server(serverconfig, mobile_number, serverstate).join() {
-            Ok(Err(err)) => warn!(
-                "Websocket connection to \"{}\" failed:\n{}",
-                server_config.servername, err
-            ),
-            Err(_) => warn!("Thread for \"{}\" paniced!", server_config.servername),
-            _ => {}
+
+        let start = Instant::now();
+        let failure = match handle_server(
+            serverconfig,
+            mobile_number.clone(),
+            serverstate,
+            stalls.clone(),
+            resume.clone(),
+            clock.clone(),
+            read_mirror.clone(),
+            disconnected_at.clone(),
+            stats.clone(),
+        )
+        .join()
+        {
+            Ok(Err(err)) => {
+                warn!(
+                    "Websocket connection to \"{}\" failed:\n{}",
+                    server_config.servername, err
+                );
+                Some(err.to_string())
+            }
+            Err(_) => {
+                warn!("Thread for \"{}\" paniced!", server_config.servername);
+                Some("the connection thread panicked".to_string())
+            }
+            _ => None,
+        };
+        disconnected_at.lock().unwrap().get_or_insert_with(Utc::now);
+        if let Some(failure) = failure {
+            if failures.record_failure() {
+                let msg = format!(
+                    "Connection to {server} keeps failing, last error: {failure}",
+                    server = server_config.servername,
+                    failure = failure,
+                );
+                error!("{}", msg);
+                if let Err(e) = send_android_notification(&mobile_number, &msg) {
+                    warn!("{}", e.display_chain());
+                }
+                if exit_on_escalation {
+                    process::exit(1);
+                }
+            }
+        }
+        let delay = reconnector.connection_ended(start.elapsed());
+        if reconnector.state() == CircuitState::Open {
+            warn!(
+                "Connection to \"{}\" failed {} times in a row, pausing for {}s",
+                server_config.servername,
+                reconnector.failures(),
+                delay.as_secs()
+            );
+        } else {
+            debug!(
+                "Reconnecting to \"{}\" in {}ms",
+                server_config.servername,
+                delay.as_millis()
+            );
         }
-        thread::sleep(Duration::from_secs(5));
+        thread::sleep(delay);
     })
 }
 
@@ -191,7 +328,8 @@ fn spawn_server_watchdog(
     thread::spawn(move || {
         let client = Client::new(server_config.base_url, server_config.token)?;
         loop {
-            if !client.is_token_valid() {
+            if let Err(err) = client.check_token() {
+                warn!("{}", err.display_chain());
                 let msg = format!(
                     "Token for {server} expired!",
                     server = server_config.servername,
@@ -205,20 +343,111 @@ fn spawn_server_watchdog(
     })
 }
 
-fn react_to_message(client: &mut WsClient, message: &str) {
-    if let Ok(Message::Push(msg)) = serde_json::from_str::<Message>(message) {
+/// Notification preferences of the own user in the channel, fetched once and then updated by events
+fn channel_notify_props(worker: &mut Worker, channel_id: &str) -> Option<NotifyProps> {
+    if let Some(props) = worker.notify_props.get(channel_id) {
+        return Some(props.clone());
+    }
+    let member = Client::new(
+        worker.serverconfig.base_url.clone(),
+        worker.serverconfig.token.clone(),
+    )
+    .and_then(|api| api.get_my_channel_member(ChannelId::from(channel_id)))
+    .map_err(|err| {
+        warn!(
+            "Failed to look up the notification preferences of channel {}:\n{}",
+            channel_id,
+            err.display_chain()
+        )
+    })
+    .ok()?;
+    worker
+        .notify_props
+        .insert(channel_id.to_string(), member.notify_props.clone());
+    Some(member.notify_props)
+}
+
+fn react_to_message(client: &mut WsClient, message: Message) {
+    if let Message::Push(msg) = message {
         debug!("Received message:\n{:?}", msg);
+        if let Some(stats) = &client.stats {
+            stats.lock().unwrap().handle(&msg);
+        }
+
+        let outcome = client.resume.lock().unwrap().observe(&msg);
+        if outcome == Some(ResumeOutcome::Lost) {
+            warn!(
+                "Could not resume the connection to \"{}\", events sent while disconnected are lost",
+                client.serverconfig.servername
+            );
+        }
+        // Servers without resumption start a new connection every time
+        if matches!(
+            outcome,
+            Some(ResumeOutcome::Initial) | Some(ResumeOutcome::Lost)
+        ) {
+            if let Some(since) = client.disconnected_at.lock().unwrap().take() {
+                if client.serverconfig.catch_up_mentions {
+                    catch_up_mentions(client, since);
+                }
+            }
+        }
 
         use crate::Events::*;
         match msg.event {
             Hello { .. } => {
                 client.own_id = Some(msg.broadcast.user_id);
+                // The username is needed to tell personal mentions apart
+                client.run_in_worker(|worker| {
+                    worker.mention_context = Client::builder(
+                        worker.serverconfig.base_url.clone(),
+                        worker.serverconfig.token.clone(),
+                    )
+                    .clock(worker.clock.clone())
+                    .build()
+                    .and_then(|api| api.get_me())
+                    .map(|me| MentionContext {
+                        user_id: me.id,
+                        username: me.username,
+                        groups: Vec::new(),
+                    })
+                    .map_err(|err| warn!("Failed to look up own user:\n{}", err.display_chain()))
+                    .ok();
+                });
+                if let Some(skew) = client.clock.skew() {
+                    if skew.num_seconds().abs() >= MAX_CLOCK_SKEW_SECS {
+                        warn!(
+                            "The clock of \"{}\" is {}s ahead of the local clock",
+                            client.serverconfig.servername,
+                            skew.num_seconds()
+                        );
+                    }
+                }
+            }
+
+            // Keep the notification preferences up to date, e.g., if the user mutes a channel
+            ChannelMemberUpdated { channel_member }
+                if client.own_id.as_deref() == Some(channel_member.user_id.as_str()) =>
+            {
+                client.run_in_worker(move |worker| {
+                    worker
+                        .notify_props
+                        .insert(channel_member.channel_id, channel_member.notify_props);
+                });
+            }
+
+            // Mark the mirrored channels on the other servers as read
+            ChannelViewed { channel_id } => {
+                let read_mirror = client.read_mirror.clone();
+                client.run_in_worker(move |worker| {
+                    read_mirror.channel_viewed(&worker.serverconfig.servername, &channel_id)
+                });
             }
 
             // Track the servers/users status to not send any notifications while in Do Not Disturb mode
-            StatusChange { status, .. } => {
+            StatusChange(user_status) => {
                 let mut serverstate = client.serverstate.lock().unwrap();
-                *serverstate = status;
+                *serverstate = user_status.status;
             }
 
             Posted {
@@ -230,25 +459,21 @@ fn react_to_message(client: &mut WsClient, message: &str) {
                 ..
             } => {
                 // React to some messages
-                if client.own_id == Some(post.user_id) && post.message.starts_with("@me") {
-                    let client = Client::new(
-                        client.serverconfig.base_url.clone(),
-                        client.serverconfig.token.clone(),
-                    );
-                    if let Ok(client) = client {
-                        // if the message we receive has a root_id, then we are already in a thread, so further use that
-                        // otherwise use the post id
-                        let root_id = if !post.root_id.is_empty() {
-                            post.root_id.clone()
-                        } else {
-                            post.id.clone()
-                        };
-
-                        let _ = client.create_post(&CreatePostRequest {
-                            channel_id: post.channel_id.clone(),
-                            message: "Hi!".to_string(),
-                            root_id: Some(root_id),
-                            ..CreatePostRequest::default()
+                if client.own_id.as_ref() == Some(&post.user_id) && post.message.starts_with("@me")
+                {
+                    let request = CreatePostRequest::builder()
+                        .in_reply_to(&post)
+                        .message("Hi!")
+                        .build();
+                    if let Ok(request) = request {
+                        client.run_in_worker(move |worker| {
+                            let client = Client::new(
+                                worker.serverconfig.base_url.clone(),
+                                worker.serverconfig.token.clone(),
+                            );
+                            if let Ok(client) = client {
+                                let _ = client.create_post(&request);
+                            }
                         });
                     }
                 }
@@ -256,7 +481,7 @@ fn react_to_message(client: &mut WsClient, message: &str) {
                 // ignore broadcast events which cover us
                 if let Some(ref own_id) = client.own_id {
                     if let Some(ref omit_users) = msg.broadcast.omit_users {
-                        if let Some(omit_me) = omit_users.get(own_id) {
+                        if let Some(omit_me) = omit_users.get(own_id.as_str()) {
                             if *omit_me {
                                 return;
                             }
@@ -267,36 +492,19 @@ fn react_to_message(client: &mut WsClient, message: &str) {
                 // only send push notification when we are mentioned
                 // Also check that the status is anything but do not disturb
                 if let Some(mentions) = mentions {
-                    let status = client.serverstate.lock().unwrap();
-                    if *status != Status::DoNotDisturb
+                    let status = *client.serverstate.lock().unwrap();
+                    if status != Status::DoNotDisturb
                         && mentions.contains(client.own_id.as_ref().unwrap())
                     {
-                        let localtime = post.create_at.with_timezone(&TzBerlin).format("%H:%M:%S");
-                        let testmessage = match channel_type {
-                            ChannelType::DirectMessage | ChannelType::Group => format!(
-                                "{server} {sender}:\n{message}\n@{time}",
-                                message = post.message,
-                                sender = sender_name,
-                                server = client.serverconfig.servername,
-                                time = localtime,
-                            ),
-                            ChannelType::Open | ChannelType::Private => format!(
-                                "{server} {sender} in {channel}:\n{message}\n@{time}",
-                                message = post.message,
-                                sender = sender_name,
-                                server = client.serverconfig.servername,
-                                channel = channel_display_name,
-                                time = localtime,
-                            ),
-                            ChannelType::Internal => {
-                                // Ignore this type.
-                                // I don't know what exactly this type even is
-                                return;
-                            }
-                        };
-                        let mobile_number = client.mobile_number.clone();
-                        thread::spawn(move || {
-                            send_android_notification(&mobile_number, &testmessage)
+                        client.run_in_worker(move |worker| {
+                            notify_mention(
+                                worker,
+                                &post,
+                                &mentions,
+                                channel_type,
+                                &channel_display_name,
+                                &sender_name,
+                            )
                         });
                     }
                 }
@@ -305,10 +513,129 @@ fn react_to_message(client: &mut WsClient, message: &str) {
             // do nothing for other patterns
             _ => {}
         }
+    }
+}
+
+/// Send a push notification for a post mentioning the own user, unless it is muted or silent
+fn notify_mention(
+    worker: &mut Worker,
+    post: &Post,
+    mentions: &[UserId],
+    channel_type: ChannelType,
+    channel_display_name: &str,
+    sender_name: &str,
+) {
+    let mention = worker
+        .mention_context
+        .as_ref()
+        .and_then(|context| context.classify(post, Some(mentions)));
+    let priority = mention.as_ref().map_or(Priority::Normal, |mention| {
+        worker
+            .serverconfig
+            .priorities
+            .priority(mention, channel_type, post)
+    });
+    // Signal has no notification levels, so only silent notifications can be honored
+    if priority == Priority::Silent {
+        debug!("Skipping silent notification for post {}", post.id);
+        return;
+    }
+    if !worker.serverconfig.ignore_notify_props {
+        if let Some(props) = channel_notify_props(worker, &post.channel_id) {
+            if !allows_push(&props, mention.as_ref()) {
+                debug!(
+                    "Skipping notification for post {}, muted by the channel preferences",
+                    post.id
+                );
+                return;
+            }
+        }
+    }
+    let create_at = if worker.serverconfig.adjust_clock_skew {
+        worker.clock.to_local(post.create_at)
     } else {
-        warn!("Could not parse the following message:");
-        warn!("{}", message);
+        post.create_at
+    };
+    let localtime = create_at.with_timezone(&TzBerlin).format("%H:%M:%S");
+    let testmessage = match channel_type {
+        ChannelType::DirectMessage | ChannelType::Group => format!(
+            "{server} {sender}:\n{message}\n@{time}",
+            message = post.message,
+            sender = sender_name,
+            server = worker.serverconfig.servername,
+            time = localtime,
+        ),
+        ChannelType::Open | ChannelType::Private => format!(
+            "{server} {sender} in {channel}:\n{message}\n@{time}",
+            message = post.message,
+            sender = sender_name,
+            server = worker.serverconfig.servername,
+            channel = channel_display_name,
+            time = localtime,
+        ),
+        ChannelType::Internal => {
+            // Ignore this type.
+            // I don't know what exactly this type even is
+            return;
+        }
+    };
+    let mobile_number = worker.mobile_number.clone();
+    thread::spawn(move || send_android_notification(&mobile_number, &testmessage));
+}
+
+/// Notify about the mentions since `since` in a single message, in the background
+fn catch_up_mentions(client: &WsClient, since: DateTime<Utc>) {
+    if *client.serverstate.lock().unwrap() == Status::DoNotDisturb {
+        return;
+    }
+    let serverconfig = client.serverconfig.clone();
+    let mobile_number = client.mobile_number.clone();
+    let clock = client.clock.clone();
+    thread::spawn(move || {
+        if let Err(err) = notify_missed_mentions(&serverconfig, &mobile_number, clock, since) {
+            warn!(
+                "Failed to catch up on the mentions on \"{}\":\n{}",
+                serverconfig.servername,
+                err.display_chain()
+            );
+        }
+    });
+}
+
+fn notify_missed_mentions(
+    serverconfig: &ServerConfig,
+    mobile_number: &str,
+    clock: ClockSkew,
+    since: DateTime<Utc>,
+) -> Result<()> {
+    /// Mentions listed in the notification, the rest are only counted
+    const MAX_LISTED: usize = 10;
+
+    let api = Client::builder(serverconfig.base_url.clone(), serverconfig.token.clone())
+        .clock(clock)
+        .build()?;
+    let mentions = api.get_my_recent_mentions(since)?;
+    if mentions.is_empty() {
+        return Ok(());
+    }
+    let mut msg = format!(
+        "{server}: {count} mentions while disconnected",
+        server = serverconfig.servername,
+        count = mentions.len(),
+    );
+    for post in mentions.iter().take(MAX_LISTED) {
+        let sender = api
+            .get_user(&post.user_id)
+            .map(|user| user.username)
+            .unwrap_or_else(|_| post.user_id.to_string());
+        msg.push_str(&format!(
+            "\n{time} {sender}: {message}",
+            time = post.create_at.with_timezone(&TzBerlin).format("%H:%M"),
+            sender = sender,
+            message = post.message,
+        ));
     }
+    send_android_notification(mobile_number, &msg)
 }
 
 fn send_android_notification(mobile_number: &str, message: &str) -> Result<()> {
@@ -0,0 +1,165 @@
+//! Find out how a post mentions somebody
+//!
+//! Besides personal `@username` mentions, Mattermost knows channel-wide mentions (`@here`, `@channel`, `@all`) and mentions of user groups.
+//! The `mentions` of a [`Events::Posted`](crate::websocket::Events::Posted) event only lists the notified users, but not why they were notified.
+//!
+//! ```
+//! use mattermost_structs::mention::{parse, MentionKind};
+//!
+//! let mentions = parse("@here please review, @alice.", &["devs"]);
+//! assert_eq!(mentions, vec![MentionKind::Here, MentionKind::User("alice".to_string())]);
+//! ```
+
+use crate::{
+    api::{Client, User},
+    error::Result,
+    ids::UserId,
+    websocket::Post,
+};
+
+/// A single mention in the text of a post
+///
+/// The variants are ordered from the most to the least specific mention.
+#[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub enum MentionKind {
+    /// `@username`, with the username in lowercase
+    User(String),
+    /// Mention of a user group, with the group name in lowercase
+    Group(String),
+    /// `@channel`, notifies all members of the channel
+    Channel,
+    /// `@all`, same as `@channel`
+    All,
+    /// `@here`, notifies only the members of the channel who are online
+    Here,
+}
+
+impl MentionKind {
+    /// Whether the mention addresses everybody in the channel instead of specific people
+    pub fn is_channel_wide(&self) -> bool {
+        match self {
+            MentionKind::Channel | MentionKind::All | MentionKind::Here => true,
+            MentionKind::User(_) | MentionKind::Group(_) => false,
+        }
+    }
+}
+
+/// All mentions in `text` in order of appearance
+///
+/// Names listed in `groups` are reported as [`MentionKind::Group`], all other names as [`MentionKind::User`].
+/// Mentions inside code spans and code blocks are ignored, like the server does.
+pub fn parse<S>(text: &str, groups: &[S]) -> Vec<MentionKind>
+where
+    S: AsRef<str>,
+{
+    let mut mentions = Vec::new();
+    let mut in_code = false;
+    let mut prev = ' ';
+    let mut chars = text.char_indices().peekable();
+    while let Some((idx, c)) = chars.next() {
+        if c == '`' {
+            // A fence of several backticks toggles the code state only once
+            while chars.peek().map(|&(_, c)| c) == Some('`') {
+                chars.next();
+            }
+            in_code = !in_code;
+        } else if c == '@' && !in_code && !is_name_char(prev) {
+            let rest = &text[idx + 1..];
+            let len = rest.find(|c| !is_name_char(c)).unwrap_or(rest.len());
+            // Trailing punctuation ends a sentence, not the name
+            let name = rest[..len].trim_end_matches(['.', '-', '_']).to_lowercase();
+            if !name.is_empty() {
+                mentions.push(classify(name, groups));
+            }
+        }
+        prev = c;
+    }
+    mentions
+}
+
+fn is_name_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '.' || c == '-' || c == '_'
+}
+
+fn classify<S>(name: String, groups: &[S]) -> MentionKind
+where
+    S: AsRef<str>,
+{
+    match &*name {
+        "here" => MentionKind::Here,
+        "channel" => MentionKind::Channel,
+        "all" => MentionKind::All,
+        _ if groups
+            .iter()
+            .any(|g| g.as_ref().eq_ignore_ascii_case(&name)) =>
+        {
+            MentionKind::Group(name)
+        }
+        _ => MentionKind::User(name),
+    }
+}
+
+/// Identity of a user, to decide how posts mention them
+#[derive(Debug, Clone, Eq, PartialEq, Default)]
+pub struct MentionContext {
+    pub user_id: UserId,
+    pub username: String,
+    /// Names of the groups the user is a member of
+    pub groups: Vec<String>,
+}
+
+impl MentionContext {
+    /// The most specific way in which `post` mentions the user, if at all
+    ///
+    /// `mentions` are the notified user ids of the `posted` event.
+    /// If they contain the user, but the text does not reveal why, e.g., for direct messages or mention keywords, the mention counts as personal.
+    pub fn classify(&self, post: &Post, mentions: Option<&[UserId]>) -> Option<MentionKind> {
+        if let Some(mentions) = mentions {
+            if !mentions.contains(&self.user_id) {
+                return None;
+            }
+        }
+        let relevant = parse(&post.message, &self.groups)
+            .into_iter()
+            .filter(|mention| match mention {
+                MentionKind::User(name) => name.eq_ignore_ascii_case(&self.username),
+                _ => true,
+            })
+            .min();
+        match (relevant, mentions) {
+            (Some(mention), _) => Some(mention),
+            (None, Some(_)) => Some(MentionKind::User(self.username.to_lowercase())),
+            (None, None) => None,
+        }
+    }
+}
+
+impl Client {
+    /// The users mentioned by `@username` in `text`, in order of their first mention
+    ///
+    /// All usernames are looked up with a single request.
+    /// Names which are no users, e.g., typos or the `groups` of [`parse`], are skipped.
+    pub fn resolve_mentions<S>(&self, text: &str, groups: &[S]) -> Result<Vec<User>>
+    where
+        S: AsRef<str>,
+    {
+        let mut usernames: Vec<String> = Vec::new();
+        for mention in parse(text, groups) {
+            if let MentionKind::User(name) = mention {
+                if !usernames.contains(&name) {
+                    usernames.push(name);
+                }
+            }
+        }
+        if usernames.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let mut users = self.get_users_by_usernames(&usernames)?;
+        users.sort_by_key(|user| {
+            let username = user.username.to_lowercase();
+            usernames.iter().position(|name| *name == username)
+        });
+        Ok(users)
+    }
+}
@@ -0,0 +1,225 @@
+//! Spot spam in the event stream
+//!
+//! A [`Moderator`] checks every new post against a few simple heuristics and reports suspicious ones as [`ModerationSignal`]s.
+//! It does not act on its own, the bot decides whether to delete the post, warn the author, or report it.
+//! The time windows use the creation time of the posts, so replayed events are judged like live ones.
+
+use crate::{
+    ids::{ChannelId, PostId, UserId},
+    mention::{parse, MentionKind},
+    websocket::{Events, MessagePush, Post, PostType},
+};
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap, VecDeque};
+
+/// Thresholds of the heuristics
+///
+/// Setting a threshold to 0 disables the heuristic.
+#[derive(Debug, Deserialize, Serialize, Clone, Eq, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct ModerationConfig {
+    /// Maximal number of posts per user within `rate_window_secs`
+    pub max_posts: usize,
+    pub rate_window_secs: u64,
+    /// Number of identical messages of a user within `duplicate_window_secs` which count as spam
+    pub max_duplicates: usize,
+    pub duplicate_window_secs: u64,
+    /// Number of distinct users and groups mentioned in a single post which count as mass mention
+    pub max_mentions: usize,
+    /// Treat `@channel`, `@all`, and `@here` as mass mention
+    pub channel_wide_mentions: bool,
+    /// Report posts which consist of nothing but links
+    pub link_only: bool,
+}
+
+impl Default for ModerationConfig {
+    fn default() -> Self {
+        ModerationConfig {
+            max_posts: 10,
+            rate_window_secs: 60,
+            max_duplicates: 3,
+            duplicate_window_secs: 600,
+            max_mentions: 10,
+            channel_wide_mentions: false,
+            link_only: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub enum SignalKind {
+    /// The author posted `count` times within the rate window
+    RateExceeded { count: usize },
+    /// The author posted the same message `count` times within the duplicate window
+    Duplicate { count: usize },
+    /// The post contains only links
+    LinkOnly,
+    /// The post mentions `count` users or groups, or the whole channel
+    MassMention { count: usize, channel_wide: bool },
+}
+
+/// A suspicious post
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct ModerationSignal {
+    pub kind: SignalKind,
+    pub post_id: PostId,
+    pub user_id: UserId,
+    pub channel_id: ChannelId,
+}
+
+/// Applies the heuristics of a [`ModerationConfig`] to posts
+#[derive(Debug, Clone)]
+pub struct Moderator {
+    config: ModerationConfig,
+    /// Creation time and normalized message of the recent posts, by user
+    recent: HashMap<UserId, VecDeque<(DateTime<Utc>, String)>>,
+    /// Creation time of the post which triggered the last eviction of idle users
+    last_eviction: Option<DateTime<Utc>>,
+}
+
+impl Moderator {
+    pub fn new(config: ModerationConfig) -> Moderator {
+        Moderator {
+            config,
+            recent: HashMap::new(),
+            last_eviction: None,
+        }
+    }
+
+    pub fn config(&self) -> &ModerationConfig {
+        &self.config
+    }
+
+    /// Check the post of a [`Events::Posted`] event, all other events are ignored
+    pub fn observe(&mut self, message: &MessagePush) -> Vec<ModerationSignal> {
+        match &message.event {
+            Events::Posted { post, .. } => self.check(post),
+            _ => Vec::new(),
+        }
+    }
+
+    /// Check a new post
+    ///
+    /// Each post must only be checked once, otherwise it counts multiple times for the rate and duplicate heuristics.
+    /// System posts are never reported.
+    pub fn check(&mut self, post: &Post) -> Vec<ModerationSignal> {
+        if post.type_ != PostType::UserMessage {
+            return Vec::new();
+        }
+        let mut kinds = Vec::new();
+
+        let normalized = normalize(&post.message);
+        let window = Duration::seconds(
+            self.config
+                .rate_window_secs
+                .max(self.config.duplicate_window_secs) as i64,
+        );
+        self.evict_idle_users(post.create_at, window);
+        let recent = self.recent.entry(post.user_id.clone()).or_default();
+        while recent
+            .front()
+            .is_some_and(|(time, _)| *time + window < post.create_at)
+        {
+            recent.pop_front();
+        }
+        recent.push_back((post.create_at, normalized.clone()));
+
+        if self.config.max_posts > 0 {
+            let since = post.create_at - Duration::seconds(self.config.rate_window_secs as i64);
+            let count = recent.iter().filter(|(time, _)| *time >= since).count();
+            if count > self.config.max_posts {
+                kinds.push(SignalKind::RateExceeded { count });
+            }
+        }
+
+        if self.config.max_duplicates > 0 && !normalized.is_empty() {
+            let since =
+                post.create_at - Duration::seconds(self.config.duplicate_window_secs as i64);
+            let count = recent
+                .iter()
+                .filter(|(time, message)| *time >= since && *message == normalized)
+                .count();
+            if count >= self.config.max_duplicates {
+                kinds.push(SignalKind::Duplicate { count });
+            }
+        }
+
+        if self.config.link_only && is_link_only(&post.message) {
+            kinds.push(SignalKind::LinkOnly);
+        }
+
+        let mentions: BTreeSet<MentionKind> =
+            parse::<&str>(&post.message, &[]).into_iter().collect();
+        let channel_wide =
+            self.config.channel_wide_mentions && mentions.iter().any(MentionKind::is_channel_wide);
+        let count = mentions
+            .iter()
+            .filter(|mention| !mention.is_channel_wide())
+            .count();
+        if channel_wide || (self.config.max_mentions > 0 && count >= self.config.max_mentions) {
+            kinds.push(SignalKind::MassMention {
+                count,
+                channel_wide,
+            });
+        }
+
+        kinds
+            .into_iter()
+            .map(|kind| ModerationSignal {
+                kind,
+                post_id: post.id.clone(),
+                user_id: post.user_id.clone(),
+                channel_id: post.channel_id.clone(),
+            })
+            .collect()
+    }
+
+    /// Forget the users without posts within `window` before `now`, at most once per `window`
+    fn evict_idle_users(&mut self, now: DateTime<Utc>, window: Duration) {
+        if self
+            .last_eviction
+            .is_some_and(|last| now >= last && now < last + window)
+        {
+            return;
+        }
+        self.last_eviction = Some(now);
+        self.recent
+            .retain(|_, recent| recent.back().is_some_and(|(time, _)| *time + window >= now));
+    }
+
+    /// Number of users whose recent posts are remembered
+    pub fn tracked_users(&self) -> usize {
+        self.recent.len()
+    }
+
+    /// Forget everything about a user, e.g., after they were warned
+    pub fn forget_user(&mut self, user_id: &str) {
+        self.recent.remove(user_id);
+    }
+}
+
+/// Messages which differ only in case or whitespace count as duplicates
+fn normalize(message: &str) -> String {
+    message
+        .split_whitespace()
+        .map(str::to_lowercase)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// The message consists of URLs and Markdown links, nothing else
+fn is_link_only(message: &str) -> bool {
+    let mut words = message.split_whitespace().peekable();
+    words.peek().is_some() && words.all(is_link)
+}
+
+fn is_link(word: &str) -> bool {
+    let word = word.trim_start_matches('<').trim_end_matches('>');
+    let url = match (word.find("]("), word.ends_with(')')) {
+        // Markdown link [text](url), only without spaces in the text
+        (Some(idx), true) if word.starts_with('[') => &word[idx + 2..word.len() - 1],
+        _ => word,
+    };
+    url.starts_with("http://") || url.starts_with("https://") || url.starts_with("www.")
+}
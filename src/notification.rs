@@ -0,0 +1,109 @@
+//! Decide how loudly to notify about a post
+//!
+//! [`PriorityRules`] map the kind of mention, the channel type, and the priority chosen by the author onto a [`Priority`].
+//! Notification backends translate the priority into their own levels, e.g., with [`Priority::ntfy`].
+
+use crate::{
+    api::ChannelType,
+    mention::MentionKind,
+    websocket::{NotifyLevel, NotifyProps, Post, PriorityLevel},
+};
+use serde::{Deserialize, Serialize};
+
+/// Priority of a notification, from quiet to loud
+#[derive(Debug, Deserialize, Serialize, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum Priority {
+    /// Do not notify at all
+    Silent,
+    /// Deliver without sound or vibration
+    Low,
+    Normal,
+    High,
+    /// Interrupt the recipient, even if notifications are muted
+    Urgent,
+}
+
+impl Priority {
+    /// Priority level of [ntfy](https://ntfy.sh), from 1 (min) to 5 (max)
+    pub fn ntfy(self) -> u8 {
+        match self {
+            Priority::Silent => 1,
+            Priority::Low => 2,
+            Priority::Normal => 3,
+            Priority::High => 4,
+            Priority::Urgent => 5,
+        }
+    }
+
+    /// Value of the `disable_notification` parameter of Telegram's `sendMessage`
+    pub fn telegram_disable_notification(self) -> bool {
+        self <= Priority::Low
+    }
+}
+
+/// Configurable mapping from posts to notification priorities
+///
+/// The priority depends on how the post mentions the recipient.
+/// Direct and group messages use `direct_message` instead.
+/// Posts marked as important or urgent by their author are raised to at least `important` or `urgent`.
+#[derive(Debug, Deserialize, Serialize, Copy, Clone, Eq, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct PriorityRules {
+    pub direct_message: Priority,
+    pub personal_mention: Priority,
+    pub group_mention: Priority,
+    /// `@channel` and `@all`
+    pub channel_mention: Priority,
+    pub here_mention: Priority,
+    pub important: Priority,
+    pub urgent: Priority,
+}
+
+impl Default for PriorityRules {
+    fn default() -> Self {
+        PriorityRules {
+            direct_message: Priority::High,
+            personal_mention: Priority::High,
+            group_mention: Priority::Normal,
+            channel_mention: Priority::Low,
+            here_mention: Priority::Low,
+            important: Priority::High,
+            urgent: Priority::Urgent,
+        }
+    }
+}
+
+impl PriorityRules {
+    pub fn priority(
+        &self,
+        mention: &MentionKind,
+        channel_type: ChannelType,
+        post: &Post,
+    ) -> Priority {
+        let base = match (channel_type, mention) {
+            (ChannelType::DirectMessage, _) | (ChannelType::Group, _) => self.direct_message,
+            (_, MentionKind::User(_)) => self.personal_mention,
+            (_, MentionKind::Group(_)) => self.group_mention,
+            (_, MentionKind::Channel) | (_, MentionKind::All) => self.channel_mention,
+            (_, MentionKind::Here) => self.here_mention,
+        };
+        match post.priority() {
+            PriorityLevel::Standard => base,
+            PriorityLevel::Important => base.max(self.important),
+            PriorityLevel::Urgent => base.max(self.urgent),
+        }
+    }
+}
+
+/// Whether the channel preferences of the user allow a push notification for `mention`
+///
+/// Muted channels and channels with push notifications turned off never notify.
+/// Channel-wide mentions are dropped if the user ignores them in the channel.
+/// `mention` is `None` if the kind of mention is unknown.
+pub fn allows_push(props: &NotifyProps, mention: Option<&MentionKind>) -> bool {
+    if props.is_muted() || props.push_level() == NotifyLevel::None {
+        return false;
+    }
+    !(mention.is_some_and(MentionKind::is_channel_wide) && props.ignores_channel_mentions())
+}
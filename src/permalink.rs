@@ -0,0 +1,117 @@
+//! Links to posts
+//!
+//! A permalink has the form `https://chat.example.com/<team name>/pl/<post id>`.
+//! The team only determines in which team the web app opens; post ids are unique on the whole server.
+//!
+//! ```
+//! use mattermost_structs::permalink;
+//!
+//! let (team, post_id) =
+//!     permalink::parse("https://chat.example.com/dev/pl/9gdedpp3k3rbxeo5hhqg9nhmwe").unwrap();
+//! assert_eq!(team, "dev");
+//! assert_eq!(post_id, "9gdedpp3k3rbxeo5hhqg9nhmwe");
+//!
+//! let found = permalink::find_all("see https://chat.example.com/dev/pl/9gdedpp3k3rbxeo5hhqg9nhmwe.");
+//! assert_eq!(found.len(), 1);
+//! ```
+
+use crate::{
+    api::Client,
+    error::{Result, ResultExt},
+    ids::PostId,
+    websocket::Post,
+};
+use url::Url;
+
+/// Length of the ids generated by the server
+const ID_LENGTH: usize = 26;
+
+fn is_id(id: &str) -> bool {
+    id.len() == ID_LENGTH
+        && id
+            .bytes()
+            .all(|b| b.is_ascii_lowercase() || b.is_ascii_digit())
+}
+
+/// Split a permalink into the team name and the post id
+///
+/// The server may be installed in a subpath, only the last three segments of the path are considered.
+pub fn parse(url: &str) -> Result<(String, String)> {
+    let url = Url::parse(url).chain_err(|| format!("Invalid permalink {}", url))?;
+    let segments: Vec<&str> = url
+        .path_segments()
+        .map(|segments| segments.filter(|segment| !segment.is_empty()).collect())
+        .unwrap_or_default();
+    match segments.as_slice() {
+        [.., team, "pl", post_id] if !team.is_empty() && is_id(post_id) => {
+            Ok((team.to_string(), post_id.to_string()))
+        }
+        _ => Err(format!("{} is not a permalink", url).into()),
+    }
+}
+
+/// All permalinks in a message, as team name and post id
+pub fn find_all(text: &str) -> Vec<(String, String)> {
+    text.split_whitespace()
+        .filter(|word| word.contains("/pl/"))
+        .filter_map(|word| {
+            // Strip Markdown and punctuation around the link
+            let start = word.find("http")?;
+            let link =
+                word[start..].trim_end_matches(|c: char| !c.is_ascii_alphanumeric() && c != '/');
+            parse(link).ok()
+        })
+        .collect()
+}
+
+/// The permalink of the post with id `post_id`
+///
+/// A subpath of the server is kept, whether `base_url` ends with a slash or not.
+///
+/// ```
+/// use mattermost_structs::permalink;
+/// use url::Url;
+///
+/// let base_url = Url::parse("https://example.com/chat").unwrap();
+/// let link = permalink::build(&base_url, "dev", "9gdedpp3k3rbxeo5hhqg9nhmwe").unwrap();
+/// assert_eq!(link.as_str(), "https://example.com/chat/dev/pl/9gdedpp3k3rbxeo5hhqg9nhmwe");
+/// ```
+pub fn build(base_url: &Url, team_name: &str, post_id: &str) -> Result<Url> {
+    Ok(base_dir(base_url).join(&format!("{}/pl/{}", team_name, post_id))?)
+}
+
+/// `base_url` with a trailing slash, such that relative links are resolved below it
+fn base_dir(base_url: &Url) -> Url {
+    let mut dir = base_url.clone();
+    if !dir.path().ends_with('/') {
+        let path = format!("{}/", dir.path());
+        dir.set_path(&path);
+    }
+    dir
+}
+
+/// Whether `url` points below the server at `base_url`
+fn is_on_server(url: &Url, base_url: &Url) -> bool {
+    url.scheme() == base_url.scheme()
+        && url.host_str() == base_url.host_str()
+        && url.port_or_known_default() == base_url.port_or_known_default()
+        && url.path().starts_with(base_dir(base_url).path())
+}
+
+/// The permalink of `post`, opened in the team `team_name`
+pub fn of_post(base_url: &Url, team_name: &str, post: &Post) -> Result<Url> {
+    build(base_url, team_name, &post.id)
+}
+
+impl Client {
+    /// Fetch the post a permalink points to
+    ///
+    /// Links to other servers are rejected, including other installations on the same host.
+    pub fn resolve_permalink(&self, url: &str) -> Result<Post> {
+        let (_, post_id) = parse(url)?;
+        if !is_on_server(&Url::parse(url)?, self.base_url()) {
+            return Err(format!("{} points to a different server", url).into());
+        }
+        self.get_post(PostId::from(post_id))
+    }
+}
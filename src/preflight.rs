@@ -0,0 +1,402 @@
+//! Check whether a server is reachable and the credentials work, before connecting for real
+//!
+//! [`preflight`] runs one check per layer, from name resolution up to the websocket upgrade.
+//! If a check fails, all checks depending on it are skipped, such that the first failure points at the cause.
+//! The [`PreflightReport`] can be printed with its `Display` implementation or serialized, e.g., as JSON.
+
+use crate::{
+    api::Client,
+    websocket::{websocket_url, AuthMethod},
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::{
+    fmt,
+    net::ToSocketAddrs,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+use url::Url;
+use ws::{util::Token, CloseCode, Handshake, Request, Sender};
+
+/// Connection settings of the server to check
+#[derive(Debug, Clone)]
+pub struct ServerConfig {
+    pub base_url: String,
+    pub token: String,
+    pub websocket_auth: AuthMethod,
+    /// Limit for each network operation
+    pub timeout: Duration,
+    /// Larger differences between the local and the server clock only produce a warning
+    pub max_clock_skew: Duration,
+}
+
+impl ServerConfig {
+    pub fn new<B, T>(base_url: B, token: T) -> ServerConfig
+    where
+        B: Into<String>,
+        T: Into<String>,
+    {
+        ServerConfig {
+            base_url: base_url.into(),
+            token: token.into(),
+            websocket_auth: AuthMethod::default(),
+            timeout: Duration::from_secs(30),
+            max_clock_skew: Duration::from_secs(60),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum Check {
+    /// The host name resolves to at least one address
+    Dns,
+    /// The server answers over HTTPS with a trusted certificate
+    Tls,
+    /// The token is accepted by the REST API
+    RestAuth,
+    /// The websocket endpoint accepts the upgrade
+    WebsocketUpgrade,
+    /// The local clock agrees with the `Date` header of the server
+    ClockSkew,
+}
+
+impl fmt::Display for Check {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Check::Dns => "DNS",
+            Check::Tls => "TLS",
+            Check::RestAuth => "REST authentication",
+            Check::WebsocketUpgrade => "Websocket upgrade",
+            Check::ClockSkew => "Clock skew",
+        })
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum CheckStatus {
+    Passed,
+    /// Works, but might cause problems
+    Warning,
+    Failed,
+    /// Not run, because an earlier check failed or it does not apply
+    Skipped,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Eq, PartialEq)]
+pub struct CheckResult {
+    pub check: Check,
+    pub status: CheckStatus,
+    pub detail: String,
+    pub duration_ms: u64,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Eq, PartialEq)]
+pub struct PreflightReport {
+    pub base_url: String,
+    pub checks: Vec<CheckResult>,
+    /// Server time minus local time, if the server sent a `Date` header
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub clock_skew_ms: Option<i64>,
+}
+
+impl PreflightReport {
+    /// No check failed, warnings are allowed
+    pub fn is_ok(&self) -> bool {
+        self.checks
+            .iter()
+            .all(|result| result.status != CheckStatus::Failed)
+    }
+
+    pub fn status(&self, check: Check) -> Option<CheckStatus> {
+        self.checks
+            .iter()
+            .find(|result| result.check == check)
+            .map(|result| result.status)
+    }
+
+    pub fn passed(&self, check: Check) -> bool {
+        matches!(
+            self.status(check),
+            Some(CheckStatus::Passed) | Some(CheckStatus::Warning)
+        )
+    }
+
+    pub fn failures(&self) -> impl Iterator<Item = &CheckResult> {
+        self.checks
+            .iter()
+            .filter(|result| result.status == CheckStatus::Failed)
+    }
+
+    fn record(&mut self, check: Check, start: Instant, outcome: (CheckStatus, String)) -> bool {
+        let (status, detail) = outcome;
+        self.checks.push(CheckResult {
+            check,
+            status,
+            detail,
+            duration_ms: start.elapsed().as_millis() as u64,
+        });
+        status != CheckStatus::Failed
+    }
+
+    fn skip(&mut self, check: Check, detail: &str) {
+        self.checks.push(CheckResult {
+            check,
+            status: CheckStatus::Skipped,
+            detail: detail.to_string(),
+            duration_ms: 0,
+        });
+    }
+}
+
+impl fmt::Display for PreflightReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Preflight for {}", self.base_url)?;
+        for result in &self.checks {
+            let status = match result.status {
+                CheckStatus::Passed => "ok",
+                CheckStatus::Warning => "warning",
+                CheckStatus::Failed => "FAILED",
+                CheckStatus::Skipped => "skipped",
+            };
+            write!(
+                f,
+                "  {:<20} {:<8} {}",
+                result.check.to_string(),
+                status,
+                result.detail
+            )?;
+            if result.status == CheckStatus::Skipped {
+                writeln!(f)?;
+            } else {
+                writeln!(f, " ({}ms)", result.duration_ms)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Run all checks against `server`
+pub fn preflight(server: &ServerConfig) -> PreflightReport {
+    let mut report = PreflightReport {
+        base_url: server.base_url.clone(),
+        checks: Vec::new(),
+        clock_skew_ms: None,
+    };
+    let all = [
+        Check::Dns,
+        Check::Tls,
+        Check::RestAuth,
+        Check::WebsocketUpgrade,
+        Check::ClockSkew,
+    ];
+
+    let url = match Url::parse(&server.base_url) {
+        Ok(url) => url,
+        Err(err) => {
+            report.record(
+                Check::Dns,
+                Instant::now(),
+                (CheckStatus::Failed, format!("Invalid URL: {}", err)),
+            );
+            for &check in &all[1..] {
+                report.skip(check, "invalid URL");
+            }
+            return report;
+        }
+    };
+
+    let start = Instant::now();
+    if !report.record(Check::Dns, start, check_dns(&url)) {
+        for &check in &all[1..] {
+            report.skip(check, "DNS failed");
+        }
+        return report;
+    }
+
+    let start = Instant::now();
+    let date = match ping(&url, server.timeout) {
+        Ok(date) => {
+            let outcome = if url.scheme() == "https" {
+                (CheckStatus::Passed, "Certificate accepted".to_string())
+            } else {
+                (
+                    CheckStatus::Skipped,
+                    "The server does not use TLS".to_string(),
+                )
+            };
+            report.record(Check::Tls, start, outcome);
+            date
+        }
+        Err(err) => {
+            report.record(Check::Tls, start, (CheckStatus::Failed, err));
+            for &check in &all[2..] {
+                report.skip(check, "the server is unreachable");
+            }
+            return report;
+        }
+    };
+
+    let start = Instant::now();
+    let client = Client::builder(&server.base_url, server.token.clone())
+        .timeout(server.timeout)
+        .build();
+    let outcome = match client.and_then(|client| client.get_me()) {
+        Ok(user) => (
+            CheckStatus::Passed,
+            format!("Logged in as {}", user.username),
+        ),
+        Err(err) => (CheckStatus::Failed, err.to_string()),
+    };
+    if report.record(Check::RestAuth, start, outcome) {
+        let start = Instant::now();
+        let outcome = check_websocket(&url, server);
+        report.record(Check::WebsocketUpgrade, start, outcome);
+    } else {
+        report.skip(Check::WebsocketUpgrade, "the token is not accepted");
+    }
+
+    match date {
+        Some((server_time, local_time)) => {
+            let skew = server_time.signed_duration_since(local_time);
+            report.clock_skew_ms = Some(skew.num_milliseconds());
+            let status = if skew.num_milliseconds().unsigned_abs()
+                > server.max_clock_skew.as_millis() as u64
+            {
+                CheckStatus::Warning
+            } else {
+                CheckStatus::Passed
+            };
+            report.checks.push(CheckResult {
+                check: Check::ClockSkew,
+                status,
+                detail: format!("The server clock is {}ms ahead", skew.num_milliseconds()),
+                duration_ms: 0,
+            });
+        }
+        None => report.skip(Check::ClockSkew, "the server sent no Date header"),
+    }
+
+    report
+}
+
+fn check_dns(url: &Url) -> (CheckStatus, String) {
+    let host = match url.host_str() {
+        Some(host) => host,
+        None => return (CheckStatus::Failed, "The URL has no host".to_string()),
+    };
+    let port = url.port_or_known_default().unwrap_or(443);
+    match (host, port).to_socket_addrs() {
+        Ok(addrs) => {
+            let addrs: Vec<String> = addrs.map(|addr| addr.ip().to_string()).collect();
+            if addrs.is_empty() {
+                (CheckStatus::Failed, format!("{} has no addresses", host))
+            } else {
+                (CheckStatus::Passed, addrs.join(", "))
+            }
+        }
+        Err(err) => (CheckStatus::Failed, err.to_string()),
+    }
+}
+
+/// Server time and local time of the same moment
+type ClockPair = (DateTime<Utc>, DateTime<Utc>);
+
+/// Call the unauthenticated ping endpoint and return the server time with the local time at the middle of the request
+fn ping(url: &Url, timeout: Duration) -> std::result::Result<Option<ClockPair>, String> {
+    let ping = url
+        .join("/api/v4/system/ping")
+        .map_err(|err| err.to_string())?;
+    let client = reqwest::Client::builder()
+        .timeout(timeout)
+        .build()
+        .map_err(|err| err.to_string())?;
+    let before = Utc::now();
+    let res = client.get(ping).send().map_err(|err| err.to_string())?;
+    let after = Utc::now();
+    let local_time = before + (after - before) / 2;
+    Ok(res
+        .headers()
+        .get("Date")
+        .and_then(|date| date.to_str().ok())
+        .and_then(|date| DateTime::parse_from_rfc2822(date).ok())
+        .map(|date| (date.with_timezone(&Utc), local_time)))
+}
+
+const TIMEOUT: Token = Token(1);
+
+/// Opens the websocket connection and closes it again right away
+struct UpgradeCheck {
+    ws: Sender,
+    auth: AuthMethod,
+    token: String,
+    result: Arc<Mutex<Option<std::result::Result<(), String>>>>,
+}
+
+impl ws::Handler for UpgradeCheck {
+    fn build_request(&mut self, url: &url::Url) -> ws::Result<Request> {
+        let mut request = Request::from_url(url)?;
+        if let Some((name, value)) = self.auth.header(&self.token) {
+            request
+                .headers_mut()
+                .push((name.to_string(), value.into_bytes()));
+        }
+        Ok(request)
+    }
+
+    fn on_open(&mut self, _: Handshake) -> ws::Result<()> {
+        *self.result.lock().unwrap() = Some(Ok(()));
+        self.ws.close(CloseCode::Normal)
+    }
+
+    fn on_timeout(&mut self, _: Token) -> ws::Result<()> {
+        let mut result = self.result.lock().unwrap();
+        if result.is_none() {
+            *result = Some(Err("The upgrade timed out".to_string()));
+        }
+        self.ws.shutdown()
+    }
+
+    fn on_error(&mut self, err: ws::Error) {
+        let mut result = self.result.lock().unwrap();
+        if result.is_none() {
+            *result = Some(Err(err.to_string()));
+        }
+    }
+}
+
+fn check_websocket(url: &Url, server: &ServerConfig) -> (CheckStatus, String) {
+    let mut ws_url = match websocket_url(url) {
+        Ok(ws_url) => ws_url,
+        Err(err) => return (CheckStatus::Failed, err.to_string()),
+    };
+    server
+        .websocket_auth
+        .prepare_url(&mut ws_url, &server.token);
+
+    let result = Arc::new(Mutex::new(None));
+    let timeout = server.timeout.as_millis() as u64;
+    let connected = ws::connect(ws_url.as_str(), |out| {
+        if out.timeout(timeout, TIMEOUT).is_err() {
+            log::error!("Websocket couldn't schedule the timeout.")
+        }
+        UpgradeCheck {
+            ws: out,
+            auth: server.websocket_auth,
+            token: server.token.clone(),
+            result: result.clone(),
+        }
+    });
+    let result = result.lock().unwrap().take();
+    match (connected, result) {
+        (Err(err), _) => (CheckStatus::Failed, err.to_string()),
+        (Ok(()), Some(Ok(()))) => (CheckStatus::Passed, "Connected".to_string()),
+        (Ok(()), Some(Err(err))) => (CheckStatus::Failed, err),
+        (Ok(()), None) => (
+            CheckStatus::Failed,
+            "The connection closed before the upgrade".to_string(),
+        ),
+    }
+}
@@ -0,0 +1,168 @@
+//! Keep track of who is online
+//!
+//! A [`PresenceTracker`] follows the `status_change` events of the websocket.
+//! Events only arrive for users whose status changes while connected, so the tracker periodically asks the server for the statuses of all known users.
+//! This allows automation like stand-up reminders to skip users who are not around.
+//!
+//! ```
+//! use chrono::{Duration, Utc};
+//! use mattermost_structs::{
+//!     presence::PresenceTracker,
+//!     websocket::{Status, UserStatus},
+//! };
+//!
+//! let mut tracker = PresenceTracker::new(std::time::Duration::from_secs(300));
+//! tracker.update(UserStatus {
+//!     user_id: "alice".to_string(),
+//!     status: Status::Away,
+//!     manual: false,
+//!     last_activity_at: Some(Utc::now() - Duration::hours(2)),
+//!     active_channel: None,
+//!     dnd_end_time: None,
+//! });
+//! assert!(!tracker.is_online("alice"));
+//! assert_eq!(tracker.idle_users(Utc::now() - Duration::hours(1)), vec!["alice"]);
+//! ```
+
+use crate::{
+    api::Client,
+    error::Result,
+    ids::UserId,
+    websocket::{Events, MessagePush, Status, UserStatus},
+};
+use chrono::{DateTime, Utc};
+use log::debug;
+use std::{
+    collections::BTreeMap,
+    time::{Duration, Instant},
+};
+
+/// Number of users whose statuses are requested at once
+const RECONCILE_BATCH_SIZE: usize = 200;
+
+#[derive(Debug, Clone)]
+struct TrackedStatus {
+    status: UserStatus,
+    /// When the tracker learned about the current [`Status`]
+    since: DateTime<Utc>,
+}
+
+impl TrackedStatus {
+    /// The last activity reported by the server, or when the user changed their status
+    fn last_active(&self) -> DateTime<Utc> {
+        self.status.last_activity_at.unwrap_or(self.since)
+    }
+}
+
+/// The latest known status of all users seen so far
+#[derive(Debug, Clone)]
+pub struct PresenceTracker {
+    users: BTreeMap<String, Option<TrackedStatus>>,
+    reconcile_interval: Duration,
+    last_reconciled: Option<Instant>,
+}
+
+impl PresenceTracker {
+    /// Ask the server for all statuses every `reconcile_interval`, see [`PresenceTracker::reconcile_if_due`]
+    pub fn new(reconcile_interval: Duration) -> PresenceTracker {
+        PresenceTracker {
+            users: BTreeMap::new(),
+            reconcile_interval,
+            last_reconciled: None,
+        }
+    }
+
+    /// Include `user_id` in the next reconciliation, even without any event about the user
+    pub fn track<S>(&mut self, user_id: S)
+    where
+        S: Into<String>,
+    {
+        self.users.entry(user_id.into()).or_insert(None);
+    }
+
+    /// Call for every event received on the websocket
+    pub fn handle(&mut self, push: &MessagePush) {
+        if let Events::StatusChange(status) = &push.event {
+            self.update(status.clone());
+        }
+    }
+
+    pub fn update(&mut self, status: UserStatus) {
+        let now = Utc::now();
+        let entry = self.users.entry(status.user_id.clone()).or_insert(None);
+        let since = match entry {
+            Some(tracked) if tracked.status.status == status.status => tracked.since,
+            _ => now,
+        };
+        *entry = Some(TrackedStatus { status, since });
+    }
+
+    /// Fetch the statuses of all tracked users from the server
+    ///
+    /// Returns the number of statuses received.
+    pub fn reconcile(&mut self, client: &Client) -> Result<usize> {
+        let user_ids: Vec<UserId> = self.users.keys().map(UserId::from).collect();
+        let mut received = 0;
+        for batch in user_ids.chunks(RECONCILE_BATCH_SIZE) {
+            for status in client.get_statuses_by_ids(batch)? {
+                self.update(status);
+                received += 1;
+            }
+        }
+        self.last_reconciled = Some(Instant::now());
+        debug!("Reconciled the statuses of {} users", received);
+        Ok(received)
+    }
+
+    /// Like [`PresenceTracker::reconcile`], but only if the last reconciliation is older than the interval
+    ///
+    /// Returns whether the statuses were reconciled.
+    pub fn reconcile_if_due(&mut self, client: &Client) -> Result<bool> {
+        let due = self
+            .last_reconciled
+            .is_none_or(|last| last.elapsed() >= self.reconcile_interval);
+        if due {
+            self.reconcile(client)?;
+        }
+        Ok(due)
+    }
+
+    /// The latest status of the user, if known
+    pub fn status(&self, user_id: &str) -> Option<&UserStatus> {
+        self.users
+            .get(user_id)
+            .and_then(Option::as_ref)
+            .map(|tracked| &tracked.status)
+    }
+
+    /// Users with an unknown status count as offline
+    pub fn is_online(&self, user_id: &str) -> bool {
+        self.status(user_id).is_some_and(UserStatus::is_online)
+    }
+
+    pub fn online_users(&self) -> Vec<&str> {
+        self.users
+            .keys()
+            .filter(|user_id| self.is_online(user_id))
+            .map(String::as_str)
+            .collect()
+    }
+
+    /// Users who are not online and were not active after `since`
+    ///
+    /// The activity is the `last_activity_at` sent by the server, or, for older servers, when the tracker learned about the status.
+    /// Users with an unknown status are not included.
+    pub fn idle_users(&self, since: DateTime<Utc>) -> Vec<&str> {
+        self.users
+            .iter()
+            .filter_map(|(user_id, tracked)| {
+                let tracked = tracked.as_ref()?;
+                if tracked.status.status != Status::Online && tracked.last_active() < since {
+                    Some(user_id.as_str())
+                } else {
+                    None
+                }
+            })
+            .collect()
+    }
+}
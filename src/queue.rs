@@ -0,0 +1,255 @@
+//! Bounded queue between the websocket and the processing of the events
+//!
+//! Processing events directly in the websocket handler blocks the websocket thread.
+//! If the processing is slow, no pings are answered and the server closes the connection.
+//! With a queue, the websocket handler only enqueues the events and a separate thread processes them.
+//! The [`OverflowPolicy`] decides what happens if the consumer cannot keep up.
+
+use crate::{error::Result, websocket::MessagePush};
+use std::{
+    collections::{BTreeMap, VecDeque},
+    fmt,
+    sync::{Arc, Condvar, Mutex, MutexGuard},
+    time::Duration,
+};
+
+/// Behavior of [`EventSender::send`] if the queue is full
+#[derive(Clone)]
+pub enum OverflowPolicy {
+    /// Wait until the consumer made room in the queue
+    Block,
+    /// Drop the oldest queued event
+    DropOldest,
+    /// Drop events for which the function returns true, e.g., typing notifications
+    ///
+    /// The oldest matching queued event is dropped first.
+    /// If no queued event matches, the new event is dropped if it matches, otherwise the sender waits like with [`OverflowPolicy::Block`].
+    DropFiltered(Arc<dyn Fn(&MessagePush) -> bool + Send + Sync>),
+}
+
+impl fmt::Debug for OverflowPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OverflowPolicy::Block => write!(f, "Block"),
+            OverflowPolicy::DropOldest => write!(f, "DropOldest"),
+            OverflowPolicy::DropFiltered(_) => write!(f, "DropFiltered(..)"),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct State {
+    queue: VecDeque<MessagePush>,
+    /// Event name -> number of dropped events
+    dropped: BTreeMap<String, u64>,
+    senders: usize,
+    receiver_alive: bool,
+}
+
+#[derive(Debug)]
+struct Shared {
+    state: Mutex<State>,
+    not_empty: Condvar,
+    not_full: Condvar,
+    capacity: usize,
+    policy: OverflowPolicy,
+}
+
+impl Shared {
+    fn lock(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap()
+    }
+
+    fn dropped(&self) -> u64 {
+        self.lock().dropped.values().sum()
+    }
+
+    fn dropped_by_event(&self) -> BTreeMap<String, u64> {
+        self.lock().dropped.clone()
+    }
+}
+
+fn count_drop(state: &mut State, message: &MessagePush) {
+    *state
+        .dropped
+        .entry(message.event.name().to_string())
+        .or_default() += 1;
+}
+
+/// Create a queue holding at most `capacity` events
+///
+/// # Panics
+///
+/// If `capacity` is 0.
+pub fn bounded(capacity: usize, policy: OverflowPolicy) -> (EventSender, EventReceiver) {
+    assert!(capacity > 0, "The capacity of the queue must not be 0");
+    let shared = Arc::new(Shared {
+        state: Mutex::new(State {
+            queue: VecDeque::with_capacity(capacity),
+            dropped: BTreeMap::new(),
+            senders: 1,
+            receiver_alive: true,
+        }),
+        not_empty: Condvar::new(),
+        not_full: Condvar::new(),
+        capacity,
+        policy,
+    });
+    (
+        EventSender {
+            shared: shared.clone(),
+        },
+        EventReceiver { shared },
+    )
+}
+
+/// Sending half of the queue, used in the websocket handler
+#[derive(Debug)]
+pub struct EventSender {
+    shared: Arc<Shared>,
+}
+
+impl EventSender {
+    /// Enqueue `message`, applying the overflow policy if the queue is full
+    ///
+    /// Fails if the [`EventReceiver`] is dropped.
+    pub fn send(&self, message: MessagePush) -> Result<()> {
+        let shared = &*self.shared;
+        let mut state = shared.lock();
+        loop {
+            if !state.receiver_alive {
+                return Err("The event receiver is disconnected".into());
+            }
+            if state.queue.len() < shared.capacity {
+                break;
+            }
+            match &shared.policy {
+                OverflowPolicy::Block => {}
+                OverflowPolicy::DropOldest => {
+                    if let Some(oldest) = state.queue.pop_front() {
+                        count_drop(&mut state, &oldest);
+                    }
+                    break;
+                }
+                OverflowPolicy::DropFiltered(filter) => {
+                    if let Some(idx) = state.queue.iter().position(|queued| filter(queued)) {
+                        let dropped = state.queue.remove(idx).expect("Index is in bounds");
+                        count_drop(&mut state, &dropped);
+                        break;
+                    }
+                    if filter(&message) {
+                        count_drop(&mut state, &message);
+                        return Ok(());
+                    }
+                }
+            }
+            state = shared.not_full.wait(state).unwrap();
+        }
+        state.queue.push_back(message);
+        shared.not_empty.notify_one();
+        Ok(())
+    }
+
+    /// Total number of dropped events
+    pub fn dropped(&self) -> u64 {
+        self.shared.dropped()
+    }
+
+    /// Number of dropped events per event name
+    pub fn dropped_by_event(&self) -> BTreeMap<String, u64> {
+        self.shared.dropped_by_event()
+    }
+}
+
+impl Clone for EventSender {
+    fn clone(&self) -> Self {
+        self.shared.lock().senders += 1;
+        EventSender {
+            shared: self.shared.clone(),
+        }
+    }
+}
+
+impl Drop for EventSender {
+    fn drop(&mut self) {
+        self.shared.lock().senders -= 1;
+        self.shared.not_empty.notify_all();
+    }
+}
+
+/// Receiving half of the queue, used by the thread processing the events
+#[derive(Debug)]
+pub struct EventReceiver {
+    shared: Arc<Shared>,
+}
+
+impl EventReceiver {
+    /// Wait for the next event
+    ///
+    /// Returns `None` once all [`EventSender`]s are dropped and the queue is empty.
+    pub fn recv(&self) -> Option<MessagePush> {
+        let shared = &*self.shared;
+        let mut state = shared.lock();
+        loop {
+            if let Some(message) = state.queue.pop_front() {
+                shared.not_full.notify_one();
+                return Some(message);
+            }
+            if state.senders == 0 {
+                return None;
+            }
+            state = shared.not_empty.wait(state).unwrap();
+        }
+    }
+
+    /// Like [`EventReceiver::recv`], but gives up after `timeout`
+    pub fn recv_timeout(&self, timeout: Duration) -> Option<MessagePush> {
+        let shared = &*self.shared;
+        let state = shared.lock();
+        let (mut state, _) = shared
+            .not_empty
+            .wait_timeout_while(state, timeout, |state| {
+                state.queue.is_empty() && state.senders > 0
+            })
+            .unwrap();
+        let message = state.queue.pop_front();
+        if message.is_some() {
+            shared.not_full.notify_one();
+        }
+        message
+    }
+
+    /// Number of queued events
+    pub fn len(&self) -> usize {
+        self.shared.lock().queue.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Total number of dropped events
+    pub fn dropped(&self) -> u64 {
+        self.shared.dropped()
+    }
+
+    /// Number of dropped events per event name
+    pub fn dropped_by_event(&self) -> BTreeMap<String, u64> {
+        self.shared.dropped_by_event()
+    }
+}
+
+impl Iterator for EventReceiver {
+    type Item = MessagePush;
+
+    fn next(&mut self) -> Option<MessagePush> {
+        self.recv()
+    }
+}
+
+impl Drop for EventReceiver {
+    fn drop(&mut self) {
+        self.shared.lock().receiver_alive = false;
+        self.shared.not_full.notify_all();
+    }
+}
@@ -0,0 +1,109 @@
+use crate::ServerConfig;
+use log::{debug, warn};
+use mattermost_structs::{api::Client, ids::ChannelId, Result};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+/// Viewing a mirrored channel causes a `channel_viewed` event on its server, which must not be mirrored back
+const ECHO_WINDOW: Duration = Duration::from_secs(30);
+
+/// Channels on different servers which are read together
+///
+/// Viewing one of the channels marks all others as viewed.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ReadMirror {
+    pub channels: Vec<MirroredChannel>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, Eq, PartialEq, Hash)]
+pub struct MirroredChannel {
+    /// The `servername` of the server
+    pub server: String,
+    pub channel_id: ChannelId,
+}
+
+/// Mirrors the read state of the channels in the [`ReadMirror`] groups
+#[derive(Debug)]
+pub struct ReadStateMirror {
+    groups: Vec<ReadMirror>,
+    clients: HashMap<String, Client>,
+    /// Channels marked as viewed by the mirror, until their event arrived
+    mirrored: Mutex<HashMap<MirroredChannel, Instant>>,
+}
+
+impl ReadStateMirror {
+    pub fn new(groups: Vec<ReadMirror>, servers: &[ServerConfig]) -> Result<ReadStateMirror> {
+        let mut clients = HashMap::new();
+        for group in &groups {
+            for channel in &group.channels {
+                if clients.contains_key(&channel.server) {
+                    continue;
+                }
+                let server = servers
+                    .iter()
+                    .find(|server| server.servername == channel.server)
+                    .ok_or_else(|| {
+                        format!(
+                            "Read mirror references the unknown server '{}'",
+                            channel.server
+                        )
+                    })?;
+                clients.insert(
+                    channel.server.clone(),
+                    Client::new(server.base_url.clone(), server.token.clone())?,
+                );
+            }
+        }
+        Ok(ReadStateMirror {
+            groups,
+            clients,
+            mirrored: Mutex::new(HashMap::new()),
+        })
+    }
+
+    /// Mark the channels mirroring `channel_id` on `server` as viewed
+    pub fn channel_viewed(&self, server: &str, channel_id: &str) {
+        let viewed = MirroredChannel {
+            server: server.to_string(),
+            channel_id: ChannelId::from(channel_id),
+        };
+        {
+            let mut mirrored = self.mirrored.lock().unwrap();
+            mirrored.retain(|_, time| time.elapsed() < ECHO_WINDOW);
+            if mirrored.remove(&viewed).is_some() {
+                debug!(
+                    "Ignoring mirrored view of channel {} on {}",
+                    channel_id, server
+                );
+                return;
+            }
+        }
+
+        for group in self
+            .groups
+            .iter()
+            .filter(|group| group.channels.contains(&viewed))
+        {
+            for target in group.channels.iter().filter(|target| **target != viewed) {
+                let client = &self.clients[&target.server];
+                self.mirrored
+                    .lock()
+                    .unwrap()
+                    .insert(target.clone(), Instant::now());
+                if let Err(err) = client.view_channel(&target.channel_id) {
+                    self.mirrored.lock().unwrap().remove(target);
+                    warn!(
+                        "Failed to mirror the read state of channel {} to {}:\n{}",
+                        target.channel_id,
+                        target.server,
+                        err.display_chain()
+                    );
+                }
+            }
+        }
+    }
+}
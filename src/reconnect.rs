@@ -0,0 +1,235 @@
+//! Decide when to reconnect to a server after the connection was lost
+//!
+//! Reconnecting at a fixed interval hammers a server which is down, especially if many clients lose their connection at the same time.
+//! [`Reconnector`] therefore waits with exponential backoff and random jitter between attempts.
+//! After too many failed attempts in a row, its circuit breaker opens and pauses reconnecting for a longer cooldown.
+//! [`FailureWindow`] decides when a connection failed often enough to notify the operator.
+
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{hash_map::RandomState, VecDeque},
+    hash::{BuildHasher, Hasher},
+    num::NonZeroUsize,
+    time::{Duration, Instant},
+};
+
+/// Exponentially growing delays with random jitter
+#[derive(Debug, Clone)]
+pub struct Backoff {
+    initial: Duration,
+    max: Duration,
+    jitter: f64,
+    attempt: u32,
+}
+
+impl Backoff {
+    /// Delays start at `initial` and double after every attempt, until they reach `max`
+    pub fn new(initial: Duration, max: Duration) -> Backoff {
+        Backoff {
+            initial,
+            max,
+            jitter: 0.5,
+            attempt: 0,
+        }
+    }
+
+    /// Shorten each delay by a random fraction of up to `jitter`, between 0 and 1
+    ///
+    /// Defaults to 0.5.
+    pub fn jitter(mut self, jitter: f64) -> Self {
+        self.jitter = jitter.clamp(0., 1.);
+        self
+    }
+
+    /// Number of delays handed out since the last reset
+    pub fn attempts(&self) -> u32 {
+        self.attempt
+    }
+
+    pub fn next_delay(&mut self) -> Duration {
+        let delay = self
+            .initial
+            .checked_mul(1 << self.attempt.min(31))
+            .map_or(self.max, |delay| delay.min(self.max));
+        self.attempt = self.attempt.saturating_add(1);
+        delay.mul_f64(1. - self.jitter * random_fraction())
+    }
+
+    pub fn reset(&mut self) {
+        self.attempt = 0;
+    }
+}
+
+/// Random number in `[0, 1)`
+///
+/// Each `RandomState` has different random keys, which is good enough for jitter and avoids a dependency.
+fn random_fraction() -> f64 {
+    let hasher = RandomState::new().build_hasher();
+    (hasher.finish() >> 11) as f64 / (1u64 << 53) as f64
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum CircuitState {
+    /// Reconnect with backoff
+    Closed,
+    /// Too many failures, wait for the cooldown before trying again
+    Open,
+    /// The cooldown expired, the next attempt decides whether the circuit closes or opens again
+    HalfOpen,
+}
+
+/// Reconnection policy of a single connection
+///
+/// Call [`Reconnector::connection_ended`] whenever the connection ends and wait for the returned delay before reconnecting.
+/// Connections which stay up for the stable period count as success, all shorter ones as failure.
+#[derive(Debug, Clone)]
+pub struct Reconnector {
+    backoff: Backoff,
+    stable_after: Duration,
+    failure_threshold: u32,
+    cooldown: Duration,
+    failures: u32,
+    open_until: Option<Instant>,
+    trips: u64,
+}
+
+impl Reconnector {
+    /// Backoff from 1 second to 5 minutes, opening the circuit for 15 minutes after 10 failures
+    pub fn new() -> Reconnector {
+        Reconnector {
+            backoff: Backoff::new(Duration::from_secs(1), Duration::from_secs(300)),
+            stable_after: Duration::from_secs(60),
+            failure_threshold: 10,
+            cooldown: Duration::from_secs(900),
+            failures: 0,
+            open_until: None,
+            trips: 0,
+        }
+    }
+
+    pub fn backoff(mut self, backoff: Backoff) -> Self {
+        self.backoff = backoff;
+        self
+    }
+
+    /// Connections lasting at least this long count as success
+    pub fn stable_after(mut self, stable_after: Duration) -> Self {
+        self.stable_after = stable_after;
+        self
+    }
+
+    /// Open the circuit after `failure_threshold` failures in a row and keep it open for `cooldown`
+    pub fn circuit_breaker(mut self, failure_threshold: u32, cooldown: Duration) -> Self {
+        self.failure_threshold = failure_threshold;
+        self.cooldown = cooldown;
+        self
+    }
+
+    pub fn state(&self) -> CircuitState {
+        match self.open_until {
+            None => CircuitState::Closed,
+            Some(until) if Instant::now() < until => CircuitState::Open,
+            Some(_) => CircuitState::HalfOpen,
+        }
+    }
+
+    /// Number of failed connections in a row
+    pub fn failures(&self) -> u32 {
+        self.failures
+    }
+
+    /// Number of times the circuit opened so far
+    pub fn trips(&self) -> u64 {
+        self.trips
+    }
+
+    /// Record a connection which lasted for `uptime` and return the delay until the next attempt
+    pub fn connection_ended(&mut self, uptime: Duration) -> Duration {
+        if uptime >= self.stable_after {
+            self.failures = 0;
+            self.open_until = None;
+            self.backoff.reset();
+            return self.backoff.next_delay();
+        }
+
+        self.failures = self.failures.saturating_add(1);
+        // A single failure while half open suffices to open the circuit again
+        if self.open_until.is_some() || self.failures >= self.failure_threshold {
+            self.open_until = Some(Instant::now() + self.cooldown);
+            self.trips += 1;
+            self.backoff.reset();
+            self.cooldown
+        } else {
+            self.backoff.next_delay()
+        }
+    }
+}
+
+impl Default for Reconnector {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Notify the operator if a connection fails too often, instead of silently retrying forever
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default)]
+pub struct EscalationPolicy {
+    /// Number of failed or panicked connections which trigger the escalation, at least 1
+    pub max_failures: NonZeroUsize,
+    /// Only failures within this many seconds count
+    pub window_secs: u64,
+    /// Exit with a non-zero status after notifying, such that a supervisor can restart the bridge
+    pub exit: bool,
+}
+
+impl Default for EscalationPolicy {
+    fn default() -> Self {
+        EscalationPolicy {
+            max_failures: NonZeroUsize::new(5).unwrap(),
+            window_secs: 600,
+            exit: false,
+        }
+    }
+}
+
+/// Failures of a connection within the window of the [`EscalationPolicy`]
+#[derive(Debug)]
+pub struct FailureWindow {
+    policy: EscalationPolicy,
+    failures: VecDeque<Instant>,
+}
+
+impl FailureWindow {
+    pub fn new(policy: EscalationPolicy) -> FailureWindow {
+        FailureWindow {
+            policy,
+            failures: VecDeque::new(),
+        }
+    }
+
+    /// Record a failure and return whether it needs to be escalated
+    ///
+    /// The window is cleared on escalation, such that the operator is not notified for every further failure.
+    pub fn record_failure(&mut self) -> bool {
+        self.record_failure_at(Instant::now())
+    }
+
+    /// Like [`FailureWindow::record_failure`], for a failure at `now`
+    pub fn record_failure_at(&mut self, now: Instant) -> bool {
+        let window = Duration::from_secs(self.policy.window_secs);
+        while let Some(&oldest) = self.failures.front() {
+            if now.duration_since(oldest) <= window {
+                break;
+            }
+            self.failures.pop_front();
+        }
+        self.failures.push_back(now);
+        if self.failures.len() >= self.policy.max_failures.get() {
+            self.failures.clear();
+            true
+        } else {
+            false
+        }
+    }
+}
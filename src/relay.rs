@@ -0,0 +1,387 @@
+//! Relay posts between channels, possibly on different servers
+//!
+//! A [`Relay`] receives the events of all connected servers and copies new posts from the source channel of each route into its target channel.
+//! Relayed posts name their author, attachments are uploaded again, and edits and deletions of the original are applied to the copies.
+//! Replies end up in the relayed thread, if its root post was relayed.
+//! Which attachments are copied is decided by the [`AttachmentPolicy`] of the target channel.
+//!
+//! # Origin tagging
+//!
+//! Every copy carries a [`RelayOrigin`] in the prop `relay_origin`, naming the server, channel, and id of the original post:
+//!
+//! ```json
+//! {"relay_origin": {"server": "community", "channel_id": "4yg...", "post_id": "9xh..."}}
+//! ```
+//!
+//! Posts with this prop are never relayed again, which prevents loops between paired channels, also across different relay implementations.
+//! Chains like A to B to C therefore need a direct route from A to C.
+//! An original post is only relayed once, even if its event is delivered again, e.g., when resuming a websocket connection.
+
+pub use crate::websocket::RelayOrigin;
+use crate::{
+    api::{Client, CreatePostRequest, PostPatch},
+    attachment::{AttachmentDecision, AttachmentPolicy},
+    error::{Result, ResultExt},
+    format::escape,
+    ids::{ChannelId, PostId},
+    websocket::{Events, MessagePush, Post, PostProps, PostType},
+};
+use log::{debug, warn};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+
+/// Number of relayed posts whose copies are remembered for edits and deletions
+const MAX_TRACKED_POSTS: usize = 10_000;
+
+/// A channel on one of the servers of a [`Relay`]
+#[derive(Debug, Deserialize, Serialize, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct ChannelRef {
+    /// Name under which the server was added with [`Relay::server`]
+    pub server: String,
+    pub channel_id: ChannelId,
+}
+
+impl ChannelRef {
+    pub fn new<S, C>(server: S, channel_id: C) -> ChannelRef
+    where
+        S: Into<String>,
+        C: Into<ChannelId>,
+    {
+        ChannelRef {
+            server: server.into(),
+            channel_id: channel_id.into(),
+        }
+    }
+}
+
+impl RelayOrigin {
+    /// The origin of `post`, if it is a relayed copy
+    pub fn of(post: &Post) -> Option<&RelayOrigin> {
+        post.props.relay_origin()
+    }
+}
+
+/// Whether `post` is a copy made by a relay
+pub fn is_relayed(post: &Post) -> bool {
+    RelayOrigin::of(post).is_some()
+}
+
+/// A copy of a relayed post
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct RelayedPost {
+    pub target: ChannelRef,
+    pub post_id: PostId,
+}
+
+/// The copies of an original post
+#[derive(Debug, Clone)]
+struct Relayed {
+    sender: String,
+    copies: Vec<RelayedPost>,
+}
+
+/// Copies posts along the configured routes
+///
+/// Events must be passed to [`Relay::handle`] together with the name of the server they were received from.
+#[derive(Debug, Clone, Default)]
+pub struct Relay {
+    clients: HashMap<String, Client>,
+    routes: Vec<(ChannelRef, ChannelRef)>,
+    /// By target channel, the default policy applies to all others
+    attachment_policies: HashMap<ChannelRef, AttachmentPolicy>,
+    /// Copies by server and id of the original post
+    relayed: HashMap<(String, PostId), Relayed>,
+    /// Order in which the originals were relayed, to forget the oldest ones
+    order: VecDeque<(String, PostId)>,
+}
+
+impl Relay {
+    pub fn new() -> Relay {
+        Relay::default()
+    }
+
+    /// Add a server under `name`
+    ///
+    /// The posts are created by the user of `client`, who needs to be a member of the target channels on this server.
+    pub fn server<S>(mut self, name: S, client: Client) -> Self
+    where
+        S: Into<String>,
+    {
+        self.clients.insert(name.into(), client);
+        self
+    }
+
+    /// Relay posts from `from` into `to`
+    pub fn route(mut self, from: ChannelRef, to: ChannelRef) -> Self {
+        self.routes.push((from, to));
+        self
+    }
+
+    /// Relay posts in both directions
+    pub fn pair(self, a: ChannelRef, b: ChannelRef) -> Self {
+        self.route(a.clone(), b.clone()).route(b, a)
+    }
+
+    /// Handle the attachments of posts relayed into `target` according to `policy`
+    pub fn attachment_policy(mut self, target: ChannelRef, policy: AttachmentPolicy) -> Self {
+        self.attachment_policies.insert(target, policy);
+        self
+    }
+
+    /// The copies of a post received from `server`
+    pub fn copies_of(&self, server: &str, post_id: &str) -> &[RelayedPost] {
+        self.relayed
+            .get(&(server.to_string(), PostId::from(post_id)))
+            .map_or(&[], |relayed| &relayed.copies)
+    }
+
+    /// Apply an event received from `server`
+    ///
+    /// Returns the number of created, changed, or deleted copies.
+    /// A failure to relay to one target is logged and does not stop the others.
+    pub fn handle(&mut self, server: &str, message: &MessagePush) -> Result<usize> {
+        match &message.event {
+            Events::Posted {
+                post, sender_name, ..
+            } => self.relay_post(server, post, sender_name),
+            Events::PostEdited { post } => self.relay_edit(server, post),
+            Events::PostDeleted { post } => self.relay_delete(server, post),
+            _ => Ok(0),
+        }
+    }
+
+    fn client(&self, server: &str) -> Result<&Client> {
+        self.clients
+            .get(server)
+            .ok_or_else(|| format!("The relay has no server named '{}'", server).into())
+    }
+
+    fn relay_post(&mut self, server: &str, post: &Post, sender_name: &str) -> Result<usize> {
+        if post.type_ != PostType::UserMessage {
+            return Ok(0);
+        }
+        if let Some(origin) = RelayOrigin::of(post) {
+            debug!(
+                "Not relaying post {}, it is a copy of {} on {}",
+                post.id, origin.post_id, origin.server
+            );
+            return Ok(0);
+        }
+        let key = (server.to_string(), post.id.clone());
+        if self.relayed.contains_key(&key) {
+            debug!("Post {} was already relayed", post.id);
+            return Ok(0);
+        }
+        let source = ChannelRef::new(server, post.channel_id.clone());
+        let targets: Vec<ChannelRef> = self
+            .routes
+            .iter()
+            .filter(|(from, _)| *from == source)
+            .map(|(_, to)| to.clone())
+            .collect();
+        if targets.is_empty() {
+            return Ok(0);
+        }
+
+        let mut copies = Vec::new();
+        for target in targets {
+            match self.create_copy(server, post, sender_name, &target) {
+                Ok(post_id) => copies.push(RelayedPost { target, post_id }),
+                Err(err) => warn!(
+                    "Failed to relay post {} to channel {} on {}: {}",
+                    post.id, target.channel_id, target.server, err
+                ),
+            }
+        }
+        let count = copies.len();
+        self.relayed.insert(
+            key.clone(),
+            Relayed {
+                sender: sender_name.to_string(),
+                copies,
+            },
+        );
+        self.order.push_back(key);
+        while self.order.len() > MAX_TRACKED_POSTS {
+            if let Some(oldest) = self.order.pop_front() {
+                self.relayed.remove(&oldest);
+            }
+        }
+        Ok(count)
+    }
+
+    fn create_copy(
+        &self,
+        server: &str,
+        post: &Post,
+        sender_name: &str,
+        target: &ChannelRef,
+    ) -> Result<PostId> {
+        let source_client = self.client(server)?;
+        let target_client = self.client(&target.server)?;
+
+        let mut props = PostProps::default();
+        props.set_relay_origin(RelayOrigin {
+            server: server.to_string(),
+            channel_id: post.channel_id.clone(),
+            post_id: post.id.clone(),
+        });
+        let default_policy = AttachmentPolicy::default();
+        let policy = self
+            .attachment_policies
+            .get(target)
+            .unwrap_or(&default_policy);
+        let files = relay_files(
+            source_client,
+            target_client,
+            &target.channel_id,
+            post,
+            policy,
+        );
+        let mut message = attributed(sender_name, &post.message);
+        for link in &files.links {
+            message.push('\n');
+            message.push_str(link);
+        }
+        let mut request = CreatePostRequest::builder()
+            .channel_id(target.channel_id.clone())
+            .message(message)
+            .props(props);
+        if !post.root_id.is_empty() {
+            let root = self
+                .copies_of(server, &post.root_id)
+                .iter()
+                .find(|copy| copy.target == *target);
+            if let Some(root) = root {
+                request = request.root_id(root.post_id.clone());
+            }
+        }
+        let request = request.file_ids(files.file_ids).build()?;
+        Ok(target_client.create_post(&request)?.id)
+    }
+
+    fn relay_edit(&self, server: &str, post: &Post) -> Result<usize> {
+        let relayed = match self.relayed.get(&(server.to_string(), post.id.clone())) {
+            Some(relayed) => relayed,
+            None => return Ok(0),
+        };
+        let patch = PostPatch {
+            message: Some(attributed(&relayed.sender, &post.message)),
+            ..Default::default()
+        };
+        let mut count = 0;
+        for copy in &relayed.copies {
+            let result = self
+                .client(&copy.target.server)
+                .and_then(|client| client.patch_post(&copy.post_id, &patch));
+            match result {
+                Ok(_) => count += 1,
+                Err(err) => warn!(
+                    "Failed to update relayed post {} on {}: {}",
+                    copy.post_id, copy.target.server, err
+                ),
+            }
+        }
+        Ok(count)
+    }
+
+    fn relay_delete(&mut self, server: &str, post: &Post) -> Result<usize> {
+        let key = (server.to_string(), post.id.clone());
+        let relayed = match self.relayed.get(&key) {
+            Some(relayed) => relayed,
+            None => return Ok(0),
+        };
+        let mut count = 0;
+        for copy in &relayed.copies {
+            let result = self
+                .client(&copy.target.server)
+                .and_then(|client| client.delete_post(&copy.post_id));
+            match result {
+                Ok(()) => count += 1,
+                Err(err) => warn!(
+                    "Failed to delete relayed post {} on {}: {}",
+                    copy.post_id, copy.target.server, err
+                ),
+            }
+        }
+        self.relayed.remove(&key);
+        self.order.retain(|entry| *entry != key);
+        Ok(count)
+    }
+}
+
+/// The message of a relayed post, starting with the name of its author
+///
+/// The name is not a mention, since a user with the same name on the target server is a different person.
+pub fn attributed(sender_name: &str, message: &str) -> String {
+    let sender = escape(sender_name.trim_start_matches('@'));
+    if message.contains('\n') {
+        format!("**{}:**\n{}", sender, message)
+    } else {
+        format!("**{}:** {}", sender, message)
+    }
+}
+
+/// Attachments of a copy
+#[derive(Debug, Default)]
+struct RelayedFiles {
+    /// Uploaded to the target channel
+    file_ids: Vec<String>,
+    /// Markdown links to files which were not uploaded
+    links: Vec<String>,
+}
+
+/// Upload or link the attachments of `post` as allowed by `policy`, skipping the files which fail
+fn relay_files(
+    source: &Client,
+    target: &Client,
+    channel_id: &ChannelId,
+    post: &Post,
+    policy: &AttachmentPolicy,
+) -> RelayedFiles {
+    let mut files = RelayedFiles::default();
+    for file_id in &post.file_ids {
+        let result = source.get_file_info(file_id).and_then(|info| {
+            let link = match policy.decide(&info) {
+                AttachmentDecision::Skip(reason) => {
+                    debug!("Not relaying file {}: {}", info.name, reason);
+                    return Ok(());
+                }
+                AttachmentDecision::Link => true,
+                AttachmentDecision::Reupload => {
+                    let mut data = Vec::new();
+                    source.get_file(file_id, &mut data)?;
+                    #[cfg(feature = "image")]
+                    let data = policy.downscale(data);
+                    if policy.fits(data.len() as u64) {
+                        let infos = target
+                            .upload_file(channel_id, info.name.clone(), data)
+                            .chain_err(|| format!("Failed to upload file {}", file_id))?;
+                        files.file_ids.extend(infos.into_iter().map(|info| info.id));
+                        false
+                    } else {
+                        true
+                    }
+                }
+            };
+            if link {
+                let name = escape(&info.name);
+                files.links.push(match source.get_file_link(file_id) {
+                    Ok(link) => format!("[{}]({})", name, link),
+                    Err(err) => {
+                        debug!("No public link for file {}: {}", file_id, err);
+                        format!("*{} (not relayed)*", name)
+                    }
+                });
+            }
+            Ok(())
+        });
+        if let Err(err) = result {
+            warn!(
+                "Failed to relay file {} of post {}: {}",
+                file_id, post.id, err
+            );
+        }
+    }
+    files
+}
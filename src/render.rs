@@ -0,0 +1,218 @@
+//! Human-readable text for system posts
+//!
+//! System posts, e.g., when somebody joins a channel, describe the event only in their props.
+//! [`render_system_post`] turns them into text like "@alice joined the channel."
+//! English is built in, other languages implement [`Locale`] and fall back to English for everything they do not translate.
+//!
+//! ```
+//! use mattermost_structs::render::{Locale, SystemMessage};
+//!
+//! struct German;
+//!
+//! impl Locale for German {
+//!     fn render(&self, message: &SystemMessage<'_>) -> Option<String> {
+//!         match message {
+//!             SystemMessage::JoinChannel { username } => {
+//!                 Some(format!("@{} ist dem Kanal beigetreten.", username))
+//!             }
+//!             SystemMessage::LeaveChannel { username } => {
+//!                 Some(format!("@{} hat den Kanal verlassen.", username))
+//!             }
+//!             _ => None,
+//!         }
+//!     }
+//! }
+//!
+//! let message = SystemMessage::JoinChannel { username: "alice" };
+//! assert_eq!(German.render(&message).unwrap(), "@alice ist dem Kanal beigetreten.");
+//! ```
+
+use crate::websocket::{Post, PostType};
+
+/// Shown if the props do not name the user
+const UNKNOWN_USER: &str = "someone";
+
+/// The event described by a system post
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum SystemMessage<'a> {
+    JoinChannel {
+        username: &'a str,
+    },
+    GuestJoinChannel {
+        username: &'a str,
+    },
+    LeaveChannel {
+        username: &'a str,
+    },
+    AddToChannel {
+        username: &'a str,
+        added_username: &'a str,
+    },
+    RemoveFromChannel {
+        removed_username: &'a str,
+    },
+    JoinTeam {
+        username: &'a str,
+    },
+    AddToTeam {
+        username: &'a str,
+        added_username: &'a str,
+    },
+    RemoveFromTeam {
+        username: &'a str,
+    },
+    LeaveTeam {
+        username: &'a str,
+    },
+    /// Empty strings mean that there was no header before or after the change
+    HeaderChange {
+        username: &'a str,
+        old_header: &'a str,
+        new_header: &'a str,
+    },
+    PurposeChange {
+        username: &'a str,
+        old_purpose: &'a str,
+        new_purpose: &'a str,
+    },
+    DisplaynameChange {
+        username: &'a str,
+        old_displayname: &'a str,
+        new_displayname: &'a str,
+    },
+    ChannelDeleted {
+        username: &'a str,
+    },
+}
+
+impl<'a> SystemMessage<'a> {
+    /// The event of `post`, if it is a system post describing one
+    pub fn from_post(post: &'a Post) -> Option<SystemMessage<'a>> {
+        let props = &post.props;
+        let username = props.username().unwrap_or(UNKNOWN_USER);
+        let message = match post.type_ {
+            PostType::SystemJoinChannel => SystemMessage::JoinChannel { username },
+            PostType::SystemGuestJoinChannel => SystemMessage::GuestJoinChannel { username },
+            PostType::SystemLeaveChannel => SystemMessage::LeaveChannel { username },
+            PostType::SystemAddToChannel => SystemMessage::AddToChannel {
+                username,
+                added_username: props.added_username().unwrap_or(UNKNOWN_USER),
+            },
+            PostType::SystemRemoveFromChannel => SystemMessage::RemoveFromChannel {
+                removed_username: props.removed_username().unwrap_or(UNKNOWN_USER),
+            },
+            PostType::SystemJoinTeam => SystemMessage::JoinTeam { username },
+            PostType::SystemAddToTeam => SystemMessage::AddToTeam {
+                username,
+                added_username: props.added_username().unwrap_or(UNKNOWN_USER),
+            },
+            PostType::SystemRemoveFromTeam => SystemMessage::RemoveFromTeam { username },
+            PostType::SystemLeaveTeam => SystemMessage::LeaveTeam { username },
+            PostType::SystemHeaderChange => SystemMessage::HeaderChange {
+                username,
+                old_header: props.old_header().unwrap_or_default(),
+                new_header: props.new_header().unwrap_or_default(),
+            },
+            PostType::SystemPurposeChange => SystemMessage::PurposeChange {
+                username,
+                old_purpose: props.old_purpose().unwrap_or_default(),
+                new_purpose: props.new_purpose().unwrap_or_default(),
+            },
+            PostType::SystemDisplaynameChange => SystemMessage::DisplaynameChange {
+                username,
+                old_displayname: props.old_displayname().unwrap_or_default(),
+                new_displayname: props.new_displayname().unwrap_or_default(),
+            },
+            PostType::SystemChannelDeleted => SystemMessage::ChannelDeleted { username },
+            PostType::UserMessage
+            | PostType::SystemEphemeral
+            | PostType::SystemCombinedUserActivity
+            | PostType::Reminder => return None,
+        };
+        Some(message)
+    }
+}
+
+/// Translations of system messages
+pub trait Locale {
+    /// The text for `message`, or `None` to use the English text
+    fn render(&self, message: &SystemMessage<'_>) -> Option<String>;
+}
+
+/// The texts of the Mattermost web app
+#[derive(Debug, Clone, Copy, Default)]
+pub struct English;
+
+impl Locale for English {
+    fn render(&self, message: &SystemMessage<'_>) -> Option<String> {
+        let text = match *message {
+            SystemMessage::JoinChannel { username } => {
+                format!("@{} joined the channel.", username)
+            }
+            SystemMessage::GuestJoinChannel { username } => {
+                format!("@{} joined the channel as a guest.", username)
+            }
+            SystemMessage::LeaveChannel { username } => format!("@{} left the channel.", username),
+            SystemMessage::AddToChannel {
+                username,
+                added_username,
+            } => format!("@{} added to the channel by @{}.", added_username, username),
+            SystemMessage::RemoveFromChannel { removed_username } => {
+                format!("@{} was removed from the channel.", removed_username)
+            }
+            SystemMessage::JoinTeam { username } => format!("@{} joined the team.", username),
+            SystemMessage::AddToTeam {
+                username,
+                added_username,
+            } => format!("@{} added to the team by @{}.", added_username, username),
+            SystemMessage::LeaveTeam { username } => format!("@{} left the team.", username),
+            SystemMessage::RemoveFromTeam { username } => {
+                format!("@{} was removed from the team.", username)
+            }
+            SystemMessage::HeaderChange {
+                username,
+                old_header,
+                new_header,
+            } => changed(username, "channel header", old_header, new_header),
+            SystemMessage::PurposeChange {
+                username,
+                old_purpose,
+                new_purpose,
+            } => changed(username, "channel purpose", old_purpose, new_purpose),
+            SystemMessage::DisplaynameChange {
+                username,
+                old_displayname,
+                new_displayname,
+            } => changed(username, "channel name", old_displayname, new_displayname),
+            SystemMessage::ChannelDeleted { username } => {
+                format!("@{} archived the channel.", username)
+            }
+        };
+        Some(text)
+    }
+}
+
+fn changed(username: &str, what: &str, old: &str, new: &str) -> String {
+    match (old.is_empty(), new.is_empty()) {
+        (true, _) => format!("@{} updated the {} to: {}", username, what, new),
+        (false, true) => format!("@{} removed the {} (was: {})", username, what, old),
+        (false, false) => format!(
+            "@{} updated the {} from: {} to: {}",
+            username, what, old, new
+        ),
+    }
+}
+
+/// Text to show for `post`
+///
+/// System posts are rendered with `locale`, falling back to [`English`].
+/// All other posts, and system posts of unknown kind, keep their message.
+pub fn render_system_post(post: &Post, locale: &dyn Locale) -> String {
+    match SystemMessage::from_post(post) {
+        Some(message) => locale
+            .render(&message)
+            .or_else(|| English.render(&message))
+            .unwrap_or_else(|| post.message.clone()),
+        None => post.message.clone(),
+    }
+}
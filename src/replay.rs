@@ -0,0 +1,180 @@
+//! Record websocket events with their timing and replay them later
+//!
+//! A recording is a file with one JSON object per line, containing the event and the time since the start of the recording:
+//!
+//! ```json
+//! {"offset_ms":1532,"message":{"event":"typing","data":{...},"broadcast":{...},"seq":4}}
+//! ```
+//!
+//! Replaying a recording into an [`EventSink`] allows testing bots against realistic traffic without a server.
+
+use crate::{error::Result, sink::EventSink, websocket::MessagePush};
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::File,
+    io::{BufRead, BufReader, Write},
+    path::Path,
+    thread,
+    time::{Duration, Instant},
+};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordedEvent {
+    /// Time since the start of the recording in milliseconds
+    pub offset_ms: u64,
+    pub message: MessagePush,
+}
+
+impl RecordedEvent {
+    pub fn offset(&self) -> Duration {
+        Duration::from_millis(self.offset_ms)
+    }
+}
+
+/// Writes all published events to a recording
+///
+/// The recording starts when the recorder is created.
+#[derive(Debug)]
+pub struct Recorder<W> {
+    writer: W,
+    start: Instant,
+}
+
+impl<W> Recorder<W>
+where
+    W: Write,
+{
+    pub fn new(writer: W) -> Recorder<W> {
+        Recorder {
+            writer,
+            start: Instant::now(),
+        }
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+impl<W> EventSink for Recorder<W>
+where
+    W: Write,
+{
+    fn publish(&mut self, message: &MessagePush) -> Result<()> {
+        // `RecordedEvent` owns the message, so the line is assembled by hand to avoid a clone
+        write!(
+            self.writer,
+            r#"{{"offset_ms":{},"message":"#,
+            self.start.elapsed().as_millis()
+        )?;
+        match &message.raw {
+            // Keep the message exactly as received, unless it would break the line format
+            Some(raw) if !raw.contains('\n') => self.writer.write_all(raw.as_bytes())?,
+            _ => serde_json::to_writer(&mut self.writer, message)?,
+        }
+        writeln!(self.writer, "}}")?;
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        Ok(self.writer.flush()?)
+    }
+}
+
+/// Speed at which a recording is replayed
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Timing {
+    /// Keep the delays between the events
+    Original,
+    /// Divide the delays between the events by this factor, which must be positive and finite
+    Accelerated(f64),
+    /// Replay all events without any delay
+    Immediate,
+}
+
+impl Timing {
+    /// Divide the delays between the events by `factor`
+    ///
+    /// Fails if `factor` is not positive and finite.
+    ///
+    /// ```
+    /// # use mattermost_structs::replay::Timing;
+    /// assert_eq!(Timing::accelerated(2.0).unwrap(), Timing::Accelerated(2.0));
+    /// assert!(Timing::accelerated(0.0).is_err());
+    /// assert!(Timing::accelerated(f64::NAN).is_err());
+    /// ```
+    pub fn accelerated(factor: f64) -> Result<Timing> {
+        if factor.is_finite() && factor > 0.0 {
+            Ok(Timing::Accelerated(factor))
+        } else {
+            Err(format!(
+                "Invalid replay speed {}, must be positive and finite",
+                factor
+            )
+            .into())
+        }
+    }
+}
+
+/// A recording loaded into memory
+#[derive(Debug, Clone, Default)]
+pub struct Replay {
+    pub events: Vec<RecordedEvent>,
+}
+
+impl Replay {
+    pub fn open<P>(path: P) -> Result<Replay>
+    where
+        P: AsRef<Path>,
+    {
+        Self::from_reader(BufReader::new(File::open(path)?))
+    }
+
+    /// Read a recording, skipping empty lines
+    pub fn from_reader<R>(reader: R) -> Result<Replay>
+    where
+        R: BufRead,
+    {
+        let mut events = Vec::new();
+        for line in reader.lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            events.push(serde_json::from_str(&line)?);
+        }
+        Ok(Replay { events })
+    }
+
+    /// Publish all events to `sink` in the order of the recording
+    ///
+    /// The sink is flushed at the end.
+    /// Fails without publishing anything if the factor of [`Timing::Accelerated`] is invalid.
+    pub fn replay(&self, sink: &mut dyn EventSink, timing: Timing) -> Result<()> {
+        if let Timing::Accelerated(factor) = timing {
+            Timing::accelerated(factor)?;
+        }
+        let start = Instant::now();
+        for event in &self.events {
+            let due = match timing {
+                Timing::Original => Some(event.offset()),
+                Timing::Accelerated(factor) => {
+                    let secs = event.offset().as_secs_f64() / factor;
+                    // Tiny factors stretch the delays beyond what a `Duration` can hold
+                    if secs >= u64::MAX as f64 {
+                        return Err(format!("Replay speed {} is too slow", factor).into());
+                    }
+                    Some(Duration::from_secs_f64(secs))
+                }
+                Timing::Immediate => None,
+            };
+            if let Some(due) = due {
+                if let Some(delay) = due.checked_sub(start.elapsed()) {
+                    thread::sleep(delay);
+                }
+            }
+            sink.publish(&event.message)?;
+        }
+        sink.flush()
+    }
+}
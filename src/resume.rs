@@ -0,0 +1,86 @@
+//! Resume websocket connections without losing events (server version 6.0 and newer)
+//!
+//! The server identifies each connection with the `connection_id` of its `hello` event.
+//! If a client reconnects with this id and the sequence number of the next expected event, the server replays the missed events.
+//! Whether this worked shows in the `hello` of the new connection: it carries the same id only if the connection was resumed.
+//! Otherwise the events in between are lost and need to be fetched via the REST API.
+
+use crate::websocket::{Events, MessagePush};
+use url::Url;
+
+/// Outcome of a (re)connection, as told by its `hello` event
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum ResumeOutcome {
+    /// First connection, there is nothing to catch up on
+    Initial,
+    /// The server replays the missed events
+    Resumed,
+    /// The server could not resume the connection, missed events need to be fetched via REST
+    Lost,
+}
+
+/// Connection id and sequence number to keep across reconnects
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct ResumeState {
+    connection_id: Option<String>,
+    /// Sequence number of the next expected event
+    next_seq: usize,
+}
+
+impl ResumeState {
+    pub fn new() -> ResumeState {
+        Self::default()
+    }
+
+    pub fn connection_id(&self) -> Option<&str> {
+        self.connection_id.as_deref()
+    }
+
+    /// Sequence number of the next expected event
+    pub fn next_seq(&self) -> usize {
+        self.next_seq
+    }
+
+    /// Whether the event was received before, i.e., the server replayed it again after resuming
+    ///
+    /// Call before [`ResumeState::observe`], which marks the event as received.
+    pub fn is_duplicate(&self, message: &MessagePush) -> bool {
+        match message.event {
+            Events::Hello { .. } => false,
+            _ => message.seq < self.next_seq,
+        }
+    }
+
+    /// Add the query parameters to resume the previous connection, if there is one
+    pub fn prepare_url(&self, url: &mut Url) {
+        if let Some(connection_id) = &self.connection_id {
+            url.query_pairs_mut()
+                .append_pair("connection_id", connection_id)
+                .append_pair("sequence_number", &self.next_seq.to_string());
+        }
+    }
+
+    /// Call for every event received
+    ///
+    /// Returns the outcome of the connection for `hello` events, `None` for all others.
+    pub fn observe(&mut self, message: &MessagePush) -> Option<ResumeOutcome> {
+        if let Events::Hello { connection_id, .. } = &message.event {
+            let outcome = match (&self.connection_id, connection_id) {
+                (None, _) => ResumeOutcome::Initial,
+                (Some(old), Some(new)) if old == new => ResumeOutcome::Resumed,
+                (Some(_), _) => ResumeOutcome::Lost,
+            };
+            if outcome != ResumeOutcome::Resumed {
+                self.connection_id = connection_id.clone();
+            }
+            // A resumed connection continues the sequence, a new one starts over
+            self.next_seq = match outcome {
+                ResumeOutcome::Resumed => self.next_seq.max(message.seq + 1),
+                ResumeOutcome::Initial | ResumeOutcome::Lost => message.seq + 1,
+            };
+            return Some(outcome);
+        }
+        self.next_seq = self.next_seq.max(message.seq + 1);
+        None
+    }
+}
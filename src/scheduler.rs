@@ -0,0 +1,428 @@
+//! Run recurring actions, like rotating a channel header or posting a stand-up reminder
+//!
+//! Each [`Job`] has a cron-like [`Schedule`] with the five fields minute, hour, day of the month, month, and day of the week.
+//! Fields are `*`, numbers, ranges like `1-5`, lists like `1,15`, and steps like `*/15` or `8-18/2`.
+//! Days of the week count from 0 (Sunday) to 6, 7 is Sunday as well.
+//! As in cron, if both days are restricted, a time matches if either of them matches.
+//!
+//! The [`Scheduler`] stores when each job ran last in a file, such that a restart neither repeats nor skips runs.
+//! Runs missed while the scheduler was not running are caught up once.
+//!
+//! ```
+//! use chrono::{TimeZone, Utc};
+//! use mattermost_structs::scheduler::Schedule;
+//!
+//! let weekdays: Schedule = "30 9 * * 1-5".parse().unwrap();
+//! // Saturday, 2024-06-01
+//! let after = Utc.with_ymd_and_hms(2024, 6, 1, 12, 0, 0).unwrap();
+//! assert_eq!(
+//!     weekdays.next_after(after, &Utc),
+//!     Some(Utc.with_ymd_and_hms(2024, 6, 3, 9, 30, 0).unwrap()),
+//! );
+//! ```
+
+use crate::{
+    api::{ChannelPatch, Client, CreatePostRequest},
+    error::Result,
+    ids::ChannelId,
+    serialize,
+};
+use chrono::{
+    offset::LocalResult, DateTime, Datelike, Duration, NaiveDate, NaiveDateTime, TimeZone,
+    Timelike, Utc,
+};
+use chrono_tz::Tz;
+use log::{debug, warn};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    fmt, fs,
+    io::ErrorKind,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+/// How far [`Schedule::next_after`] looks ahead, long enough for schedules on the 29th of February
+const MAX_LOOKAHEAD_DAYS: i64 = 9 * 366;
+
+/// When a [`Job`] runs, parsed from a cron expression like `0 9 * * 1-5`
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct Schedule {
+    expression: String,
+    /// Bit sets of the matching values of each field
+    minutes: u64,
+    hours: u64,
+    days: u64,
+    months: u64,
+    weekdays: u64,
+    /// Whether the day of the month or week is restricted, which decides how they combine
+    days_restricted: bool,
+    weekdays_restricted: bool,
+}
+
+/// Parse one field into a bit set of the values in `min..=max`
+fn parse_field(field: &str, min: u32, max: u32) -> Result<u64> {
+    let mut bits = 0;
+    for part in field.split(',') {
+        let (range, step) = match part.find('/') {
+            Some(pos) => (&part[..pos], Some(&part[pos + 1..])),
+            None => (part, None),
+        };
+        let parse = |value: &str| -> Result<u32> {
+            match value.parse() {
+                Ok(value) if (min..=max).contains(&value) => Ok(value),
+                _ => Err(format!("'{}' is not a number from {} to {}", value, min, max).into()),
+            }
+        };
+        let (start, end) = if range == "*" {
+            (min, max)
+        } else if let Some(pos) = range.find('-') {
+            (parse(&range[..pos])?, parse(&range[pos + 1..])?)
+        } else {
+            let start = parse(range)?;
+            // `5/15` means every 15 starting at 5
+            (start, if step.is_some() { max } else { start })
+        };
+        let step = match step {
+            Some(step) => match step.parse::<u32>() {
+                Ok(step) if step > 0 => step,
+                _ => return Err(format!("Invalid step '{}' in '{}'", step, part).into()),
+            },
+            None => 1,
+        };
+        if start > end {
+            return Err(format!("Empty range '{}'", part).into());
+        }
+        for value in (start..=end).step_by(step as usize) {
+            bits |= 1 << value;
+        }
+    }
+    Ok(bits)
+}
+
+fn matches(bits: u64, value: u32) -> bool {
+    bits & (1 << value) != 0
+}
+
+impl FromStr for Schedule {
+    type Err = crate::Error;
+
+    fn from_str(expression: &str) -> Result<Schedule> {
+        let fields: Vec<&str> = expression.split_whitespace().collect();
+        if fields.len() != 5 {
+            return Err(format!(
+                "'{}' needs the five fields minute, hour, day of month, month, and day of week",
+                expression
+            )
+            .into());
+        }
+        let mut weekdays = parse_field(fields[4], 0, 7)?;
+        if matches(weekdays, 7) {
+            weekdays |= 1;
+        }
+        Ok(Schedule {
+            expression: fields.join(" "),
+            minutes: parse_field(fields[0], 0, 59)?,
+            hours: parse_field(fields[1], 0, 23)?,
+            days: parse_field(fields[2], 1, 31)?,
+            months: parse_field(fields[3], 1, 12)?,
+            weekdays,
+            days_restricted: fields[2] != "*",
+            weekdays_restricted: fields[4] != "*",
+        })
+    }
+}
+
+impl fmt::Display for Schedule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.expression)
+    }
+}
+
+impl Schedule {
+    fn matches_date(&self, date: NaiveDate) -> bool {
+        let day = matches(self.days, date.day());
+        let weekday = matches(self.weekdays, date.weekday().num_days_from_sunday());
+        match (self.days_restricted, self.weekdays_restricted) {
+            (true, true) => day || weekday,
+            _ => day && weekday,
+        }
+    }
+
+    /// The first time matching the schedule after `after`, with the fields interpreted in the time zone `tz`
+    ///
+    /// Local times skipped by a daylight saving time change never match.
+    /// Returns `None` if the schedule never matches, e.g., for the 31st of February.
+    pub fn next_after<T>(&self, after: DateTime<Utc>, tz: &T) -> Option<DateTime<Utc>>
+    where
+        T: TimeZone,
+    {
+        let local = after.with_timezone(tz).naive_local();
+        let mut time =
+            local.date().and_hms_opt(local.hour(), local.minute(), 0)? + Duration::minutes(1);
+        let end = time + Duration::days(MAX_LOOKAHEAD_DAYS);
+        while time < end {
+            let date = time.date();
+            if !matches(self.months, date.month()) {
+                let (year, month) = if date.month() == 12 {
+                    (date.year() + 1, 1)
+                } else {
+                    (date.year(), date.month() + 1)
+                };
+                time = NaiveDate::from_ymd_opt(year, month, 1)?.and_hms_opt(0, 0, 0)?;
+                continue;
+            }
+            if !self.matches_date(date) {
+                time = date.succ_opt()?.and_hms_opt(0, 0, 0)?;
+                continue;
+            }
+            if !matches(self.hours, time.hour()) {
+                time = date.and_hms_opt(time.hour(), 0, 0)? + Duration::hours(1);
+                continue;
+            }
+            if matches(self.minutes, time.minute()) {
+                if let Some(found) = resolve(tz, &time).filter(|found| *found > after) {
+                    return Some(found);
+                }
+            }
+            time += Duration::minutes(1);
+        }
+        None
+    }
+}
+
+/// The earliest instant of a local time, `None` if the time does not exist
+fn resolve<T>(tz: &T, time: &NaiveDateTime) -> Option<DateTime<Utc>>
+where
+    T: TimeZone,
+{
+    match tz.from_local_datetime(time) {
+        LocalResult::Single(time) | LocalResult::Ambiguous(time, _) => {
+            Some(time.with_timezone(&Utc))
+        }
+        LocalResult::None => None,
+    }
+}
+
+/// What a [`Job`] does
+#[derive(Debug, Clone, Eq, PartialEq, Hash, Deserialize, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Action {
+    /// Set the channel header to the next of `headers`, starting over after the last one
+    RotateHeader {
+        channel_id: ChannelId,
+        headers: Vec<String>,
+    },
+    /// Post `message` to the channel
+    Post {
+        channel_id: ChannelId,
+        message: String,
+    },
+}
+
+impl Action {
+    /// Perform the action for the `run`th time, counting from 0
+    pub fn run(&self, client: &Client, run: u64) -> Result<()> {
+        match self {
+            Action::RotateHeader {
+                channel_id,
+                headers,
+            } => {
+                if headers.is_empty() {
+                    return Err("There are no headers to rotate".into());
+                }
+                let header = &headers[(run % headers.len() as u64) as usize];
+                let patch = ChannelPatch {
+                    header: Some(header.clone()),
+                    ..ChannelPatch::default()
+                };
+                client.patch_channel(channel_id, &patch)?;
+            }
+            Action::Post {
+                channel_id,
+                message,
+            } => {
+                let post = CreatePostRequest::builder()
+                    .channel_id(channel_id)
+                    .message(message)
+                    .build()?;
+                client.create_post(&post)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn default_timezone() -> Tz {
+    Tz::UTC
+}
+
+/// A named [`Action`] with its [`Schedule`]
+///
+/// ```yaml
+/// name: standup
+/// schedule: "45 9 * * 1-5"
+/// timezone: Europe/Berlin
+/// action:
+///   type: post
+///   channel_id: 4xp9fdt77pncbef59f4k1qe83o
+///   message: "Stand-up in 15 minutes"
+/// ```
+#[derive(Debug, Clone, Eq, PartialEq, Deserialize, Serialize)]
+pub struct Job {
+    /// Key of the stored run times, must be unique
+    pub name: String,
+    #[serde(with = "::serde_with::rust::display_fromstr")]
+    pub schedule: Schedule,
+    /// Time zone of the schedule, defaults to UTC
+    #[serde(
+        with = "::serde_with::rust::display_fromstr",
+        default = "default_timezone"
+    )]
+    pub timezone: Tz,
+    pub action: Action,
+}
+
+impl Job {
+    pub fn next_after(&self, after: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        self.schedule.next_after(after, &self.timezone)
+    }
+}
+
+/// Bookkeeping of a job across restarts
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Deserialize, Serialize)]
+pub struct JobState {
+    #[serde(with = "serialize::ts_seconds")]
+    pub last_run: DateTime<Utc>,
+    /// Number of successful runs, which selects the next header of [`Action::RotateHeader`]
+    pub runs: u64,
+}
+
+/// Outcome of a job started by [`Scheduler::run_due`]
+#[derive(Debug)]
+pub struct JobRun {
+    pub name: String,
+    pub result: Result<()>,
+}
+
+/// Runs the jobs when they are due and remembers when they ran
+#[derive(Debug, Clone)]
+pub struct Scheduler {
+    jobs: Vec<Job>,
+    states: BTreeMap<String, JobState>,
+    path: Option<PathBuf>,
+    /// Jobs which never ran are first due after this point
+    started: DateTime<Utc>,
+}
+
+impl Scheduler {
+    /// A scheduler which only keeps the run times in memory
+    pub fn new(jobs: Vec<Job>) -> Result<Scheduler> {
+        for (i, job) in jobs.iter().enumerate() {
+            if jobs[..i].iter().any(|other| other.name == job.name) {
+                return Err(format!("There are multiple jobs named '{}'", job.name).into());
+            }
+        }
+        Ok(Scheduler {
+            jobs,
+            states: BTreeMap::new(),
+            path: None,
+            started: Utc::now(),
+        })
+    }
+
+    /// Load the run times from `path`, starting empty if the file does not exist yet
+    pub fn load<P>(jobs: Vec<Job>, path: P) -> Result<Scheduler>
+    where
+        P: Into<PathBuf>,
+    {
+        let mut scheduler = Self::new(jobs)?;
+        let path = path.into();
+        scheduler.states = match fs::read(&path) {
+            Ok(data) => serde_json::from_slice(&data)?,
+            Err(ref err) if err.kind() == ErrorKind::NotFound => BTreeMap::new(),
+            Err(err) => return Err(err.into()),
+        };
+        scheduler.path = Some(path);
+        Ok(scheduler)
+    }
+
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    pub fn jobs(&self) -> &[Job] {
+        &self.jobs
+    }
+
+    pub fn state(&self, name: &str) -> Option<&JobState> {
+        self.states.get(name)
+    }
+
+    /// When the job runs next, `None` if its schedule never matches
+    ///
+    /// The time is in the past if the job is due.
+    pub fn next_run(&self, job: &Job) -> Option<DateTime<Utc>> {
+        let after = self
+            .states
+            .get(&job.name)
+            .map_or(self.started, |state| state.last_run);
+        job.next_after(after)
+    }
+
+    /// The earliest time any job runs next
+    pub fn next_wakeup(&self) -> Option<DateTime<Utc>> {
+        self.jobs.iter().filter_map(|job| self.next_run(job)).min()
+    }
+
+    pub fn due(&self, now: DateTime<Utc>) -> Vec<&Job> {
+        self.jobs
+            .iter()
+            .filter(|job| self.next_run(job).is_some_and(|next| next <= now))
+            .collect()
+    }
+
+    /// Run all due jobs and store their run times
+    ///
+    /// A failed job is not retried until its next scheduled time, otherwise a permanent failure would be repeated on every call.
+    pub fn run_due(&mut self, client: &Client, now: DateTime<Utc>) -> Result<Vec<JobRun>> {
+        let due: Vec<Job> = self.due(now).into_iter().cloned().collect();
+        let mut runs = Vec::with_capacity(due.len());
+        for job in due {
+            let state = self.states.entry(job.name.clone()).or_insert(JobState {
+                last_run: now,
+                runs: 0,
+            });
+            debug!("Running job '{}' (run #{})", job.name, state.runs + 1);
+            let result = job.action.run(client, state.runs);
+            match &result {
+                Ok(()) => state.runs += 1,
+                Err(err) => warn!("Job '{}' failed: {}", job.name, err),
+            }
+            state.last_run = now;
+            runs.push(JobRun {
+                name: job.name,
+                result,
+            });
+        }
+        if !runs.is_empty() {
+            self.save()?;
+        }
+        Ok(runs)
+    }
+
+    /// Write the run times to the file, if there is one
+    ///
+    /// The file is replaced atomically, such that a crash cannot leave truncated run times behind.
+    pub fn save(&self) -> Result<()> {
+        let path = match &self.path {
+            Some(path) => path,
+            None => return Ok(()),
+        };
+        let mut tmp = path.clone().into_os_string();
+        tmp.push(".tmp");
+        fs::write(&tmp, serde_json::to_vec_pretty(&self.states)?)?;
+        fs::rename(&tmp, path)?;
+        Ok(())
+    }
+}
@@ -1,281 +1,202 @@
-pub mod ts_seconds {
-    use chrono::{
-        offset::{LocalResult, TimeZone},
-        DateTime, FixedOffset, Utc,
-    };
-    use serde::{de, ser};
-    use std::fmt;
+//! Timestamps, usually in milliseconds since the epoch
+//!
+//! Negative values are timestamps before the epoch, i.e., `-1` is one millisecond before 1970.
+//! Values outside of the range of [`DateTime`](chrono::DateTime) are rejected with an error instead of overflowing.
+
+use chrono::{
+    offset::{LocalResult, TimeZone},
+    DateTime, Utc,
+};
+use serde::{de, ser};
+use std::{convert::TryFrom, fmt};
+
+/// The timestamp of `dt`, if it fits into an `i64`
+fn to_millis(dt: &DateTime<Utc>) -> Option<i64> {
+    dt.timestamp()
+        .checked_mul(1000)?
+        .checked_add(i64::from(dt.timestamp_subsec_millis()))
+}
 
-    /// Deserialize a `DateTime` from a milliseconds timestamp
-    ///
-    /// Intended for use with `serde`s `deserialize_with` attribute.
-    ///
-    /// # Example:
-    ///
-    /// ```rust
-    /// # // We mark this ignored so that we can test on 1.13 (which does not
-    /// # // support custom derive), and run tests with --ignored on beta and
-    /// # // nightly to actually trigger these.
-    /// #
-    /// # #[macro_use] extern crate serde_derive;
-    /// # #[macro_use] extern crate serde_json;
-    /// # extern crate chrono;
-    /// # use chrono::{DateTime, Utc};
-    /// use chrono::serde::ts_seconds::deserialize as from_ts;
-    /// #[derive(Deserialize)]
-    /// struct S {
-    ///     #[serde(deserialize_with = "from_ts")]
-    ///     time: DateTime<Utc>
-    /// }
-    ///
-    /// # fn example() -> Result<S, serde_json::Error> {
-    /// let my_s: S = serde_json::from_str(r#"{ "time": 1431684000 }"#)?;
-    /// # Ok(my_s)
-    /// # }
-    /// # fn main() { example().unwrap(); }
-    /// ```
-    pub fn deserialize<'de, D>(d: D) -> Result<DateTime<Utc>, D::Error>
+fn serialize_millis<S>(dt: &DateTime<Utc>, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: ser::Serializer,
+{
+    match to_millis(dt) {
+        Some(millis) => serializer.serialize_i64(millis),
+        None => Err(ser::Error::custom(format!(
+            "{} cannot be represented as a timestamp in milliseconds",
+            dt
+        ))),
+    }
+}
+
+struct MillisecondsTimestampVisitor;
+
+impl<'de> de::Visitor<'de> for MillisecondsTimestampVisitor {
+    type Value = DateTime<Utc>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(formatter, "a unix timestamp in milliseconds")
+    }
+
+    fn visit_i64<E>(self, value: i64) -> Result<Self::Value, E>
     where
-        D: de::Deserializer<'de>,
+        E: de::Error,
     {
-        Ok(r#try!(d
-            .deserialize_i64(MillisecondsTimestampVisitor)
-            .map(|dt| dt.with_timezone(&Utc))))
+        // Round towards negative infinity, such that the sub-second part is never negative
+        let secs = value.div_euclid(1000);
+        let nanos = value.rem_euclid(1000) as u32 * 1_000_000;
+        from(Utc.timestamp_opt(secs, nanos), &value)
     }
 
-    /// Serialize a UTC datetime into an integer number of milliseconds since the epoch
-    ///
-    /// Intended for use with `serde`s `serialize_with` attribute.
-    ///
-    /// # Example:
-    ///
-    /// ```rust
-    /// # // We mark this ignored so that we can test on 1.13 (which does not
-    /// # // support custom derive), and run tests with --ignored on beta and
-    /// # // nightly to actually trigger these.
-    /// #
-    /// # #[macro_use] extern crate serde_derive;
-    /// # #[macro_use] extern crate serde_json;
-    /// # extern crate chrono;
-    /// # use chrono::{TimeZone, DateTime, Utc};
-    /// use chrono::serde::ts_seconds::serialize as to_ts;
-    /// #[derive(Serialize)]
-    /// struct S {
-    ///     #[serde(serialize_with = "to_ts")]
-    ///     time: DateTime<Utc>
-    /// }
-    ///
-    /// # fn example() -> Result<String, serde_json::Error> {
-    /// let my_s = S {
-    ///     time: Utc.ymd(2015, 5, 15).and_hms(10, 0, 0),
-    /// };
-    /// let as_string = serde_json::to_string(&my_s)?;
-    /// assert_eq!(as_string, r#"{"time":1431684000}"#);
-    /// # Ok(as_string)
-    /// # }
-    /// # fn main() { example().unwrap(); }
-    /// ```
-    pub fn serialize<S>(dt: &DateTime<Utc>, serializer: S) -> Result<S::Ok, S::Error>
+    fn visit_u64<E>(self, value: u64) -> Result<Self::Value, E>
     where
-        S: ser::Serializer,
+        E: de::Error,
     {
-        serializer.serialize_i64(dt.timestamp() * 1000 + i64::from(dt.timestamp_subsec_millis()))
+        match i64::try_from(value) {
+            Ok(value) => self.visit_i64(value),
+            Err(_) => Err(E::custom(format!(
+                "value is not a legal timestamp: {}",
+                value
+            ))),
+        }
     }
+}
 
-    struct MillisecondsTimestampVisitor;
-
-    impl<'de> de::Visitor<'de> for MillisecondsTimestampVisitor {
-        type Value = DateTime<FixedOffset>;
-
-        fn expecting(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
-            write!(formatter, "a unix timestamp in milliseconds")
-        }
+// try!-like function to convert a LocalResult into a serde-ish Result
+fn from<T, E, V>(me: LocalResult<T>, ts: &V) -> Result<T, E>
+where
+    E: de::Error,
+    V: fmt::Display,
+    T: fmt::Display,
+{
+    match me {
+        LocalResult::None => Err(E::custom(format!("value is not a legal timestamp: {}", ts))),
+        LocalResult::Ambiguous(min, max) => Err(E::custom(format!(
+            "value is an ambiguous timestamp: {}, could be either of {}, {}",
+            ts, min, max
+        ))),
+        LocalResult::Single(val) => Ok(val),
+    }
+}
 
-        /// Deserialize a timestamp in seconds since the epoch
-        fn visit_i64<E>(self, value: i64) -> Result<DateTime<FixedOffset>, E>
-        where
-            E: de::Error,
-        {
-            from(
-                FixedOffset::east(0).timestamp_opt(value / 1000, (value % 1000 * 1_000_000) as u32),
-                &value,
-            )
-        }
+/// A `DateTime<Utc>` as milliseconds timestamp
+///
+/// Despite the name, the timestamps are in milliseconds, like everywhere in the Mattermost API.
+/// Intended for use with `serde`s `with` attribute.
+pub mod ts_seconds {
+    use super::MillisecondsTimestampVisitor;
+    use chrono::{DateTime, Utc};
+    use serde::{de, ser};
 
-        /// Deserialize a timestamp in seconds since the epoch
-        fn visit_u64<E>(self, value: u64) -> Result<DateTime<FixedOffset>, E>
-        where
-            E: de::Error,
-        {
-            from(
-                FixedOffset::east(0)
-                    .timestamp_opt((value / 1000) as i64, (value % 1000 * 1_000_000) as u32),
-                &value,
-            )
-        }
+    pub fn deserialize<'de, D>(d: D) -> Result<DateTime<Utc>, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        d.deserialize_i64(MillisecondsTimestampVisitor)
     }
 
-    // try!-like function to convert a LocalResult into a serde-ish Result
-    fn from<T, E, V>(me: LocalResult<T>, ts: &V) -> Result<T, E>
+    pub fn serialize<S>(dt: &DateTime<Utc>, serializer: S) -> Result<S::Ok, S::Error>
     where
-        E: de::Error,
-        V: fmt::Display,
-        T: fmt::Display,
+        S: ser::Serializer,
     {
-        match me {
-            LocalResult::None => Err(E::custom(format!("value is not a legal timestamp: {}", ts))),
-            LocalResult::Ambiguous(min, max) => Err(E::custom(format!(
-                "value is an ambiguous timestamp: {}, could be either of {}, {}",
-                ts, min, max
-            ))),
-            LocalResult::Single(val) => Ok(val),
-        }
+        super::serialize_millis(dt, serializer)
     }
 }
 
+/// An `Option<DateTime<Utc>>` as milliseconds timestamp or `null`
+///
+/// Intended for use with `serde`s `with` attribute.
 pub mod option_ts_milliseconds {
-    //FIXME comments
-    use chrono::{
-        offset::{LocalResult, TimeZone},
-        DateTime, FixedOffset, Utc,
-    };
+    use super::MillisecondsTimestampVisitor;
+    use chrono::{DateTime, Utc};
     use serde::{de, ser};
     use std::fmt;
 
-    /// Deserialize a `DateTime` from a milliseconds timestamp
-    ///
-    /// Intended for use with `serde`s `deserialize_with` attribute.
-    ///
-    /// # Example:
-    ///
-    /// ```rust
-    /// # // We mark this ignored so that we can test on 1.13 (which does not
-    /// # // support custom derive), and run tests with --ignored on beta and
-    /// # // nightly to actually trigger these.
-    /// #
-    /// # #[macro_use] extern crate serde_derive;
-    /// # #[macro_use] extern crate serde_json;
-    /// # extern crate chrono;
-    /// # use chrono::{DateTime, Utc};
-    /// use chrono::serde::ts_seconds::deserialize as from_ts;
-    /// #[derive(Deserialize)]
-    /// struct S {
-    ///     #[serde(deserialize_with = "from_ts")]
-    ///     time: DateTime<Utc>
-    /// }
-    ///
-    /// # fn example() -> Result<S, serde_json::Error> {
-    /// let my_s: S = serde_json::from_str(r#"{ "time": 1431684000 }"#)?;
-    /// # Ok(my_s)
-    /// # }
-    /// # fn main() { example().unwrap(); }
-    /// ```
     pub fn deserialize<'de, D>(d: D) -> Result<Option<DateTime<Utc>>, D::Error>
     where
         D: de::Deserializer<'de>,
     {
-        Ok(Some(
-            d.deserialize_i64(MillisecondsTimestampVisitor)
-                .map(|dt| dt.with_timezone(&Utc))?,
-        ))
+        d.deserialize_option(OptionMillisecondsTimestampVisitor)
     }
 
-    /// Serialize a UTC datetime into an integer number of milliseconds since the epoch
-    ///
-    /// Intended for use with `serde`s `serialize_with` attribute.
-    ///
-    /// # Example:
-    ///
-    /// ```rust
-    /// # // We mark this ignored so that we can test on 1.13 (which does not
-    /// # // support custom derive), and run tests with --ignored on beta and
-    /// # // nightly to actually trigger these.
-    /// #
-    /// # #[macro_use] extern crate serde_derive;
-    /// # #[macro_use] extern crate serde_json;
-    /// # extern crate chrono;
-    /// # use chrono::{TimeZone, DateTime, Utc};
-    /// use chrono::serde::ts_seconds::serialize as to_ts;
-    /// #[derive(Serialize)]
-    /// struct S {
-    ///     #[serde(serialize_with = "to_ts")]
-    ///     time: DateTime<Utc>
-    /// }
-    ///
-    /// # fn example() -> Result<String, serde_json::Error> {
-    /// let my_s = S {
-    ///     time: Utc.ymd(2015, 5, 15).and_hms(10, 0, 0),
-    /// };
-    /// let as_string = serde_json::to_string(&my_s)?;
-    /// assert_eq!(as_string, r#"{"time":1431684000}"#);
-    /// # Ok(as_string)
-    /// # }
-    /// # fn main() { example().unwrap(); }
-    /// ```
     pub fn serialize<S>(dt: &Option<DateTime<Utc>>, serializer: S) -> Result<S::Ok, S::Error>
     where
         S: ser::Serializer,
     {
-        if let Some(dt) = *dt {
-            serializer
-                .serialize_i64(dt.timestamp() * 1000 + i64::from(dt.timestamp_subsec_millis()))
-        } else {
-            serializer.serialize_unit()
+        /// Serializes the inner value of `Some`
+        struct Millis<'a>(&'a DateTime<Utc>);
+
+        impl ser::Serialize for Millis<'_> {
+            fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+            where
+                S: ser::Serializer,
+            {
+                super::serialize_millis(self.0, serializer)
+            }
+        }
+
+        match dt {
+            Some(dt) => serializer.serialize_some(&Millis(dt)),
+            None => serializer.serialize_none(),
         }
     }
 
-    struct MillisecondsTimestampVisitor;
+    /// Accepts `null` in addition to timestamps
+    struct OptionMillisecondsTimestampVisitor;
 
-    impl<'de> de::Visitor<'de> for MillisecondsTimestampVisitor {
-        type Value = DateTime<FixedOffset>;
+    impl<'de> de::Visitor<'de> for OptionMillisecondsTimestampVisitor {
+        type Value = Option<DateTime<Utc>>;
 
         fn expecting(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
-            write!(formatter, "a unix timestamp in milliseconds")
+            write!(formatter, "a unix timestamp in milliseconds or null")
         }
 
-        // FIXME comments
-        // also in copy above
-
-        // FIXME convert to self::value above
-
-        /// Deserialize a timestamp in seconds since the epoch
-        fn visit_i64<E>(self, value: i64) -> Result<Self::Value, E>
+        fn visit_none<E>(self) -> Result<Self::Value, E>
         where
             E: de::Error,
         {
-            from(
-                FixedOffset::east(0).timestamp_opt(value / 1000, (value % 1000 * 1_000_000) as u32),
-                &value,
-            )
+            Ok(None)
         }
 
-        /// Deserialize a timestamp in seconds since the epoch
-        fn visit_u64<E>(self, value: u64) -> Result<Self::Value, E>
+        fn visit_unit<E>(self) -> Result<Self::Value, E>
         where
             E: de::Error,
         {
-            from(
-                FixedOffset::east(0)
-                    .timestamp_opt((value / 1000) as i64, (value % 1000 * 1_000_000) as u32),
-                &value,
-            )
+            Ok(None)
+        }
+
+        fn visit_some<D>(self, d: D) -> Result<Self::Value, D::Error>
+        where
+            D: de::Deserializer<'de>,
+        {
+            d.deserialize_i64(MillisecondsTimestampVisitor).map(Some)
         }
     }
+}
+
+/// An `Option<DateTime<Utc>>` as seconds timestamp, where `0` or `null` are `None`
+///
+/// Intended for use with `serde`s `with` attribute.
+pub mod option_ts_seconds {
+    use super::from;
+    use chrono::{offset::TimeZone, DateTime, Utc};
+    use serde::{de, ser, Deserialize};
 
-    // try!-like function to convert a LocalResult into a serde-ish Result
-    fn from<T, E, V>(me: LocalResult<T>, ts: &V) -> Result<T, E>
+    pub fn deserialize<'de, D>(d: D) -> Result<Option<DateTime<Utc>>, D::Error>
     where
-        E: de::Error,
-        V: fmt::Display,
-        T: fmt::Display,
+        D: de::Deserializer<'de>,
     {
-        match me {
-            LocalResult::None => Err(E::custom(format!("value is not a legal timestamp: {}", ts))),
-            LocalResult::Ambiguous(min, max) => Err(E::custom(format!(
-                "value is an ambiguous timestamp: {}, could be either of {}, {}",
-                ts, min, max
-            ))),
-            LocalResult::Single(val) => Ok(val),
+        match Option::<i64>::deserialize(d)? {
+            None | Some(0) => Ok(None),
+            Some(secs) => from(Utc.timestamp_opt(secs, 0), &secs).map(Some),
         }
     }
+
+    pub fn serialize<S>(dt: &Option<DateTime<Utc>>, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: ser::Serializer,
+    {
+        serializer.serialize_i64(dt.map_or(0, |dt| dt.timestamp()))
+    }
 }
@@ -0,0 +1,146 @@
+//! Publish websocket events to message brokers
+//!
+//! The sinks are only available with the corresponding cargo features:
+//!
+//! * `kafka`: [`KafkaSink`]
+//! * `nats`: [`NatsSink`]
+
+use crate::{error::Result, websocket::MessagePush};
+
+/// Destination for a stream of websocket events
+pub trait EventSink {
+    fn publish(&mut self, message: &MessagePush) -> Result<()>;
+
+    /// Block until all published events are delivered
+    fn flush(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(feature = "kafka")]
+pub use self::kafka::KafkaSink;
+#[cfg(feature = "nats")]
+pub use self::nats::NatsSink;
+
+#[cfg(feature = "kafka")]
+mod kafka {
+    use super::EventSink;
+    use crate::{
+        error::{Result, ResultExt},
+        websocket::MessagePush,
+    };
+    use rdkafka::{
+        config::ClientConfig,
+        producer::{BaseProducer, BaseRecord, Producer},
+    };
+    use std::time::Duration;
+
+    /// Publishes events as JSON to a Kafka topic
+    ///
+    /// The channel id of the event is used as record key, such that all events of a channel end up in the same partition.
+    /// Events without a channel, like team or user events, are published without key.
+    pub struct KafkaSink {
+        producer: BaseProducer,
+        topic: String,
+    }
+
+    impl KafkaSink {
+        /// Connect to the Kafka cluster with the comma separated list of `bootstrap_servers`
+        pub fn new<B, T>(bootstrap_servers: B, topic: T) -> Result<KafkaSink>
+        where
+            B: AsRef<str>,
+            T: Into<String>,
+        {
+            let mut config = ClientConfig::new();
+            config.set("bootstrap.servers", bootstrap_servers.as_ref());
+            Self::from_config(&config, topic)
+        }
+
+        /// Create the producer from a custom configuration, e.g., to set up authentication
+        pub fn from_config<T>(config: &ClientConfig, topic: T) -> Result<KafkaSink>
+        where
+            T: Into<String>,
+        {
+            Ok(KafkaSink {
+                producer: config
+                    .create()
+                    .chain_err(|| "Failed to create Kafka producer")?,
+                topic: topic.into(),
+            })
+        }
+    }
+
+    impl EventSink for KafkaSink {
+        fn publish(&mut self, message: &MessagePush) -> Result<()> {
+            let payload = serde_json::to_vec(message)?;
+            let mut record = BaseRecord::to(&self.topic).payload(&payload);
+            if !message.broadcast.channel_id.is_empty() {
+                record = record.key(&message.broadcast.channel_id);
+            }
+            self.producer
+                .send(record)
+                .map_err(|(err, _)| err)
+                .chain_err(|| format!("Failed to publish event to topic '{}'", self.topic))?;
+            // Serve the delivery callbacks, otherwise the internal queue fills up
+            self.producer.poll(Duration::from_secs(0));
+            Ok(())
+        }
+
+        fn flush(&mut self) -> Result<()> {
+            self.producer.flush(Duration::from_secs(30));
+            Ok(())
+        }
+    }
+}
+
+#[cfg(feature = "nats")]
+mod nats {
+    use super::EventSink;
+    use crate::{
+        error::{Result, ResultExt},
+        websocket::MessagePush,
+    };
+
+    /// Publishes events as JSON to a NATS subject
+    ///
+    /// NATS messages have no key, so the channel id is appended to the subject instead, i.e., events are published to `<subject>.<channel id>`.
+    /// Events without a channel, like team or user events, are published to `<subject>`.
+    pub struct NatsSink {
+        connection: ::nats::Connection,
+        subject: String,
+    }
+
+    impl NatsSink {
+        pub fn new<U, S>(url: U, subject: S) -> Result<NatsSink>
+        where
+            U: AsRef<str>,
+            S: Into<String>,
+        {
+            Ok(NatsSink {
+                connection: ::nats::connect(url.as_ref())
+                    .chain_err(|| "Failed to connect to NATS")?,
+                subject: subject.into(),
+            })
+        }
+    }
+
+    impl EventSink for NatsSink {
+        fn publish(&mut self, message: &MessagePush) -> Result<()> {
+            let payload = serde_json::to_vec(message)?;
+            let subject = if message.broadcast.channel_id.is_empty() {
+                self.subject.clone()
+            } else {
+                format!("{}.{}", self.subject, message.broadcast.channel_id)
+            };
+            self.connection
+                .publish(&subject, payload)
+                .chain_err(|| format!("Failed to publish event to subject '{}'", subject))
+        }
+
+        fn flush(&mut self) -> Result<()> {
+            self.connection
+                .flush()
+                .chain_err(|| "Failed to flush NATS connection")
+        }
+    }
+}
@@ -0,0 +1,490 @@
+//! Count the events of the websocket in a sliding window
+//!
+//! An [`EventCounter`] keeps the number of events per event type, per channel, and per sender for the last `window`.
+//! The counts are kept in buckets of a sixtieth of the window, so events leave the window in steps.
+//!
+//! ```
+//! use chrono::{Duration, TimeZone, Utc};
+//! use mattermost_structs::{stats::EventCounter, websocket::MessagePush};
+//!
+//! let push: MessagePush = serde_json::from_str(
+//!     r#"{"event":"typing","data":{"parent_id":"","user_id":"alice"},
+//!         "broadcast":{"omit_users":null,"user_id":"","channel_id":"town-square","team_id":""},"seq":1}"#,
+//! )?;
+//! let start = Utc.with_ymd_and_hms(2024, 1, 1, 12, 0, 0).unwrap();
+//! let mut counter = EventCounter::new(std::time::Duration::from_secs(3600));
+//! counter.record(&push, start);
+//! counter.record(&push, start + Duration::minutes(30));
+//!
+//! let summary = counter.summary(start + Duration::minutes(45));
+//! assert_eq!(summary.total, 2);
+//! assert_eq!(summary.top_senders(1), vec![("alice", 2)]);
+//! // The first event left the window
+//! assert_eq!(counter.summary(start + Duration::minutes(75)).total, 1);
+//! # Ok::<(), serde_json::Error>(())
+//! ```
+
+use crate::{
+    api::{Client, ExportFormat},
+    error::Result,
+    ids::{ChannelId, UserId},
+    websocket::{Events, MessagePush, Post, PostType},
+};
+use chrono::{DateTime, Datelike, Timelike, Utc, Weekday};
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, HashMap, VecDeque},
+    fmt,
+    io::Write,
+    time::Duration,
+};
+
+/// Number of buckets a window is divided into
+const BUCKETS_PER_WINDOW: i64 = 60;
+
+/// Counts of the events in one bucket
+#[derive(Debug, Clone, Default)]
+struct Counts {
+    total: u64,
+    events: BTreeMap<String, u64>,
+    channels: BTreeMap<String, u64>,
+    senders: BTreeMap<String, u64>,
+}
+
+impl Counts {
+    fn add(&mut self, other: &Counts) {
+        self.total += other.total;
+        for (target, source) in [
+            (&mut self.events, &other.events),
+            (&mut self.channels, &other.channels),
+            (&mut self.senders, &other.senders),
+        ] {
+            for (key, count) in source {
+                *target.entry(key.clone()).or_insert(0) += count;
+            }
+        }
+    }
+}
+
+/// Called with the summary of the window, see [`EventCounter::on_summary`]
+type SummaryCallback = Box<dyn FnMut(&EventSummary) + Send>;
+
+/// Sliding-window counts of websocket events
+pub struct EventCounter {
+    window: Duration,
+    /// Length of a bucket in milliseconds
+    bucket_millis: i64,
+    /// Buckets by their index, oldest first
+    buckets: VecDeque<(i64, Counts)>,
+    summary_interval: Duration,
+    last_summary: Option<DateTime<Utc>>,
+    summary_callback: Option<SummaryCallback>,
+}
+
+impl fmt::Debug for EventCounter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EventCounter")
+            .field("window", &self.window)
+            .field("buckets", &self.buckets.len())
+            .field("summary_interval", &self.summary_interval)
+            .field("last_summary", &self.last_summary)
+            .finish()
+    }
+}
+
+impl EventCounter {
+    /// Count the events of the last `window`
+    pub fn new(window: Duration) -> EventCounter {
+        let window_millis = window.as_millis().min(i64::MAX as u128) as i64;
+        EventCounter {
+            window,
+            bucket_millis: (window_millis / BUCKETS_PER_WINDOW).max(1),
+            buckets: VecDeque::new(),
+            summary_interval: window,
+            last_summary: None,
+            summary_callback: None,
+        }
+    }
+
+    /// Call `callback` with the [`summary`](EventCounter::summary) every `interval`
+    ///
+    /// The callback is called by [`record`](EventCounter::record), so it is late if no events arrive.
+    pub fn on_summary<F>(mut self, interval: Duration, callback: F) -> Self
+    where
+        F: FnMut(&EventSummary) + Send + 'static,
+    {
+        self.summary_interval = interval;
+        self.summary_callback = Some(Box::new(callback));
+        self
+    }
+
+    pub fn window(&self) -> Duration {
+        self.window
+    }
+
+    /// Call for every event received on the websocket
+    pub fn handle(&mut self, push: &MessagePush) {
+        self.record(push, Utc::now());
+    }
+
+    /// Count `push` as received at `at`
+    ///
+    /// Events older than the window are ignored.
+    pub fn record(&mut self, push: &MessagePush, at: DateTime<Utc>) {
+        self.expire(at);
+        let index = self.bucket_index(at);
+        if index > self.oldest_index(at) {
+            let position = self
+                .buckets
+                .iter()
+                .position(|(existing, _)| *existing >= index);
+            let counts = match position {
+                Some(position) if self.buckets[position].0 == index => {
+                    &mut self.buckets[position].1
+                }
+                Some(position) => {
+                    self.buckets.insert(position, (index, Counts::default()));
+                    &mut self.buckets[position].1
+                }
+                None => {
+                    self.buckets.push_back((index, Counts::default()));
+                    &mut self.buckets.back_mut().unwrap().1
+                }
+            };
+            counts.total += 1;
+            *counts
+                .events
+                .entry(push.event.name().to_string())
+                .or_insert(0) += 1;
+            if let Some(channel_id) = channel_of(push) {
+                *counts.channels.entry(channel_id.to_string()).or_insert(0) += 1;
+            }
+            if let Some(sender) = sender_of(&push.event) {
+                *counts.senders.entry(sender.to_string()).or_insert(0) += 1;
+            }
+        }
+
+        // The first summary is due one interval after the first event
+        let last = *self.last_summary.get_or_insert(at);
+        let due = (at - last)
+            .to_std()
+            .is_ok_and(|elapsed| elapsed >= self.summary_interval);
+        if due {
+            let summary = self.summary(at);
+            if let Some(callback) = &mut self.summary_callback {
+                callback(&summary);
+            }
+            self.last_summary = Some(at);
+        }
+    }
+
+    /// Counts of the events within the window ending at `now`
+    pub fn summary(&self, now: DateTime<Utc>) -> EventSummary {
+        let oldest = self.bucket_index(now) - BUCKETS_PER_WINDOW;
+        let mut counts = Counts::default();
+        for (_, bucket) in self.buckets.iter().filter(|(index, _)| *index > oldest) {
+            counts.add(bucket);
+        }
+        EventSummary {
+            until: now,
+            window_secs: self.window.as_secs(),
+            total: counts.total,
+            events: counts.events,
+            channels: counts.channels,
+            senders: counts.senders,
+        }
+    }
+
+    fn bucket_index(&self, at: DateTime<Utc>) -> i64 {
+        at.timestamp_millis().div_euclid(self.bucket_millis)
+    }
+
+    /// Index of the newest bucket outside of the window, which ends at `now` or the latest event, whichever is later
+    fn oldest_index(&self, now: DateTime<Utc>) -> i64 {
+        let latest = self.buckets.back().map_or(i64::MIN, |(index, _)| *index);
+        self.bucket_index(now).max(latest) - BUCKETS_PER_WINDOW
+    }
+
+    /// Drop the buckets which left the window
+    fn expire(&mut self, now: DateTime<Utc>) {
+        let oldest = self.oldest_index(now);
+        while matches!(self.buckets.front(), Some((index, _)) if *index <= oldest) {
+            self.buckets.pop_front();
+        }
+    }
+}
+
+/// The channel an event concerns, if any
+fn channel_of(push: &MessagePush) -> Option<&str> {
+    match &push.event {
+        Events::Posted { post, .. }
+        | Events::PostEdited { post }
+        | Events::PostDeleted { post } => Some(&post.channel_id),
+        _ if !push.broadcast.channel_id.is_empty() => Some(&push.broadcast.channel_id),
+        _ => None,
+    }
+}
+
+/// The user who caused an event, for events with an author
+///
+/// The `user_id` of the broadcast is the recipient, so it is not used.
+fn sender_of(event: &Events) -> Option<&str> {
+    match event {
+        Events::Posted { post, .. } | Events::PostEdited { post } => Some(&post.user_id),
+        Events::Typing { user_id, .. } => Some(user_id),
+        Events::ReactionAdded { reaction } | Events::ReactionRemoved { reaction } => {
+            Some(&reaction.user_id)
+        }
+        _ => None,
+    }
+}
+
+/// Event counts of a window, see [`EventCounter::summary`]
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct EventSummary {
+    /// End of the window
+    pub until: DateTime<Utc>,
+    pub window_secs: u64,
+    pub total: u64,
+    /// Counts by [`Events::name`]
+    pub events: BTreeMap<String, u64>,
+    /// Counts by channel id
+    pub channels: BTreeMap<String, u64>,
+    /// Counts by the user id of the author, only for posts, reactions, and typing
+    pub senders: BTreeMap<String, u64>,
+}
+
+impl EventSummary {
+    /// The `n` most frequent event types, the most frequent first
+    pub fn top_events(&self, n: usize) -> Vec<(&str, u64)> {
+        top(&self.events, n)
+    }
+
+    pub fn top_channels(&self, n: usize) -> Vec<(&str, u64)> {
+        top(&self.channels, n)
+    }
+
+    pub fn top_senders(&self, n: usize) -> Vec<(&str, u64)> {
+        top(&self.senders, n)
+    }
+}
+
+/// Ties are ordered by the key, such that the result is stable
+fn top(counts: &BTreeMap<String, u64>, n: usize) -> Vec<(&str, u64)> {
+    let mut entries: Vec<(&str, u64)> = counts
+        .iter()
+        .map(|(key, count)| (key.as_str(), *count))
+        .collect();
+    entries.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+    entries.truncate(n);
+    entries
+}
+
+/// Length of [`ChannelActivity::hour_of_week`]
+pub const HOURS_PER_WEEK: usize = 7 * 24;
+
+/// Number of posts of a user, see [`ChannelActivity::posters`]
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct PosterCount {
+    pub user_id: UserId,
+    /// Only known if looked up by [`ChannelActivity::fetch`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub username: Option<String>,
+    pub posts: u64,
+}
+
+/// When and by whom a channel was used during a time window
+///
+/// ```
+/// use chrono::{Duration, TimeZone, Utc, Weekday};
+/// use mattermost_structs::{stats::ChannelActivity, websocket::Post};
+///
+/// let post: Post = serde_json::from_str(
+///     r#"{"id":"s8bq9xwsbpyc7gjcw4rs7c4qmh","create_at":1704114000000,"update_at":1704114000000,
+///         "edit_at":0,"delete_at":0,"is_pinned":false,"user_id":"alice","channel_id":"town-square",
+///         "root_id":"","original_id":"","message":"hi","type":"","props":{},"hashtags":"","pending_post_id":""}"#,
+/// )?;
+/// let until = Utc.with_ymd_and_hms(2024, 1, 2, 0, 0, 0).unwrap();
+/// let activity = ChannelActivity::from_posts(
+///     "town-square",
+///     until - Duration::days(7),
+///     until,
+///     chrono_tz::Europe::Berlin,
+///     vec![post],
+/// );
+/// // 2024-01-01 13:00 UTC was a Monday, 14:00 in Berlin
+/// assert_eq!(activity.posts_at(Weekday::Mon, 14), 1);
+/// assert_eq!(activity.posters[0].user_id, "alice");
+/// # Ok::<(), serde_json::Error>(())
+/// ```
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct ChannelActivity {
+    pub channel_id: ChannelId,
+    pub since: DateTime<Utc>,
+    pub until: DateTime<Utc>,
+    /// Time zone of [`hour_of_week`](ChannelActivity::hour_of_week)
+    #[serde(with = "::serde_with::rust::display_fromstr")]
+    pub timezone: Tz,
+    /// Number of posts in the time window
+    pub total_posts: u64,
+    /// Posts per hour of the week, starting with Monday 00:00 to 01:00
+    pub hour_of_week: Vec<u64>,
+    /// All users who posted, the most active first
+    pub posters: Vec<PosterCount>,
+}
+
+impl ChannelActivity {
+    /// Count the posts created in `since..until`
+    ///
+    /// Deleted posts and system messages are ignored, like for a [`Digest`](crate::digest::Digest).
+    pub fn from_posts<S, I>(
+        channel_id: S,
+        since: DateTime<Utc>,
+        until: DateTime<Utc>,
+        timezone: Tz,
+        posts: I,
+    ) -> ChannelActivity
+    where
+        S: Into<ChannelId>,
+        I: IntoIterator<Item = Post>,
+    {
+        let mut hour_of_week = vec![0; HOURS_PER_WEEK];
+        let mut posters: HashMap<UserId, u64> = HashMap::new();
+        let mut total_posts = 0;
+        for post in posts {
+            if post.create_at < since
+                || post.create_at >= until
+                || post.is_deleted()
+                || post.type_ != PostType::UserMessage
+            {
+                continue;
+            }
+            total_posts += 1;
+            let local = post.create_at.with_timezone(&timezone);
+            hour_of_week[hour_index(local.weekday(), local.hour())] += 1;
+            *posters.entry(post.user_id).or_insert(0) += 1;
+        }
+
+        let mut posters: Vec<PosterCount> = posters
+            .into_iter()
+            .map(|(user_id, posts)| PosterCount {
+                user_id,
+                username: None,
+                posts,
+            })
+            .collect();
+        // Ties are ordered by the user id, such that the result is stable
+        posters.sort_by(|a, b| b.posts.cmp(&a.posts).then(a.user_id.cmp(&b.user_id)));
+        ChannelActivity {
+            channel_id: channel_id.into(),
+            since,
+            until,
+            timezone,
+            total_posts,
+            hour_of_week,
+            posters,
+        }
+    }
+
+    /// Fetch the posts of the channel and count them, including the usernames of the posters
+    ///
+    /// The posts are fetched page by page, newest first, until a page reaches back to `since`.
+    pub fn fetch<S>(
+        client: &Client,
+        channel_id: S,
+        since: DateTime<Utc>,
+        until: DateTime<Utc>,
+        timezone: Tz,
+    ) -> Result<ChannelActivity>
+    where
+        S: AsRef<ChannelId>,
+    {
+        // Queries with `since` return at most 1000 posts, so page instead
+        const PER_PAGE: usize = 200;
+
+        let channel_id = channel_id.as_ref();
+        let mut posts = Vec::new();
+        for page in 0.. {
+            let list = client
+                .get_posts_for_channel(channel_id, page, PER_PAGE)?
+                .into_ordered();
+            let count = list.len();
+            let reached_since = list
+                .last()
+                .map(|post| post.create_at < since)
+                .unwrap_or(true);
+            posts.extend(list);
+            if count < PER_PAGE || reached_since {
+                break;
+            }
+        }
+        let mut activity =
+            ChannelActivity::from_posts(channel_id.clone(), since, until, timezone, posts);
+
+        let user_ids: Vec<UserId> = activity
+            .posters
+            .iter()
+            .map(|poster| poster.user_id.clone())
+            .collect();
+        if !user_ids.is_empty() {
+            let usernames: HashMap<UserId, String> = client
+                .get_users_by_id(&user_ids)?
+                .into_iter()
+                .map(|user| (user.id, user.username))
+                .collect();
+            for poster in &mut activity.posters {
+                poster.username = usernames.get(&poster.user_id).cloned();
+            }
+        }
+        Ok(activity)
+    }
+
+    /// Posts during the hour starting at `hour` on `weekday`, in [`timezone`](ChannelActivity::timezone)
+    pub fn posts_at(&self, weekday: Weekday, hour: u32) -> u64 {
+        self.hour_of_week[hour_index(weekday, hour)]
+    }
+
+    /// Only keep the `n` most active posters
+    pub fn truncate_posters(&mut self, n: usize) {
+        self.posters.truncate(n);
+    }
+
+    /// Write the activity as JSON object or as CSV
+    ///
+    /// The CSV has the columns `kind`, `key`, and `posts`.
+    /// The first 168 records are of kind `hour_of_week` with keys like `Mon 09`, followed by one record of kind `poster` per poster.
+    /// Posters are keyed by their username, if known, otherwise by their user id.
+    pub fn write<W>(&self, mut writer: W, format: ExportFormat) -> Result<()>
+    where
+        W: Write,
+    {
+        match format {
+            ExportFormat::Json => {
+                serde_json::to_writer_pretty(&mut writer, self)?;
+                writeln!(writer)?;
+            }
+            ExportFormat::Csv => {
+                // Neither the keys nor usernames and ids can contain characters which need quoting
+                writeln!(writer, "kind,key,posts")?;
+                let mut weekday = Weekday::Mon;
+                for day in self.hour_of_week.chunks(24) {
+                    for (hour, posts) in day.iter().enumerate() {
+                        writeln!(writer, "hour_of_week,{} {:02},{}", weekday, hour, posts)?;
+                    }
+                    weekday = weekday.succ();
+                }
+                for poster in &self.posters {
+                    let key = poster.username.as_deref().unwrap_or(&poster.user_id);
+                    writeln!(writer, "poster,{},{}", key, poster.posts)?;
+                }
+            }
+        }
+        writer.flush()?;
+        Ok(())
+    }
+}
+
+fn hour_index(weekday: Weekday, hour: u32) -> usize {
+    weekday.num_days_from_monday() as usize * 24 + hour as usize
+}
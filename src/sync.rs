@@ -0,0 +1,119 @@
+//! Remember how far incremental syncs got
+//!
+//! Endpoints like [`Client::get_posts_for_channel_since`](crate::api::Client::get_posts_for_channel_since) only return what changed after a point in time.
+//! A [`SyncCursor`] stores this point per synced object, e.g., per channel, in a file, such that a mirror can resume after a restart.
+
+use crate::error::Result;
+use chrono::{DateTime, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    fs,
+    io::ErrorKind,
+    path::{Path, PathBuf},
+};
+
+/// Last sync points by key, persisted as JSON
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct SyncCursor {
+    path: Option<PathBuf>,
+    positions: BTreeMap<String, DateTime<Utc>>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct StoredCursor {
+    /// Milliseconds since the epoch by key
+    positions: BTreeMap<String, i64>,
+}
+
+impl SyncCursor {
+    /// A cursor which is only kept in memory
+    pub fn new() -> SyncCursor {
+        Self::default()
+    }
+
+    /// Load the cursor from `path`, starting empty if the file does not exist yet
+    pub fn load<P>(path: P) -> Result<SyncCursor>
+    where
+        P: Into<PathBuf>,
+    {
+        let path = path.into();
+        let positions = match fs::read(&path) {
+            Ok(data) => {
+                let stored: StoredCursor = serde_json::from_slice(&data)?;
+                stored
+                    .positions
+                    .into_iter()
+                    .map(|(key, ms)| {
+                        let time = Utc
+                            .timestamp_millis_opt(ms)
+                            .single()
+                            .ok_or_else(|| format!("Invalid sync point {} for '{}'", ms, key))?;
+                        Ok((key, time))
+                    })
+                    .collect::<Result<_>>()?
+            }
+            Err(ref err) if err.kind() == ErrorKind::NotFound => BTreeMap::new(),
+            Err(err) => return Err(err.into()),
+        };
+        Ok(SyncCursor {
+            path: Some(path),
+            positions,
+        })
+    }
+
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    /// Point after which changes of `key` still need to be synced, `None` if it was never synced
+    pub fn since<K>(&self, key: K) -> Option<DateTime<Utc>>
+    where
+        K: AsRef<str>,
+    {
+        self.positions.get(key.as_ref()).copied()
+    }
+
+    /// Move the sync point of `key` forward to `time`
+    ///
+    /// Earlier times are ignored, such that out-of-order results cannot cause changes to be synced twice.
+    pub fn advance<K>(&mut self, key: K, time: DateTime<Utc>)
+    where
+        K: Into<String>,
+    {
+        let position = self.positions.entry(key.into()).or_insert(time);
+        if *position < time {
+            *position = time;
+        }
+    }
+
+    /// Forget the sync point of `key`, such that the next sync starts from scratch
+    pub fn reset<K>(&mut self, key: K)
+    where
+        K: AsRef<str>,
+    {
+        self.positions.remove(key.as_ref());
+    }
+
+    /// Write the cursor to its file, if it has one
+    ///
+    /// The file is replaced atomically, such that a crash cannot leave a truncated cursor behind.
+    pub fn save(&self) -> Result<()> {
+        let path = match &self.path {
+            Some(path) => path,
+            None => return Ok(()),
+        };
+        let stored = StoredCursor {
+            positions: self
+                .positions
+                .iter()
+                .map(|(key, time)| (key.clone(), time.timestamp_millis()))
+                .collect(),
+        };
+        let mut tmp = path.clone().into_os_string();
+        tmp.push(".tmp");
+        fs::write(&tmp, serde_json::to_vec_pretty(&stored)?)?;
+        fs::rename(&tmp, path)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,478 @@
+//! Record HTTP interactions with a real server and replay them in tests
+//!
+//! A [`CassetteServer`] is a small HTTP server on localhost, which the [`Client`](crate::api::Client) uses as its base URL.
+//! When recording, it forwards all requests to the real server and records the requests with their responses in a [`Cassette`].
+//! When replaying, it answers the requests with the recorded responses, without any network access.
+//! Cassettes are stored as YAML, such that responses of different server versions can be kept side by side.
+//!
+//! Tokens are never recorded, neither the `Authorization` header nor the `Token` and `Set-Cookie` headers of responses.
+//! Bodies must be UTF-8, so binary up- and downloads cannot be recorded.
+//!
+//! ```
+//! # fn main() -> mattermost_structs::Result<()> {
+//! use mattermost_structs::{
+//!     api::Client,
+//!     ids::UserId,
+//!     testing::cassette::{Cassette, CassetteServer, Interaction},
+//! };
+//!
+//! let mut cassette = Cassette::default();
+//! cassette.interactions.push(Interaction::json(
+//!     "GET",
+//!     "/api/v4/users/me",
+//!     200,
+//!     include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/golden/user.json")),
+//! ));
+//! let server = CassetteServer::replay(cassette)?;
+//! let client = Client::new(server.base_url(), "token")?;
+//! assert_eq!(client.get_me()?.username, "alice");
+//! assert!(client.get_user(UserId::from("someone else")).is_err());
+//! assert_eq!(server.unmatched().len(), 1);
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::error::{Result, ResultExt};
+use log::{debug, warn};
+use reqwest::{Client as WebClient, Method};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::{
+    collections::BTreeMap,
+    fs::{self, File},
+    io::{BufRead, BufReader, Read, Write},
+    net::{SocketAddr, TcpListener, TcpStream},
+    path::Path,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    thread::{self, JoinHandle},
+};
+use url::Url;
+
+/// Headers which are not recorded, because they contain secrets or describe the connection
+const SKIPPED_HEADERS: &[&str] = &[
+    "accept-encoding",
+    "authorization",
+    "connection",
+    "content-encoding",
+    "content-length",
+    "host",
+    "set-cookie",
+    "token",
+    "transfer-encoding",
+];
+
+/// A request as recorded in a [`Cassette`]
+#[derive(Debug, Clone, Eq, PartialEq, Deserialize, Serialize)]
+pub struct RecordedRequest {
+    pub method: String,
+    /// Path and query of the URL
+    pub path: String,
+    /// A replayed request only matches if its body is the same, JSON bodies are compared as JSON
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub body: Option<String>,
+}
+
+impl RecordedRequest {
+    fn matches(&self, request: &RecordedRequest) -> bool {
+        if self.method != request.method || self.path != request.path {
+            return false;
+        }
+        match (&self.body, &request.body) {
+            (None, _) => true,
+            (Some(recorded), Some(body)) => {
+                match (
+                    serde_json::from_str::<Value>(recorded),
+                    serde_json::from_str::<Value>(body),
+                ) {
+                    (Ok(recorded), Ok(body)) => recorded == body,
+                    _ => recorded == body,
+                }
+            }
+            (Some(_), None) => false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Deserialize, Serialize)]
+pub struct RecordedResponse {
+    pub status: u16,
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+    #[serde(default)]
+    pub body: String,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Deserialize, Serialize)]
+pub struct Interaction {
+    pub request: RecordedRequest,
+    pub response: RecordedResponse,
+}
+
+impl Interaction {
+    /// A request without body answered with a JSON response
+    pub fn json<M, P, B>(method: M, path: P, status: u16, body: B) -> Interaction
+    where
+        M: Into<String>,
+        P: Into<String>,
+        B: Into<String>,
+    {
+        let mut headers = BTreeMap::new();
+        headers.insert("content-type".to_string(), "application/json".to_string());
+        Interaction {
+            request: RecordedRequest {
+                method: method.into(),
+                path: path.into(),
+                body: None,
+            },
+            response: RecordedResponse {
+                status,
+                headers,
+                body: body.into(),
+            },
+        }
+    }
+}
+
+/// Recorded interactions with a server
+#[derive(Debug, Clone, Default, Eq, PartialEq, Deserialize, Serialize)]
+pub struct Cassette {
+    /// Version of the recorded server, from the `X-Version-Id` header
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub server_version: Option<String>,
+    pub interactions: Vec<Interaction>,
+}
+
+impl Cassette {
+    pub fn load<P>(path: P) -> Result<Cassette>
+    where
+        P: AsRef<Path>,
+    {
+        let path = path.as_ref();
+        let file =
+            File::open(path).chain_err(|| format!("Cannot open cassette {}", path.display()))?;
+        Ok(serde_yaml::from_reader(file)?)
+    }
+
+    pub fn save<P>(&self, path: P) -> Result<()>
+    where
+        P: AsRef<Path>,
+    {
+        fs::write(path, serde_yaml::to_string(self)?)?;
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+enum Mode {
+    /// Forward to the server at the URL
+    Record(Url, WebClient),
+    Replay,
+}
+
+#[derive(Debug)]
+struct State {
+    mode: Mode,
+    cassette: Cassette,
+    /// Whether each interaction was replayed already
+    used: Vec<bool>,
+    unmatched: Vec<RecordedRequest>,
+}
+
+impl State {
+    fn handle(
+        &mut self,
+        request: RecordedRequest,
+        headers: &[(String, String)],
+    ) -> RecordedResponse {
+        match &self.mode {
+            Mode::Record(upstream, client) => {
+                let response = forward(upstream, client, &request, headers).unwrap_or_else(|err| {
+                    warn!(
+                        "Cassette: forwarding {} {} failed: {}",
+                        request.method, request.path, err
+                    );
+                    error_response(502, &err.to_string())
+                });
+                if self.cassette.server_version.is_none() {
+                    self.cassette.server_version = response.headers.get("x-version-id").cloned();
+                }
+                self.cassette.interactions.push(Interaction {
+                    request,
+                    response: response.clone(),
+                });
+                response
+            }
+            Mode::Replay => {
+                let interactions = &self.cassette.interactions;
+                let matching = |i: &usize| interactions[*i].request.matches(&request);
+                // Replay the interactions in order, repeat the last one if all were used
+                let found = (0..interactions.len())
+                    .filter(matching)
+                    .find(|i| !self.used[*i])
+                    .or_else(|| (0..interactions.len()).rev().find(matching));
+                match found {
+                    Some(i) => {
+                        self.used[i] = true;
+                        interactions[i].response.clone()
+                    }
+                    None => {
+                        debug!(
+                            "Cassette: no interaction for {} {}",
+                            request.method, request.path
+                        );
+                        self.unmatched.push(request);
+                        error_response(404, "No recorded interaction matches the request")
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Response with an error body like the ones of the server
+fn error_response(status: u16, message: &str) -> RecordedResponse {
+    let mut headers = BTreeMap::new();
+    headers.insert("content-type".to_string(), "application/json".to_string());
+    RecordedResponse {
+        status,
+        headers,
+        body: json!({
+            "id": "testing.cassette.app_error",
+            "message": message,
+            "status_code": status,
+        })
+        .to_string(),
+    }
+}
+
+fn forward(
+    upstream: &Url,
+    client: &WebClient,
+    request: &RecordedRequest,
+    headers: &[(String, String)],
+) -> Result<RecordedResponse> {
+    let method = Method::from_bytes(request.method.as_bytes())
+        .chain_err(|| format!("Invalid method {}", request.method))?;
+    let mut builder = client.request(method, upstream.join(&request.path)?);
+    for (name, value) in headers {
+        if name == "authorization" || !SKIPPED_HEADERS.contains(&name.as_str()) {
+            builder = builder.header(name.as_str(), value.as_str());
+        }
+    }
+    if let Some(body) = &request.body {
+        builder = builder.body(body.clone());
+    }
+    let mut res = builder.send().chain_err(|| "Failed to send webrequest")?;
+    let headers = res
+        .headers()
+        .iter()
+        .filter(|(name, _)| !SKIPPED_HEADERS.contains(&name.as_str()))
+        .filter_map(|(name, value)| Some((name.to_string(), value.to_str().ok()?.to_string())))
+        .collect();
+    Ok(RecordedResponse {
+        status: res.status().as_u16(),
+        headers,
+        body: res.text()?,
+    })
+}
+
+/// HTTP server recording or replaying a [`Cassette`], see the [module documentation](self)
+///
+/// The server listens on a random port on localhost and stops when dropped.
+pub struct CassetteServer {
+    addr: SocketAddr,
+    state: Arc<Mutex<State>>,
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl CassetteServer {
+    /// Forward all requests to the server at `upstream` and record them
+    pub fn record<U>(upstream: U) -> Result<CassetteServer>
+    where
+        U: AsRef<str>,
+    {
+        let upstream = Url::parse(upstream.as_ref())?;
+        Self::start(
+            Mode::Record(upstream, WebClient::new()),
+            Cassette::default(),
+        )
+    }
+
+    /// Answer the requests with the responses of `cassette`
+    ///
+    /// Each request is answered with the first unused interaction with the same method, path, and body.
+    /// Once all matching interactions were used, the last one is repeated.
+    /// Requests without any matching interaction fail with `404 Not Found`.
+    pub fn replay(cassette: Cassette) -> Result<CassetteServer> {
+        Self::start(Mode::Replay, cassette)
+    }
+
+    fn start(mode: Mode, cassette: Cassette) -> Result<CassetteServer> {
+        let listener =
+            TcpListener::bind("127.0.0.1:0").chain_err(|| "Failed to start the cassette server")?;
+        let addr = listener.local_addr()?;
+        let state = Arc::new(Mutex::new(State {
+            mode,
+            used: vec![false; cassette.interactions.len()],
+            cassette,
+            unmatched: Vec::new(),
+        }));
+        let stop = Arc::new(AtomicBool::new(false));
+        let thread = {
+            let state = state.clone();
+            let stop = stop.clone();
+            thread::spawn(move || {
+                for stream in listener.incoming() {
+                    if stop.load(Ordering::SeqCst) {
+                        break;
+                    }
+                    let state = state.clone();
+                    match stream {
+                        Ok(stream) => {
+                            thread::spawn(move || {
+                                if let Err(err) = serve(stream, &state) {
+                                    debug!("Cassette: connection failed: {}", err);
+                                }
+                            });
+                        }
+                        Err(err) => warn!("Cassette: accepting a connection failed: {}", err),
+                    }
+                }
+            })
+        };
+        Ok(CassetteServer {
+            addr,
+            state,
+            stop,
+            thread: Some(thread),
+        })
+    }
+
+    /// Base URL as used for [`Client::new`](crate::api::Client::new)
+    pub fn base_url(&self) -> String {
+        format!("http://{}", self.addr)
+    }
+
+    /// The interactions recorded so far, or the replayed cassette
+    pub fn cassette(&self) -> Cassette {
+        self.state.lock().unwrap().cassette.clone()
+    }
+
+    /// Replayed requests which matched no interaction
+    pub fn unmatched(&self) -> Vec<RecordedRequest> {
+        self.state.lock().unwrap().unmatched.clone()
+    }
+
+    /// Whether every interaction of the cassette was replayed at least once
+    pub fn all_used(&self) -> bool {
+        self.state.lock().unwrap().used.iter().all(|used| *used)
+    }
+}
+
+impl Drop for CassetteServer {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::SeqCst);
+        // Wake up the listener, which then sees the stop flag
+        let _ = TcpStream::connect(self.addr);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// Answer the requests of one connection, until the client closes it
+fn serve(stream: TcpStream, state: &Mutex<State>) -> Result<()> {
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut writer = stream;
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 {
+            return Ok(());
+        }
+        let mut parts = line.split_whitespace();
+        let (method, path) = match (parts.next(), parts.next()) {
+            (Some(method), Some(path)) => (method.to_string(), path.to_string()),
+            _ => return Err(format!("Invalid request line {:?}", line).into()),
+        };
+
+        let mut headers = Vec::new();
+        loop {
+            let mut line = String::new();
+            reader.read_line(&mut line)?;
+            let line = line.trim_end();
+            if line.is_empty() {
+                break;
+            }
+            if let Some(pos) = line.find(':') {
+                headers.push((
+                    line[..pos].trim().to_ascii_lowercase(),
+                    line[pos + 1..].trim().to_string(),
+                ));
+            }
+        }
+        let header = |name: &str| {
+            headers
+                .iter()
+                .find(|(key, _)| key == name)
+                .map(|(_, value)| value.as_str())
+        };
+        let body = if header("transfer-encoding").is_some_and(|te| te.contains("chunked")) {
+            read_chunked(&mut reader)?
+        } else {
+            let length = header("content-length").map_or(Ok(0), str::parse::<usize>);
+            let mut body = vec![0; length.chain_err(|| "Invalid Content-Length")?];
+            reader.read_exact(&mut body)?;
+            body
+        };
+        let close = header("connection").is_some_and(|c| c.eq_ignore_ascii_case("close"));
+
+        let request = RecordedRequest {
+            method,
+            path,
+            body: if body.is_empty() {
+                None
+            } else {
+                Some(String::from_utf8_lossy(&body).into_owned())
+            },
+        };
+        let response = state.lock().unwrap().handle(request, &headers);
+
+        write!(writer, "HTTP/1.1 {} Cassette\r\n", response.status)?;
+        for (name, value) in &response.headers {
+            write!(writer, "{}: {}\r\n", name, value)?;
+        }
+        write!(
+            writer,
+            "content-length: {}\r\n\r\n{}",
+            response.body.len(),
+            response.body
+        )?;
+        writer.flush()?;
+        if close {
+            return Ok(());
+        }
+    }
+}
+
+fn read_chunked<R>(reader: &mut R) -> Result<Vec<u8>>
+where
+    R: BufRead,
+{
+    let mut body = Vec::new();
+    loop {
+        let mut line = String::new();
+        reader.read_line(&mut line)?;
+        let size = line.trim().split(';').next().unwrap_or("");
+        let size = usize::from_str_radix(size, 16).chain_err(|| "Invalid chunk size")?;
+        let mut chunk = vec![0; size + 2];
+        reader.read_exact(&mut chunk)?;
+        if size == 0 {
+            return Ok(body);
+        }
+        body.extend_from_slice(&chunk[..size]);
+    }
+}
@@ -0,0 +1,385 @@
+//! Builders for model types with sensible defaults
+//!
+//! All fixtures start out with unique ids and fixed timestamps, such that only the fields relevant for a test need to be set.
+//!
+//! ```
+//! use mattermost_structs::testing::fixtures::{MessagePushFixture, PostFixture};
+//!
+//! let post = PostFixture::new().message("hi").in_channel("c1").build();
+//! let push = MessagePushFixture::posted(post).build();
+//! assert_eq!(push.broadcast.channel_id, "c1");
+//! ```
+
+use crate::{
+    api::{Channel, ChannelType, User, UserRole},
+    ids::{ChannelId, PostId, TeamId, UserId},
+    websocket::{Broadcast, Events, MessagePush, Post, PostProps, PostType},
+};
+use chrono::{DateTime, TimeZone, Utc};
+use std::{
+    collections::HashSet,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+/// Unique id with the same length as the ids of the server
+fn next_id<T>() -> T
+where
+    T: From<String>,
+{
+    static COUNTER: AtomicUsize = AtomicUsize::new(1);
+    format!("fixture{:019}", COUNTER.fetch_add(1, Ordering::Relaxed)).into()
+}
+
+/// Timestamp used for all creation times, 2019-01-01T00:00:00Z
+fn default_time() -> DateTime<Utc> {
+    Utc.timestamp_millis_opt(1_546_300_800_000).unwrap()
+}
+
+/// The server uses 0 for timestamps which are not set
+fn unset_time() -> DateTime<Utc> {
+    Utc.timestamp_millis_opt(0).unwrap()
+}
+
+#[derive(Debug, Clone)]
+pub struct PostFixture {
+    post: Post,
+}
+
+impl PostFixture {
+    pub fn new() -> PostFixture {
+        PostFixture {
+            post: Post {
+                id: next_id(),
+                create_at: default_time(),
+                update_at: default_time(),
+                edit_at: unset_time(),
+                delete_at: unset_time(),
+                is_pinned: false,
+                user_id: next_id(),
+                channel_id: next_id(),
+                root_id: PostId::default(),
+                parent_id: PostId::default(),
+                original_id: PostId::default(),
+                message: String::new(),
+                message_source: None,
+                type_: PostType::UserMessage,
+                props: PostProps::default(),
+                hashtags: HashSet::new(),
+                pending_post_id: String::new(),
+                file_ids: Vec::new(),
+                has_reactions: None,
+                metadata: None,
+                reply_count: None,
+                remote_id: None,
+            },
+        }
+    }
+
+    pub fn id<S>(mut self, id: S) -> Self
+    where
+        S: Into<PostId>,
+    {
+        self.post.id = id.into();
+        self
+    }
+
+    pub fn message<S>(mut self, message: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.post.message = message.into();
+        self
+    }
+
+    pub fn in_channel<S>(mut self, channel_id: S) -> Self
+    where
+        S: Into<ChannelId>,
+    {
+        self.post.channel_id = channel_id.into();
+        self
+    }
+
+    pub fn by_user<S>(mut self, user_id: S) -> Self
+    where
+        S: Into<UserId>,
+    {
+        self.post.user_id = user_id.into();
+        self
+    }
+
+    /// Make the post a reply in the thread of `root_id`
+    pub fn reply_to<S>(mut self, root_id: S) -> Self
+    where
+        S: Into<PostId>,
+    {
+        self.post.root_id = root_id.into();
+        self
+    }
+
+    pub fn type_(mut self, type_: PostType) -> Self {
+        self.post.type_ = type_;
+        self
+    }
+
+    pub fn created_at(mut self, time: DateTime<Utc>) -> Self {
+        self.post.create_at = time;
+        self.post.update_at = time;
+        self
+    }
+
+    pub fn edited_at(mut self, time: DateTime<Utc>) -> Self {
+        self.post.edit_at = time;
+        self.post.update_at = time;
+        self
+    }
+
+    pub fn deleted_at(mut self, time: DateTime<Utc>) -> Self {
+        self.post.delete_at = time;
+        self.post.update_at = time;
+        self
+    }
+
+    pub fn build(self) -> Post {
+        self.post
+    }
+}
+
+impl Default for PostFixture {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct UserFixture {
+    user: User,
+}
+
+impl UserFixture {
+    /// A user with the role `system_user` and a unique username
+    pub fn new() -> UserFixture {
+        let id: UserId = next_id();
+        let mut roles = HashSet::new();
+        roles.insert(UserRole::SystemUser);
+        UserFixture {
+            user: User {
+                create_at: default_time(),
+                update_at: default_time(),
+                delete_at: unset_time(),
+                username: format!("user-{}", id),
+                first_name: String::new(),
+                last_name: String::new(),
+                nickname: String::new(),
+                email: format!("{}@example.com", id),
+                email_verified: Some(true),
+                auth_data: String::new(),
+                auth_service: String::new(),
+                position: String::new(),
+                roles,
+                locale: "en".to_string(),
+                last_password_update: None,
+                last_picture_update: None,
+                failed_attempts: None,
+                mfa_active: None,
+                timezone: None,
+                remote_id: None,
+                id,
+            },
+        }
+    }
+
+    pub fn id<S>(mut self, id: S) -> Self
+    where
+        S: Into<UserId>,
+    {
+        self.user.id = id.into();
+        self
+    }
+
+    pub fn username<S>(mut self, username: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.user.username = username.into();
+        self
+    }
+
+    pub fn email<S>(mut self, email: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.user.email = email.into();
+        self
+    }
+
+    pub fn name<F, L>(mut self, first_name: F, last_name: L) -> Self
+    where
+        F: Into<String>,
+        L: Into<String>,
+    {
+        self.user.first_name = first_name.into();
+        self.user.last_name = last_name.into();
+        self
+    }
+
+    pub fn role(mut self, role: UserRole) -> Self {
+        self.user.roles.insert(role);
+        self
+    }
+
+    pub fn deleted_at(mut self, time: DateTime<Utc>) -> Self {
+        self.user.delete_at = time;
+        self
+    }
+
+    pub fn build(self) -> User {
+        self.user
+    }
+}
+
+impl Default for UserFixture {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ChannelFixture {
+    channel: Channel,
+}
+
+impl ChannelFixture {
+    /// An open channel
+    pub fn new() -> ChannelFixture {
+        let id: ChannelId = next_id();
+        ChannelFixture {
+            channel: Channel {
+                create_at: default_time(),
+                update_at: default_time(),
+                delete_at: unset_time(),
+                team_id: next_id(),
+                type_: ChannelType::Open,
+                display_name: format!("Channel {}", id),
+                name: format!("channel-{}", id),
+                header: String::new(),
+                purpose: String::new(),
+                last_post_at: default_time(),
+                total_msg_count: 0,
+                extra_update_at: default_time(),
+                creator_id: next_id(),
+                shared: None,
+                scheme_id: None,
+                id,
+            },
+        }
+    }
+
+    pub fn id<S>(mut self, id: S) -> Self
+    where
+        S: Into<ChannelId>,
+    {
+        self.channel.id = id.into();
+        self
+    }
+
+    pub fn in_team<S>(mut self, team_id: S) -> Self
+    where
+        S: Into<TeamId>,
+    {
+        self.channel.team_id = team_id.into();
+        self
+    }
+
+    pub fn display_name<S>(mut self, display_name: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.channel.display_name = display_name.into();
+        self
+    }
+
+    pub fn type_(mut self, type_: ChannelType) -> Self {
+        self.channel.type_ = type_;
+        self
+    }
+
+    pub fn build(self) -> Channel {
+        self.channel
+    }
+}
+
+impl Default for ChannelFixture {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct MessagePushFixture {
+    message: MessagePush,
+}
+
+impl MessagePushFixture {
+    /// An event broadcast to nobody in particular
+    pub fn new(event: Events) -> MessagePushFixture {
+        MessagePushFixture {
+            message: MessagePush {
+                event,
+                broadcast: Broadcast::default(),
+                seq: 1,
+                raw: None,
+            },
+        }
+    }
+
+    /// A `posted` event for `post` in an open channel, broadcast to the channel of the post
+    pub fn posted(post: Post) -> MessagePushFixture {
+        let channel_id = post.channel_id.clone();
+        MessagePushFixture::new(Events::Posted {
+            channel_display_name: "Channel".to_string(),
+            channel_name: "channel".to_string(),
+            channel_type: ChannelType::Open,
+            sender_name: format!("@{}", post.user_id),
+            team_id: String::new(),
+            mentions: None,
+            image: None,
+            other_file: None,
+            post,
+        })
+        .channel_id(channel_id)
+    }
+
+    pub fn channel_id<S>(mut self, channel_id: S) -> Self
+    where
+        S: Into<ChannelId>,
+    {
+        self.message.broadcast.channel_id = channel_id.into();
+        self
+    }
+
+    pub fn team_id<S>(mut self, team_id: S) -> Self
+    where
+        S: Into<TeamId>,
+    {
+        self.message.broadcast.team_id = team_id.into();
+        self
+    }
+
+    /// Broadcast the event only to this user
+    pub fn user_id<S>(mut self, user_id: S) -> Self
+    where
+        S: Into<UserId>,
+    {
+        self.message.broadcast.user_id = user_id.into();
+        self
+    }
+
+    pub fn seq(mut self, seq: usize) -> Self {
+        self.message.seq = seq;
+        self
+    }
+
+    pub fn build(self) -> MessagePush {
+        self.message
+    }
+}
@@ -0,0 +1,9 @@
+//! Helpers for testing code built on this crate
+//!
+//! Only available with the cargo feature `testing`.
+
+pub mod cassette;
+pub mod fixtures;
+mod server;
+
+pub use self::server::FakeServer;
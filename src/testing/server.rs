@@ -0,0 +1,385 @@
+use crate::{
+    error::{Result, ResultExt},
+    websocket::{Broadcast, Events, MessagePush},
+};
+use log::{debug, warn};
+use serde_json::{json, Value};
+use std::{
+    collections::VecDeque,
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+    thread::{self, JoinHandle},
+};
+use ws::{CloseCode, Handler, Handshake, Message, Sender, WebSocket};
+
+/// Version reported in the `hello` event
+const SERVER_VERSION: &str = "6.0.0.6.0.0.fake.fake.false";
+
+/// Number of events kept per session for resuming it, like the dead queue of the server
+const HISTORY_SIZE: usize = 128;
+
+/// Minimal in-process websocket server speaking the Mattermost protocol
+///
+/// The server accepts the `authentication_challenge` with the configured token, answers it with a reply and a `hello` event,
+/// and answers all other actions with an `OK` reply.
+/// Connections with the token in the `Authorization` header or the `access_token` query parameter are authenticated right away.
+/// Events injected with [`FakeServer::send_event`] are delivered to all authenticated connections.
+///
+/// Like the server since version 6.0, a closed connection can be resumed with the `connection_id` of its `hello` and the `sequence_number` of the next expected event.
+/// The events sent in between are replayed before the `hello`, as long as they are among the last 128 events of the connection.
+///
+/// The server listens on a random port on localhost and stops when dropped.
+pub struct FakeServer {
+    addr: SocketAddr,
+    broadcaster: Sender,
+    state: Arc<Mutex<State>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+#[derive(Debug)]
+struct State {
+    token: String,
+    user_id: String,
+    connections: Vec<Connection>,
+    /// Sessions of closed connections, which can be resumed
+    closed: Vec<Session>,
+    /// Number of sessions started so far
+    sessions: usize,
+    /// All messages received from clients
+    received: Vec<Value>,
+}
+
+/// Sequence and recent events of a connection, kept after it closed
+#[derive(Debug)]
+struct Session {
+    id: String,
+    /// Sequence number of the next event
+    seq: usize,
+    history: VecDeque<MessagePush>,
+}
+
+impl Session {
+    fn new(id: String) -> Session {
+        Session {
+            id,
+            seq: 0,
+            history: VecDeque::new(),
+        }
+    }
+
+    fn next_event(&mut self, event: Events, broadcast: Broadcast) -> MessagePush {
+        let message = MessagePush {
+            event,
+            broadcast,
+            seq: self.seq,
+            raw: None,
+        };
+        self.seq += 1;
+        message
+    }
+
+    /// Keep the event for replaying it after a resume
+    fn record(&mut self, message: &MessagePush) {
+        if self.history.len() == HISTORY_SIZE {
+            self.history.pop_front();
+        }
+        self.history.push_back(message.clone());
+    }
+
+    /// Whether all events starting at `seq` are still known
+    fn can_resume_from(&self, seq: usize) -> bool {
+        seq == self.seq
+            || (seq < self.seq && self.history.front().is_some_and(|first| first.seq <= seq))
+    }
+}
+
+#[derive(Debug)]
+struct Connection {
+    out: Sender,
+    authenticated: bool,
+    session: Session,
+    /// Sequence number to replay the history from, once authenticated
+    resume_from: Option<usize>,
+}
+
+impl Connection {
+    fn send_event(
+        &mut self,
+        event: Events,
+        broadcast: Broadcast,
+    ) -> std::result::Result<(), Box<ws::Error>> {
+        let message = self.session.next_event(event, broadcast);
+        if !matches!(message.event, Events::Hello { .. }) {
+            self.session.record(&message);
+        }
+        self.send(&message)
+    }
+
+    fn send(&self, message: &MessagePush) -> std::result::Result<(), Box<ws::Error>> {
+        let text = serde_json::to_string(message)
+            .map_err(|err| ws::Error::new(ws::ErrorKind::Internal, err.to_string()))?;
+        Ok(self.out.send(text)?)
+    }
+}
+
+impl FakeServer {
+    /// Start a server accepting `token` and identifying all clients as `user_id`
+    pub fn start<T, U>(token: T, user_id: U) -> Result<FakeServer>
+    where
+        T: Into<String>,
+        U: Into<String>,
+    {
+        let state = Arc::new(Mutex::new(State {
+            token: token.into(),
+            user_id: user_id.into(),
+            connections: Vec::new(),
+            closed: Vec::new(),
+            sessions: 0,
+            received: Vec::new(),
+        }));
+        let factory_state = state.clone();
+        let socket = WebSocket::new(move |out| ConnectionHandler {
+            out,
+            state: factory_state.clone(),
+        })
+        .chain_err(|| "Failed to start the fake websocket server")?
+        .bind("127.0.0.1:0")
+        .chain_err(|| "Failed to start the fake websocket server")?;
+        let addr = socket.local_addr()?;
+        let broadcaster = socket.broadcaster();
+        let thread = thread::spawn(move || {
+            if let Err(err) = socket.run() {
+                warn!("Fake websocket server failed: {}", err);
+            }
+        });
+
+        Ok(FakeServer {
+            addr,
+            broadcaster,
+            state,
+            thread: Some(thread),
+        })
+    }
+
+    /// Base URL as used for [`Client::new`](crate::api::Client::new)
+    pub fn base_url(&self) -> String {
+        format!("http://{}", self.addr)
+    }
+
+    /// URL of the websocket endpoint
+    pub fn websocket_url(&self) -> String {
+        format!("ws://{}/api/v4/websocket", self.addr)
+    }
+
+    /// Number of open connections, authenticated or not
+    pub fn connection_count(&self) -> usize {
+        self.state.lock().unwrap().connections.len()
+    }
+
+    /// Send `event` to all authenticated connections
+    ///
+    /// Closed connections miss the event, but receive it when they are resumed.
+    /// Returns the number of connections the event was sent to.
+    pub fn send_event(&self, event: Events, broadcast: Broadcast) -> Result<usize> {
+        let mut state = self.state.lock().unwrap();
+        let mut count = 0;
+        for connection in state.connections.iter_mut().filter(|c| c.authenticated) {
+            connection
+                .send_event(event.clone(), broadcast.clone())
+                .chain_err(|| "Failed to send event")?;
+            count += 1;
+        }
+        for session in &mut state.closed {
+            let message = session.next_event(event.clone(), broadcast.clone());
+            session.record(&message);
+        }
+        Ok(count)
+    }
+
+    /// Send a text message as is to all connections, e.g., to test unparsable messages
+    pub fn send_raw<S>(&self, text: S) -> Result<()>
+    where
+        S: Into<String>,
+    {
+        self.broadcaster
+            .send(text.into())
+            .chain_err(|| "Failed to send message")
+    }
+
+    /// Close all connections, e.g., to test reconnecting
+    pub fn disconnect_all(&self) -> Result<()> {
+        self.broadcaster
+            .close(CloseCode::Away)
+            .chain_err(|| "Failed to close connections")
+    }
+
+    /// All messages the clients sent so far, like the `authentication_challenge`
+    pub fn received(&self) -> Vec<Value> {
+        self.state.lock().unwrap().received.clone()
+    }
+}
+
+impl Drop for FakeServer {
+    fn drop(&mut self) {
+        let _ = self.broadcaster.shutdown();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+struct ConnectionHandler {
+    out: Sender,
+    state: Arc<Mutex<State>>,
+}
+
+impl ConnectionHandler {
+    /// Mark the connection as authenticated and greet it with `hello`
+    fn authenticate(&self, state: &mut State) -> std::result::Result<(), Box<ws::Error>> {
+        let user_id = state.user_id.clone();
+        let id = self.out.connection_id();
+        if let Some(connection) = state
+            .connections
+            .iter_mut()
+            .find(|c| c.out.connection_id() == id)
+        {
+            connection.authenticated = true;
+            // The server replays the missed events before the `hello`
+            if let Some(seq) = connection.resume_from.take() {
+                for message in connection.session.history.iter().filter(|m| m.seq >= seq) {
+                    connection.send(message)?;
+                }
+            }
+            let session_id = connection.session.id.clone();
+            connection.send_event(
+                Events::Hello {
+                    server_version: SERVER_VERSION.to_string(),
+                    connection_id: Some(session_id),
+                },
+                Broadcast {
+                    user_id: user_id.into(),
+                    ..Broadcast::default()
+                },
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Value of the query parameter `key` of the request
+fn query_param(request: &ws::Request, key: &str) -> Option<String> {
+    let url = url::Url::parse("ws://localhost")
+        .ok()?
+        .join(request.resource())
+        .ok()?;
+    url.query_pairs()
+        .find(|(k, _)| k == key)
+        .map(|(_, value)| value.into_owned())
+}
+
+/// Token sent in the `Authorization` header or the `access_token` query parameter
+fn token_of_request(request: &ws::Request) -> Option<String> {
+    if let Some(header) = request.header("authorization") {
+        let header = String::from_utf8_lossy(header);
+        let mut parts = header.splitn(2, ' ');
+        if let (Some(scheme), Some(token)) = (parts.next(), parts.next()) {
+            if scheme.eq_ignore_ascii_case("bearer") {
+                return Some(token.to_string());
+            }
+        }
+    }
+    query_param(request, "access_token")
+}
+
+impl Handler for ConnectionHandler {
+    fn on_open(&mut self, handshake: Handshake) -> ws::Result<()> {
+        let mut state = self.state.lock().unwrap();
+        let resume_id = query_param(&handshake.request, "connection_id");
+        let resume_seq = query_param(&handshake.request, "sequence_number")
+            .and_then(|seq| seq.parse::<usize>().ok());
+        let resumable = state.closed.iter().position(|session| {
+            Some(&session.id) == resume_id.as_ref()
+                && resume_seq.is_some_and(|seq| session.can_resume_from(seq))
+        });
+        let (session, resume_from) = match resumable {
+            Some(index) => (state.closed.remove(index), resume_seq),
+            None => {
+                state.sessions += 1;
+                let id = format!("fake-connection-{}", state.sessions);
+                (Session::new(id), None)
+            }
+        };
+        state.connections.push(Connection {
+            out: self.out.clone(),
+            authenticated: false,
+            session,
+            resume_from,
+        });
+        if token_of_request(&handshake.request).as_deref() == Some(&*state.token) {
+            self.authenticate(&mut state).map_err(|err| *err)?;
+        }
+        Ok(())
+    }
+
+    fn on_message(&mut self, msg: Message) -> ws::Result<()> {
+        let text = match msg {
+            Message::Text(text) => text,
+            Message::Binary(_) => return Ok(()),
+        };
+        let request: Value = match serde_json::from_str(&text) {
+            Ok(request) => request,
+            Err(err) => {
+                debug!("Fake server received invalid JSON: {}", err);
+                return self.out.close(CloseCode::Invalid);
+            }
+        };
+        let seq = request.get("seq").cloned().unwrap_or(Value::Null);
+        let action = request
+            .get("action")
+            .and_then(Value::as_str)
+            .unwrap_or("")
+            .to_string();
+
+        let mut state = self.state.lock().unwrap();
+        state.received.push(request.clone());
+
+        if action != "authentication_challenge" {
+            return self
+                .out
+                .send(json!({"status": "OK", "seq_reply": seq}).to_string());
+        }
+
+        let token = request.pointer("/data/token").and_then(Value::as_str);
+        if token != Some(&*state.token) {
+            self.out.send(
+                json!({
+                    "status": "FAIL",
+                    "seq_reply": seq,
+                    "error": {
+                        "id": "api.web_socket_router.not_authenticated.app_error",
+                        "message": "Invalid or expired session, please login again."
+                    }
+                })
+                .to_string(),
+            )?;
+            return self.out.close(CloseCode::Policy);
+        }
+
+        self.out
+            .send(json!({"status": "OK", "seq_reply": seq}).to_string())?;
+        self.authenticate(&mut state).map_err(|err| *err)
+    }
+
+    fn on_close(&mut self, _: CloseCode, _: &str) {
+        let id = self.out.connection_id();
+        let mut state = self.state.lock().unwrap();
+        if let Some(index) = state
+            .connections
+            .iter()
+            .position(|c| c.out.connection_id() == id)
+        {
+            let connection = state.connections.remove(index);
+            state.closed.push(connection.session);
+        }
+    }
+}
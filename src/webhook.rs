@@ -0,0 +1,121 @@
+//! Receive outgoing webhooks
+//!
+//! Mattermost posts to the URL of an outgoing webhook whenever a message in the configured channels starts with a trigger word.
+//! Depending on the content type configured for the webhook, the body is form encoded or JSON.
+//! The service answers with an [`OutgoingWebhookResponse`], which the server posts as reply.
+
+use crate::{
+    command::verify_token,
+    error::{Result, ResultExt},
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Body of an outgoing webhook request
+#[derive(Debug, Deserialize, Serialize, Clone, Eq, PartialEq)]
+pub struct OutgoingWebhook {
+    /// Secret of the webhook, see [`OutgoingWebhook::verify`]
+    pub token: String,
+    pub team_id: String,
+    pub team_domain: String,
+    pub channel_id: String,
+    pub channel_name: String,
+    #[serde(with = "crate::serialize::ts_seconds")]
+    pub timestamp: DateTime<Utc>,
+    pub user_id: String,
+    pub user_name: String,
+    pub post_id: String,
+    /// The complete message, including the trigger word
+    pub text: String,
+    /// Empty if the webhook triggers on all messages of a channel
+    #[serde(default)]
+    pub trigger_word: String,
+    /// Comma separated ids of the attached files
+    #[serde(default)]
+    pub file_ids: String,
+}
+
+impl OutgoingWebhook {
+    /// Parse a request with content type `application/x-www-form-urlencoded`
+    pub fn from_form(body: &[u8]) -> Result<OutgoingWebhook> {
+        serde_urlencoded::from_bytes(body).chain_err(|| "Invalid outgoing webhook")
+    }
+
+    /// Parse a request with content type `application/json`
+    pub fn from_json(body: &[u8]) -> Result<OutgoingWebhook> {
+        serde_json::from_slice(body).chain_err(|| "Invalid outgoing webhook")
+    }
+
+    /// Check that the request carries the token of the webhook
+    ///
+    /// Without this check anybody who knows the URL of the service can fake requests.
+    pub fn verify(&self, token: &str) -> Result<()> {
+        if verify_token(token, &self.token) {
+            Ok(())
+        } else {
+            Err("The outgoing webhook has an invalid token".into())
+        }
+    }
+
+    /// The message without the trigger word
+    pub fn text_without_trigger(&self) -> &str {
+        self.text
+            .strip_prefix(&*self.trigger_word)
+            .unwrap_or(&self.text)
+            .trim_start()
+    }
+
+    pub fn file_ids(&self) -> impl Iterator<Item = &str> {
+        self.file_ids.split(',').filter(|id| !id.is_empty())
+    }
+}
+
+/// Where the server places the reply
+#[derive(
+    Debug, Default, Deserialize, Serialize, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum WebhookResponseType {
+    /// A new post in the channel
+    #[default]
+    Post,
+    /// A reply in the thread of the triggering post
+    Comment,
+}
+
+/// Reply to an [`OutgoingWebhook`]
+///
+/// The username and icon are only used if the server allows overriding them.
+#[derive(Debug, Deserialize, Serialize, Clone, Eq, PartialEq, Default)]
+pub struct OutgoingWebhookResponse {
+    pub text: String,
+    #[serde(default)]
+    pub response_type: WebhookResponseType,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub username: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub icon_url: Option<String>,
+}
+
+impl OutgoingWebhookResponse {
+    pub fn post<S>(text: S) -> OutgoingWebhookResponse
+    where
+        S: Into<String>,
+    {
+        OutgoingWebhookResponse {
+            text: text.into(),
+            ..Default::default()
+        }
+    }
+
+    pub fn comment<S>(text: S) -> OutgoingWebhookResponse
+    where
+        S: Into<String>,
+    {
+        OutgoingWebhookResponse {
+            text: text.into(),
+            response_type: WebhookResponseType::Comment,
+            ..Default::default()
+        }
+    }
+}
@@ -1,44 +1,235 @@
+mod client;
+
+pub(crate) use self::client::websocket_url;
+pub use self::client::{Client, EventHandler, Messages};
 use crate::{
-    api::{Channel, ChannelType, User, UserRole},
+    api::{
+        Channel, ChannelBookmark, ChannelType, ScheduledPost, UpdateChannelBookmarkResponse, User,
+        UserRole,
+    },
+    error::{self, Error},
+    ids::{ChannelId, PostId, TeamId, UserId},
     serialize,
 };
 use chrono::prelude::{DateTime, Utc};
-use serde::{Deserialize, Serialize};
-use std::collections::{BTreeMap, HashMap, HashSet};
+use flate2::read::{GzDecoder, ZlibDecoder};
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use serde_json::Value;
+use std::{
+    borrow::Cow,
+    cmp::Ordering,
+    collections::{BTreeMap, HashMap, HashSet},
+    hash::{Hash, Hasher},
+    io::Read,
+};
+
+/// How a websocket connection authenticates
+#[derive(Debug, Default, Deserialize, Serialize, Copy, Clone, Eq, PartialEq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum AuthMethod {
+    /// Send an `authentication_challenge` action as first message after connecting
+    #[default]
+    Challenge,
+    /// Send the token in the `Authorization` header of the upgrade request
+    ///
+    /// This avoids relying on the ordering of the first frames, which some proxies do not preserve.
+    Header,
+    /// Send the token as `access_token` query parameter, for proxies which strip the `Authorization` header
+    ///
+    /// The token may end up in the access logs of proxies.
+    Query,
+}
+
+impl AuthMethod {
+    /// The `authentication_challenge` action to send after connecting, if any
+    pub fn challenge(self, token: &str, seq: usize) -> Option<String> {
+        match self {
+            AuthMethod::Challenge => Some(
+                serde_json::json!({
+                    "seq": seq,
+                    "action": "authentication_challenge",
+                    "data": {"token": token},
+                })
+                .to_string(),
+            ),
+            AuthMethod::Header | AuthMethod::Query => None,
+        }
+    }
+
+    /// Header to add to the upgrade request, if any
+    pub fn header(self, token: &str) -> Option<(&'static str, String)> {
+        match self {
+            AuthMethod::Header => Some(("Authorization", format!("Bearer {}", token))),
+            AuthMethod::Challenge | AuthMethod::Query => None,
+        }
+    }
+
+    /// Add the query parameter to the websocket URL, if needed
+    pub fn prepare_url(self, url: &mut url::Url, token: &str) {
+        if let AuthMethod::Query = self {
+            url.query_pairs_mut().append_pair("access_token", token);
+        }
+    }
+}
+
+/// Largest decompressed binary frame, larger ones are rejected instead of filling the memory
+pub const MAX_DECOMPRESSED_FRAME: u64 = 16 * 1024 * 1024;
+
+/// Decode the payload of a binary websocket frame into the JSON text of a message
+///
+/// The server only sends text frames, but some proxies forward them as binary frames, possibly compressed.
+/// Gzip and zlib compressed payloads are decompressed up to [`MAX_DECOMPRESSED_FRAME`] bytes, all others must be UTF-8.
+///
+/// ```
+/// # use flate2::{write::GzEncoder, Compression};
+/// # use mattermost_structs::websocket::{decode_binary_frame, MAX_DECOMPRESSED_FRAME};
+/// # use std::io::Write;
+/// let mut encoder = GzEncoder::new(Vec::new(), Compression::best());
+/// encoder.write_all(&vec![b' '; MAX_DECOMPRESSED_FRAME as usize + 1]).unwrap();
+/// let bomb = encoder.finish().unwrap();
+/// assert!(decode_binary_frame(&bomb).is_err());
+/// ```
+pub fn decode_binary_frame(data: &[u8]) -> error::Result<String> {
+    let mut text = String::new();
+    // One byte more than allowed, to tell a frame of exactly the maximum size from a larger one
+    let limit = MAX_DECOMPRESSED_FRAME + 1;
+    let decompressed = match data {
+        [0x1f, 0x8b, ..] => GzDecoder::new(data).take(limit).read_to_string(&mut text),
+        // zlib header: deflate with a check value in the second byte
+        [0x78, flags, ..] if (0x7800 | u16::from(*flags)) % 31 == 0 => {
+            ZlibDecoder::new(data).take(limit).read_to_string(&mut text)
+        }
+        _ => {
+            return String::from_utf8(data.to_vec())
+                .map_err(|err| Error::UndecodableFrame(err.to_string()))
+        }
+    };
+    decompressed.map_err(|err| Error::UndecodableFrame(err.to_string()))?;
+    if text.len() as u64 > MAX_DECOMPRESSED_FRAME {
+        return Err(Error::UndecodableFrame(format!(
+            "decompresses to more than {} bytes",
+            MAX_DECOMPRESSED_FRAME
+        )));
+    }
+    Ok(text)
+}
 
 #[allow(clippy::large_enum_variant)]
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Eq, PartialEq, Hash, Serialize, Deserialize)]
 #[serde(untagged)]
 pub enum Message {
     Push(MessagePush),
     Reply(MessageReply),
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+impl Message {
+    /// Parse a websocket message
+    ///
+    /// If `keep_raw` is set, the text of push messages is kept in [`MessagePush::raw`].
+    /// Otherwise this is the same as [`serde_json::from_str`].
+    pub fn parse(text: &str, keep_raw: bool) -> serde_json::Result<Message> {
+        let mut message = serde_json::from_str(text)?;
+        if keep_raw {
+            if let Message::Push(push) = &mut message {
+                push.raw = Some(text.into());
+            }
+        }
+        Ok(message)
+    }
+}
+
+/// An event pushed by the server
+///
+/// Equality, hashing, and ordering ignore [`raw`](MessagePush::raw), such that a message compares equal to its parsed copy.
+/// As the data of plugin events is arbitrary JSON, hashing and ordering only use the sequence number, the broadcast, and the event name.
+/// Messages are ordered by their sequence number first, i.e., in the order a connection receives them.
+/// Messages which differ only in their event data are ordered as equal although they are not `==`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
 // #[serde(untagged)]
 pub struct MessagePush {
     #[serde(flatten)]
     pub event: Events,
     pub broadcast: Broadcast,
     pub seq: usize,
+    /// The message exactly as received, see [`Message::parse`]
+    #[serde(skip)]
+    pub raw: Option<Box<str>>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+impl PartialEq for MessagePush {
+    fn eq(&self, other: &Self) -> bool {
+        self.seq == other.seq && self.broadcast == other.broadcast && self.event == other.event
+    }
+}
+
+impl Eq for MessagePush {}
+
+impl Hash for MessagePush {
+    /// Only hashes the event name, as the event data cannot be hashed
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.seq.hash(state);
+        self.broadcast.hash(state);
+        self.event.name().hash(state);
+    }
+}
+
+impl PartialOrd for MessagePush {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for MessagePush {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (self.seq, &self.broadcast, self.event.name()).cmp(&(
+            other.seq,
+            &other.broadcast,
+            other.event.name(),
+        ))
+    }
+}
+
+/// Answer of the server to a request sent over the websocket
+///
+/// ```
+/// # use mattermost_structs::websocket::{Message, MessageStatus};
+/// let text = r#"{"status":"FAIL","seq_reply":1,"error":{"id":"api.web_socket_router.not_authenticated.app_error","message":"Invalid or expired session, please login again."}}"#;
+/// match Message::parse(text, false).unwrap() {
+///     Message::Reply(reply) => {
+///         assert_eq!(reply.status, MessageStatus::Fail);
+///         assert_eq!(reply.error.unwrap().id, "api.web_socket_router.not_authenticated.app_error");
+///     }
+///     Message::Push(_) => panic!("not a reply"),
+/// }
+/// ```
+#[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Serialize, Deserialize)]
 // #[serde(untagged)]
 pub struct MessageReply {
     pub status: MessageStatus,
     pub seq_reply: usize,
+    /// Why the request failed, only set for [`MessageStatus::Fail`]
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub error: Option<ReplyError>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Serialize, Deserialize)]
 #[serde(rename_all = "SCREAMING_SNAKE_CASE", deny_unknown_fields)]
 pub enum MessageStatus {
     Ok,
+    Fail,
+}
+
+/// Error of a failed [`MessageReply`]
+#[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Serialize, Deserialize)]
+pub struct ReplyError {
+    pub id: String,
+    pub message: String,
 }
 
 #[allow(clippy::large_enum_variant)]
 #[derive(Debug, Deserialize, Serialize, Clone, Eq, PartialEq)]
 #[serde(
+    remote = "Self",
     tag = "event",
     content = "data",
     deny_unknown_fields,
@@ -47,11 +238,11 @@ pub enum MessageStatus {
 pub enum Events {
     Hello {
         server_version: String,
+        /// Id to resume the connection with, see [`crate::resume`] (server version 6.0 and newer)
+        #[serde(skip_serializing_if = "Option::is_none", default)]
+        connection_id: Option<String>,
     },
-    StatusChange {
-        status: Status,
-        user_id: String,
-    },
+    StatusChange(UserStatus),
     EphemeralMessage {
         #[serde(with = "::serde_with::json::nested")]
         post: Post,
@@ -73,11 +264,12 @@ pub enum Events {
             skip_serializing_if = "Option::is_none",
             with = "::serde_with::json::nested"
         )]
-        mentions: Option<Vec<String>>,
+        mentions: Option<Vec<UserId>>,
         // TODO this might also be a boolean
+        #[serde(skip_serializing_if = "Option::is_none", default)]
         image: Option<String>,
         // TODO this might also be a boolean
-        #[serde(rename = "otherFile")]
+        #[serde(rename = "otherFile", skip_serializing_if = "Option::is_none", default)]
         other_file: Option<String>,
     },
     ReactionAdded {
@@ -123,11 +315,24 @@ pub enum Events {
         #[serde(with = "::serde_with::json::nested")]
         emoji: Emoji,
     },
+    /// The channel was archived
     ChannelDeleted {
         channel_id: String,
-        #[serde(with = "serialize::option_ts_milliseconds", default)]
+        #[serde(
+            skip_serializing_if = "Option::is_none",
+            with = "serialize::option_ts_milliseconds",
+            default
+        )]
         delete_at: Option<DateTime<Utc>>,
     },
+    /// An archived channel was restored
+    ChannelRestored {
+        channel_id: String,
+    },
+    /// A public channel was converted into a private channel
+    ChannelConverted {
+        channel_id: String,
+    },
     DirectAdded {
         teammate_id: String,
     },
@@ -162,15 +367,322 @@ pub enum Events {
         #[serde(rename = "channelMember", with = "::serde_with::json::nested")]
         channel_member: ChannelMember,
     },
+    ChannelBookmarkCreated {
+        #[serde(with = "::serde_with::json::nested")]
+        bookmark: ChannelBookmark,
+    },
+    ChannelBookmarkUpdated {
+        #[serde(with = "::serde_with::json::nested")]
+        bookmarks: UpdateChannelBookmarkResponse,
+    },
+    ChannelBookmarkDeleted {
+        #[serde(with = "::serde_with::json::nested")]
+        bookmark: ChannelBookmark,
+    },
+    ChannelBookmarkSorted {
+        #[serde(with = "::serde_with::json::nested")]
+        bookmarks: Vec<ChannelBookmark>,
+    },
+    ScheduledPostCreated {
+        #[serde(rename = "scheduledPost", with = "::serde_with::json::nested")]
+        scheduled_post: ScheduledPost,
+    },
+    ScheduledPostUpdated {
+        #[serde(rename = "scheduledPost", with = "::serde_with::json::nested")]
+        scheduled_post: ScheduledPost,
+    },
+    ScheduledPostDeleted {
+        #[serde(rename = "scheduledPost", with = "::serde_with::json::nested")]
+        scheduled_post: ScheduledPost,
+    },
+    LicenseChanged {
+        license: ClientLicense,
+    },
+    /// Events sent by plugins, like Playbooks or Boards
+    ///
+    /// The event names have the form `custom_<plugin id>_<event>`.
+    /// The payload is kept as is, since it is defined by the plugin.
+    #[serde(skip)]
+    PluginCustom {
+        event: String,
+        data: Value,
+    },
+    /// Events about shared channels and the remote clusters they are shared with
+    ///
+    /// The event names have the form `sharedchannel_<event>`.
+    /// The payload is kept as is, since it changes between server versions.
+    #[serde(skip)]
+    SharedChannel {
+        event: String,
+        data: Value,
+    },
+    /// Events which are not modelled (yet), e.g., because they were added in a newer server version
+    ///
+    /// The payload is kept as is.
+    /// Events with a known name but an unexpected payload still fail to parse.
+    #[serde(skip)]
+    Unknown {
+        event: String,
+        data: Value,
+    },
 }
 
-#[derive(Debug, Deserialize, Serialize, Clone, Eq, PartialEq)]
+impl Events {
+    /// The name of the event as sent by the server, e.g., `posted`
+    pub fn name(&self) -> &str {
+        match self {
+            Events::Hello { .. } => "hello",
+            Events::StatusChange { .. } => "status_change",
+            Events::EphemeralMessage { .. } => "ephemeral_message",
+            Events::Typing { .. } => "typing",
+            Events::Posted { .. } => "posted",
+            Events::ReactionAdded { .. } => "reaction_added",
+            Events::PostEdited { .. } => "post_edited",
+            Events::ChannelCreated { .. } => "channel_created",
+            Events::PreferencesChanged { .. } => "preferences_changed",
+            Events::UserUpdated { .. } => "user_updated",
+            Events::PostDeleted { .. } => "post_deleted",
+            Events::ChannelViewed { .. } => "channel_viewed",
+            Events::PreferencesDeleted { .. } => "preferences_deleted",
+            Events::ChannelUpdated { .. } => "channel_updated",
+            Events::ReactionRemoved { .. } => "reaction_removed",
+            Events::NewUser { .. } => "new_user",
+            Events::EmojiAdded { .. } => "emoji_added",
+            Events::ChannelDeleted { .. } => "channel_deleted",
+            Events::ChannelRestored { .. } => "channel_restored",
+            Events::ChannelConverted { .. } => "channel_converted",
+            Events::DirectAdded { .. } => "direct_added",
+            Events::UpdateTeam { .. } => "update_team",
+            Events::UserAdded { .. } => "user_added",
+            Events::UserRemoved { .. } => "user_removed",
+            Events::LeaveTeam { .. } => "leave_team",
+            Events::ConfigChanged { .. } => "config_changed",
+            Events::GroupAdded { .. } => "group_added",
+            Events::DeleteTeam { .. } => "delete_team",
+            Events::ChannelMemberUpdated { .. } => "channel_member_updated",
+            Events::ChannelBookmarkCreated { .. } => "channel_bookmark_created",
+            Events::ChannelBookmarkUpdated { .. } => "channel_bookmark_updated",
+            Events::ChannelBookmarkDeleted { .. } => "channel_bookmark_deleted",
+            Events::ChannelBookmarkSorted { .. } => "channel_bookmark_sorted",
+            Events::ScheduledPostCreated { .. } => "scheduled_post_created",
+            Events::ScheduledPostUpdated { .. } => "scheduled_post_updated",
+            Events::ScheduledPostDeleted { .. } => "scheduled_post_deleted",
+            Events::LicenseChanged { .. } => "license_changed",
+            Events::PluginCustom { event, .. }
+            | Events::SharedChannel { event, .. }
+            | Events::Unknown { event, .. } => event,
+        }
+    }
+}
+
+/// Names of the events modelled by [`Events`], all other names become [`Events::Unknown`]
+///
+/// Must match [`Events::name`].
+const KNOWN_EVENTS: &[&str] = &[
+    "hello",
+    "status_change",
+    "ephemeral_message",
+    "typing",
+    "posted",
+    "reaction_added",
+    "post_edited",
+    "channel_created",
+    "preferences_changed",
+    "user_updated",
+    "post_deleted",
+    "channel_viewed",
+    "preferences_deleted",
+    "channel_updated",
+    "reaction_removed",
+    "new_user",
+    "emoji_added",
+    "channel_deleted",
+    "channel_restored",
+    "channel_converted",
+    "direct_added",
+    "update_team",
+    "user_added",
+    "user_removed",
+    "leave_team",
+    "config_changed",
+    "group_added",
+    "delete_team",
+    "channel_member_updated",
+    "channel_bookmark_created",
+    "channel_bookmark_updated",
+    "channel_bookmark_deleted",
+    "channel_bookmark_sorted",
+    "scheduled_post_created",
+    "scheduled_post_updated",
+    "scheduled_post_deleted",
+    "license_changed",
+];
+
+/// The raw form of [`Events`] as it appears in the json
+#[derive(Deserialize, Serialize)]
+struct RawEvent<'a> {
+    event: Cow<'a, str>,
+    #[serde(default)]
+    data: Cow<'a, Value>,
+}
+
+impl<'de> Deserialize<'de> for Events {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let raw = RawEvent::deserialize(deserializer)?;
+        if raw.event.starts_with("custom_") {
+            return Ok(Events::PluginCustom {
+                event: raw.event.into_owned(),
+                data: raw.data.into_owned(),
+            });
+        }
+        if raw.event.starts_with("sharedchannel_") {
+            return Ok(Events::SharedChannel {
+                event: raw.event.into_owned(),
+                data: raw.data.into_owned(),
+            });
+        }
+        if !KNOWN_EVENTS.contains(&&*raw.event) {
+            return Ok(Events::Unknown {
+                event: raw.event.into_owned(),
+                data: raw.data.into_owned(),
+            });
+        }
+        // Use the derived implementation for all other events
+        let value = serde_json::to_value(&raw).map_err(de::Error::custom)?;
+        Events::deserialize(value).map_err(de::Error::custom)
+    }
+}
+
+impl Serialize for Events {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match self {
+            Events::PluginCustom { event, data }
+            | Events::SharedChannel { event, data }
+            | Events::Unknown { event, data } => RawEvent {
+                event: Cow::Borrowed(event),
+                data: Cow::Borrowed(data),
+            }
+            .serialize(serializer),
+            _ => Events::serialize(self, serializer),
+        }
+    }
+}
+
+/// Parse a websocket message and report all fields which are ignored by the models
+///
+/// `callback` is called with the path of every ignored field, e.g., `data.post.props.some_field`.
+/// Payloads which are JSON encoded inside of strings, like the `post` of [`Events::Posted`], are inspected too.
+/// Models which deny unknown fields still fail to parse, with the field named in the error.
+///
+/// Only available with the cargo feature `ignored-fields`.
+#[cfg(feature = "ignored-fields")]
+pub fn parse_message_reporting_ignored<F>(
+    text: &str,
+    mut callback: F,
+) -> serde_json::Result<Message>
+where
+    F: FnMut(String),
+{
+    fn probe<T>(prefix: &str, value: Value, callback: &mut dyn FnMut(String))
+    where
+        T: de::DeserializeOwned,
+    {
+        let _: Result<T, _> = serde_ignored::deserialize(value, |path| {
+            callback(format!("{}.{}", prefix, path));
+        });
+    }
+
+    let value: Value = serde_json::from_str(text)?;
+    if let Some(Value::Object(data)) = value.get("data") {
+        for (key, nested) in data {
+            let nested = match nested.as_str().map(serde_json::from_str) {
+                Some(Ok(nested)) => nested,
+                _ => continue,
+            };
+            let prefix = format!("data.{}", key);
+            match &**key {
+                "post" => probe::<Post>(&prefix, nested, &mut callback),
+                "reaction" => probe::<Reaction>(&prefix, nested, &mut callback),
+                "channel" => probe::<Channel>(&prefix, nested, &mut callback),
+                "channelMember" => probe::<ChannelMember>(&prefix, nested, &mut callback),
+                "emoji" => probe::<Emoji>(&prefix, nested, &mut callback),
+                "team" => probe::<Team>(&prefix, nested, &mut callback),
+                "bookmark" => probe::<ChannelBookmark>(&prefix, nested, &mut callback),
+                "scheduledPost" => probe::<ScheduledPost>(&prefix, nested, &mut callback),
+                _ => {}
+            }
+        }
+    }
+    // `Message` is untagged, which hides the ignored fields and the parse errors of the variants
+    let report = |path: serde_ignored::Path<'_>| callback(path.to_string());
+    if value.get("event").is_some() {
+        serde_ignored::deserialize(value, report).map(Message::Push)
+    } else {
+        serde_ignored::deserialize(value, report).map(Message::Reply)
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Eq, PartialEq, Default)]
 #[serde(deny_unknown_fields)]
 pub struct Broadcast {
     pub omit_users: Option<HashMap<String, bool>>,
-    pub user_id: String,
-    pub channel_id: String,
-    pub team_id: String,
+    pub user_id: UserId,
+    pub channel_id: ChannelId,
+    pub team_id: TeamId,
+    /// Only this connection receives the event (server version 7.0 and newer)
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub connection_id: Option<String>,
+    /// This connection does not receive the event (server version 7.0 and newer)
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub omit_connection_id: Option<String>,
+}
+
+impl Hash for Broadcast {
+    /// Skips [`omit_users`](Broadcast::omit_users), which has no stable order
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.user_id.hash(state);
+        self.channel_id.hash(state);
+        self.team_id.hash(state);
+        self.connection_id.hash(state);
+        self.omit_connection_id.hash(state);
+    }
+}
+
+impl PartialOrd for Broadcast {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Broadcast {
+    /// Compares [`omit_users`](Broadcast::omit_users) last, sorted by user id
+    fn cmp(&self, other: &Self) -> Ordering {
+        fn sorted(omit_users: &Option<HashMap<String, bool>>) -> Option<BTreeMap<&String, &bool>> {
+            omit_users.as_ref().map(|users| users.iter().collect())
+        }
+
+        (
+            &self.user_id,
+            &self.channel_id,
+            &self.team_id,
+            &self.connection_id,
+            &self.omit_connection_id,
+        )
+            .cmp(&(
+                &other.user_id,
+                &other.channel_id,
+                &other.team_id,
+                &other.connection_id,
+                &other.omit_connection_id,
+            ))
+            .then_with(|| sorted(&self.omit_users).cmp(&sorted(&other.omit_users)))
+    }
 }
 
 #[derive(Debug, Deserialize, Serialize, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
@@ -183,10 +695,53 @@ pub enum Status {
     Offline,
 }
 
+/// The status of a user, as sent by `status_change` events and the status endpoints
+///
+/// Older servers only send the [`Status`] itself, so the other fields have defaults.
+#[derive(Debug, Deserialize, Serialize, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+#[serde(deny_unknown_fields)]
+pub struct UserStatus {
+    pub user_id: String,
+    pub status: Status,
+    /// The user set the status, instead of it following their activity
+    #[serde(skip_serializing_if = "std::ops::Not::not", default)]
+    pub manual: bool,
+    #[serde(
+        skip_serializing_if = "Option::is_none",
+        with = "serialize::option_ts_milliseconds",
+        default
+    )]
+    pub last_activity_at: Option<DateTime<Utc>>,
+    /// Only sent to the user itself
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub active_channel: Option<String>,
+    /// When the [`Status::DoNotDisturb`] ends, if it was set for a limited time
+    #[serde(
+        skip_serializing_if = "Option::is_none",
+        with = "serialize::option_ts_seconds",
+        default
+    )]
+    pub dnd_end_time: Option<DateTime<Utc>>,
+}
+
+impl UserStatus {
+    pub fn is_online(&self) -> bool {
+        self.status == Status::Online
+    }
+}
+
+/// A message in a channel
+///
+/// Equality compares all fields, so two versions of an edited post are different.
+/// Hashing and ordering only use the [`id`](Post::id) and [`update_at`](Post::update_at), which identify a version of a post.
+/// Posts are ordered by their id first, which is not chronological, sort by [`create_at`](Post::create_at) for that.
+///
+/// Copies of a post which differ in other fields, e.g., after changing one locally, are ordered as equal although they are not `==`.
+/// Do not mix such copies in a `BTreeSet` or as keys of a `BTreeMap`.
 #[derive(Debug, Deserialize, Serialize, Clone, Eq, PartialEq)]
 #[serde(deny_unknown_fields)]
 pub struct Post {
-    pub id: String,
+    pub id: PostId,
     #[serde(with = "serialize::ts_seconds")]
     pub create_at: DateTime<Utc>,
     #[serde(with = "serialize::ts_seconds")]
@@ -196,13 +751,16 @@ pub struct Post {
     #[serde(with = "serialize::ts_seconds")]
     pub delete_at: DateTime<Utc>,
     pub is_pinned: bool,
-    pub user_id: String,
-    pub channel_id: String,
+    pub user_id: UserId,
+    pub channel_id: ChannelId,
     // TODO empty equals not set
-    pub root_id: String,
-    pub parent_id: String,
-    pub original_id: String,
+    pub root_id: PostId,
+    /// Same as `root_id`, not sent by server version 6.0 and newer
+    #[serde(default)]
+    pub parent_id: PostId,
+    pub original_id: PostId,
     pub message: String,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
     pub message_source: Option<String>,
     #[serde(rename = "type")]
     pub type_: PostType,
@@ -216,6 +774,99 @@ pub struct Post {
     pub has_reactions: Option<bool>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub metadata: Option<PostMetadata>,
+    /// Number of replies in the thread (server version 6.0 and newer)
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub reply_count: Option<u64>,
+    /// Id of the remote cluster the post originates from, if posted in a shared channel
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub remote_id: Option<String>,
+}
+
+impl Hash for Post {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.id.hash(state);
+        self.update_at.hash(state);
+    }
+}
+
+impl PartialOrd for Post {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Post {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (&self.id, self.update_at).cmp(&(&other.id, other.update_at))
+    }
+}
+
+impl Post {
+    /// Individual activities of a [`PostType::SystemCombinedUserActivity`] post
+    ///
+    /// Repeated activities of the same user and actor are merged and counted.
+    /// The entries keep the order of the props, all other posts have none.
+    pub fn expand_user_activity(&self) -> Vec<UserActivity> {
+        let activity = match (&self.type_, &self.props.user_activity) {
+            (PostType::SystemCombinedUserActivity, Some(activity)) => activity,
+            _ => return Vec::new(),
+        };
+        let mut entries: Vec<UserActivity> = Vec::new();
+        for data in &activity.message_data {
+            for user_id in &data.user_ids {
+                let existing = entries.iter_mut().find(|entry| {
+                    entry.user_id == *user_id
+                        && entry.action == data.post_type
+                        && entry.actor_id == data.actor_id
+                });
+                match existing {
+                    Some(entry) => entry.count += 1,
+                    None => entries.push(UserActivity {
+                        user_id: user_id.clone(),
+                        action: data.post_type,
+                        actor_id: data.actor_id.clone(),
+                        count: 1,
+                    }),
+                }
+            }
+        }
+        entries
+    }
+
+    pub fn is_deleted(&self) -> bool {
+        self.delete_at.timestamp_millis() != 0
+    }
+
+    /// Id of the first post of the thread, which is the post itself for posts which are no reply
+    pub fn thread_id(&self) -> &PostId {
+        if self.root_id.is_empty() {
+            &self.id
+        } else {
+            &self.root_id
+        }
+    }
+
+    /// Reactions included in the metadata, which is not sent for all posts
+    pub fn reactions(&self) -> &[Reaction] {
+        self.metadata
+            .as_ref()
+            .map_or(&[], |metadata| &metadata.reactions[..])
+    }
+
+    /// Link previews and other embedded content, which is not sent for all posts
+    pub fn embeds(&self) -> &[PostEmbed] {
+        self.metadata
+            .as_ref()
+            .map_or(&[], |metadata| &metadata.embeds[..])
+    }
+
+    /// Priority chosen by the author, [`PriorityLevel::Standard`] if none is set
+    pub fn priority(&self) -> PriorityLevel {
+        self.metadata
+            .as_ref()
+            .and_then(|metadata| metadata.priority.as_ref())
+            .map_or(PriorityLevel::Standard, |priority| priority.priority)
+    }
 }
 
 #[derive(Debug, Deserialize, Serialize, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
@@ -232,12 +883,21 @@ pub enum PostType {
     SystemAddToChannel,
     SystemRemoveFromChannel,
     SystemJoinTeam,
+    SystemAddToTeam,
     SystemRemoveFromTeam,
+    SystemLeaveTeam,
     SystemLeaveChannel,
+    SystemGuestJoinChannel,
+    /// Several consecutive join and leave posts combined into one, see [`Post::expand_user_activity`]
+    SystemCombinedUserActivity,
+    /// Reminder about a post, sent by the system bot
+    Reminder,
 }
 
-#[derive(Debug, Deserialize, Serialize, Clone, Eq, PartialEq)]
-#[serde(deny_unknown_fields)]
+/// Props of posts and scheduled posts
+///
+/// Unknown props are ignored, as newer servers and plugins keep adding their own.
+#[derive(Debug, Deserialize, Serialize, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Default)]
 pub struct PostProps {
     #[serde(skip_serializing_if = "Option::is_none")]
     override_icon_url: Option<String>,
@@ -269,18 +929,366 @@ pub struct PostProps {
     added_user_id: Option<String>,
     #[serde(rename = "userId", skip_serializing_if = "Option::is_none")]
     user_id: Option<String>,
-    #[serde(skip_serializing_if = "HashMap::is_empty", default)]
-    channel_mentions: HashMap<String, ChannelInfo>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty", default)]
+    channel_mentions: BTreeMap<String, ChannelInfo>,
     #[serde(rename = "removedUserId", skip_serializing_if = "Option::is_none")]
     removed_user_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    post_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    team_name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    target_time: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    user_activity: Option<CombinedUserActivity>,
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    user_activity_posts: Vec<Post>,
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    system_post_ids: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    messages: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    relay_origin: Option<RelayOrigin>,
 }
 
-/// THis has a field `embed` in json, which is very complex and not modelled here
-#[derive(Debug, Deserialize, Serialize, Clone, Eq, PartialEq)]
+/// Where a relayed post was copied from, stored in the prop `relay_origin` of the copy, see [`crate::relay`]
+#[derive(Debug, Deserialize, Serialize, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct RelayOrigin {
+    /// Identifies the server of the original post, e.g., its name in the relay or its site URL
+    pub server: String,
+    pub channel_id: ChannelId,
+    pub post_id: PostId,
+}
+
+/// Props of a [`PostType::SystemCombinedUserActivity`] post
+#[derive(Debug, Deserialize, Serialize, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Default)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct CombinedUserActivity {
+    #[serde(default)]
+    pub all_user_ids: Vec<String>,
+    #[serde(default)]
+    pub all_usernames: Vec<String>,
+    #[serde(default)]
+    pub message_data: Vec<UserActivityData>,
+}
+
+/// One kind of activity in a combined post, performed on several users
+#[derive(Debug, Deserialize, Serialize, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct UserActivityData {
+    pub post_type: PostType,
+    /// User who added or removed the others, if any
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub actor_id: Option<String>,
+    pub user_ids: Vec<String>,
+}
+
+/// A single user's part in a combined activity post
+#[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct UserActivity {
+    pub user_id: String,
+    /// The system post type of the individual post, e.g., [`PostType::SystemJoinChannel`]
+    pub action: PostType,
+    /// User who added or removed `user_id`, if any
+    pub actor_id: Option<String>,
+    /// How often the user did this, e.g., joining the channel twice
+    pub count: usize,
+}
+
+impl PostProps {
+    /// Username shown instead of the name of the author, see [`Config::allows_username_override`]
+    pub fn override_username(&self) -> Option<&str> {
+        self.override_username.as_deref()
+    }
+
+    /// Profile picture shown instead of the one of the author, see [`Config::allows_icon_override`]
+    pub fn override_icon_url(&self) -> Option<&str> {
+        self.override_icon_url.as_deref()
+    }
+
+    pub fn from_webhook(&self) -> bool {
+        self.from_webhook.as_deref() == Some("true")
+    }
+
+    /// User who caused a system post, e.g., who joined the channel or changed the header
+    pub fn username(&self) -> Option<&str> {
+        self.username.as_deref()
+    }
+
+    pub fn added_username(&self) -> Option<&str> {
+        self.added_username.as_deref()
+    }
+
+    pub fn removed_username(&self) -> Option<&str> {
+        self.removed_username.as_deref()
+    }
+
+    /// Id of the user who caused a system post, e.g., who added somebody to the channel
+    pub fn user_id(&self) -> Option<&str> {
+        self.user_id.as_deref()
+    }
+
+    pub fn added_user_id(&self) -> Option<&str> {
+        self.added_user_id.as_deref()
+    }
+
+    pub fn removed_user_id(&self) -> Option<&str> {
+        self.removed_user_id.as_deref()
+    }
+
+    pub fn old_header(&self) -> Option<&str> {
+        self.old_header.as_deref()
+    }
+
+    pub fn new_header(&self) -> Option<&str> {
+        self.new_header.as_deref()
+    }
+
+    pub fn old_purpose(&self) -> Option<&str> {
+        self.old_purpose.as_deref()
+    }
+
+    pub fn new_purpose(&self) -> Option<&str> {
+        self.new_purpose.as_deref()
+    }
+
+    pub fn old_displayname(&self) -> Option<&str> {
+        self.old_displayname.as_deref()
+    }
+
+    pub fn new_displayname(&self) -> Option<&str> {
+        self.new_displayname.as_deref()
+    }
+
+    pub fn set_override_username<S>(&mut self, username: S)
+    where
+        S: Into<String>,
+    {
+        self.override_username = Some(username.into());
+    }
+
+    pub fn set_override_icon_url<S>(&mut self, icon_url: S)
+    where
+        S: Into<String>,
+    {
+        self.override_icon_url = Some(icon_url.into());
+    }
+
+    /// The original post, if this post was copied by a relay
+    pub fn relay_origin(&self) -> Option<&RelayOrigin> {
+        self.relay_origin.as_ref()
+    }
+
+    pub fn set_relay_origin(&mut self, origin: RelayOrigin) {
+        self.relay_origin = Some(origin);
+    }
+
+    /// Mark the post as sent by an integration
+    ///
+    /// The clients only show the overridden sender for posts of integrations.
+    /// The server removes the mark from posts of regular users.
+    pub fn set_from_webhook(&mut self, from_webhook: bool) {
+        self.from_webhook = if from_webhook {
+            Some("true".to_string())
+        } else {
+            None
+        };
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Default)]
 // #[serde(deny_unknown_fields)]
 pub struct PostMetadata {
     #[serde(default, skip_serializing_if = "Vec::is_empty")]
     reactions: Vec<Reaction>,
+    /// Priority chosen by the author (server version 7.7 and newer)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub priority: Option<PostPriority>,
+    /// Link previews and other content shown below the message
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub embeds: Vec<PostEmbed>,
+    /// Dimensions of the linked and previewed images, by their URL
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub images: BTreeMap<String, PostImage>,
+}
+
+#[derive(Debug, Deserialize, Serialize, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum EmbedType {
+    /// A link to an image, which is shown inline
+    Image,
+    /// The post has [`PostProps::attachments`]
+    MessageAttachment,
+    /// A link to a website with OpenGraph metadata
+    Opengraph,
+    /// A link without any preview data
+    Link,
+    /// A permalink to another post
+    Permalink,
+    /// A type unknown to this library, e.g., added by a newer server
+    ///
+    /// Serialized as `unknown`.
+    #[serde(other)]
+    Unknown,
+}
+
+/// Content shown below the message of a post, see [`Post::embeds`]
+///
+/// Only the data of [`EmbedType::Opengraph`] embeds is modelled, the data of the other types is dropped.
+#[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct PostEmbed {
+    pub type_: EmbedType,
+    /// The embedded URL, empty for [`EmbedType::MessageAttachment`]
+    pub url: String,
+    pub opengraph: Option<OpenGraph>,
+}
+
+impl PostEmbed {
+    /// Title of the linked page, if the server found one
+    ///
+    /// Renderers can show it instead of the bare URL.
+    ///
+    /// ```
+    /// use mattermost_structs::websocket::{EmbedType, PostEmbed};
+    ///
+    /// let embed: PostEmbed = serde_json::from_str(
+    ///     r#"{"type": "opengraph", "url": "https://example.com", "data": {"title": "Example"}}"#,
+    /// )
+    /// .unwrap();
+    /// assert_eq!(embed.title(), Some("Example"));
+    /// let embed: PostEmbed =
+    ///     serde_json::from_str(r#"{"type": "link", "url": "https://example.com"}"#).unwrap();
+    /// assert_eq!(embed.title(), None);
+    /// let embed: PostEmbed =
+    ///     serde_json::from_str(r#"{"type": "boards", "url": "", "data": {"id": "1"}}"#).unwrap();
+    /// assert_eq!(embed.type_, EmbedType::Unknown);
+    /// ```
+    pub fn title(&self) -> Option<&str> {
+        self.opengraph
+            .as_ref()
+            .map(|opengraph| opengraph.title.as_str())
+            .filter(|title| !title.is_empty())
+    }
+}
+
+/// The `data` of an embed depends on its `type`
+#[derive(Deserialize, Serialize)]
+struct RawPostEmbed<D> {
+    #[serde(rename = "type")]
+    type_: EmbedType,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    url: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    data: Option<D>,
+}
+
+impl<'de> Deserialize<'de> for PostEmbed {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let raw = RawPostEmbed::<Value>::deserialize(deserializer)?;
+        let opengraph = match (raw.type_, raw.data) {
+            (EmbedType::Opengraph, Some(data)) => {
+                Some(serde_json::from_value(data).map_err(de::Error::custom)?)
+            }
+            _ => None,
+        };
+        Ok(PostEmbed {
+            type_: raw.type_,
+            url: raw.url,
+            opengraph,
+        })
+    }
+}
+
+impl Serialize for PostEmbed {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        RawPostEmbed {
+            type_: self.type_,
+            url: self.url.clone(),
+            data: self.opengraph.as_ref(),
+        }
+        .serialize(serializer)
+    }
+}
+
+/// OpenGraph metadata of a linked website
+#[derive(Debug, Deserialize, Serialize, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Default)]
+pub struct OpenGraph {
+    /// The OpenGraph type, e.g., `website` or `article`
+    #[serde(rename = "type", default, skip_serializing_if = "String::is_empty")]
+    pub type_: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub url: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub title: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub description: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub site_name: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub images: Vec<OpenGraphImage>,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Default)]
+pub struct OpenGraphImage {
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub url: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub secure_url: String,
+    /// The MIME type
+    #[serde(rename = "type", default, skip_serializing_if = "String::is_empty")]
+    pub type_: String,
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub width: u32,
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub height: u32,
+}
+
+/// Dimensions of an image linked in a post, see [`PostMetadata::images`]
+#[derive(Debug, Deserialize, Serialize, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Default)]
+pub struct PostImage {
+    pub width: u32,
+    pub height: u32,
+    /// The file format, e.g., `png`
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub format: String,
+    /// Number of frames of animated images, 0 for still images
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub frame_count: u32,
+}
+
+fn is_zero(value: &u32) -> bool {
+    *value == 0
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Default)]
+pub struct PostPriority {
+    #[serde(default)]
+    pub priority: PriorityLevel,
+    /// The author asks the recipients to acknowledge the post
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub requested_ack: Option<bool>,
+    /// Mentioned users are notified repeatedly until they acknowledge the post
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub persistent_notifications: Option<bool>,
+}
+
+#[derive(
+    Debug, Default, Deserialize, Serialize, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum PriorityLevel {
+    /// No priority set
+    #[default]
+    #[serde(rename = "")]
+    Standard,
+    Important,
+    Urgent,
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
@@ -313,12 +1321,30 @@ pub struct Reaction {
     pub emoji_name: String,
     #[serde(with = "serialize::ts_seconds")]
     pub create_at: DateTime<Utc>,
+    /// Only sent by server version 6.0 and newer, like the following fields
+    #[serde(
+        skip_serializing_if = "Option::is_none",
+        with = "serialize::option_ts_milliseconds",
+        default
+    )]
+    pub update_at: Option<DateTime<Utc>>,
+    #[serde(
+        skip_serializing_if = "Option::is_none",
+        with = "serialize::option_ts_milliseconds",
+        default
+    )]
+    pub delete_at: Option<DateTime<Utc>>,
+    /// Id of the remote cluster the reaction originates from, if the post is in a shared channel
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub remote_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub channel_id: Option<String>,
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
 #[serde(deny_unknown_fields)]
 pub struct Team {
-    pub id: String,
+    pub id: TeamId,
     #[serde(with = "serialize::ts_seconds")]
     pub create_at: DateTime<Utc>,
     #[serde(with = "serialize::ts_seconds")]
@@ -335,7 +1361,7 @@ pub struct Team {
     pub allowed_domains: String,
     pub invite_id: String,
     pub allow_open_invite: bool,
-    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none", default)]
     pub scheme_id: Option<String>,
     #[serde(
         skip_serializing_if = "Option::is_none",
@@ -345,9 +1371,97 @@ pub struct Team {
     pub last_team_icon_update: Option<DateTime<Utc>>,
 }
 
-#[derive(Debug, Deserialize, Serialize, Clone, Eq, PartialEq, Ord, PartialOrd)]
+#[derive(Debug, Deserialize, Serialize, Clone, Eq, PartialEq, Hash, Ord, PartialOrd)]
 pub struct Config(pub BTreeMap<String, String>);
 
+impl Config {
+    /// Whether the boolean setting `key` is `"true"`
+    pub fn is_enabled(&self, key: &str) -> bool {
+        self.0.get(key).is_some_and(|v| v == "true")
+    }
+
+    /// Whether integrations may set [`PostProps::override_username`]
+    pub fn allows_username_override(&self) -> bool {
+        self.is_enabled("EnablePostUsernameOverride")
+    }
+
+    /// Whether integrations may set [`PostProps::override_icon_url`]
+    pub fn allows_icon_override(&self) -> bool {
+        self.is_enabled("EnablePostIconOverride")
+    }
+
+    pub fn features(&self) -> ClientFeatures {
+        ClientFeatures {
+            custom_emoji: self.is_enabled("EnableCustomEmoji"),
+            file_attachments: self.is_enabled("EnableFileAttachments"),
+            public_links: self.is_enabled("EnablePublicLink"),
+            link_previews: self.is_enabled("EnableLinkPreviews"),
+            slash_commands: self.is_enabled("EnableCommands"),
+            incoming_webhooks: self.is_enabled("EnableIncomingWebhooks"),
+            outgoing_webhooks: self.is_enabled("EnableOutgoingWebhooks"),
+            bot_accounts: self.is_enabled("EnableBotAccountCreation"),
+            guest_accounts: self.is_enabled("EnableGuestAccounts"),
+            multifactor_authentication: self.is_enabled("EnableMultifactorAuthentication"),
+            username_override: self.allows_username_override(),
+            icon_override: self.allows_icon_override(),
+            post_priority: self.is_enabled("PostPriority"),
+            collapsed_threads: self
+                .0
+                .get("CollapsedThreads")
+                .is_some_and(|v| v != "disabled"),
+        }
+    }
+}
+
+/// Commonly checked flags of the [`Config`]
+///
+/// Flags missing from the configuration, e.g., on older servers, are `false`.
+#[derive(
+    Debug, Deserialize, Serialize, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Default,
+)]
+pub struct ClientFeatures {
+    pub custom_emoji: bool,
+    pub file_attachments: bool,
+    /// Public links to files, see [`Client::get_file_link`](crate::api::Client::get_file_link)
+    pub public_links: bool,
+    pub link_previews: bool,
+    /// Custom slash commands
+    pub slash_commands: bool,
+    pub incoming_webhooks: bool,
+    pub outgoing_webhooks: bool,
+    /// Users may create bot accounts
+    pub bot_accounts: bool,
+    pub guest_accounts: bool,
+    pub multifactor_authentication: bool,
+    pub username_override: bool,
+    pub icon_override: bool,
+    /// Message priorities and acknowledgements (server version 7.7 and newer)
+    pub post_priority: bool,
+    /// Threads are shown in a separate view, at least for users who opt in
+    pub collapsed_threads: bool,
+}
+
+/// The parts of the license which are visible to all users
+///
+/// All values are strings, e.g., `"true"` for boolean flags.
+#[derive(Debug, Deserialize, Serialize, Clone, Eq, PartialEq, Hash, Ord, PartialOrd)]
+pub struct ClientLicense(pub BTreeMap<String, String>);
+
+impl ClientLicense {
+    pub fn is_licensed(&self) -> bool {
+        self.0.get("IsLicensed").is_some_and(|v| v == "true")
+    }
+
+    /// Short name of the license type, e.g., `professional` or `enterprise`
+    pub fn sku_short_name(&self) -> Option<&str> {
+        self.0.get("SkuShortName").map(String::as_str)
+    }
+}
+
+/// Membership of a user in a channel
+///
+/// Hashing and ordering only use the [`channel_id`](ChannelMember::channel_id), [`user_id`](ChannelMember::user_id), and [`last_update_at`](ChannelMember::last_update_at), as the roles are unordered.
+/// Copies which differ in other fields are ordered as equal although they are not `==`.
 #[derive(Debug, Deserialize, Serialize, Clone, Eq, PartialEq)]
 #[serde(deny_unknown_fields)]
 pub struct ChannelMember {
@@ -372,10 +1486,36 @@ pub struct ChannelMember {
     pub last_update_at: Option<DateTime<Utc>>,
     pub scheme_user: bool,
     pub scheme_admin: bool,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub scheme_guest: Option<bool>,
     #[serde(with = "::serde_with::rust::StringWithSeparator::<::serde_with::SpaceSeparator>")]
     pub explicit_roles: HashSet<UserRole>,
 }
 
+impl Hash for ChannelMember {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.channel_id.hash(state);
+        self.user_id.hash(state);
+        self.last_update_at.hash(state);
+    }
+}
+
+impl PartialOrd for ChannelMember {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for ChannelMember {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (&self.channel_id, &self.user_id, self.last_update_at).cmp(&(
+            &other.channel_id,
+            &other.user_id,
+            other.last_update_at,
+        ))
+    }
+}
+
 #[derive(Debug, Deserialize, Serialize, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
 #[serde(deny_unknown_fields)]
 pub struct NotifyProps {
@@ -391,6 +1531,49 @@ pub struct NotifyProps {
     pub push: Option<String>,
 }
 
+impl NotifyProps {
+    /// The user muted the channel
+    pub fn is_muted(&self) -> bool {
+        self.mark_unread.as_deref() == Some("mention")
+    }
+
+    /// `@channel`, `@all`, and `@here` do not notify the user in this channel
+    ///
+    /// `default` uses the account setting, which is not part of the channel member and counts as `false`.
+    pub fn ignores_channel_mentions(&self) -> bool {
+        self.ignore_channel_mentions.as_deref() == Some("on")
+    }
+
+    pub fn push_level(&self) -> NotifyLevel {
+        NotifyLevel::parse(self.push.as_deref())
+    }
+
+    pub fn desktop_level(&self) -> NotifyLevel {
+        NotifyLevel::parse(self.desktop.as_deref())
+    }
+}
+
+/// Which posts in a channel trigger a notification
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub enum NotifyLevel {
+    /// Use the account setting
+    Default,
+    All,
+    Mention,
+    None,
+}
+
+impl NotifyLevel {
+    fn parse(level: Option<&str>) -> NotifyLevel {
+        match level {
+            Some("all") => NotifyLevel::All,
+            Some("mention") => NotifyLevel::Mention,
+            Some("none") => NotifyLevel::None,
+            _ => NotifyLevel::Default,
+        }
+    }
+}
+
 #[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Serialize, Deserialize)]
 #[serde(deny_unknown_fields)]
 pub struct ChannelInfo {
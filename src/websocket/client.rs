@@ -0,0 +1,532 @@
+//! Connection to the websocket of a server
+//!
+//! [`Client`] connects to `/api/v4/websocket`, authenticates, and keeps the connection alive with pings.
+//! The received messages are passed to an [`EventHandler`], or returned by the iterator of [`Client::messages`].
+//! [`Client::run_reconnecting`] reconnects with backoff whenever the connection ends, resumes the previous connection, and drops events received twice.
+//!
+//! ```no_run
+//! # fn main() -> mattermost_structs::Result<()> {
+//! use mattermost_structs::websocket::{Client, Events, Message};
+//!
+//! let client = Client::new("https://chat.example.com", "token")?;
+//! for message in client.messages() {
+//!     if let Message::Push(push) = message {
+//!         if let Events::Posted { post, .. } = push.event {
+//!             println!("{}", post.message);
+//!         }
+//!     }
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use super::{decode_binary_frame, AuthMethod, Message};
+use crate::{
+    error::{Error, Result},
+    liveness::LivenessDetector,
+    reconnect::Reconnector,
+    resume::{ResumeOutcome, ResumeState},
+};
+use log::{debug, warn};
+use std::{
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        mpsc, Arc,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+use url::Url;
+use ws::{
+    util::{Timeout, Token},
+    CloseCode, Frame, Handshake, OpCode, Request, Sender,
+};
+
+/// URL of the websocket of the server at `base_url`
+///
+/// The websocket library only supports TLS with OpenSSL, so `https` servers require the `native-tls` feature.
+/// With only `rustls-tls` this is an error, instead of silently connecting without TLS.
+pub(crate) fn websocket_url(base_url: &Url) -> Result<Url> {
+    let scheme = match base_url.scheme() {
+        "http" => "ws",
+        "https" if cfg!(feature = "native-tls") => "wss",
+        "https" => {
+            return Err(format!(
+                "Cannot connect to the websocket of {}: websockets over TLS require the native-tls feature, rustls-tls only covers REST requests",
+                base_url
+            )
+            .into())
+        }
+        scheme => {
+            return Err(format!(
+                "Unsupported scheme '{}' of {}, expected http or https",
+                scheme, base_url
+            )
+            .into())
+        }
+    };
+    let mut url = base_url.clone();
+    url.set_scheme(scheme)
+        .expect("Setting the scheme to ws or wss must always work");
+    Ok(url.join("/api/v4/websocket")?)
+}
+
+const PING: Token = Token(1);
+const EXPIRE: Token = Token(2);
+const STALL: Token = Token(3);
+const CONNECT: Token = Token(4);
+
+/// Payload of the pings, such that only the answers to our own pings count as pongs
+const PING_PAYLOAD: &[u8] = b"mattermost-client";
+
+/// Receives the messages of a websocket connection
+///
+/// Closures taking a [`Message`] implement the trait.
+pub trait EventHandler {
+    /// Called for every message received
+    fn on_message(&mut self, message: Message);
+
+    /// Called for text which cannot be parsed as [`Message`], e.g., events unknown to this library
+    fn on_invalid(&mut self, text: &str, err: &Error) {
+        warn!("Could not parse the following message: {}\n{}", err, text);
+    }
+
+    /// Called for the `hello` of each connection of [`Client::run_reconnecting`], before [`EventHandler::on_message`]
+    ///
+    /// After [`ResumeOutcome::Lost`] the events sent while disconnected need to be fetched via the REST API.
+    fn on_resume(&mut self, outcome: ResumeOutcome) {
+        debug!("WS: Connected, {:?}", outcome);
+    }
+
+    /// Called whenever a connection of [`Client::run_reconnecting`] ends, with the error if it failed
+    ///
+    /// Return `false` to stop reconnecting.
+    fn on_disconnect(&mut self, err: Option<&Error>) -> bool {
+        if let Some(err) = err {
+            warn!("WS: Connection failed: {}", err);
+        }
+        true
+    }
+
+    /// Checked after every message, return `true` to close the connection and stop reconnecting
+    fn is_finished(&self) -> bool {
+        false
+    }
+}
+
+impl<F> EventHandler for F
+where
+    F: FnMut(Message),
+{
+    fn on_message(&mut self, message: Message) {
+        self(message)
+    }
+}
+
+/// Websocket connection to a server
+///
+/// The settings are applied by the next call to [`Client::run`] or [`Client::messages`].
+#[derive(Debug, Clone)]
+pub struct Client {
+    url: Url,
+    token: String,
+    auth: AuthMethod,
+    ping_interval: Duration,
+    expire_timeout: Duration,
+    connect_timeout: Option<Duration>,
+    liveness: Option<LivenessDetector>,
+    keep_raw: bool,
+    /// Number of binary frames which could not be decoded, shared by clones and kept across connections
+    ignored_frames: Arc<AtomicU64>,
+}
+
+impl Client {
+    /// Connect to the server at `base_url`, which is the same URL as for [`api::Client`](crate::api::Client)
+    pub fn new<B, T>(base_url: B, token: T) -> Result<Client>
+    where
+        B: AsRef<str>,
+        T: Into<String>,
+    {
+        Ok(Client {
+            url: websocket_url(&Url::parse(base_url.as_ref())?)?,
+            token: token.into(),
+            auth: AuthMethod::default(),
+            ping_interval: Duration::from_secs(10),
+            expire_timeout: Duration::from_secs(60),
+            connect_timeout: None,
+            liveness: None,
+            keep_raw: false,
+            ignored_frames: Arc::new(AtomicU64::new(0)),
+        })
+    }
+
+    /// Number of binary frames which could not be decoded and were dropped, over all connections
+    pub fn ignored_frames(&self) -> u64 {
+        self.ignored_frames.load(Ordering::Relaxed)
+    }
+
+    /// The websocket URL, without the parameters of the [`AuthMethod`]
+    pub fn url(&self) -> &Url {
+        &self.url
+    }
+
+    /// Defaults to [`AuthMethod::Challenge`]
+    pub fn auth(mut self, auth: AuthMethod) -> Self {
+        self.auth = auth;
+        self
+    }
+
+    /// Send a ping every `interval`, defaults to 10 seconds
+    pub fn ping_interval(mut self, interval: Duration) -> Self {
+        self.ping_interval = interval;
+        self
+    }
+
+    /// Close the connection if no pong arrives for `timeout`, defaults to 60 seconds
+    pub fn expire_timeout(mut self, timeout: Duration) -> Self {
+        self.expire_timeout = timeout;
+        self
+    }
+
+    /// Give up if the handshake does not finish within `timeout`
+    ///
+    /// [`Client::run`] then fails with [`Error::Timeout`].
+    pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.connect_timeout = Some(timeout);
+        self
+    }
+
+    /// Close the connection once `detector` considers it stalled, see [`liveness`](crate::liveness)
+    pub fn liveness(mut self, detector: LivenessDetector) -> Self {
+        self.liveness = Some(detector);
+        self
+    }
+
+    /// Ask the server to replay the events missed since the connection of `state` ended
+    pub fn resume(mut self, state: &ResumeState) -> Self {
+        state.prepare_url(&mut self.url);
+        self
+    }
+
+    /// Keep the text of push messages, see [`Message::parse`]
+    pub fn keep_raw(mut self, keep_raw: bool) -> Self {
+        self.keep_raw = keep_raw;
+        self
+    }
+
+    /// Connect and pass all messages to `handler`, until the connection closes
+    pub fn run<H>(&self, handler: &mut H) -> Result<()>
+    where
+        H: EventHandler,
+    {
+        let mut url = self.url.clone();
+        self.auth.prepare_url(&mut url, &self.token);
+        let timed_out = Arc::new(AtomicBool::new(false));
+        let mut handler = Some(handler);
+        ws::connect(url.as_str(), |out| {
+            if let Some(timeout) = self.connect_timeout {
+                if out.timeout(timeout.as_millis() as u64, CONNECT).is_err() {
+                    warn!("WS: Couldn't schedule the connect timeout");
+                }
+            }
+            Connection {
+                ws: out,
+                client: self,
+                handler: handler
+                    .take()
+                    .expect("A websocket client only opens one connection"),
+                expire: None,
+                liveness: self.liveness.clone(),
+                opened: false,
+                timed_out: timed_out.clone(),
+            }
+        })
+        .map_err(|err| Error::from(format!("Websocket connection failed: {}", err)))?;
+        if timed_out.load(Ordering::SeqCst) {
+            return Err(Error::Timeout(format!("connecting to {}", self.url)));
+        }
+        Ok(())
+    }
+
+    /// Like [`Client::run`], but reconnect whenever the connection ends, until [`EventHandler::on_disconnect`] returns `false`
+    ///
+    /// The `reconnector` decides how long to wait before reconnecting.
+    /// Each new connection asks the server to resume the previous one, see [`resume`](crate::resume).
+    /// Events which the server sends again after resuming are dropped, such that the handler sees every event once.
+    /// Returns the result of the last connection.
+    pub fn run_reconnecting<H>(&self, handler: &mut H, reconnector: &mut Reconnector) -> Result<()>
+    where
+        H: EventHandler,
+    {
+        let mut resume = ResumeState::new();
+        loop {
+            let start = Instant::now();
+            let mut resuming = Resuming {
+                handler: &mut *handler,
+                resume: &mut resume,
+                duplicates: 0,
+            };
+            let result = self.clone().resume(resuming.resume).run(&mut resuming);
+            if resuming.duplicates > 0 {
+                debug!("WS: Dropped {} duplicate events", resuming.duplicates);
+            }
+            if handler.is_finished() || !handler.on_disconnect(result.as_ref().err()) {
+                return result;
+            }
+            let delay = reconnector.connection_ended(start.elapsed());
+            debug!("WS: Reconnecting in {}ms", delay.as_millis());
+            thread::sleep(delay);
+        }
+    }
+
+    /// Connect in a background thread and iterate over the messages
+    ///
+    /// The iterator ends when the connection closes.
+    /// Dropping the iterator closes the connection with the next message.
+    pub fn messages(&self) -> Messages {
+        let (sender, receiver) = mpsc::channel();
+        let client = self.clone();
+        let thread = thread::spawn(move || {
+            client.run(&mut Channel {
+                sender,
+                closed: false,
+            })
+        });
+        Messages {
+            receiver,
+            thread: Some(thread),
+        }
+    }
+}
+
+/// Iterator over the messages of a connection, see [`Client::messages`]
+#[derive(Debug)]
+pub struct Messages {
+    receiver: mpsc::Receiver<Message>,
+    thread: Option<thread::JoinHandle<Result<()>>>,
+}
+
+impl Messages {
+    /// Wait until the connection closes and return why it failed, if it did
+    pub fn finish(mut self) -> Result<()> {
+        match self.thread.take().map(thread::JoinHandle::join) {
+            Some(Ok(result)) => result,
+            Some(Err(_)) => Err("The websocket thread panicked".into()),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Iterator for Messages {
+    type Item = Message;
+
+    fn next(&mut self) -> Option<Message> {
+        self.receiver.recv().ok()
+    }
+}
+
+/// Sends the messages to the iterator of [`Client::messages`]
+struct Channel {
+    sender: mpsc::Sender<Message>,
+    /// Set once the iterator was dropped
+    closed: bool,
+}
+
+impl EventHandler for Channel {
+    fn on_message(&mut self, message: Message) {
+        self.closed = self.sender.send(message).is_err();
+    }
+
+    fn is_finished(&self) -> bool {
+        self.closed
+    }
+}
+
+/// Tracks the sequence numbers for [`Client::run_reconnecting`] and filters duplicates
+struct Resuming<'a, H> {
+    handler: &'a mut H,
+    resume: &'a mut ResumeState,
+    duplicates: u64,
+}
+
+impl<H> EventHandler for Resuming<'_, H>
+where
+    H: EventHandler,
+{
+    fn on_message(&mut self, message: Message) {
+        if let Message::Push(push) = &message {
+            if self.resume.is_duplicate(push) {
+                self.duplicates += 1;
+                return;
+            }
+            if let Some(outcome) = self.resume.observe(push) {
+                self.handler.on_resume(outcome);
+            }
+        }
+        self.handler.on_message(message)
+    }
+
+    fn on_invalid(&mut self, text: &str, err: &Error) {
+        self.handler.on_invalid(text, err)
+    }
+
+    fn is_finished(&self) -> bool {
+        self.handler.is_finished()
+    }
+}
+
+/// Handler of a single connection for the `ws` crate
+struct Connection<'a, H> {
+    ws: Sender,
+    client: &'a Client,
+    handler: &'a mut H,
+    /// Pending timeout which closes the connection if no pong arrives
+    expire: Option<Timeout>,
+    liveness: Option<LivenessDetector>,
+    /// Whether the handshake finished
+    opened: bool,
+    /// Set if the handshake did not finish before the connect timeout
+    timed_out: Arc<AtomicBool>,
+}
+
+impl<H> Connection<'_, H>
+where
+    H: EventHandler,
+{
+    fn handle_text(&mut self, text: &str) -> std::result::Result<(), Box<ws::Error>> {
+        match Message::parse(text, self.client.keep_raw) {
+            Ok(message) => self.handler.on_message(message),
+            Err(err) => self.handler.on_invalid(text, &err.into()),
+        }
+        if self.handler.is_finished() {
+            debug!("WS: Handler finished, closing the connection");
+            self.ws.close(CloseCode::Normal)?;
+        }
+        Ok(())
+    }
+}
+
+impl<H> ws::Handler for Connection<'_, H>
+where
+    H: EventHandler,
+{
+    fn build_request(&mut self, url: &url::Url) -> ws::Result<Request> {
+        let mut request = Request::from_url(url)?;
+        if let Some((name, value)) = self.client.auth.header(&self.client.token) {
+            request
+                .headers_mut()
+                .push((name.to_string(), value.into_bytes()));
+        }
+        Ok(request)
+    }
+
+    fn on_open(&mut self, _: Handshake) -> ws::Result<()> {
+        self.opened = true;
+        if let Some(challenge) = self.client.auth.challenge(&self.client.token, 1) {
+            self.ws.send(challenge)?;
+        }
+        if let Some(liveness) = &mut self.liveness {
+            liveness.record_activity();
+            self.ws
+                .timeout(liveness.deadline().as_millis() as u64, STALL)?;
+        }
+        self.ws
+            .timeout(self.client.ping_interval.as_millis() as u64, PING)?;
+        self.ws
+            .timeout(self.client.expire_timeout.as_millis() as u64, EXPIRE)
+    }
+
+    fn on_message(&mut self, msg: ws::Message) -> ws::Result<()> {
+        if let Some(liveness) = &mut self.liveness {
+            liveness.record_activity();
+        }
+        match msg {
+            ws::Message::Text(text) => self.handle_text(&text).map_err(|err| *err)?,
+            ws::Message::Binary(data) => match decode_binary_frame(&data) {
+                Ok(text) => self.handle_text(&text).map_err(|err| *err)?,
+                Err(err) => {
+                    let ignored = self.client.ignored_frames.fetch_add(1, Ordering::Relaxed) + 1;
+                    warn!(
+                        "WS: Ignoring binary frame of {} bytes ({} so far): {}",
+                        data.len(),
+                        ignored,
+                        err
+                    );
+                }
+            },
+        }
+        Ok(())
+    }
+
+    fn on_timeout(&mut self, event: Token) -> ws::Result<()> {
+        match event {
+            PING => {
+                debug!("WS: Perform ping");
+                self.ws.ping(PING_PAYLOAD.to_vec())?;
+                self.ws
+                    .timeout(self.client.ping_interval.as_millis() as u64, PING)
+            }
+            EXPIRE => self.ws.close(CloseCode::Away),
+            CONNECT if self.opened => Ok(()),
+            CONNECT => {
+                warn!("WS: Connecting timed out");
+                self.timed_out.store(true, Ordering::SeqCst);
+                self.ws.shutdown()
+            }
+            STALL => match &mut self.liveness {
+                Some(liveness) if liveness.is_stalled() => {
+                    liveness.record_stall();
+                    warn!(
+                        "WS: No activity for {:?}, reconnecting (stall #{})",
+                        liveness.deadline(),
+                        liveness.stalls()
+                    );
+                    self.ws.close(CloseCode::Away)
+                }
+                Some(liveness) => {
+                    let remaining = liveness.remaining().as_millis() as u64;
+                    self.ws.timeout(remaining.max(1), STALL)
+                }
+                None => Ok(()),
+            },
+            _ => Err(ws::Error::new(
+                ws::ErrorKind::Internal,
+                "Invalid timeout token encountered!",
+            )),
+        }
+    }
+
+    fn on_new_timeout(&mut self, event: Token, timeout: Timeout) -> ws::Result<()> {
+        if event == EXPIRE {
+            if let Some(t) = self.expire.take() {
+                debug!("WS: Cancel expire timeout");
+                self.ws.cancel(t)?
+            }
+            self.expire = Some(timeout)
+        }
+        Ok(())
+    }
+
+    fn on_frame(&mut self, frame: Frame) -> ws::Result<Option<Frame>> {
+        // default implementation doesn't allow for reserved bits to be set
+        if frame.has_rsv1() || frame.has_rsv2() || frame.has_rsv3() {
+            return Err(ws::Error::new(
+                ws::ErrorKind::Protocol,
+                "Encountered frame with reserved bits set.",
+            ));
+        }
+        if frame.opcode() == OpCode::Ping {
+            // Pings are sent by the server, so unlike pongs they cannot be faked by a proxy
+            if let Some(liveness) = &mut self.liveness {
+                liveness.record_activity();
+            }
+        }
+        if frame.opcode() == OpCode::Pong && frame.payload() == PING_PAYLOAD {
+            debug!("WS: Received pong");
+            // reset timeout if ping/pong was successful
+            self.ws
+                .timeout(self.client.expire_timeout.as_millis() as u64, EXPIRE)?
+        }
+        Ok(Some(frame))
+    }
+}
@@ -1,90 +1,81 @@
-use crate::{react_to_message, ServerConfig};
-use lazy_static::lazy_static;
-use log::debug;
-use mattermost_structs::websocket::Status;
-use std::sync::{Arc, Mutex};
-use ws::{
-    util::{Timeout, Token},
-    CloseCode, Frame, Handshake, OpCode, Sender,
+use crate::{react_to_message, read_mirror::ReadStateMirror, ServerConfig};
+use chrono::{DateTime, Utc};
+use mattermost_structs::{
+    clock::ClockSkew,
+    ids::UserId,
+    mention::MentionContext,
+    resume::ResumeState,
+    stats::EventCounter,
+    websocket::{EventHandler, Message, NotifyProps, Status},
+};
+use std::{
+    collections::HashMap,
+    sync::{mpsc, Arc, Mutex},
+    thread,
 };
 
-const PING: Token = Token(1);
-const PING_TIMEOUT: u64 = 10_000;
-const EXPIRE: Token = Token(2);
-const EXPIRE_TIMEOUT: u64 = 60_000;
+/// Reconnect if the server does not send anything for 5 minutes, even if the pongs arrive
+pub const STALL_TIMEOUT: u64 = 300_000;
 
-lazy_static! {
-    /// A special value used for the Ping messages.
-    static ref PING_PONG: Vec<u8> = Vec::from(b"mattermost-client" as &[u8]);
-}
+/// Work for the [`Worker`] of a connection
+pub type Job = Box<dyn FnOnce(&mut Worker) + Send>;
 
-pub struct WsClient {
-    pub ws: Sender,
-    pub timeout: Option<Timeout>,
-    pub own_id: Option<String>,
+/// Runs the REST calls needed to handle the events of a connection
+///
+/// The calls block until the server answers, so they run on a separate thread instead of delaying the websocket.
+/// Jobs run in the order they were sent, e.g., the own user is looked up before the first post is classified.
+pub struct Worker {
+    /// Set after the `hello` event, if the own user could be looked up
+    pub mention_context: Option<MentionContext>,
+    /// Notification preferences of the own user, by channel id
+    pub notify_props: HashMap<String, NotifyProps>,
     pub serverconfig: ServerConfig,
     pub mobile_number: String,
-    pub serverstate: Arc<Mutex<Status>>,
+    pub clock: ClockSkew,
 }
 
-use ws::{Error, ErrorKind, Result};
-impl ::ws::Handler for WsClient {
-    fn on_message(&mut self, msg: ::ws::Message) -> Result<()> {
-        if msg.is_text() {
-            let msg = msg.into_text().expect("Must be text");
-            react_to_message(self, &msg);
-        }
-        Ok(())
-    }
-
-    fn on_open(&mut self, _: Handshake) -> Result<()> {
-        // schedule a timeout to send a ping every 5 seconds
-        self.ws.timeout(PING_TIMEOUT, PING)?;
-        // schedule a timeout to close the connection if there is no activity for 30 seconds
-        self.ws.timeout(EXPIRE_TIMEOUT, EXPIRE)
-    }
-
-    fn on_timeout(&mut self, event: Token) -> Result<()> {
-        match event {
-            PING => {
-                debug!("WS: Perform ping");
-                self.ws.ping(PING_PONG.clone())?;
-                self.ws.timeout(PING_TIMEOUT, PING)
+impl Worker {
+    /// Run the jobs on a new thread, which ends once the returned sender is dropped
+    pub fn spawn(mut self) -> mpsc::Sender<Job> {
+        let (sender, receiver) = mpsc::channel::<Job>();
+        thread::spawn(move || {
+            for job in receiver {
+                job(&mut self);
             }
-            EXPIRE => self.ws.close(CloseCode::Away),
-            _ => Err(Error::new(
-                ErrorKind::Internal,
-                "Invalid timeout token encountered!",
-            )),
-        }
+        });
+        sender
     }
+}
 
-    fn on_new_timeout(&mut self, event: Token, timeout: Timeout) -> Result<()> {
-        if event == EXPIRE {
-            if let Some(t) = self.timeout.take() {
-                debug!("WS: Cancel expire timeout");
-                self.ws.cancel(t)?
-            }
-            self.timeout = Some(timeout)
-        }
-        Ok(())
+pub struct WsClient {
+    pub own_id: Option<UserId>,
+    pub worker: mpsc::Sender<Job>,
+    pub serverconfig: ServerConfig,
+    pub mobile_number: String,
+    pub serverstate: Arc<Mutex<Status>>,
+    pub resume: Arc<Mutex<ResumeState>>,
+    /// Offset of the server clock, shared across reconnects
+    pub clock: ClockSkew,
+    /// Shared by the connections to all servers
+    pub read_mirror: Arc<ReadStateMirror>,
+    /// When the previous connection ended, taken by the first `hello` of the next connection
+    pub disconnected_at: Arc<Mutex<Option<DateTime<Utc>>>>,
+    /// Counts the received events, if enabled, shared across reconnects
+    pub stats: Option<Arc<Mutex<EventCounter>>>,
+}
+
+impl WsClient {
+    pub fn run_in_worker<F>(&self, job: F)
+    where
+        F: FnOnce(&mut Worker) + Send + 'static,
+    {
+        // The worker only stops after the client is dropped
+        let _ = self.worker.send(Box::new(job));
     }
+}
 
-    fn on_frame(&mut self, frame: Frame) -> Result<Option<Frame>> {
-        debug!("Handler received: {}", frame);
-        // default implementation doesn't allow for reserved bits to be set
-        if frame.has_rsv1() || frame.has_rsv2() || frame.has_rsv3() {
-            Err(Error::new(
-                ErrorKind::Protocol,
-                "Encountered frame with reserved bits set.",
-            ))
-        } else {
-            if frame.opcode() == OpCode::Pong && frame.payload() == &*PING_PONG {
-                debug!("WS: Received pong");
-                // reset timeout if ping/pong was successful
-                self.ws.timeout(EXPIRE_TIMEOUT, EXPIRE)?
-            }
-            Ok(Some(frame))
-        }
+impl EventHandler for WsClient {
+    fn on_message(&mut self, message: Message) {
+        react_to_message(self, message)
     }
 }
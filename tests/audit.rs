@@ -0,0 +1,188 @@
+//! Membership changes from system posts, events, and the audit log
+
+use chrono::{DateTime, Duration, TimeZone, Utc};
+use mattermost_structs::{
+    api::Audit,
+    audit::{AuditSource, MembershipAction, MembershipAuditStream, MembershipRecord},
+    websocket::{MessagePush, Post},
+};
+use serde_json::json;
+
+const WINDOW: std::time::Duration = std::time::Duration::from_secs(60);
+
+fn start() -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(2024, 1, 1, 12, 0, 0).unwrap()
+}
+
+fn system_post(type_: &str, user_id: &str, props: serde_json::Value, at: DateTime<Utc>) -> Post {
+    serde_json::from_value(json!({
+        "id": "s8bq9xwsbpyc7gjcw4rs7c4qmh", "create_at": at.timestamp_millis(),
+        "update_at": at.timestamp_millis(), "edit_at": 0, "delete_at": 0, "is_pinned": false,
+        "user_id": user_id, "channel_id": "town-square", "root_id": "", "original_id": "",
+        "message": "", "type": type_, "props": props, "hashtags": "", "pending_post_id": "",
+    }))
+    .unwrap()
+}
+
+fn event(event: &str, data: serde_json::Value) -> MessagePush {
+    serde_json::from_value(json!({
+        "event": event,
+        "data": data,
+        "broadcast": {"omit_users": null, "user_id": "", "channel_id": "town-square", "team_id": ""},
+        "seq": 1,
+    }))
+    .unwrap()
+}
+
+fn audit(action: &str, extra_info: &str, at: DateTime<Utc>) -> Audit {
+    serde_json::from_value(json!({
+        "id": "c3tn7ek7wpbqbkzryz9rbuc1ye",
+        "create_at": at.timestamp_millis(),
+        "user_id": "admin",
+        "action": action,
+        "extra_info": extra_info,
+        "ip_address": "127.0.0.1",
+        "session_id": "8ipgnp5zxfrqxcrf1d6ie8hnbo",
+    }))
+    .unwrap()
+}
+
+#[test]
+fn normalizes_all_sources() {
+    let joined = MembershipRecord::from_post(&system_post(
+        "system_join_channel",
+        "alice",
+        json!({"username": "alice"}),
+        start(),
+    ))
+    .unwrap();
+    assert_eq!(joined.user_id, "alice");
+    assert_eq!(joined.channel_id, "town-square");
+    assert_eq!(joined.action, MembershipAction::Joined);
+    assert_eq!(joined.actor_id.as_ref().unwrap(), "alice");
+    assert_eq!(joined.at, start());
+    assert_eq!(joined.source, AuditSource::Post);
+
+    let removed = MembershipRecord::from_post(&system_post(
+        "system_remove_from_channel",
+        "admin",
+        json!({"removedUserId": "bob", "removedUsername": "bob"}),
+        start(),
+    ))
+    .unwrap();
+    assert_eq!(
+        (removed.user_id.as_str(), removed.action),
+        ("bob", MembershipAction::Left)
+    );
+    assert_eq!(removed.actor_id.unwrap(), "admin");
+
+    let added = MembershipRecord::from_event(
+        &event("user_added", json!({"team_id": "", "user_id": "carol"})),
+        start(),
+    )
+    .unwrap();
+    assert_eq!(
+        (added.user_id.as_str(), added.action, added.actor_id),
+        ("carol", MembershipAction::Joined, None)
+    );
+
+    let audited = MembershipRecord::from_audit(&audit(
+        "/api/v4/channels/town-square/members",
+        "name=town-square user_id=carol",
+        start(),
+    ))
+    .unwrap();
+    assert_eq!(audited.channel_id, "town-square");
+    assert_eq!(
+        (audited.user_id.as_str(), audited.action),
+        ("carol", MembershipAction::Joined)
+    );
+    assert_eq!(audited.actor_id.unwrap(), "admin");
+    let audited = MembershipRecord::from_audit(&audit(
+        "/api/v4/channels/town-square/members/carol",
+        "name=town-square user_id=carol",
+        start(),
+    ))
+    .unwrap();
+    assert_eq!(audited.action, MembershipAction::Left);
+
+    assert!(MembershipRecord::from_audit(&audit("/api/v4/users/login", "", start())).is_none());
+    assert!(MembershipRecord::from_post(&system_post(
+        "system_header_change",
+        "alice",
+        json!({}),
+        start()
+    ))
+    .is_none());
+}
+
+#[test]
+fn deduplicates_across_sources() {
+    let mut stream = MembershipAuditStream::new(WINDOW);
+    let post = system_post(
+        "system_add_to_channel",
+        "admin",
+        json!({"userId": "admin", "username": "admin", "addedUserId": "carol", "addedUsername": "carol"}),
+        start(),
+    );
+    let added = event("user_added", json!({"team_id": "", "user_id": "carol"}));
+    let removed = event(
+        "user_removed",
+        json!({"remover_id": "admin", "user_id": "carol"}),
+    );
+
+    let first = stream
+        .record_event(&added, start() + Duration::seconds(1))
+        .unwrap();
+    assert_eq!(first.source, AuditSource::Event);
+    assert!(stream.record_post(&post).is_none());
+    assert!(stream
+        .record_audit(&audit(
+            "/api/v4/channels/town-square/members",
+            "name=town-square user_id=carol",
+            start(),
+        ))
+        .is_none());
+
+    // Leaving and joining again are new changes, even within the window
+    let left = stream
+        .record_event(&removed, start() + Duration::seconds(10))
+        .unwrap();
+    assert_eq!(left.action, MembershipAction::Left);
+    assert!(stream
+        .record_event(&added, start() + Duration::seconds(20))
+        .is_none());
+    assert!(stream
+        .record_event(&added, start() + Duration::minutes(5))
+        .is_some());
+}
+
+#[test]
+fn forgets_old_changes() {
+    let mut stream =
+        MembershipAuditStream::new(WINDOW).retention(std::time::Duration::from_secs(3600));
+    let added = event("user_added", json!({"team_id": "", "user_id": "carol"}));
+
+    assert!(stream.record_event(&added, start()).is_some());
+    assert!(stream
+        .record_event(&added, start() + Duration::hours(2))
+        .is_some());
+    // Older than the retention compared to the newest change
+    assert!(stream
+        .record_event(&added, start() + Duration::minutes(30))
+        .is_none());
+    let record = stream
+        .record_event(&added, start() + Duration::minutes(70))
+        .unwrap();
+    assert_eq!(
+        serde_json::to_value(&record).unwrap(),
+        json!({
+            "channel_id": "town-square",
+            "user_id": "carol",
+            "action": "joined",
+            "actor_id": null,
+            "at": (start() + Duration::minutes(70)).timestamp_millis(),
+            "source": "event",
+        })
+    );
+}
@@ -0,0 +1,411 @@
+//! Endpoints replayed from recorded cassettes, see `mattermost_structs::testing::cassette`
+
+#![cfg(feature = "testing")]
+
+use chrono::{NaiveDate, TimeZone, Utc};
+use mattermost_structs::{
+    api::{
+        ChannelPatch, ChannelType, Client, CreateChannelRequest, OffboardOptions, OffboardStep,
+        SearchTerms, UserRole,
+    },
+    emoji::EmojiResolver,
+    error::Error,
+    ids::{ChannelId, PostId, TeamId, UserId},
+    relay::{ChannelRef, Relay, RelayedPost},
+    testing::{
+        cassette::{Cassette, CassetteServer},
+        fixtures::{MessagePushFixture, PostFixture},
+    },
+    websocket::Events,
+};
+use std::{io, path::Path};
+
+fn replay(name: &str) -> (CassetteServer, Client) {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/cassettes")
+        .join(format!("{}.yaml", name));
+    let server = CassetteServer::replay(Cassette::load(path).unwrap()).unwrap();
+    let client = Client::new(server.base_url(), "token").unwrap();
+    (server, client)
+}
+
+#[test]
+fn users() {
+    let (server, client) = replay("users");
+
+    let me = client.get_me().unwrap();
+    assert_eq!(me.username, "alice");
+    let users = client
+        .get_users_by_id(std::slice::from_ref(&me.id))
+        .unwrap();
+    assert_eq!(users.len(), 1);
+    assert_eq!(users[0].id, me.id);
+    let missing = client.get_user(UserId::from("missing")).unwrap_err();
+    assert!(missing.is_not_found());
+    match &missing {
+        Error::Api {
+            id,
+            message,
+            status,
+            request_id,
+        } => {
+            assert_eq!(id, "app.user.missing_account.const");
+            assert_eq!(message, "Unable to find the user.");
+            assert_eq!(*status, 404);
+            assert_eq!(request_id, "x3ncmpmjmpnybgqa45tbhbrsjy");
+        }
+        err => panic!("Expected an API error, got {:?}", err),
+    }
+    assert_eq!(missing.to_string(), "Unable to find the user. (status 404)");
+
+    assert!(server.all_used());
+    assert!(server.unmatched().is_empty());
+}
+
+#[test]
+fn unmatched_requests_fail() {
+    let (server, client) = replay("users");
+
+    // The body differs from the recorded one
+    assert!(client.get_users_by_id(&[UserId::from("other")]).is_err());
+    assert_eq!(server.unmatched().len(), 1);
+    assert_eq!(server.unmatched()[0].path, "/api/v4/users/ids");
+}
+
+#[test]
+fn record_through_replay() {
+    let (upstream, _) = replay("users");
+    let recorder = CassetteServer::record(upstream.base_url()).unwrap();
+    let client = Client::new(recorder.base_url(), "secret token").unwrap();
+    client.get_me().unwrap();
+    client.get_user(UserId::from("missing")).unwrap_err();
+
+    let cassette = recorder.cassette();
+    assert_eq!(
+        cassette.server_version.as_deref(),
+        None,
+        "the replayed responses carry no version header"
+    );
+    assert_eq!(cassette.interactions.len(), 2);
+    assert_eq!(cassette.interactions[0].request.path, "/api/v4/users/me");
+    assert_eq!(cassette.interactions[1].response.status, 404);
+    let yaml = serde_yaml::to_string(&cassette).unwrap();
+    assert!(!yaml.contains("secret token"));
+}
+
+#[test]
+fn login() {
+    let (server, _) = replay("login");
+
+    let err = Client::login(server.base_url(), "alice", "hunter2").unwrap_err();
+    match &err {
+        Error::LoginFailed(reason) => assert_eq!(reason, "Invalid MFA token."),
+        kind => panic!("Expected a failed login, got {:?}", kind),
+    }
+    assert!(err.is_auth_error());
+
+    let client = Client::login_with_mfa(server.base_url(), "alice", "hunter2", "123456").unwrap();
+    assert_eq!(client.token(), "pbq8zdn5zt8rdqaahxq5w3fbzc");
+    assert_eq!(client.get_me().unwrap().username, "alice");
+    assert!(server.all_used());
+}
+
+#[test]
+fn resolve_mentions() {
+    let (server, client) = replay("mentions");
+
+    let users = client
+        .resolve_mentions("@bob can you ask @Alice? cc @bob @devs @nobody", &["devs"])
+        .unwrap();
+    let usernames: Vec<&str> = users.iter().map(|user| user.username.as_str()).collect();
+    assert_eq!(usernames, vec!["bob", "alice"]);
+    // No mentions, no request
+    assert!(client
+        .resolve_mentions("`@alice` in code", &[] as &[&str])
+        .unwrap()
+        .is_empty());
+    assert!(server.all_used());
+}
+
+#[test]
+fn resolve_emoji() {
+    let (server, client) = replay("emoji");
+    let resolver = EmojiResolver::new(client);
+
+    let thumbsup = resolver.resolve_name("+1").unwrap();
+    assert_eq!(thumbsup.unicode.as_deref(), Some("\u{1F44D}"));
+    assert_eq!(thumbsup.image, None);
+    let flag = resolver.resolve_name("flag-de").unwrap();
+    assert_eq!(flag.unicode.as_deref(), Some("\u{1F1E9}\u{1F1EA}"));
+    // System emoji need no request
+    assert_eq!(resolver.cached(), 0);
+
+    let parrot = resolver.resolve_name("partyparrot").unwrap();
+    assert_eq!(parrot.unicode, None);
+    assert_eq!(
+        parrot.image.unwrap().path(),
+        "/api/v4/emoji/pzbe5xkq3fbp8mzfpd3mgqw7jo/image"
+    );
+    let deleted = resolver.resolve_name("deleted").unwrap();
+    assert_eq!((deleted.unicode, deleted.image), (None, None));
+    assert_eq!(resolver.cached(), 2);
+    assert!(resolver
+        .resolve_name("partyparrot")
+        .unwrap()
+        .image
+        .is_some());
+    assert_eq!(resolver.cached(), 2);
+    assert!(server.all_used());
+}
+
+#[test]
+fn files() {
+    let (server, client) = replay("files");
+    let file_id = "7xwfezrnrtfu5dmbh9k5mzqtrw";
+
+    let info = client.get_file_info(file_id).unwrap();
+    assert_eq!(info.name, "chart.png");
+    assert_eq!((info.width, info.height), (Some(640), Some(480)));
+    assert!(info.has_preview_image);
+
+    let mut content = Vec::new();
+    assert_eq!(client.get_file(file_id, &mut content).unwrap(), info.size);
+    assert_eq!(content, b"full size");
+    let mut thumbnail = Vec::new();
+    client.get_file_thumbnail(file_id, &mut thumbnail).unwrap();
+    assert_eq!(thumbnail, b"thumbnail");
+    let mut preview = Vec::new();
+    client.get_file_preview(file_id, &mut preview).unwrap();
+    assert_eq!(preview, b"preview");
+    assert!(server.all_used());
+}
+
+#[test]
+fn upload_file_from_reader() {
+    let (server, client) = replay("upload_file");
+
+    let content = io::Cursor::new(b"hello world".to_vec());
+    let infos = client
+        .upload_file_from_reader(
+            ChannelId::from("3df1ha9a7i8ftybnsp9u4iwcme"),
+            "notes.txt",
+            content,
+        )
+        .unwrap();
+    assert_eq!(infos.len(), 1);
+    assert_eq!(infos[0].name, "notes.txt");
+    assert_eq!(infos[0].size, 11);
+    assert!(server.all_used());
+}
+
+#[test]
+fn search_posts() {
+    let (server, client) = replay("search");
+
+    let terms = SearchTerms::new()
+        .word("release")
+        .phrase("known issues")
+        .from("alice")
+        .after(NaiveDate::from_ymd_opt(2018, 12, 31).unwrap());
+    let results = client
+        .search_posts(TeamId::from("fkcztyxq5ibw7nt3ijrs4cdmqw"), &terms, false)
+        .unwrap();
+    // Ordered by relevance, not by id or time
+    let ids: Vec<&str> = results.iter().map(|post| post.id.as_str()).collect();
+    assert_eq!(
+        ids,
+        vec!["zzpn5qbeafbdjr6p9dzxaxsnmc", "aab6u7wxkfgdpgopgtb9azbqyo"]
+    );
+    assert_eq!(
+        results
+            .highlighted_message("zzpn5qbeafbdjr6p9dzxaxsnmc")
+            .unwrap(),
+        "The **release** \"**known issues**\" list"
+    );
+    let posts = results.into_ordered();
+    assert_eq!(posts[1].message, "Release notes are out");
+    assert!(server.all_used());
+}
+
+#[test]
+fn recent_mentions_are_paged() {
+    let (server, client) = replay("recent_mentions");
+
+    let since = Utc.with_ymd_and_hms(2019, 1, 10, 0, 0, 0).unwrap();
+    let mentions = client.get_my_recent_mentions(since).unwrap();
+    // A full page of 60 and two more from the second page, which reaches back before `since`
+    assert_eq!(mentions.len(), 62);
+    assert_eq!(mentions[0].message, "@alice ping 0");
+    assert_eq!(mentions[61].message, "@alice ping 61");
+    assert!(mentions.iter().all(|post| post.create_at > since));
+    assert!(server.all_used());
+}
+
+#[test]
+fn archive_inactive_channels() {
+    let (server, client) = replay("inactive_channels");
+
+    let since = Utc.with_ymd_and_hms(2019, 6, 1, 0, 0, 0).unwrap();
+    let inactive = client
+        .get_inactive_channels(since, &[] as &[TeamId])
+        .unwrap();
+    // Least recently active first, without the active, new, and archived channels
+    let names: Vec<&str> = inactive
+        .iter()
+        .map(|channel| channel.channel.name.as_str())
+        .collect();
+    assert_eq!(names, vec!["empty", "release-planning"]);
+
+    let channel_ids: Vec<&ChannelId> = inactive
+        .iter()
+        .map(|channel| channel.channel.id())
+        .collect();
+    let dry_run = client.archive_channels(&channel_ids, true).unwrap();
+    assert_eq!(dry_run.archived.len(), 2);
+    let report = client.archive_channels(&channel_ids, false).unwrap();
+    assert_eq!(
+        report.archived,
+        vec![ChannelId::from("w5fimsbmp7dfxgnrbf7d4ynqay")]
+    );
+    assert_eq!(report.failed.len(), 1);
+    assert_eq!(report.failed[0].channel_id, "3df1ha9a7i8ftybnsp9u4iwcme");
+    assert!(!report.is_complete());
+    assert!(server.all_used());
+}
+
+#[test]
+fn manage_channels() {
+    let (server, client) = replay("channels");
+
+    let request = CreateChannelRequest::builder("ka6h8ccbe7rn5kq4o8ik7t9yao", "release-planning")
+        .display_name("Release Planning")
+        .channel_type(ChannelType::Private)
+        .purpose("Plan releases")
+        .build()
+        .unwrap();
+    let channel = client.create_channel(&request).unwrap();
+    assert_eq!(channel.name, "release-planning");
+    assert_eq!(channel.type_, ChannelType::Private);
+
+    let patch = ChannelPatch {
+        header: Some("Next release: 1.2".to_string()),
+        ..ChannelPatch::default()
+    };
+    let channel = client.patch_channel(channel.id(), &patch).unwrap();
+    assert_eq!(channel.header, "Next release: 1.2");
+
+    client.delete_channel(channel.id()).unwrap();
+    assert!(client
+        .get_channel_by_id(channel.id())
+        .unwrap()
+        .is_archived());
+    assert!(!client.restore_channel(channel.id()).unwrap().is_archived());
+    assert!(server.all_used());
+}
+
+#[test]
+fn channel_members() {
+    let (server, client) = replay("channel_members");
+    let channel_id = ChannelId::from("3df1ha9a7i8ftybnsp9u4iwcme");
+    let alice = UserId::from("ur6ckbszh7nzje6hkkxjbngswo");
+    let bob = UserId::from("9x3f8cnh4jdt5qzsu1yxkgcw6h");
+
+    let added = client.add_channel_member(&channel_id, &bob).unwrap();
+    assert_eq!(added.user_id, bob);
+    let members = client.get_channel_members(&channel_id, 0, 60).unwrap();
+    assert_eq!(members.len(), 2);
+    let member = client.get_channel_member(&channel_id, &alice).unwrap();
+    assert!(member.roles.contains(&UserRole::ChannelAdmin));
+    client.remove_channel_member(&channel_id, &bob).unwrap();
+    assert!(client
+        .get_channel_member(&channel_id, &bob)
+        .unwrap_err()
+        .is_not_found());
+    assert!(server.all_used());
+}
+
+#[test]
+fn offboard_user() {
+    let (server, client) = replay("offboard");
+    let bob = UserId::from("9x3f8cnh4jdt5qzsu1yxkgcw6h");
+    let forbidden_team = TeamId::from("q9qdwq9ykfgmbgtxhtn3rsqsmo");
+    let options = OffboardOptions {
+        message: Some("Your account is being deactivated today. Thanks for everything!".into()),
+        team_ids: vec![
+            TeamId::from("ka6h8ccbe7rn5kq4o8ik7t9yao"),
+            forbidden_team.clone(),
+        ],
+        ..OffboardOptions::default()
+    };
+
+    let report = client.offboard_user(&bob, &options).unwrap();
+    assert_eq!(report.user_id, bob);
+    assert_eq!(report.steps.len(), 6);
+    assert_eq!(report.steps[5].step, OffboardStep::Deactivate);
+    let failed: Vec<_> = report.failed().map(|step| &step.step).collect();
+    assert_eq!(failed, [&OffboardStep::LeaveTeam(forbidden_team)]);
+    assert!(!report.is_complete());
+    assert!(server.all_used());
+}
+
+#[test]
+fn direct_messages() {
+    let (server, client) = replay("direct_messages");
+    let alice = UserId::from("ur6ckbszh7nzje6hkkxjbngswo");
+    let bob = UserId::from("9x3f8cnh4jdt5qzsu1yxkgcw6h");
+    let carol = UserId::from("p8hbqbxf6ffp3yjatgkhxk1c9a");
+
+    let post = client
+        .send_direct_message(&bob, "The deploy finished.")
+        .unwrap();
+    assert_eq!(post.channel_id, "h5wsyfcs6tr1ujnq3x4mbxuxhr");
+    let group = client.create_group_channel(&[alice, bob, carol]).unwrap();
+    assert_eq!(group.type_, ChannelType::Group);
+    assert_eq!(group.display_name, "alice, bob, carol");
+    assert!(server.all_used());
+}
+
+#[test]
+fn relay_copy_edit_delete() {
+    let (server, client) = replay("relay");
+    let source = ChannelRef::new("community", "4yg8x3qnqjfq3jzrw5z3kbm8ch");
+    let target = ChannelRef::new("internal", "3df1ha9a7i8ftybnsp9u4iwcme");
+    let mut relay = Relay::new()
+        .server("community", client.clone())
+        .server("internal", client)
+        .route(source, target.clone());
+
+    let post = PostFixture::new()
+        .id("9xh1gtyq5fy8bkakw57b7tgqeh")
+        .in_channel("4yg8x3qnqjfq3jzrw5z3kbm8ch")
+        .message("Release notes are out")
+        .build();
+    let mut posted = MessagePushFixture::posted(post.clone()).build();
+    if let Events::Posted { sender_name, .. } = &mut posted.event {
+        *sender_name = "@alice".to_string();
+    }
+    assert_eq!(relay.handle("community", &posted).unwrap(), 1);
+    let copies = relay.copies_of("community", "9xh1gtyq5fy8bkakw57b7tgqeh");
+    assert_eq!(
+        copies,
+        [RelayedPost {
+            target,
+            post_id: PostId::from("p4n7ynhrfjg9db3bkyo6gqm1ya"),
+        }]
+    );
+    // Delivered again, e.g., after resuming the websocket connection
+    assert_eq!(relay.handle("community", &posted).unwrap(), 0);
+
+    let mut edited = post.clone();
+    edited.message = "Release notes are out, finally".to_string();
+    let edited = MessagePushFixture::new(Events::PostEdited { post: edited }).build();
+    assert_eq!(relay.handle("community", &edited).unwrap(), 1);
+
+    let deleted = MessagePushFixture::new(Events::PostDeleted { post }).build();
+    assert_eq!(relay.handle("community", &deleted).unwrap(), 1);
+    assert!(relay
+        .copies_of("community", "9xh1gtyq5fy8bkakw57b7tgqeh")
+        .is_empty());
+    assert!(server.all_used());
+    assert!(server.unmatched().is_empty());
+}
@@ -0,0 +1,149 @@
+//! Compatibility of the models with different server versions
+//!
+//! `tests/compat/<version>/rest` contains one response per model, named after the model,
+//! `tests/compat/<version>/websocket` one message per event, named after the event.
+//! Every version must cover all of [`REST_MODELS`] and [`EVENTS`], such that adding a model or a version extends the whole matrix.
+
+use mattermost_structs::{
+    api::{Channel, User},
+    websocket::{ChannelMember, Events, Message, Post, Team, UserStatus},
+};
+use serde::de::DeserializeOwned;
+use std::{
+    collections::BTreeSet,
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// Server versions with fixtures, the directory names in `tests/compat`
+const VERSIONS: &[&str] = &["5.x", "6.x", "7.x", "9.x"];
+
+const REST_MODELS: &[&str] = &[
+    "channel",
+    "channel_member",
+    "post",
+    "team",
+    "user",
+    "user_status",
+];
+
+const EVENTS: &[&str] = &[
+    "hello",
+    "posted",
+    "reaction_added",
+    "status_change",
+    "typing",
+];
+
+fn compat_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/compat")
+}
+
+/// Names of the fixtures in `dir`, without the extension
+fn fixtures(dir: &Path) -> BTreeSet<String> {
+    fs::read_dir(dir)
+        .unwrap_or_else(|err| panic!("Failed to list {}: {}", dir.display(), err))
+        .map(|entry| {
+            let path = entry.unwrap().path();
+            assert_eq!(
+                path.extension().and_then(|ext| ext.to_str()),
+                Some("json"),
+                "{} is not a json fixture",
+                path.display()
+            );
+            path.file_stem().unwrap().to_string_lossy().into_owned()
+        })
+        .collect()
+}
+
+fn assert_covers(dir: &Path, expected: &[&str]) {
+    let expected: BTreeSet<String> = expected.iter().map(ToString::to_string).collect();
+    assert_eq!(
+        fixtures(dir),
+        expected,
+        "{} does not contain exactly the expected fixtures",
+        dir.display()
+    );
+}
+
+fn parse<T>(path: &Path) -> T
+where
+    T: DeserializeOwned,
+{
+    let text = fs::read_to_string(path).unwrap();
+    serde_json::from_str(&text)
+        .unwrap_or_else(|err| panic!("Failed to parse {}: {}", path.display(), err))
+}
+
+fn check_rest(version: &str) {
+    let dir = compat_dir().join(version).join("rest");
+    assert_covers(&dir, REST_MODELS);
+    for model in REST_MODELS {
+        let path = dir.join(format!("{}.json", model));
+        match *model {
+            "channel" => drop(parse::<Channel>(&path)),
+            "channel_member" => drop(parse::<ChannelMember>(&path)),
+            "post" => drop(parse::<Post>(&path)),
+            "team" => drop(parse::<Team>(&path)),
+            "user" => drop(parse::<User>(&path)),
+            "user_status" => drop(parse::<UserStatus>(&path)),
+            _ => panic!("No model registered for {}", path.display()),
+        }
+    }
+}
+
+fn check_websocket(version: &str) {
+    let dir = compat_dir().join(version).join("websocket");
+    assert_covers(&dir, EVENTS);
+    for event in EVENTS {
+        let path = dir.join(format!("{}.json", event));
+        let push = match parse::<Message>(&path) {
+            Message::Push(push) => push,
+            Message::Reply(reply) => panic!("{} is a reply: {:?}", path.display(), reply),
+        };
+        // Catches events which are only accepted as `Events::Unknown`
+        assert_eq!(push.event.name(), *event, "{}", path.display());
+        if let Events::Unknown { .. } = push.event {
+            panic!("{} is not modelled", path.display());
+        }
+        if let Events::Hello { server_version, .. } = &push.event {
+            let major = version.trim_end_matches(".x");
+            assert!(
+                server_version.starts_with(&format!("{}.", major)),
+                "{} reports version {}",
+                path.display(),
+                server_version
+            );
+        }
+    }
+}
+
+#[test]
+fn all_versions_are_tested() {
+    let dirs: BTreeSet<String> = fs::read_dir(compat_dir())
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+        .collect();
+    let versions: BTreeSet<String> = VERSIONS.iter().map(ToString::to_string).collect();
+    assert_eq!(dirs, versions);
+}
+
+macro_rules! compat_tests {
+    ($($name:ident: $version:expr,)*) => {
+        $(
+            #[test]
+            fn $name() {
+                assert!(VERSIONS.contains(&$version));
+                check_rest($version);
+                check_websocket($version);
+            }
+        )*
+    };
+}
+
+compat_tests! {
+    server_5: "5.x",
+    server_6: "6.x",
+    server_7: "7.x",
+    server_9: "9.x",
+}
@@ -0,0 +1,89 @@
+//! Validation of `mattermost_structs::api::CreatePostRequestBuilder`
+
+#![cfg(feature = "testing")]
+
+use mattermost_structs::{
+    api::{CreatePostRequest, MAX_POST_FILE_IDS, MAX_POST_MESSAGE_LENGTH},
+    error::Error,
+    ids::PostId,
+    testing::fixtures::PostFixture,
+};
+
+const CHANNEL_ID: &str = "4xp9fdt77pncbef59f4k1qe83o";
+
+#[test]
+fn message_length_counts_characters() {
+    // Multi-byte characters count once, like on the server
+    let message = "ä".repeat(MAX_POST_MESSAGE_LENGTH);
+    let request = CreatePostRequest::builder()
+        .channel_id(CHANNEL_ID)
+        .message(message.clone())
+        .build()
+        .unwrap();
+    assert_eq!(request.message, message);
+
+    let err = CreatePostRequest::builder()
+        .channel_id(CHANNEL_ID)
+        .message(message + "ä")
+        .build()
+        .unwrap_err();
+    assert!(matches!(err, Error::InvalidPostRequest(_)), "{:?}", err);
+}
+
+#[test]
+fn file_ids_are_limited() {
+    let file_ids: Vec<_> = (0..MAX_POST_FILE_IDS)
+        .map(|i| format!("file{}", i))
+        .collect();
+    let request = CreatePostRequest::builder()
+        .channel_id(CHANNEL_ID)
+        .file_ids(file_ids.clone())
+        .build()
+        .unwrap();
+    assert_eq!(request.file_ids, file_ids);
+
+    let err = CreatePostRequest::builder()
+        .channel_id(CHANNEL_ID)
+        .file_ids(file_ids)
+        .file_id("one too many")
+        .build()
+        .unwrap_err();
+    assert!(matches!(err, Error::InvalidPostRequest(_)), "{:?}", err);
+}
+
+#[test]
+fn channel_is_required() {
+    let err = CreatePostRequest::builder()
+        .message("hello")
+        .build()
+        .unwrap_err();
+    assert!(matches!(err, Error::InvalidPostRequest(_)), "{:?}", err);
+}
+
+#[test]
+fn reply_to_a_root_post_starts_a_thread() {
+    let root = PostFixture::new().in_channel(CHANNEL_ID).build();
+    let request = CreatePostRequest::builder()
+        .in_reply_to(&root)
+        .message("reply")
+        .build()
+        .unwrap();
+    assert_eq!(request.root_id.as_ref(), Some(&root.id));
+    assert_eq!(request.channel_id, root.channel_id);
+}
+
+#[test]
+fn reply_to_a_reply_stays_in_the_thread() {
+    let root_id = PostId::from("ia4zjk6r8tfybqz4cx9ed6s5ch");
+    let reply = PostFixture::new()
+        .in_channel(CHANNEL_ID)
+        .reply_to(root_id.clone())
+        .build();
+    let request = CreatePostRequest::builder()
+        .in_reply_to(&reply)
+        .message("another reply")
+        .build()
+        .unwrap();
+    assert_eq!(request.root_id, Some(root_id));
+    assert_eq!(request.channel_id, reply.channel_id);
+}
@@ -0,0 +1,139 @@
+//! Golden serialization tests
+//!
+//! Each file in `tests/golden` contains a payload as sent by the server.
+//! Deserializing and serializing it again must result in an equivalent document.
+
+use mattermost_structs::{
+    api::{Channel, ChannelBookmark, LogEntry, PostList, Role, ScheduledPost, Scheme, User},
+    websocket::{ChannelMember, Events, Message, Post, Team, UserStatus},
+};
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::Value;
+use std::{fs, path::Path};
+
+/// Fields containing space separated sets, whose order is not preserved
+const SET_FIELDS: &[&str] = &["roles", "explicit_roles", "hashtags"];
+
+/// Bring a document into a canonical form
+///
+/// * `null` values are treated like missing fields.
+/// * Strings containing JSON, like the `post` of a `posted` event, are compared by their content.
+/// * Space separated sets are sorted.
+fn normalize(value: Value) -> Value {
+    match value {
+        Value::Object(map) => Value::Object(
+            map.into_iter()
+                .filter(|(_, v)| !v.is_null())
+                .map(|(k, v)| {
+                    let v = match v {
+                        Value::String(s) if SET_FIELDS.contains(&&*k) => {
+                            let mut items: Vec<_> = s.split_whitespace().collect();
+                            items.sort();
+                            Value::String(items.join(" "))
+                        }
+                        v => normalize(v),
+                    };
+                    (k, v)
+                })
+                .collect(),
+        ),
+        Value::Array(values) => Value::Array(values.into_iter().map(normalize).collect()),
+        Value::String(s) => {
+            if s.starts_with('{') || s.starts_with('[') {
+                if let Ok(nested) = serde_json::from_str(&s) {
+                    return normalize(nested);
+                }
+            }
+            Value::String(s)
+        }
+        v => v,
+    }
+}
+
+fn check<T>(name: &str)
+where
+    T: DeserializeOwned + Serialize,
+{
+    let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/golden")
+        .join(format!("{}.json", name));
+    let text = fs::read_to_string(&path).unwrap();
+    let original: Value = serde_json::from_str(&text).unwrap();
+    let parsed: T = serde_json::from_str(&text)
+        .unwrap_or_else(|err| panic!("Failed to parse {}: {}", path.display(), err));
+    let reserialized = serde_json::to_value(&parsed).unwrap();
+    assert_eq!(
+        normalize(original),
+        normalize(reserialized),
+        "{} does not round-trip",
+        path.display()
+    );
+}
+
+macro_rules! golden_tests {
+    ($($name:ident: $type:ty,)*) => {
+        $(
+            #[test]
+            fn $name() {
+                check::<$type>(stringify!($name));
+            }
+        )*
+    };
+}
+
+golden_tests! {
+    message_hello: Message,
+    message_hello_resumable: Message,
+    message_reply: Message,
+    message_posted: Message,
+    message_typing: Message,
+    message_status_change: Message,
+    message_status_change_manual: Message,
+    message_reaction_added: Message,
+    message_post_edited: Message,
+    message_channel_deleted: Message,
+    message_channel_restored: Message,
+    message_channel_converted: Message,
+    message_channel_viewed: Message,
+    message_license_changed: Message,
+    message_unknown_event: Message,
+    post: Post,
+    post_combined_user_activity: Post,
+    post_relayed: Post,
+    post_embeds: Post,
+    user: User,
+    user_status: UserStatus,
+    channel: Channel,
+    channel_member: ChannelMember,
+    team: Team,
+    scheduled_post: ScheduledPost,
+    channel_bookmark: ChannelBookmark,
+    role: Role,
+    scheme: Scheme,
+    post_list: PostList,
+    log_entry: LogEntry,
+}
+
+#[test]
+fn unknown_events_are_kept() {
+    let path =
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/golden/message_unknown_event.json");
+    let message: Message = serde_json::from_str(&fs::read_to_string(path).unwrap()).unwrap();
+    match message {
+        Message::Push(push) => match push.event {
+            Events::Unknown { event, data } => {
+                assert_eq!(event, "post_acknowledgement_added");
+                assert!(data.get("acknowledgement").is_some());
+            }
+            event => panic!("Expected an unknown event, got {:?}", event),
+        },
+        message => panic!("Expected a push message, got {:?}", message),
+    }
+
+    // Known events must still match their model
+    let invalid = r#"{"event":"typing","data":{"user_id":"ur6ckbszh7nzje6hkkxjbngswo"},"broadcast":{"omit_users":null,"user_id":"","channel_id":"","team_id":""},"seq":3}"#;
+    assert!(serde_json::from_str::<Message>(invalid).is_err());
+    // Also if only a nested value is unknown
+    let invalid = r#"{"event":"status_change","data":{"user_id":"ur6ckbszh7nzje6hkkxjbngswo","status":"busy"},"broadcast":{"omit_users":null,"user_id":"","channel_id":"","team_id":""},"seq":3}"#;
+    assert!(serde_json::from_str::<Message>(invalid).is_err());
+}
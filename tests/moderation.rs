@@ -0,0 +1,147 @@
+//! Heuristics of `mattermost_structs::moderation`
+
+#![cfg(feature = "testing")]
+
+use chrono::{DateTime, Duration, TimeZone, Utc};
+use mattermost_structs::{
+    moderation::{ModerationConfig, Moderator, SignalKind},
+    testing::fixtures::PostFixture,
+    websocket::PostType,
+};
+
+fn start() -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(2024, 3, 1, 12, 0, 0).unwrap()
+}
+
+/// Check a post of `user_id` and return the kinds of the signals
+fn check(moderator: &mut Moderator, user_id: &str, message: &str, secs: i64) -> Vec<SignalKind> {
+    let post = PostFixture::new()
+        .by_user(user_id)
+        .message(message)
+        .created_at(start() + Duration::seconds(secs))
+        .build();
+    moderator
+        .check(&post)
+        .into_iter()
+        .map(|signal| signal.kind)
+        .collect()
+}
+
+#[test]
+fn rate_exceeded() {
+    let mut moderator = Moderator::new(ModerationConfig::default());
+    for i in 0..10 {
+        assert_eq!(
+            check(&mut moderator, "u1", &format!("post {}", i), i),
+            vec![]
+        );
+    }
+    assert_eq!(
+        check(&mut moderator, "u1", "post 10", 10),
+        vec![SignalKind::RateExceeded { count: 11 }]
+    );
+    // Other users have their own rate
+    assert_eq!(check(&mut moderator, "u2", "post", 11), vec![]);
+    // The first posts left the window of 60 seconds
+    assert_eq!(check(&mut moderator, "u1", "post 11", 65), vec![]);
+}
+
+#[test]
+fn duplicates_ignore_case_and_whitespace() {
+    let mut moderator = Moderator::new(ModerationConfig::default());
+    assert_eq!(check(&mut moderator, "u1", "Buy now", 0), vec![]);
+    assert_eq!(check(&mut moderator, "u1", "buy  NOW", 100), vec![]);
+    assert_eq!(
+        check(&mut moderator, "u1", " buy now\n", 200),
+        vec![SignalKind::Duplicate { count: 3 }]
+    );
+    // The first two left the window of 600 seconds
+    assert_eq!(check(&mut moderator, "u1", "buy now", 750), vec![]);
+}
+
+#[test]
+fn link_only() {
+    let mut moderator = Moderator::new(ModerationConfig::default());
+    assert_eq!(
+        check(
+            &mut moderator,
+            "u1",
+            "https://example.com <www.example.org> [docs](https://example.com/docs)",
+            0
+        ),
+        vec![SignalKind::LinkOnly]
+    );
+    assert_eq!(
+        check(&mut moderator, "u2", "see https://example.com", 0),
+        vec![]
+    );
+
+    let mut moderator = Moderator::new(ModerationConfig {
+        link_only: false,
+        ..ModerationConfig::default()
+    });
+    assert_eq!(
+        check(&mut moderator, "u1", "https://example.com", 0),
+        vec![]
+    );
+}
+
+#[test]
+fn mass_mention() {
+    let mut moderator = Moderator::new(ModerationConfig {
+        max_mentions: 3,
+        ..ModerationConfig::default()
+    });
+    assert_eq!(check(&mut moderator, "u1", "@alice @bob", 0), vec![]);
+    assert_eq!(
+        check(&mut moderator, "u1", "@alice @bob @carol", 1),
+        vec![SignalKind::MassMention {
+            count: 3,
+            channel_wide: false
+        }]
+    );
+    // Channel wide mentions only count if configured
+    assert_eq!(check(&mut moderator, "u1", "@channel hi", 2), vec![]);
+
+    let mut moderator = Moderator::new(ModerationConfig {
+        channel_wide_mentions: true,
+        ..ModerationConfig::default()
+    });
+    assert_eq!(
+        check(&mut moderator, "u1", "@here hi @alice", 0),
+        vec![SignalKind::MassMention {
+            count: 1,
+            channel_wide: true
+        }]
+    );
+}
+
+#[test]
+fn system_posts_are_ignored() {
+    let mut moderator = Moderator::new(ModerationConfig::default());
+    let post = PostFixture::new()
+        .type_(PostType::SystemJoinChannel)
+        .message("https://example.com")
+        .build();
+    assert_eq!(moderator.check(&post), vec![]);
+    assert_eq!(moderator.tracked_users(), 0);
+}
+
+#[test]
+fn idle_users_are_evicted() {
+    let mut moderator = Moderator::new(ModerationConfig::default());
+    check(&mut moderator, "u1", "hello", 0);
+    check(&mut moderator, "u2", "hello", 10);
+    assert_eq!(moderator.tracked_users(), 2);
+
+    // Users are only evicted once the window of 600 seconds passed since the last eviction
+    check(&mut moderator, "u3", "hello", 300);
+    assert_eq!(moderator.tracked_users(), 3);
+    check(&mut moderator, "u3", "hello again", 605);
+    assert_eq!(moderator.tracked_users(), 2);
+    check(&mut moderator, "u3", "and again", 1300);
+    assert_eq!(moderator.tracked_users(), 1);
+
+    moderator.forget_user("u3");
+    assert_eq!(moderator.tracked_users(), 0);
+}
@@ -0,0 +1,126 @@
+//! Overflow policies of `mattermost_structs::queue`
+
+#![cfg(feature = "testing")]
+
+use mattermost_structs::{
+    queue::{bounded, OverflowPolicy},
+    testing::fixtures::{MessagePushFixture, PostFixture},
+    websocket::{Events, MessagePush},
+};
+use std::{
+    sync::{mpsc, Arc},
+    thread,
+    time::Duration,
+};
+
+fn typing(seq: usize) -> MessagePush {
+    MessagePushFixture::new(Events::Typing {
+        parent_id: String::new(),
+        user_id: "i1pe3qz5ajyuzkb8e5ja4pc7ow".to_string(),
+    })
+    .seq(seq)
+    .build()
+}
+
+fn posted(seq: usize) -> MessagePush {
+    MessagePushFixture::posted(PostFixture::new().build())
+        .seq(seq)
+        .build()
+}
+
+fn is_typing(message: &MessagePush) -> bool {
+    matches!(message.event, Events::Typing { .. })
+}
+
+#[test]
+fn block_waits_for_the_receiver() {
+    let (sender, receiver) = bounded(1, OverflowPolicy::Block);
+    sender.send(posted(1)).unwrap();
+
+    let (done, sent) = mpsc::channel();
+    let thread = thread::spawn(move || {
+        let result = sender.send(posted(2));
+        done.send(()).unwrap();
+        result
+    });
+    assert!(sent.recv_timeout(Duration::from_millis(100)).is_err());
+    assert_eq!(receiver.len(), 1);
+
+    assert_eq!(receiver.recv().map(|m| m.seq), Some(1));
+    thread.join().unwrap().unwrap();
+    assert_eq!(receiver.recv().map(|m| m.seq), Some(2));
+    assert_eq!(receiver.dropped(), 0);
+}
+
+#[test]
+fn drop_oldest_counts_drops() {
+    let (sender, receiver) = bounded(2, OverflowPolicy::DropOldest);
+    sender.send(typing(1)).unwrap();
+    sender.send(posted(2)).unwrap();
+    sender.send(posted(3)).unwrap();
+    sender.send(typing(4)).unwrap();
+    sender.send(posted(5)).unwrap();
+    drop(sender);
+
+    assert_eq!(receiver.dropped(), 3);
+    let dropped = receiver.dropped_by_event();
+    assert_eq!(dropped.get("typing"), Some(&1));
+    assert_eq!(dropped.get("posted"), Some(&2));
+    let seqs: Vec<_> = receiver.map(|m| m.seq).collect();
+    assert_eq!(seqs, vec![4, 5]);
+}
+
+#[test]
+fn drop_filtered_falls_back_to_blocking() {
+    let (sender, receiver) = bounded(2, OverflowPolicy::DropFiltered(Arc::new(is_typing)));
+    sender.send(typing(1)).unwrap();
+    sender.send(posted(2)).unwrap();
+    // The queued typing notification makes room
+    sender.send(posted(3)).unwrap();
+    // Nothing queued matches, but the new event does
+    sender.send(typing(4)).unwrap();
+    assert_eq!(receiver.dropped_by_event().get("typing"), Some(&2));
+    assert_eq!(receiver.len(), 2);
+
+    // Nothing matches at all, so the sender waits
+    let (done, sent) = mpsc::channel();
+    let thread = thread::spawn(move || {
+        let result = sender.send(posted(5));
+        done.send(()).unwrap();
+        result
+    });
+    assert!(sent.recv_timeout(Duration::from_millis(100)).is_err());
+
+    assert_eq!(receiver.recv().map(|m| m.seq), Some(2));
+    thread.join().unwrap().unwrap();
+    let seqs: Vec<_> = receiver.map(|m| m.seq).collect();
+    assert_eq!(seqs, vec![3, 5]);
+}
+
+#[test]
+fn recv_ends_after_all_senders_are_dropped() {
+    let (sender, receiver) = bounded(4, OverflowPolicy::Block);
+    let other = sender.clone();
+    sender.send(posted(1)).unwrap();
+    drop(sender);
+    other.send(posted(2)).unwrap();
+    drop(other);
+
+    // Queued events are still delivered
+    assert_eq!(receiver.recv().map(|m| m.seq), Some(1));
+    assert_eq!(receiver.recv().map(|m| m.seq), Some(2));
+    assert_eq!(receiver.recv(), None);
+    assert_eq!(receiver.recv_timeout(Duration::from_secs(10)), None);
+}
+
+#[test]
+fn send_fails_after_the_receiver_is_dropped() {
+    let (sender, receiver) = bounded(1, OverflowPolicy::Block);
+    sender.send(posted(1)).unwrap();
+
+    // Also wakes up a sender waiting for room
+    let thread = thread::spawn(move || sender.send(posted(2)));
+    thread::sleep(Duration::from_millis(50));
+    drop(receiver);
+    assert!(thread.join().unwrap().is_err());
+}
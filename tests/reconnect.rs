@@ -0,0 +1,119 @@
+//! Backoff, circuit breaker, and escalation of `mattermost_structs::reconnect`
+
+use mattermost_structs::reconnect::{
+    Backoff, CircuitState, EscalationPolicy, FailureWindow, Reconnector,
+};
+use std::{
+    num::NonZeroUsize,
+    thread,
+    time::{Duration, Instant},
+};
+
+const SEC: Duration = Duration::from_secs(1);
+
+#[test]
+fn backoff_doubles_up_to_max() {
+    let mut backoff = Backoff::new(SEC, 10 * SEC).jitter(0.);
+    let delays: Vec<_> = (0..6).map(|_| backoff.next_delay().as_secs()).collect();
+    assert_eq!(delays, vec![1, 2, 4, 8, 10, 10]);
+    assert_eq!(backoff.attempts(), 6);
+
+    backoff.reset();
+    assert_eq!(backoff.attempts(), 0);
+    assert_eq!(backoff.next_delay(), SEC);
+}
+
+#[test]
+fn backoff_does_not_overflow() {
+    let mut backoff = Backoff::new(SEC, Duration::MAX).jitter(0.);
+    for _ in 0..100 {
+        backoff.next_delay();
+    }
+    assert_eq!(backoff.next_delay(), SEC * (1 << 31));
+}
+
+#[test]
+fn jitter_only_shortens_delays() {
+    let mut backoff = Backoff::new(8 * SEC, 8 * SEC).jitter(0.25);
+    for _ in 0..100 {
+        let delay = backoff.next_delay();
+        assert!(delay > 6 * SEC && delay <= 8 * SEC, "{:?}", delay);
+    }
+
+    // Jitter is limited to [0, 1]
+    let mut backoff = Backoff::new(8 * SEC, 8 * SEC).jitter(-1.);
+    assert_eq!(backoff.next_delay(), 8 * SEC);
+    let mut backoff = Backoff::new(8 * SEC, 8 * SEC).jitter(2.);
+    for _ in 0..100 {
+        assert!(backoff.next_delay() <= 8 * SEC);
+    }
+}
+
+#[test]
+fn circuit_opens_after_failures_in_a_row() {
+    let cooldown = Duration::from_millis(50);
+    let mut reconnector = Reconnector::new()
+        .backoff(Backoff::new(SEC, 60 * SEC).jitter(0.))
+        .stable_after(60 * SEC)
+        .circuit_breaker(3, cooldown);
+    assert_eq!(reconnector.state(), CircuitState::Closed);
+
+    assert_eq!(reconnector.connection_ended(SEC), SEC);
+    assert_eq!(reconnector.connection_ended(SEC), 2 * SEC);
+    assert_eq!(reconnector.state(), CircuitState::Closed);
+    // The third failure opens the circuit for the cooldown
+    assert_eq!(reconnector.connection_ended(SEC), cooldown);
+    assert_eq!(reconnector.state(), CircuitState::Open);
+    assert_eq!(reconnector.failures(), 3);
+    assert_eq!(reconnector.trips(), 1);
+
+    thread::sleep(cooldown);
+    assert_eq!(reconnector.state(), CircuitState::HalfOpen);
+    // A single failure opens it again
+    assert_eq!(reconnector.connection_ended(SEC), cooldown);
+    assert_eq!(reconnector.state(), CircuitState::Open);
+    assert_eq!(reconnector.trips(), 2);
+
+    thread::sleep(cooldown);
+    assert_eq!(reconnector.state(), CircuitState::HalfOpen);
+    // A stable connection closes it and starts the backoff over
+    assert_eq!(reconnector.connection_ended(60 * SEC), SEC);
+    assert_eq!(reconnector.state(), CircuitState::Closed);
+    assert_eq!(reconnector.failures(), 0);
+    assert_eq!(reconnector.connection_ended(SEC), 2 * SEC);
+}
+
+fn failure_window(max_failures: usize) -> FailureWindow {
+    FailureWindow::new(EscalationPolicy {
+        max_failures: NonZeroUsize::new(max_failures).unwrap(),
+        window_secs: 60,
+        exit: false,
+    })
+}
+
+#[test]
+fn failures_within_the_window_escalate() {
+    let start = Instant::now();
+    let mut failures = failure_window(3);
+    assert!(!failures.record_failure_at(start));
+    assert!(!failures.record_failure_at(start + 20 * SEC));
+    assert!(failures.record_failure_at(start + 60 * SEC));
+
+    // The window starts over after the escalation
+    assert!(!failures.record_failure_at(start + 61 * SEC));
+    assert!(!failures.record_failure_at(start + 62 * SEC));
+    assert!(failures.record_failure_at(start + 63 * SEC));
+}
+
+#[test]
+fn failures_outside_the_window_do_not_escalate() {
+    let start = Instant::now();
+    let mut failures = failure_window(3);
+    for i in 0..10 {
+        assert!(!failures.record_failure_at(start + i * 31 * SEC), "{}", i);
+    }
+
+    // A single failure escalates right away
+    let mut failures = failure_window(1);
+    assert!(failures.record_failure_at(start));
+}
@@ -0,0 +1,106 @@
+//! Next run times of cron-like schedules
+
+use chrono::{DateTime, TimeZone, Utc};
+use chrono_tz::Europe::Berlin;
+use mattermost_structs::scheduler::{Job, Schedule, Scheduler};
+
+fn utc(year: i32, month: u32, day: u32, hour: u32, min: u32) -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(year, month, day, hour, min, 0)
+        .unwrap()
+}
+
+fn next(expression: &str, after: DateTime<Utc>) -> Option<DateTime<Utc>> {
+    expression
+        .parse::<Schedule>()
+        .unwrap()
+        .next_after(after, &Utc)
+}
+
+#[test]
+fn next_after() {
+    let after = utc(2024, 3, 30, 12, 0);
+    assert_eq!(next("* * * * *", after), Some(utc(2024, 3, 30, 12, 1)));
+    assert_eq!(next("*/15 * * * *", after), Some(utc(2024, 3, 30, 12, 15)));
+    assert_eq!(next("0 9 * * 1-5", after), Some(utc(2024, 4, 1, 9, 0)));
+    assert_eq!(next("0 0 1 1 *", after), Some(utc(2025, 1, 1, 0, 0)));
+    assert_eq!(next("0 0 29 2 *", after), Some(utc(2028, 2, 29, 0, 0)));
+    assert_eq!(next("0 0 31 2 *", after), None);
+}
+
+#[test]
+fn days_combine_like_cron() {
+    let after = utc(2024, 3, 30, 12, 0);
+    // Sunday, 7 is Sunday as well
+    assert_eq!(next("0 8 * * 7", after), Some(utc(2024, 3, 31, 8, 0)));
+    // The 1st of April or any Sunday
+    assert_eq!(next("0 8 1 * 0", after), Some(utc(2024, 3, 31, 8, 0)));
+    // Only the 1st of April, the day of the week is not restricted
+    assert_eq!(next("0 8 1 * *", after), Some(utc(2024, 4, 1, 8, 0)));
+}
+
+#[test]
+fn daylight_saving_time() {
+    let schedule: Schedule = "30 2 * * *".parse().unwrap();
+    // 02:30 does not exist on the 31st of March in Berlin
+    assert_eq!(
+        schedule.next_after(utc(2024, 3, 30, 12, 0), &Berlin),
+        Some(utc(2024, 4, 1, 0, 30))
+    );
+    // 02:30 exists twice on the 27th of October, only the first one matches
+    assert_eq!(
+        schedule.next_after(utc(2024, 10, 26, 12, 0), &Berlin),
+        Some(utc(2024, 10, 27, 0, 30))
+    );
+}
+
+#[test]
+fn invalid_schedules() {
+    for expression in &[
+        "* * * *",
+        "60 * * * *",
+        "* 24 * * *",
+        "*/0 * * * *",
+        "5-1 * * * *",
+    ] {
+        assert!(
+            expression.parse::<Schedule>().is_err(),
+            "{} should be invalid",
+            expression
+        );
+    }
+}
+
+#[test]
+fn jobs_from_yaml() {
+    let jobs: Vec<Job> = serde_yaml::from_str(
+        r#"
+- name: standup
+  schedule: "45 9 * * 1-5"
+  timezone: Europe/Berlin
+  action:
+    type: post
+    channel_id: 4xp9fdt77pncbef59f4k1qe83o
+    message: Stand-up in 15 minutes
+- name: topic
+  schedule: "0 0 * * 1"
+  action:
+    type: rotate_header
+    channel_id: 4xp9fdt77pncbef59f4k1qe83o
+    headers: [Week A, Week B]
+"#,
+    )
+    .unwrap();
+    assert_eq!(jobs[0].timezone, Berlin);
+    assert_eq!(jobs[1].timezone, chrono_tz::UTC);
+    // Monday at 9:45 in summer time is 7:45 UTC
+    assert_eq!(
+        jobs[0].next_after(utc(2024, 6, 1, 0, 0)),
+        Some(utc(2024, 6, 3, 7, 45))
+    );
+
+    let scheduler = Scheduler::new(jobs.clone()).unwrap();
+    assert!(scheduler.due(Utc::now()).is_empty());
+    let mut duplicated = jobs.clone();
+    duplicated.push(jobs[0].clone());
+    assert!(Scheduler::new(duplicated).is_err());
+}
@@ -0,0 +1,217 @@
+//! Sliding-window event counts
+
+use chrono::{DateTime, Duration, TimeZone, Utc, Weekday};
+use mattermost_structs::{
+    api::ExportFormat,
+    stats::{ChannelActivity, EventCounter, EventSummary, HOURS_PER_WEEK},
+    websocket::{MessagePush, Post},
+};
+use serde_json::json;
+use std::sync::{Arc, Mutex};
+
+const HOUR: std::time::Duration = std::time::Duration::from_secs(3600);
+
+fn start() -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(2024, 1, 1, 12, 0, 0).unwrap()
+}
+
+fn push(event: &str, data: serde_json::Value, channel_id: &str) -> MessagePush {
+    serde_json::from_value(json!({
+        "event": event,
+        "data": data,
+        "broadcast": {"omit_users": null, "user_id": "", "channel_id": channel_id, "team_id": ""},
+        "seq": 1,
+    }))
+    .unwrap()
+}
+
+fn post_json(user_id: &str, channel_id: &str, create_at: i64) -> serde_json::Value {
+    json!({
+        "id": "s8bq9xwsbpyc7gjcw4rs7c4qmh", "create_at": create_at, "update_at": create_at, "edit_at": 0,
+        "delete_at": 0, "is_pinned": false, "user_id": user_id, "channel_id": channel_id, "root_id": "",
+        "original_id": "", "message": "hi", "type": "", "props": {}, "hashtags": "", "pending_post_id": "",
+    })
+}
+
+fn posted(user_id: &str, channel_id: &str) -> MessagePush {
+    let post = post_json(user_id, channel_id, 0);
+    push(
+        "posted",
+        json!({
+            "channel_display_name": "Town Square",
+            "channel_name": "town-square",
+            "channel_type": "O",
+            "post": post.to_string(),
+            "sender_name": "@someone",
+            "team_id": "",
+        }),
+        // Posts are attributed to the channel of the post, not the broadcast
+        "",
+    )
+}
+
+fn typing(user_id: &str, channel_id: &str) -> MessagePush {
+    push(
+        "typing",
+        json!({"parent_id": "", "user_id": user_id}),
+        channel_id,
+    )
+}
+
+#[test]
+fn counts_by_event_channel_and_sender() {
+    let mut counter = EventCounter::new(HOUR);
+    counter.record(&posted("alice", "town-square"), start());
+    counter.record(&posted("alice", "off-topic"), start());
+    counter.record(&posted("bob", "town-square"), start());
+    counter.record(&typing("bob", "town-square"), start());
+    counter.record(
+        &push("channel_viewed", json!({"channel_id": "off-topic"}), ""),
+        start(),
+    );
+
+    let summary = counter.summary(start());
+    assert_eq!(summary.total, 5);
+    assert_eq!(
+        summary.top_events(2),
+        vec![("posted", 3), ("channel_viewed", 1)]
+    );
+    assert_eq!(
+        summary.top_channels(5),
+        vec![("town-square", 3), ("off-topic", 1)]
+    );
+    assert_eq!(summary.top_senders(5), vec![("alice", 2), ("bob", 2)]);
+}
+
+#[test]
+fn events_leave_the_window() {
+    let mut counter = EventCounter::new(HOUR);
+    counter.record(&typing("alice", "town-square"), start());
+    counter.record(
+        &typing("bob", "town-square"),
+        start() + Duration::minutes(40),
+    );
+    assert_eq!(counter.summary(start() + Duration::minutes(50)).total, 2);
+    assert_eq!(counter.summary(start() + Duration::minutes(70)).total, 1);
+
+    // Too old compared to the latest event
+    counter.record(
+        &typing("carol", "town-square"),
+        start() - Duration::hours(1),
+    );
+    let summary = counter.summary(start() + Duration::minutes(50));
+    assert_eq!(summary.total, 2);
+    assert!(!summary.senders.contains_key("carol"));
+
+    counter.record(
+        &typing("carol", "town-square"),
+        start() + Duration::hours(3),
+    );
+    assert_eq!(counter.summary(start() + Duration::hours(3)).total, 1);
+}
+
+#[test]
+fn periodic_summaries() {
+    let summaries: Arc<Mutex<Vec<EventSummary>>> = Arc::default();
+    let collected = summaries.clone();
+    let mut counter = EventCounter::new(HOUR)
+        .on_summary(std::time::Duration::from_secs(600), move |summary| {
+            collected.lock().unwrap().push(summary.clone())
+        });
+    for minute in 0..=30 {
+        counter.record(
+            &typing("alice", "town-square"),
+            start() + Duration::minutes(minute),
+        );
+    }
+
+    let summaries = summaries.lock().unwrap();
+    let totals: Vec<u64> = summaries.iter().map(|summary| summary.total).collect();
+    assert_eq!(totals, vec![11, 21, 31]);
+    assert_eq!(summaries[0].until, start() + Duration::minutes(10));
+    assert_eq!(summaries[0].window_secs, 3600);
+}
+
+fn post_at(user_id: &str, at: DateTime<Utc>) -> Post {
+    serde_json::from_value(post_json(user_id, "town-square", at.timestamp_millis())).unwrap()
+}
+
+#[test]
+fn channel_activity_by_hour_and_poster() {
+    // Monday, 12:00 UTC
+    let until = start() + Duration::weeks(1);
+    let since = start();
+    let mut deleted = post_at("carol", start() + Duration::hours(1));
+    deleted.delete_at = start() + Duration::hours(2);
+    let posts = vec![
+        post_at("alice", start()),
+        post_at("alice", start() + Duration::minutes(59)),
+        post_at("bob", start() + Duration::days(2) + Duration::hours(3)),
+        // Outside of the window
+        post_at("bob", start() - Duration::minutes(1)),
+        post_at("bob", until),
+        deleted,
+    ];
+
+    let activity = ChannelActivity::from_posts("town-square", since, until, chrono_tz::UTC, posts);
+    assert_eq!(activity.total_posts, 3);
+    assert_eq!(activity.hour_of_week.len(), HOURS_PER_WEEK);
+    assert_eq!(activity.hour_of_week.iter().sum::<u64>(), 3);
+    assert_eq!(activity.posts_at(Weekday::Mon, 12), 2);
+    assert_eq!(activity.posts_at(Weekday::Wed, 15), 1);
+    let posters: Vec<(&str, u64)> = activity
+        .posters
+        .iter()
+        .map(|poster| (poster.user_id.as_str(), poster.posts))
+        .collect();
+    assert_eq!(posters, vec![("alice", 2), ("bob", 1)]);
+
+    // The hours shift with the time zone, 13:00 in Berlin
+    let berlin = ChannelActivity::from_posts(
+        "town-square",
+        since,
+        until,
+        chrono_tz::Europe::Berlin,
+        vec![post_at("alice", start())],
+    );
+    assert_eq!(berlin.posts_at(Weekday::Mon, 13), 1);
+}
+
+#[test]
+fn channel_activity_export() {
+    let mut activity = ChannelActivity::from_posts(
+        "town-square",
+        start(),
+        start() + Duration::days(1),
+        chrono_tz::UTC,
+        vec![
+            post_at("alice", start()),
+            post_at("bob", start()),
+            post_at("bob", start() + Duration::hours(1)),
+        ],
+    );
+    activity.posters[0].username = Some("bobby".to_string());
+    activity.truncate_posters(1);
+
+    let mut csv = Vec::new();
+    activity.write(&mut csv, ExportFormat::Csv).unwrap();
+    let csv = String::from_utf8(csv).unwrap();
+    let lines: Vec<&str> = csv.lines().collect();
+    assert_eq!(lines.len(), 1 + HOURS_PER_WEEK + 1);
+    assert_eq!(lines[0], "kind,key,posts");
+    assert_eq!(lines[1], "hour_of_week,Mon 00,0");
+    assert_eq!(lines[1 + 12], "hour_of_week,Mon 12,2");
+    assert_eq!(lines[HOURS_PER_WEEK], "hour_of_week,Sun 23,0");
+    assert_eq!(lines[HOURS_PER_WEEK + 1], "poster,bobby,2");
+
+    let mut json = Vec::new();
+    activity.write(&mut json, ExportFormat::Json).unwrap();
+    let parsed: ChannelActivity = serde_json::from_slice(&json).unwrap();
+    assert_eq!(parsed, activity);
+    let value: serde_json::Value = serde_json::from_slice(&json).unwrap();
+    assert_eq!(value["timezone"], "UTC");
+    assert_eq!(
+        value["posters"],
+        json!([{"user_id": "bob", "username": "bobby", "posts": 2}])
+    );
+}
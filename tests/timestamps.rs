@@ -0,0 +1,109 @@
+//! Boundaries of the millisecond timestamps
+//!
+//! The fields of the golden files are replaced with extreme values, which must either round-trip or fail with an error.
+
+use chrono::{DateTime, NaiveDate, TimeZone, Utc};
+use mattermost_structs::websocket::{Post, Team};
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use std::{fs, path::Path};
+
+fn golden(name: &str) -> Value {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/golden")
+        .join(format!("{}.json", name));
+    serde_json::from_str(&fs::read_to_string(path).unwrap()).unwrap()
+}
+
+/// Parse `name` with `field` set to `value`
+fn with_field<T>(name: &str, field: &str, value: Value) -> serde_json::Result<T>
+where
+    T: DeserializeOwned,
+{
+    let mut document = golden(name);
+    document[field] = value;
+    serde_json::from_value(document)
+}
+
+fn utc(
+    year: i32,
+    month: u32,
+    day: u32,
+    hour: u32,
+    min: u32,
+    sec: u32,
+    milli: u32,
+) -> DateTime<Utc> {
+    let naive = NaiveDate::from_ymd_opt(year, month, day)
+        .and_then(|date| date.and_hms_milli_opt(hour, min, sec, milli))
+        .unwrap();
+    Utc.from_utc_datetime(&naive)
+}
+
+fn post_created_at(millis: i64) -> Post {
+    with_field("post", "create_at", millis.into())
+        .unwrap_or_else(|err| panic!("Failed to parse timestamp {}: {}", millis, err))
+}
+
+#[test]
+fn pre_epoch_timestamps() {
+    let post = post_created_at(-1);
+    assert_eq!(post.create_at, utc(1969, 12, 31, 23, 59, 59, 999));
+
+    let post = post_created_at(-1500);
+    assert_eq!(post.create_at, utc(1969, 12, 31, 23, 59, 58, 500));
+    assert_eq!(serde_json::to_value(&post).unwrap()["create_at"], -1500);
+}
+
+#[test]
+fn extreme_timestamps_round_trip() {
+    for &dt in &[
+        utc(-262_000, 1, 1, 0, 0, 0, 0),
+        utc(262_000, 12, 31, 23, 59, 59, 999),
+    ] {
+        let millis = dt.timestamp_millis();
+        let post = post_created_at(millis);
+        assert_eq!(post.create_at, dt);
+        assert_eq!(serde_json::to_value(&post).unwrap()["create_at"], millis);
+    }
+}
+
+#[test]
+fn out_of_range_timestamps_are_errors() {
+    let values: Vec<Value> = vec![
+        i64::MIN.into(),
+        i64::MAX.into(),
+        u64::MAX.into(),
+        // Around the year 285000, beyond the dates supported by chrono
+        9_000_000_000_000_000i64.into(),
+        (-9_000_000_000_000_000i64).into(),
+    ];
+    for value in values {
+        assert!(
+            with_field::<Post>("post", "create_at", value.clone()).is_err(),
+            "{} was accepted",
+            value
+        );
+        assert!(
+            with_field::<Team>("team", "last_team_icon_update", value.clone()).is_err(),
+            "{} was accepted",
+            value
+        );
+    }
+}
+
+#[test]
+fn optional_timestamps() {
+    let team: Team = with_field("team", "last_team_icon_update", Value::Null).unwrap();
+    assert_eq!(team.last_team_icon_update, None);
+
+    let team: Team = with_field("team", "last_team_icon_update", (-1).into()).unwrap();
+    assert_eq!(
+        team.last_team_icon_update,
+        Some(utc(1969, 12, 31, 23, 59, 59, 999))
+    );
+    assert_eq!(
+        serde_json::to_value(&team).unwrap()["last_team_icon_update"],
+        -1
+    );
+}
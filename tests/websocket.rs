@@ -0,0 +1,253 @@
+//! Reconnecting websocket connections against `mattermost_structs::testing::FakeServer`
+
+#![cfg(feature = "testing")]
+
+use mattermost_structs::{
+    error::Error,
+    presence::PresenceTracker,
+    reconnect::{Backoff, Reconnector},
+    resume::ResumeOutcome,
+    testing::FakeServer,
+    websocket::{
+        Broadcast, Client, EventHandler, Events, Message, MessagePush, Status, UserStatus,
+    },
+    Result,
+};
+use std::{
+    sync::mpsc::{self, Receiver, Sender},
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+const TOKEN: &str = "fake-token";
+const USER_ID: &str = "i1pe3qz5ajyuzkb8e5ja4pc7ow";
+const OTHER_USER_ID: &str = "yb8nkt9oepdz8mbe6oe3egwgqw";
+
+#[derive(Debug)]
+enum Record {
+    Push(Box<MessagePush>),
+    Resume(ResumeOutcome),
+    Disconnect,
+}
+
+/// Reports everything to the test and reconnects only when the test says so
+struct Recorder {
+    records: Sender<Record>,
+    reconnect: Receiver<()>,
+}
+
+impl EventHandler for Recorder {
+    fn on_message(&mut self, message: Message) {
+        if let Message::Push(push) = message {
+            let _ = self.records.send(Record::Push(Box::new(push)));
+        }
+    }
+
+    fn on_resume(&mut self, outcome: ResumeOutcome) {
+        let _ = self.records.send(Record::Resume(outcome));
+    }
+
+    fn on_disconnect(&mut self, _: Option<&Error>) -> bool {
+        let _ = self.records.send(Record::Disconnect);
+        self.reconnect.recv().is_ok()
+    }
+}
+
+/// A client running [`Client::run_reconnecting`] in the background
+struct Session {
+    records: Receiver<Record>,
+    reconnect: Option<Sender<()>>,
+    thread: Option<JoinHandle<Result<()>>>,
+}
+
+impl Session {
+    fn start(server: &FakeServer) -> Session {
+        let (records, records_receiver) = mpsc::channel();
+        let (reconnect, reconnect_receiver) = mpsc::channel();
+        let client = Client::new(server.base_url(), TOKEN).unwrap();
+        let thread = thread::spawn(move || {
+            let mut reconnector = Reconnector::new().backoff(Backoff::new(
+                Duration::from_millis(1),
+                Duration::from_millis(1),
+            ));
+            client.run_reconnecting(
+                &mut Recorder {
+                    records,
+                    reconnect: reconnect_receiver,
+                },
+                &mut reconnector,
+            )
+        });
+        Session {
+            records: records_receiver,
+            reconnect: Some(reconnect),
+            thread: Some(thread),
+        }
+    }
+
+    fn next(&self) -> Record {
+        self.records
+            .recv_timeout(Duration::from_secs(10))
+            .expect("No record within 10 seconds")
+    }
+
+    fn next_push(&self) -> MessagePush {
+        match self.next() {
+            Record::Push(push) => *push,
+            record => panic!("Expected an event, got {:?}", record),
+        }
+    }
+
+    fn expect_resume(&self, expected: ResumeOutcome) -> MessagePush {
+        match self.next() {
+            Record::Resume(outcome) => assert_eq!(outcome, expected),
+            record => panic!("Expected {:?}, got {:?}", expected, record),
+        }
+        let hello = self.next_push();
+        assert!(matches!(hello.event, Events::Hello { .. }), "{:?}", hello);
+        hello
+    }
+
+    /// Close the connection and wait until the client noticed
+    fn disconnect(&self, server: &FakeServer) {
+        server.disconnect_all().unwrap();
+        match self.next() {
+            Record::Disconnect => {}
+            record => panic!("Expected the disconnect, got {:?}", record),
+        }
+    }
+
+    fn reconnect(&self) {
+        self.reconnect.as_ref().unwrap().send(()).unwrap();
+    }
+
+    /// Close the connection and stop reconnecting
+    fn stop(mut self, server: &FakeServer) {
+        self.reconnect.take();
+        server.disconnect_all().unwrap();
+        let result = self.thread.take().unwrap().join().unwrap();
+        assert!(result.is_ok(), "{:?}", result);
+    }
+}
+
+fn status_change(user_id: &str, status: Status) -> Events {
+    Events::StatusChange(UserStatus {
+        user_id: user_id.to_string(),
+        status,
+        manual: false,
+        last_activity_at: None,
+        active_channel: None,
+        dnd_end_time: None,
+    })
+}
+
+fn connection_id(hello: &MessagePush) -> Option<&str> {
+    match &hello.event {
+        Events::Hello { connection_id, .. } => connection_id.as_deref(),
+        _ => None,
+    }
+}
+
+fn send(server: &FakeServer, event: Events) -> usize {
+    let broadcast = Broadcast {
+        user_id: USER_ID.into(),
+        ..Broadcast::default()
+    };
+    server.send_event(event, broadcast).unwrap()
+}
+
+#[test]
+fn resume_replays_missed_events() {
+    let server = FakeServer::start(TOKEN, USER_ID).unwrap();
+    let session = Session::start(&server);
+    let first_hello = session.expect_resume(ResumeOutcome::Initial);
+
+    assert_eq!(send(&server, status_change(USER_ID, Status::Online)), 1);
+    assert_eq!(session.next_push().seq, first_hello.seq + 1);
+
+    session.disconnect(&server);
+    send(&server, status_change(USER_ID, Status::Away));
+    send(&server, status_change(USER_ID, Status::Offline));
+    session.reconnect();
+
+    // The missed events arrive before the `hello`, in order and only once
+    let missed: Vec<_> = (0..2).map(|_| session.next_push()).collect();
+    assert_eq!(missed[0].seq, first_hello.seq + 2);
+    assert_eq!(missed[0].event, status_change(USER_ID, Status::Away));
+    assert_eq!(missed[1].seq, first_hello.seq + 3);
+    assert_eq!(missed[1].event, status_change(USER_ID, Status::Offline));
+    let hello = session.expect_resume(ResumeOutcome::Resumed);
+    assert_eq!(hello.seq, first_hello.seq + 4);
+
+    send(&server, status_change(USER_ID, Status::Online));
+    assert_eq!(session.next_push().seq, first_hello.seq + 5);
+    session.stop(&server);
+}
+
+#[test]
+fn duplicates_are_dropped() {
+    let server = FakeServer::start(TOKEN, USER_ID).unwrap();
+    let session = Session::start(&server);
+    session.expect_resume(ResumeOutcome::Initial);
+    send(&server, status_change(USER_ID, Status::Online));
+    let received = session.next_push();
+
+    // Replayed again, e.g., by a server resuming from an older sequence number
+    server
+        .send_raw(serde_json::to_string(&received).unwrap())
+        .unwrap();
+    send(&server, status_change(USER_ID, Status::Away));
+    let next = session.next_push();
+    assert_eq!(next.seq, received.seq + 1);
+    assert_eq!(next.event, status_change(USER_ID, Status::Away));
+    session.stop(&server);
+}
+
+#[test]
+fn resume_fails_after_too_many_missed_events() {
+    let server = FakeServer::start(TOKEN, USER_ID).unwrap();
+    let session = Session::start(&server);
+    let first_hello = session.expect_resume(ResumeOutcome::Initial);
+
+    session.disconnect(&server);
+    for _ in 0..200 {
+        send(&server, status_change(OTHER_USER_ID, Status::Online));
+    }
+    session.reconnect();
+
+    // A new connection starts over, without replaying anything
+    let hello = session.expect_resume(ResumeOutcome::Lost);
+    assert_eq!(hello.seq, 0);
+    assert_ne!(connection_id(&hello), connection_id(&first_hello));
+    send(&server, status_change(USER_ID, Status::Online));
+    assert_eq!(session.next_push().seq, 1);
+    session.stop(&server);
+}
+
+#[test]
+fn presence_follows_reconnects() {
+    let server = FakeServer::start(TOKEN, USER_ID).unwrap();
+    let session = Session::start(&server);
+    let mut presence = PresenceTracker::new(Duration::from_secs(3600));
+    presence.handle(&session.expect_resume(ResumeOutcome::Initial));
+
+    send(&server, status_change(USER_ID, Status::Online));
+    send(&server, status_change(OTHER_USER_ID, Status::Online));
+    for _ in 0..2 {
+        presence.handle(&session.next_push());
+    }
+    assert_eq!(presence.online_users(), vec![USER_ID, OTHER_USER_ID]);
+
+    session.disconnect(&server);
+    send(&server, status_change(OTHER_USER_ID, Status::Away));
+    session.reconnect();
+    presence.handle(&session.next_push());
+    presence.handle(&session.expect_resume(ResumeOutcome::Resumed));
+    assert!(presence.is_online(USER_ID));
+    assert!(!presence.is_online(OTHER_USER_ID));
+    assert_eq!(
+        presence.status(OTHER_USER_ID).map(|status| status.status),
+        Some(Status::Away)
+    );
+    session.stop(&server);
+}